@@ -14,10 +14,12 @@
 //! 1. [`bytes`]: contains Byteorder like functions for reading integers and strings from byte slices.
 //! 2. [`testing`]: contains alternatives to the `assert!` family that return `Result`s instead of panicking.
 //! 3. [`vfs`]: contains traits for a virtual filesystem and some basic filesystems that allow for parsing without extracting
+//! 4. [`texture`]: contains helpers for decoding textures that are shared between the platforms
 //!
 //! ## Features
 //! This crate has no features that can be enabled
 //!
 
 pub mod bytes;
+pub mod texture;
 pub mod vfs;
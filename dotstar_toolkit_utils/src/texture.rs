@@ -0,0 +1,275 @@
+#![allow(
+    clippy::arithmetic_side_effects,
+    reason = "The operands are 8-bit to 32-bit pixel values or bounded by the size of the buffer"
+)]
+//! Helpers for decoding textures to RGBA8
+//!
+//! These are shared by the texture decoders of the different platforms. Only the parts that don't
+//! depend on the platform are here, like the block layout and the BC4/BC5 formats.
+
+use test_eq::{test_le, TestFailure};
+
+/// Expand the lowest `bits` bits of `value` to 8 bits
+#[must_use]
+pub fn expand(value: u32, bits: u32) -> u8 {
+    let max = (1 << bits) - 1;
+    let expanded = ((value & max) * 255 + max / 2) / max;
+    u8::try_from(expanded).unwrap_or(u8::MAX)
+}
+
+/// Convert a signed normalized value in the range `-127..=127` to 8 bits
+#[must_use]
+pub fn snorm_to_unorm(value: i32) -> u8 {
+    u8::try_from(((value.clamp(-127, 127) + 127) * 255 + 127) / 254).unwrap_or(u8::MAX)
+}
+
+/// Decode an image of uncompressed pixels of `N` bytes to RGBA8
+///
+/// # Errors
+/// Will return an error if `data` is too small for the image
+pub fn decode_pixels<const N: usize>(
+    data: &[u8],
+    buf: &mut [u8],
+    decode_pixel: impl Fn([u8; N]) -> [u8; 4],
+) -> Result<(), TestFailure> {
+    let pixels = buf.len() / 4;
+    test_le!(pixels * N, data.len())?;
+    for (pixel, out) in data.chunks_exact(N).zip(buf.chunks_exact_mut(4)) {
+        let pixel: [u8; N] = pixel.try_into().unwrap_or_else(|_| unreachable!());
+        out.copy_from_slice(&decode_pixel(pixel));
+    }
+    Ok(())
+}
+
+/// Decode an image of blocks to RGBA8
+///
+/// A block is `block_width` by `block_height` pixels stored in `block_size` bytes. The blocks are
+/// stored from left to right and top to bottom, `decode_block` returns the pixels of a block in
+/// the same order.
+///
+/// # Errors
+/// Will return an error if `data` is too small for the image
+pub fn decode_blocks<P: AsRef<[[u8; 4]]>>(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    buf: &mut [u8],
+    (block_width, block_height): (usize, usize),
+    block_size: usize,
+    decode_block: impl Fn(&[u8]) -> P,
+) -> Result<(), TestFailure> {
+    let width_in_blocks = width.div_ceil(block_width);
+    let height_in_blocks = height.div_ceil(block_height);
+    let blocks = width_in_blocks * height_in_blocks;
+    test_le!(blocks * block_size, data.len())?;
+    for (i, block) in data.chunks_exact(block_size).take(blocks).enumerate() {
+        let block_x = (i % width_in_blocks) * block_width;
+        let block_y = (i / width_in_blocks) * block_height;
+        let pixels = decode_block(block);
+        for (j, pixel) in pixels
+            .as_ref()
+            .iter()
+            .take(block_width * block_height)
+            .enumerate()
+        {
+            let x = block_x + j % block_width;
+            let y = block_y + j / block_width;
+            // Blocks on the edge can be partially outside the image
+            if x < width && y < height {
+                let offset = (y * width + x) * 4;
+                buf[offset..offset + 4].copy_from_slice(pixel);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode the 16 values of a BC4 block
+#[must_use]
+pub fn bc4_values(block: &[u8], signed: bool) -> [u8; 16] {
+    let (r0, r1, min, max) = if signed {
+        (
+            i32::from(i8::from_le_bytes([block[0]])).max(-127),
+            i32::from(i8::from_le_bytes([block[1]])).max(-127),
+            -127,
+            127,
+        )
+    } else {
+        (i32::from(block[0]), i32::from(block[1]), 0, 255)
+    };
+
+    let palette = if r0 > r1 {
+        [
+            r0,
+            r1,
+            (6 * r0 + r1) / 7,
+            (5 * r0 + 2 * r1) / 7,
+            (4 * r0 + 3 * r1) / 7,
+            (3 * r0 + 4 * r1) / 7,
+            (2 * r0 + 5 * r1) / 7,
+            (r0 + 6 * r1) / 7,
+        ]
+    } else {
+        [
+            r0,
+            r1,
+            (4 * r0 + r1) / 5,
+            (3 * r0 + 2 * r1) / 5,
+            (2 * r0 + 3 * r1) / 5,
+            (r0 + 4 * r1) / 5,
+            min,
+            max,
+        ]
+    };
+    let palette = palette.map(|value| {
+        if signed {
+            snorm_to_unorm(value)
+        } else {
+            u8::try_from(value).unwrap_or(u8::MAX)
+        }
+    });
+
+    // 16 indices of 3 bits
+    let indices = u64::from_le_bytes([
+        block[2], block[3], block[4], block[5], block[6], block[7], 0, 0,
+    ]);
+    let mut values = [0; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        let index =
+            usize::try_from((indices >> (i * 3)) & 0b111).unwrap_or_else(|_| unreachable!());
+        *value = palette[index];
+    }
+    values
+}
+
+/// Decode a BC4 block, the red channel is copied to green and blue
+#[must_use]
+pub fn bc4_block(block: &[u8], signed: bool) -> [[u8; 4]; 16] {
+    bc4_values(block, signed).map(|r| [r, r, r, u8::MAX])
+}
+
+/// Decode a BC5 block, consisting of a BC4 block for red and one for green
+#[must_use]
+pub fn bc5_block(block: &[u8], signed: bool) -> [[u8; 4]; 16] {
+    let red = bc4_values(&block[..8], signed);
+    let green = bc4_values(&block[8..], signed);
+    let mut pixels = [[0, 0, 0, u8::MAX]; 16];
+    for ((pixel, r), g) in pixels.iter_mut().zip(red).zip(green) {
+        pixel[0] = r;
+        pixel[1] = g;
+    }
+    pixels
+}
+
+/// Decode a one byte red pixel, the red channel is copied to green and blue
+#[must_use]
+pub const fn r8(pixel: [u8; 1]) -> [u8; 4] {
+    [pixel[0], pixel[0], pixel[0], u8::MAX]
+}
+
+/// Decode a two byte red and green pixel
+#[must_use]
+pub const fn r8g8(pixel: [u8; 2]) -> [u8; 4] {
+    [pixel[0], pixel[1], 0, u8::MAX]
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand(0, 5), 0, "Zero should stay zero");
+        assert_eq!(expand(31, 5), 255, "Max should be max");
+        assert_eq!(expand(1, 1), 255, "Max should be max");
+        assert_eq!(expand(0x3FF, 10), 255, "Max should be max");
+        assert_eq!(expand(0b1_1111, 4), 255, "Higher bits should be ignored");
+    }
+
+    #[test]
+    fn test_bc4_unsigned() {
+        // Index 0, 1, 2 and 7 for the first four pixels
+        let block = [255, 0, 0b1000_1000, 0b0000_1110, 0, 0, 0, 0];
+        let values = bc4_values(&block, false);
+        assert_eq!(values[0], 255, "Index 0 is r0");
+        assert_eq!(values[1], 0, "Index 1 is r1");
+        assert_eq!(values[2], 218, "Index 2 is 6/7 r0 + 1/7 r1");
+        assert_eq!(values[3], 36, "Index 7 is 1/7 r0 + 6/7 r1");
+        assert_eq!(values[4], 255, "Index 0 is r0");
+    }
+
+    #[test]
+    fn test_bc4_unsigned_six_values() {
+        // r0 <= r1, so index 6 and 7 are the minimum and maximum
+        let block = [0, 100, 0b1111_1110, 0, 0, 0, 0, 0];
+        let values = bc4_values(&block, false);
+        assert_eq!(values[0], 0, "Index 6 is 0");
+        assert_eq!(values[1], 255, "Index 7 is 255");
+        assert_eq!(values[2], 60, "Index 3 is 2/5 r0 + 3/5 r1");
+    }
+
+    #[test]
+    fn test_bc4_signed() {
+        let block = [0x7F, 0x81, 0b0000_1000, 0, 0, 0, 0, 0];
+        let values = bc4_values(&block, true);
+        assert_eq!(values[0], 255, "127 is the maximum");
+        assert_eq!(values[1], 0, "-127 is the minimum");
+        assert_eq!(values[2], 255, "Index 0 is r0");
+    }
+
+    #[test]
+    fn test_bc5() {
+        let mut block = [0; 16];
+        block[0] = 200;
+        block[1] = 200;
+        block[8] = 50;
+        block[9] = 50;
+        let pixels = bc5_block(&block, false);
+        assert!(
+            pixels.iter().all(|p| p == &[200, 50, 0, 255]),
+            "Solid block decoded wrong"
+        );
+    }
+
+    #[test]
+    fn test_decode_blocks_partial() {
+        let block = [128, 128, 0, 0, 0, 0, 0, 0];
+        let mut buf = vec![0; 2 * 3 * 4];
+        decode_blocks(&block, 2, 3, &mut buf, (4, 4), 8, |b| bc4_block(b, false)).unwrap();
+        assert!(
+            buf.chunks_exact(4).all(|p| p == [128, 128, 128, 255]),
+            "Partial block decoded wrong"
+        );
+    }
+
+    #[test]
+    fn test_decode_blocks_too_little_data() {
+        let block = [0; 8];
+        let mut buf = vec![0; 8 * 4 * 4];
+        assert!(
+            decode_blocks(&block, 8, 4, &mut buf, (4, 4), 8, |b| bc4_block(b, false)).is_err(),
+            "Two blocks are needed"
+        );
+    }
+
+    #[test]
+    fn test_decode_blocks_rectangular() {
+        // Two blocks of 2x1 pixels with one byte per pixel
+        let data = [1, 2, 3, 4];
+        let mut buf = vec![0; 4 * 4];
+        decode_blocks(&data, 4, 1, &mut buf, (2, 1), 2, |b| {
+            [r8([b[0]]), r8([b[1]])]
+        })
+        .unwrap();
+        let red: Vec<_> = buf.chunks_exact(4).map(|p| p[0]).collect();
+        assert_eq!(red, [1, 2, 3, 4], "Blocks should be placed left to right");
+    }
+
+    #[test]
+    fn test_decode_pixels() {
+        let mut buf = vec![0; 8];
+        decode_pixels(&[1, 2, 3, 4], &mut buf, r8g8).unwrap();
+        assert_eq!(buf, [1, 2, 0, 255, 3, 4, 0, 255], "Two pixels");
+    }
+}
@@ -69,6 +69,21 @@ impl<'a> WalkFs<'a> {
         Self { paths }
     }
 
+    /// Create a `WalkFs` that iterates over the paths in `list` at `path` and deeper
+    ///
+    /// The paths in `list` have no leading slash, see [`VirtualPath::clean_relative`]. An empty
+    /// `path` or `.` matches every path.
+    #[must_use]
+    pub fn filtered(list: &'a [VirtualPathBuf], path: &VirtualPath) -> Self {
+        let path = path.clean_relative();
+        Self::new(
+            list.iter()
+                .filter(|p| matches!(path.as_str(), "" | ".") || p.starts_with(&path))
+                .map(VirtualPathBuf::as_path)
+                .collect(),
+        )
+    }
+
     /// Merge another `WalkFs` iterator into this one
     pub fn merge(&mut self, other: &Self) {
         self.paths.extend_from_slice(&other.paths);
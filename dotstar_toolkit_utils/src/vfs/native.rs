@@ -38,6 +38,12 @@ impl NativeFs {
         })
     }
 
+    /// The root of this filesystem
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
     /// Create a canonical version of `path` with all relative things removed
     ///
     /// # Errors
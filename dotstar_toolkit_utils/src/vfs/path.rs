@@ -1817,6 +1817,28 @@ impl VirtualPath {
         path
     }
 
+    /// Clean the path from `//` and remove the leading slash
+    ///
+    /// This is how filesystems that store their paths without a leading slash look up paths.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use dotstar_toolkit_utils::vfs::{VirtualPath, VirtualPathBuf};
+    /// assert_eq!(
+    ///     VirtualPath::new("/foo//bar").clean_relative(),
+    ///     VirtualPathBuf::from("foo/bar")
+    /// );
+    /// ```
+    #[must_use]
+    pub fn clean_relative(&self) -> VirtualPathBuf {
+        let path = self.clean();
+        match path.as_str().strip_prefix('/') {
+            Some(stripped) => VirtualPathBuf::from(stripped),
+            None => path,
+        }
+    }
+
     /// Checks the path for '..' and '.' and panics if it finds them
     fn check_path(&self) {
         if self.inner.starts_with("../") || self.inner.ends_with("/..") {
@@ -75,6 +75,13 @@ impl SymlinkFs<'_> {
         }
         Ok(size)
     }
+
+    /// Iterate over all mappings as `(new_path, orig_path)`
+    pub fn mappings(&self) -> impl Iterator<Item = (&VirtualPath, &VirtualPath)> {
+        self.mapping
+            .iter()
+            .map(|(new_path, orig_path)| (new_path.as_path(), orig_path.as_path()))
+    }
 }
 
 impl IntoIterator for SymlinkFs<'_> {
@@ -67,6 +67,13 @@ impl VecFs {
         u64::try_from(self.files.values().map(Vec::len).sum::<usize>())
             .map_err(|_| std::io::Error::other("Overflow occured"))
     }
+
+    /// Iterate over all files in the filesystem
+    pub fn files(&self) -> impl Iterator<Item = (&VirtualPath, &[u8])> {
+        self.files
+            .iter()
+            .map(|(path, content)| (path.as_path(), content.as_slice()))
+    }
 }
 
 impl IntoIterator for VecFs {
@@ -1,8 +1,11 @@
 use std::{collections::VecDeque, num::TryFromIntError};
 
-use dotstar_toolkit_utils::bytes::{
-    primitives::u32be,
-    read::{BinaryDeserialize, ReadAtExt, ReadError},
+use dotstar_toolkit_utils::{
+    bytes::{
+        primitives::u32be,
+        read::{BinaryDeserialize, ReadAtExt, ReadError},
+    },
+    texture,
 };
 use image::{
     error::{DecodingError, ImageFormatHint},
@@ -12,7 +15,10 @@ use test_eq::{test_eq, test_le, TestFailure};
 use thiserror::Error;
 use wiiu_swizzle::{deswizzle_mipmap, SwizzleError, TileMode};
 
-use crate::types::{Block, Data, Format, GfdHeader, GtxRaw, Gx2Surface};
+use crate::{
+    formats,
+    types::{Block, Data, Format, GfdHeader, GtxRaw, Gx2Surface},
+};
 
 /// Errors returned when the decoder fails
 #[derive(Error, Debug)]
@@ -86,7 +92,9 @@ impl<R: ReadAtExt> GtxDecoder<R> {
                         Ok(())
                     }
                 }
-                Block::DataLazy(_) => Err(DecoderError::DataBlockWithoutHeaderBlock),
+                Block::DataLazy(_) | Block::Data(_) => {
+                    Err(DecoderError::DataBlockWithoutHeaderBlock)
+                }
                 Block::Mip(_) | Block::Two => Ok(()),
             }?;
         }
@@ -127,7 +135,7 @@ impl<R: ReadAtExt> ImageDecoder for GtxDecoder<R> {
         let bpp = hdr.format.bytes_per_pixel();
         let is_bcn = hdr.format.is_bcn();
         let (width_in_blocks, height_in_blocks) = if is_bcn {
-            (hdr.width / 4, hdr.height / 4)
+            (hdr.width.div_ceil(4), hdr.height.div_ceil(4))
         } else {
             (hdr.width, hdr.height)
         };
@@ -172,8 +180,79 @@ impl<R: ReadAtExt> ImageDecoder for GtxDecoder<R> {
             Format::TBc3Srgb | Format::TBc3Unorm => {
                 texpresso::Format::Bc3.decompress(&deswizzled, width_usize, height_usize, buf);
             }
-            Format::TcsR8G8B8A8Srgb | Format::TcsR8G8B8A8Unorm => buf.copy_from_slice(&deswizzled),
-            _ => unimplemented!("Decoding of {:?} is not yet implemented", hdr.format),
+            Format::TBc4Unorm => {
+                texture::decode_blocks(
+                    &deswizzled,
+                    width_usize,
+                    height_usize,
+                    buf,
+                    (4, 4),
+                    8,
+                    |block| texture::bc4_block(block, false),
+                )
+                .map_err(DecoderError::from)?;
+            }
+            Format::TBc4Snorm => {
+                texture::decode_blocks(
+                    &deswizzled,
+                    width_usize,
+                    height_usize,
+                    buf,
+                    (4, 4),
+                    8,
+                    |block| texture::bc4_block(block, true),
+                )
+                .map_err(DecoderError::from)?;
+            }
+            Format::TBc5Unorm => {
+                texture::decode_blocks(
+                    &deswizzled,
+                    width_usize,
+                    height_usize,
+                    buf,
+                    (4, 4),
+                    16,
+                    |block| texture::bc5_block(block, false),
+                )
+                .map_err(DecoderError::from)?;
+            }
+            Format::TBc5Snorm => {
+                texture::decode_blocks(
+                    &deswizzled,
+                    width_usize,
+                    height_usize,
+                    buf,
+                    (4, 4),
+                    16,
+                    |block| texture::bc5_block(block, true),
+                )
+                .map_err(DecoderError::from)?;
+            }
+            Format::TcsR8G8B8A8Srgb | Format::TcsR8G8B8A8Unorm => {
+                test_le!(buf.len(), deswizzled.len()).map_err(DecoderError::from)?;
+                buf.copy_from_slice(&deswizzled[..buf.len()]);
+            }
+            Format::TcsR10G10B10A2Unorm => {
+                texture::decode_pixels(&deswizzled, buf, formats::r10g10b10a2)
+                    .map_err(DecoderError::from)?;
+            }
+            Format::TcsR5G6B5Unorm => texture::decode_pixels(&deswizzled, buf, formats::r5g6b5)
+                .map_err(DecoderError::from)?,
+            Format::TcR5G5B5A1Unorm => {
+                texture::decode_pixels(&deswizzled, buf, formats::r5g5b5a1)
+                    .map_err(DecoderError::from)?;
+            }
+            Format::TcR4G4B4A4Unorm => {
+                texture::decode_pixels(&deswizzled, buf, formats::r4g4b4a4)
+                    .map_err(DecoderError::from)?;
+            }
+            Format::TcR8Unorm => {
+                texture::decode_pixels(&deswizzled, buf, texture::r8).map_err(DecoderError::from)?
+            }
+            Format::TcR8G8Unorm => texture::decode_pixels(&deswizzled, buf, texture::r8g8)
+                .map_err(DecoderError::from)?,
+            Format::TcR4G4Unorm => texture::decode_pixels(&deswizzled, buf, formats::r4g4)
+                .map_err(DecoderError::from)?,
         }
 
         Ok(())
@@ -366,6 +445,7 @@ impl BinaryDeserialize<'_> for Format {
         position: &mut u64,
         _ctx: Self::Ctx,
     ) -> Result<Self::Output, ReadError> {
-        Self::try_from(reader.read_at::<u32be>(position)?)
+        let value = reader.read_at::<u32be>(position)?;
+        Self::try_from(value).map_err(|_| ReadError::custom(format!("Unknown format: 0x{value:x}")))
     }
 }
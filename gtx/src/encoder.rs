@@ -0,0 +1,515 @@
+use std::{borrow::Cow, collections::VecDeque, num::TryFromIntError};
+
+use dotstar_toolkit_utils::bytes::{
+    primitives::u32be,
+    write::{BinarySerialize, WriteAt, WriteError},
+};
+use image::{
+    error::{EncodingError, ImageFormatHint},
+    ExtendedColorType, ImageEncoder, ImageError, ImageResult,
+};
+use test_eq::TestFailure;
+use thiserror::Error;
+use wiiu_swizzle::{swizzle_mipmap, AaMode, SwizzleError, TileMode};
+
+use crate::types::{Block, Format, GfdHeader, GtxRaw, Gx2Surface};
+
+/// Pipe interleave of the GPU in bytes, also the alignment of 1D tiled surfaces
+const PIPE_INTERLEAVE_BYTES: u32 = 0x100;
+/// Width of a micro tile in elements
+const MICRO_TILE_WIDTH: u32 = 8;
+/// Width of a macro tile in elements (8 * number of banks)
+const MACRO_TILE_WIDTH: u32 = 32;
+/// Height of a macro tile in elements (8 * number of pipes)
+const MACRO_TILE_HEIGHT: u32 = 16;
+
+/// Component selection for red, green, blue and alpha
+const COMP_SEL_RGBA: [u8; 4] = [0, 1, 2, 3];
+
+/// Encoder for WiiU GX2 texture files
+///
+/// Most common extension is `.gtx`.
+/// Magic is `Gfx2`.
+///
+/// The surface is written with the 2D tiled mode like the textures of the game,
+/// surfaces smaller than a macro tile use the 1D tiled mode.
+pub struct GtxEncoder<W: WriteAt> {
+    writer: W,
+    position: u64,
+    format: Option<Format>,
+    params: texpresso::Params,
+    align_mode: u32,
+}
+
+impl<W: WriteAt> GtxEncoder<W> {
+    /// Create a new encoder that start writing in `writer` at `position`
+    pub fn new(writer: W, position: u64) -> Self {
+        Self {
+            writer,
+            position,
+            format: None,
+            params: texpresso::Params {
+                algorithm: texpresso::Algorithm::IterativeClusterFit,
+                ..Default::default()
+            },
+            align_mode: 1,
+        }
+    }
+
+    /// Set the texture encoding that will be used
+    ///
+    /// If not set, `BC3` will be used for RGBA images
+    /// and `BC1` will be used for RGB images.
+    pub fn set_format(&mut self, format: Format) {
+        self.format = Some(format);
+    }
+
+    /// Set the encoder parameters
+    ///
+    /// The default is `texpresso::Params::default()` except for the
+    /// algorithm, which is set to `IterativeClusterFit`.
+    pub fn set_encoder_params(&mut self, params: texpresso::Params) {
+        self.params = params;
+    }
+
+    /// Set the align mode of the GFD header
+    ///
+    /// If the align mode is 1, padding blocks are inserted before the
+    /// image data so it's aligned. Defaults to 1.
+    pub fn set_align_mode(&mut self, align_mode: u32) {
+        self.align_mode = align_mode;
+    }
+}
+
+/// Errors returned when the encoder fails
+#[derive(Error, Debug)]
+pub enum EncoderError {
+    /// The input color type was wrong
+    #[error("The input color format was not RGBA8")]
+    InvalidInputColor,
+    /// The texture format is not supported by the encoder
+    #[error("Encoding {0:?} is not supported")]
+    UnsupportedFormat(Format),
+    /// Swizzling went wrong
+    #[error("Texture swizzling failed")]
+    SwizzleError(#[from] SwizzleError),
+    /// Write failure
+    #[error("Write error")]
+    Write(#[from] WriteError),
+    /// Test failure
+    #[error("Value test failed")]
+    Test(#[from] TestFailure),
+    /// Integer conversion failed
+    #[error("Integer conversion failed")]
+    TryFromInt(#[from] TryFromIntError),
+}
+
+impl From<EncoderError> for ImageError {
+    fn from(err: EncoderError) -> Self {
+        Self::Encoding(EncodingError::new(ImageFormatHint::Name("GTX".into()), err))
+    }
+}
+
+impl<W: WriteAt> ImageEncoder for GtxEncoder<W> {
+    fn write_image(
+        mut self,
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ExtendedColorType,
+    ) -> ImageResult<()> {
+        if color_type != ExtendedColorType::Rgba8 {
+            return Err(EncoderError::InvalidInputColor.into());
+        }
+
+        let format = match self.format {
+            Some(format) => format,
+            None => {
+                if buf.iter().skip(3).step_by(4).all(|b| *b == u8::MAX) {
+                    Format::TBc1Unorm
+                } else {
+                    Format::TBc3Unorm
+                }
+            }
+        };
+
+        let width_usize = usize::try_from(width).map_err(EncoderError::from)?;
+        let height_usize = usize::try_from(height).map_err(EncoderError::from)?;
+
+        let data = match format {
+            Format::TcsR8G8B8A8Unorm | Format::TcsR8G8B8A8Srgb => Cow::Borrowed(buf),
+            Format::TBc1Unorm | Format::TBc1Srgb => Cow::Owned(compress(
+                texpresso::Format::Bc1,
+                buf,
+                width_usize,
+                height_usize,
+                self.params,
+            )),
+            Format::TBc2Unorm | Format::TBc2Srgb => Cow::Owned(compress(
+                texpresso::Format::Bc2,
+                buf,
+                width_usize,
+                height_usize,
+                self.params,
+            )),
+            Format::TBc3Unorm | Format::TBc3Srgb => Cow::Owned(compress(
+                texpresso::Format::Bc3,
+                buf,
+                width_usize,
+                height_usize,
+                self.params,
+            )),
+            _ => return Err(EncoderError::UnsupportedFormat(format).into()),
+        };
+
+        let (width_in_blocks, height_in_blocks) = if format.is_bcn() {
+            (width.div_ceil(4), height.div_ceil(4))
+        } else {
+            (width, height)
+        };
+        let bpp = format.bytes_per_pixel();
+        let (tile_mode, pitch, alignment) = surface_layout(width_in_blocks, height_in_blocks, bpp);
+
+        let swizzled = swizzle_mipmap(
+            width_in_blocks,
+            height_in_blocks,
+            1,
+            &data,
+            0,
+            pitch,
+            tile_mode,
+            bpp,
+            AaMode::X1,
+        )
+        .map_err(EncoderError::from)?;
+        drop(data); // Don't keep the original image in ram
+
+        let surface = Gx2Surface {
+            dim: 1,
+            width,
+            height,
+            depth: 1,
+            num_mips: 1,
+            format,
+            use_it: 1,
+            image_size: u32::try_from(swizzled.len()).map_err(EncoderError::from)?,
+            image_ptr: 0,
+            mip_size: 0,
+            mip_ptr: 0,
+            tile_mode,
+            swizzle: 0,
+            alignment,
+            pitch,
+            mip_offsets: [0; 13],
+        };
+
+        let mut blocks = VecDeque::with_capacity(2);
+        blocks.push_back(Block::Surface(surface));
+        blocks.push_back(Block::Data(swizzled));
+
+        let gtx = GtxRaw {
+            header: GfdHeader {
+                align_mode: self.align_mode,
+            },
+            blocks,
+        };
+
+        self.writer
+            .write_at::<GtxRaw>(&mut self.position, gtx)
+            .map_err(EncoderError::from)?;
+
+        Ok(())
+    }
+}
+
+/// Compress `buf` with the BCn `format`
+fn compress(
+    format: texpresso::Format,
+    buf: &[u8],
+    width: usize,
+    height: usize,
+    params: texpresso::Params,
+) -> Vec<u8> {
+    let mut output = vec![0; format.compressed_size(width, height)];
+    format.compress(buf, width, height, params, &mut output);
+    output
+}
+
+/// Calculate the tile mode, pitch (in elements) and alignment (in bytes) of a surface
+///
+/// Follows the GX2 address library: a 2D tiled surface needs to fill at least one macro tile,
+/// otherwise the 1D tiled mode is used. The pitch of a 1D tiled surface is aligned so a row of
+/// micro tiles fills the pipe interleave.
+const fn surface_layout(
+    width_in_blocks: u32,
+    height_in_blocks: u32,
+    bytes_per_pixel: u32,
+) -> (TileMode, u32, u32) {
+    if width_in_blocks >= MACRO_TILE_WIDTH && height_in_blocks >= MACRO_TILE_HEIGHT {
+        let pitch = width_in_blocks.next_multiple_of(MACRO_TILE_WIDTH);
+        let macro_tile_bytes = MACRO_TILE_WIDTH * MACRO_TILE_HEIGHT * bytes_per_pixel;
+        (TileMode::D2TiledThin1, pitch, macro_tile_bytes)
+    } else {
+        let micro_tile_row = PIPE_INTERLEAVE_BYTES / (bytes_per_pixel * MICRO_TILE_WIDTH);
+        let pitch_align = if micro_tile_row > MICRO_TILE_WIDTH {
+            micro_tile_row
+        } else {
+            MICRO_TILE_WIDTH
+        };
+        let pitch = width_in_blocks.next_multiple_of(pitch_align);
+        (TileMode::D1TiledThin1, pitch, PIPE_INTERLEAVE_BYTES)
+    }
+}
+
+impl BinarySerialize for GtxRaw<'_> {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        gtx: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        let start = *position;
+        let align_mode = gtx.header.align_mode;
+        writer.write_at::<GfdHeader>(position, gtx.header)?;
+
+        let mut alignment = None;
+        for block in gtx.blocks {
+            match block {
+                Block::Surface(surface) => {
+                    alignment = Some(surface.alignment);
+                    write_block_header(writer, position, 0x0B, Gx2Surface::SIZE)?;
+                    writer.write_at::<Gx2Surface>(position, surface)?;
+                }
+                Block::Data(data) => {
+                    let alignment = alignment.ok_or_else(|| {
+                        WriteError::custom("Found a data block without a surface block".into())
+                    })?;
+                    if align_mode == 1 {
+                        write_padding(writer, position, start, alignment)?;
+                    }
+                    write_block_header(writer, position, 0x0C, u32::try_from(data.len())?)?;
+                    writer.write_slice_at(position, &data)?;
+                }
+                Block::Mip(data) => {
+                    write_block_header(writer, position, 0x0D, u32::try_from(data.len())?)?;
+                    writer.write_slice_at(position, &data)?;
+                }
+                Block::Two => {} // Padding is generated when needed
+                Block::DataLazy(_) => {
+                    return Err(WriteError::custom("Cannot write a lazy data block".into()))
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Write a block header of `type_it` with a data size of `data_size`
+fn write_block_header(
+    writer: &mut (impl WriteAt + ?Sized),
+    position: &mut u64,
+    type_it: u32,
+    data_size: u32,
+) -> Result<(), WriteError> {
+    writer.write_at::<u32be>(position, Block::MAGIC)?;
+    writer.write_at::<u32be>(position, Block::HEADER_SIZE)?;
+    writer.write_at::<u32be>(position, 1)?; // major version
+    writer.write_at::<u32be>(position, 0)?; // minor version
+    writer.write_at::<u32be>(position, type_it)?;
+    writer.write_at::<u32be>(position, data_size)?;
+    writer.write_at::<u32be>(position, 0)?; // id
+    writer.write_at::<u32be>(position, 0)?; // type idx
+    Ok(())
+}
+
+/// Write a padding block so the data of the next block is aligned to `alignment` (relative to `start`)
+fn write_padding(
+    writer: &mut (impl WriteAt + ?Sized),
+    position: &mut u64,
+    start: u64,
+    alignment: u32,
+) -> Result<(), WriteError> {
+    let alignment = u64::from(alignment);
+    let header_size = u64::from(Block::HEADER_SIZE);
+    let relative = *position - start;
+    if (relative + header_size) % alignment == 0 {
+        // Already aligned
+        return Ok(());
+    }
+    // Padding block header, padding, and the header of the next block
+    let padding =
+        (relative + header_size * 2).next_multiple_of(alignment) - relative - header_size * 2;
+    write_block_header(writer, position, 0x02, u32::try_from(padding)?)?;
+    writer.write_slice_at(position, &vec![0; usize::try_from(padding)?])?;
+    Ok(())
+}
+
+impl BinarySerialize for GfdHeader {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        header: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<u32be>(position, Self::MAGIC)?;
+        writer.write_at::<u32be>(position, 0x20)?; // size
+        writer.write_at::<u32be>(position, 7)?; // major version
+        writer.write_at::<u32be>(position, 1)?; // minor version
+        writer.write_at::<u32be>(position, Self::GPU_VERSION)?;
+        writer.write_at::<u32be>(position, header.align_mode)?;
+        writer.write_at::<u32be>(position, 0)?; // reserved1
+        writer.write_at::<u32be>(position, 0)?; // reserved2
+        Ok(())
+    }
+}
+
+impl BinarySerialize for Gx2Surface {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        surface: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<u32be>(position, surface.dim)?;
+        writer.write_at::<u32be>(position, surface.width)?;
+        writer.write_at::<u32be>(position, surface.height)?;
+        writer.write_at::<u32be>(position, surface.depth)?;
+        writer.write_at::<u32be>(position, surface.num_mips)?;
+        writer.write_at::<Format>(position, surface.format)?;
+        writer.write_at::<u32be>(position, 0)?; // aa
+        writer.write_at::<u32be>(position, surface.use_it)?;
+        writer.write_at::<u32be>(position, surface.image_size)?;
+        writer.write_at::<u32be>(position, surface.image_ptr)?;
+        writer.write_at::<u32be>(position, surface.mip_size)?;
+        writer.write_at::<u32be>(position, surface.mip_ptr)?;
+        #[allow(clippy::as_conversions, reason = "TileMode is repr(u32)")]
+        writer.write_at::<u32be>(position, surface.tile_mode as u32)?;
+        writer.write_at::<u32be>(position, surface.swizzle)?;
+        writer.write_at::<u32be>(position, surface.alignment)?;
+        writer.write_at::<u32be>(position, surface.pitch)?;
+        writer.write_at::<[u32be; 13]>(position, surface.mip_offsets)?;
+        // Texture view
+        writer.write_at::<u32be>(position, 0)?; // first mip
+        writer.write_at::<u32be>(position, surface.num_mips)?;
+        writer.write_at::<u32be>(position, 0)?; // first slice
+        writer.write_at::<u32be>(position, surface.depth)?; // number of slices
+        writer.write_at::<[u8; 4]>(position, COMP_SEL_RGBA)?;
+        // Texture registers, these are initialised by GX2InitTextureRegs when loading
+        writer.write_slice_at(position, &[0; 20])?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for Format {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        format: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<u32be>(position, u32::from(format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ExtendedColorType, ImageEncoder as _, Rgba, RgbaImage};
+
+    use wiiu_swizzle::TileMode;
+
+    use super::{surface_layout, GtxEncoder};
+    use crate::{types::Format, GtxDecoder};
+
+    fn test_image() -> RgbaImage {
+        RgbaImage::from_fn(64, 32, |x, y| {
+            let x = u8::try_from(x).unwrap();
+            let y = u8::try_from(y).unwrap();
+            Rgba([x * 4, y * 8, x ^ y, 0xFF - x])
+        })
+    }
+
+    #[test]
+    fn test_roundtrip_rgba8() {
+        let image = test_image();
+        let mut vec = Vec::new();
+        let mut encoder = GtxEncoder::new(&mut vec, 0);
+        encoder.set_format(Format::TcsR8G8B8A8Unorm);
+        encoder
+            .write_image(image.as_raw(), 64, 32, ExtendedColorType::Rgba8)
+            .unwrap();
+
+        let decoder = GtxDecoder::new(vec.as_slice(), &mut 0).unwrap();
+        let decoded = DynamicImage::from_decoder(decoder).unwrap().into_rgba8();
+        assert_eq!(image, decoded, "Lossless roundtrip does not match");
+    }
+
+    #[test]
+    fn test_roundtrip_bc3() {
+        let image = test_image();
+        let mut vec = Vec::new();
+        let encoder = GtxEncoder::new(&mut vec, 0);
+        encoder
+            .write_image(image.as_raw(), 64, 32, ExtendedColorType::Rgba8)
+            .unwrap();
+
+        let decoder = GtxDecoder::new(vec.as_slice(), &mut 0).unwrap();
+        let decoded = DynamicImage::from_decoder(decoder).unwrap().into_rgba8();
+        assert_eq!(decoded.dimensions(), (64, 32), "Dimensions do not match");
+    }
+
+    #[test]
+    fn test_surface_layout() {
+        assert_eq!(
+            surface_layout(64, 32, 4),
+            (TileMode::D2TiledThin1, 64, 0x2000),
+            "Surface that fills a macro tile should be 2D tiled"
+        );
+        assert_eq!(
+            surface_layout(33, 16, 8),
+            (TileMode::D2TiledThin1, 64, 0x1000),
+            "Pitch should be aligned to the macro tile width"
+        );
+        assert_eq!(
+            surface_layout(16, 8, 16),
+            (TileMode::D1TiledThin1, 16, 0x100),
+            "Small surface should be 1D tiled"
+        );
+        assert_eq!(
+            surface_layout(5, 5, 1),
+            (TileMode::D1TiledThin1, 32, 0x100),
+            "1D pitch should fill the pipe interleave"
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_rgba8_small() {
+        let image = RgbaImage::from_fn(12, 6, |x, y| {
+            let x = u8::try_from(x).unwrap();
+            let y = u8::try_from(y).unwrap();
+            Rgba([x * 16, y * 32, x ^ y, 0x80])
+        });
+        let mut vec = Vec::new();
+        let mut encoder = GtxEncoder::new(&mut vec, 0);
+        encoder.set_format(Format::TcsR8G8B8A8Unorm);
+        encoder
+            .write_image(image.as_raw(), 12, 6, ExtendedColorType::Rgba8)
+            .unwrap();
+
+        let decoder = GtxDecoder::new(vec.as_slice(), &mut 0).unwrap();
+        let decoded = DynamicImage::from_decoder(decoder).unwrap().into_rgba8();
+        assert_eq!(image, decoded, "Lossless 1D tiled roundtrip does not match");
+    }
+}
@@ -0,0 +1,86 @@
+//! Conversion of the packed formats to RGBA8, the other formats are handled by `texpresso` and
+//! [`dotstar_toolkit_utils::texture`]
+//!
+//! The packed GX2 formats are little endian and have the first component in the
+//! least significant bits.
+
+use dotstar_toolkit_utils::texture::expand;
+
+/// Decode a `R4G4` pixel
+pub fn r4g4(pixel: [u8; 1]) -> [u8; 4] {
+    let value = u32::from(pixel[0]);
+    [expand(value, 4), expand(value >> 4, 4), 0, u8::MAX]
+}
+
+/// Decode a `R5G6B5` pixel
+pub fn r5g6b5(pixel: [u8; 2]) -> [u8; 4] {
+    let value = u32::from(u16::from_le_bytes(pixel));
+    [
+        expand(value, 5),
+        expand(value >> 5, 6),
+        expand(value >> 11, 5),
+        u8::MAX,
+    ]
+}
+
+/// Decode a `R5G5B5A1` pixel
+pub fn r5g5b5a1(pixel: [u8; 2]) -> [u8; 4] {
+    let value = u32::from(u16::from_le_bytes(pixel));
+    [
+        expand(value, 5),
+        expand(value >> 5, 5),
+        expand(value >> 10, 5),
+        expand(value >> 15, 1),
+    ]
+}
+
+/// Decode a `R4G4B4A4` pixel
+pub fn r4g4b4a4(pixel: [u8; 2]) -> [u8; 4] {
+    let value = u32::from(u16::from_le_bytes(pixel));
+    [
+        expand(value, 4),
+        expand(value >> 4, 4),
+        expand(value >> 8, 4),
+        expand(value >> 12, 4),
+    ]
+}
+
+/// Decode a `R10G10B10A2` pixel
+pub fn r10g10b10a2(pixel: [u8; 4]) -> [u8; 4] {
+    let value = u32::from_le_bytes(pixel);
+    [
+        expand(value, 10),
+        expand(value >> 10, 10),
+        expand(value >> 20, 10),
+        expand(value >> 30, 2),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::texture::decode_pixels;
+
+    use super::*;
+
+    #[test]
+    fn test_packed_pixels() {
+        assert_eq!(r5g6b5([0xFF, 0xFF]), [255, 255, 255, 255], "White");
+        assert_eq!(r5g6b5([0x1F, 0x00]), [255, 0, 0, 255], "Red");
+        assert_eq!(r5g5b5a1([0x00, 0x80]), [0, 0, 0, 255], "Black with alpha");
+        assert_eq!(r4g4b4a4([0x0F, 0xF0]), [255, 0, 0, 255], "Red with alpha");
+        assert_eq!(
+            r10g10b10a2([0xFF, 0x03, 0, 0xC0]),
+            [255, 0, 0, 255],
+            "Red with alpha"
+        );
+        assert_eq!(r4g4([0xF0]), [0, 255, 0, 255], "Green");
+    }
+
+    #[test]
+    fn test_decode_pixels() {
+        let data = [0x1F, 0x00, 0xFF, 0xFF];
+        let mut buf = vec![0; 8];
+        decode_pixels(&data, &mut buf, r5g6b5).unwrap();
+        assert_eq!(buf, [255, 0, 0, 255, 255, 255, 255, 255], "Two pixels");
+    }
+}
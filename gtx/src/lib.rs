@@ -1,4 +1,8 @@
 mod decoder;
+mod encoder;
+mod formats;
 mod types;
 
 pub use decoder::GtxDecoder;
+pub use encoder::GtxEncoder;
+pub use types::Format;
@@ -1,8 +1,9 @@
 use std::{borrow::Cow, collections::VecDeque, fmt::Debug};
 
-use dotstar_toolkit_utils::bytes::read::ReadError;
 use wiiu_swizzle::TileMode;
 
+use crate::decoder::DecoderError;
+
 #[derive(Debug)]
 pub struct GtxRaw<'a> {
     pub header: GfdHeader,
@@ -44,12 +45,17 @@ impl GfdHeader {
 pub enum Block<'a> {
     Surface(Gx2Surface),
     DataLazy(Data),
+    /// Only used when writing
+    Data(Vec<u8>),
     Mip(Cow<'a, [u8]>),
+    /// Padding block, will be inserted automatically when writing
     Two,
 }
 
 impl Block<'_> {
     pub const MAGIC: u32 = 0x424C_4B7B;
+    /// Size of the block header
+    pub const HEADER_SIZE: u32 = 0x20;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -78,6 +84,11 @@ pub struct Gx2Surface {
     pub mip_offsets: [u32; 13],
 }
 
+impl Gx2Surface {
+    /// Size of the surface including the view and texture registers
+    pub const SIZE: u32 = 0x9C;
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum Format {
@@ -141,7 +152,7 @@ impl Format {
 }
 
 impl TryFrom<u32> for Format {
-    type Error = ReadError;
+    type Error = DecoderError;
 
     fn try_from(value: u32) -> Result<Self, Self::Error> {
         match value {
@@ -164,7 +175,7 @@ impl TryFrom<u32> for Format {
             0x234 => Ok(Self::TBc4Snorm),
             0x035 => Ok(Self::TBc5Unorm),
             0x235 => Ok(Self::TBc5Snorm),
-            _ => Err(ReadError::custom(format!("Unknown format!: 0x{value:x}"))),
+            _ => Err(DecoderError::UnknownTextureFormat(value)),
         }
     }
 }
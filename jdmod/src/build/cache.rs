@@ -0,0 +1,467 @@
+//! # Build cache
+//! Keeps the build files of every song in `.mod/cache`, so songs that did not change since the
+//! previous export don't have to be rebuilt.
+//!
+//! Every song gets its own directory in the cache, named after the song directory. It contains
+//! the generated files and a manifest with the key they were built with. The key is a hash of all
+//! the files in the song directory and the directories of the songs it uses (the source maps of a
+//! mashup and the songs of Dance Lab blocks), combined with the base key, which covers the jdmod
+//! version and the base game. If any of those change, the song is rebuilt.
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use anyhow::{anyhow, Error};
+use dotstar_toolkit_utils::vfs::{
+    native::NativeFs, VirtualFileSystem, VirtualPath, VirtualPathBuf,
+};
+use hipstr::HipStr;
+use serde::{Deserialize, Serialize};
+use ubiart_toolkit::utils::UniqueGameId;
+
+use super::{base_ipk_names, BuildFiles, BuildState};
+use crate::types::{
+    song::{DanceLab, Mashup, RelativeSongDirectoryTree},
+    DirectoryTree, RelativeDirectoryTree,
+};
+
+/// Describes the build files of a song in the cache
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    /// The key the files were built with
+    key: u64,
+    /// The map name of the song
+    map_name: String,
+    /// Paths of the generated files, the content is in the `files` directory
+    generated_files: Vec<String>,
+    /// The static files as `(new_path, orig_path)`
+    static_files: Vec<(String, String)>,
+}
+
+/// The parts of `song.json` that are needed to find the songs it uses
+#[derive(Deserialize)]
+struct SongReferences<'a> {
+    /// The map name of the song
+    #[serde(borrow)]
+    map_name: HipStr<'a>,
+    /// The source maps of the mashup segments
+    #[serde(borrow, default)]
+    mashup: Option<Mashup<'a>>,
+    /// The songs of the Dance Lab blocks
+    #[serde(borrow, default)]
+    dance_lab: Option<DanceLab<'a>>,
+}
+
+impl SongReferences<'_> {
+    /// The lowercase map names of the songs that are used by this song
+    fn map_names(&self) -> BTreeSet<String> {
+        let blocks = self
+            .dance_lab
+            .iter()
+            .flat_map(|dance_lab| &dance_lab.blocks);
+        let block_songs = blocks
+            .flat_map(|block| std::iter::once(&block.base).chain(&block.alternatives))
+            .map(|descriptor| &descriptor.song_name);
+        let source_maps = self
+            .mashup
+            .iter()
+            .flat_map(|mashup| &mashup.segments)
+            .map(|segment| &segment.source_map);
+        block_songs
+            .chain(source_maps)
+            // Empty blocks don't have a song
+            .filter(|map_name| !map_name.is_empty())
+            .map(|map_name| map_name.to_lowercase())
+            .collect()
+    }
+}
+
+/// Find the song directory of every map in `song_dirs`, by lowercase map name
+///
+/// Songs without a readable `song.json` are skipped, building them will report the error.
+///
+/// # Errors
+/// Will return an error if a `song.json` cannot be opened
+pub fn map_dirs(
+    native_vfs: &NativeFs,
+    song_dirs: &[&VirtualPath],
+) -> Result<HashMap<String, VirtualPathBuf>, Error> {
+    let mut map_dirs = HashMap::with_capacity(song_dirs.len());
+    for &dir in song_dirs {
+        let song_file = native_vfs.open(&dir.join("song.json"))?;
+        if let Ok(song) = serde_json::from_slice::<SongReferences>(&song_file) {
+            map_dirs.insert(song.map_name.to_lowercase(), dir.to_owned());
+        }
+    }
+    Ok(map_dirs)
+}
+
+/// Hash the jdmod version and the base game of `ugi`, used as the starting point for every song key
+///
+/// # Errors
+/// Will return an error if the config of the mod cannot be read
+pub fn base_key(
+    native_vfs: &NativeFs,
+    rel_tree: &RelativeDirectoryTree,
+    ugi: UniqueGameId,
+) -> Result<u64, Error> {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    let config = native_vfs.open(&rel_tree.dot_mod().join("config.json"))?;
+    config.hash(&mut hasher);
+    // The base game files are too big to hash every export, an update will change the size
+    for name in base_ipk_names(ugi) {
+        let size = native_vfs
+            .metadata(&rel_tree.base().join(name))
+            .map_or(0, |metadata| metadata.file_size());
+        size.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+/// Hash all files in the song directory and in the directories of the songs it uses
+///
+/// Songs that are not in the mod are part of the base game, which is covered by the base key.
+///
+/// # Errors
+/// Will return an error if the song directory cannot be read
+pub fn song_key(bs: &BuildState<'_>, dirs: &RelativeSongDirectoryTree) -> Result<u64, Error> {
+    let mut hasher = DefaultHasher::new();
+    bs.cache_key.hash(&mut hasher);
+    hash_dir(bs.native_vfs, dirs.song(), &mut hasher)?;
+
+    // A broken song.json fails the build, so there is nothing to cache
+    let song_file = bs.native_vfs.open(&dirs.song().join("song.json"))?;
+    if let Ok(song) = serde_json::from_slice::<SongReferences>(&song_file) {
+        for map_name in song.map_names() {
+            if let Some(dir) = bs
+                .map_dirs
+                .get(&map_name)
+                .filter(|dir| dir.as_str() != dirs.song().as_str())
+            {
+                map_name.hash(&mut hasher);
+                hash_dir(bs.native_vfs, dir, &mut hasher)?;
+            }
+        }
+    }
+    Ok(hasher.finish())
+}
+
+/// Hash the paths and contents of all files in `dir`
+fn hash_dir(
+    native_vfs: &NativeFs,
+    dir: &VirtualPath,
+    hasher: &mut DefaultHasher,
+) -> Result<(), Error> {
+    let mut paths: Vec<_> = native_vfs.walk_filesystem(dir)?.collect();
+    // Sort them, so the key does not depend on the order of the filesystem
+    paths.sort_unstable();
+    for path in paths {
+        path.as_str().hash(hasher);
+        let file = native_vfs.open(path)?;
+        file.hash(hasher);
+    }
+    Ok(())
+}
+
+/// Add the cached build files of the song to `bf` if they were built with `key`
+///
+/// Returns the map name of the song if the cached files were used. If the cache is missing,
+/// outdated, or incomplete nothing is added and `None` is returned.
+///
+/// # Errors
+/// Will return an error if the cached files conflict with files already in `bf`
+pub fn load(
+    bs: &BuildState<'_>,
+    bf: &mut BuildFiles,
+    dirs: &RelativeSongDirectoryTree,
+    key: u64,
+) -> Result<Option<String>, Error> {
+    let dir = bs.rel_tree.cache().join(entry_name(dirs)?);
+    let Ok(manifest_file) = bs.native_vfs.open(&dir.join("manifest.json")) else {
+        return Ok(None);
+    };
+    let Ok(manifest) = serde_json::from_slice::<Manifest>(&manifest_file) else {
+        return Ok(None);
+    };
+    if manifest.key != key {
+        return Ok(None);
+    }
+
+    // Check everything first, so nothing is added if a file is missing
+    let files_dir = dir.join("files");
+    let mut generated_files = Vec::with_capacity(manifest.generated_files.len());
+    for path in manifest.generated_files {
+        let cached_path = files_dir.join(path.trim_start_matches('/'));
+        if !bs.native_vfs.exists(&cached_path) {
+            return Ok(None);
+        }
+        generated_files.push((VirtualPathBuf::from(path), cached_path));
+    }
+
+    // The generated files are not read, they are added as links to the files in the cache
+    for (path, cached_path) in generated_files {
+        bf.static_files.add_file(cached_path, path)?;
+    }
+    for (new_path, orig_path) in manifest.static_files {
+        bf.static_files.add_file(
+            VirtualPathBuf::from(orig_path),
+            VirtualPathBuf::from(new_path),
+        )?;
+    }
+
+    Ok(Some(manifest.map_name))
+}
+
+/// Save the build files of the song in the cache
+///
+/// # Errors
+/// Will return an error if writing to the cache fails
+pub fn store(
+    bs: &BuildState<'_>,
+    bf: &BuildFiles,
+    dirs: &RelativeSongDirectoryTree,
+    key: u64,
+    map_name: &str,
+) -> Result<(), Error> {
+    let dir_tree = DirectoryTree::new(bs.native_vfs.root());
+    let dir = dir_tree.cache().join(entry_name(dirs)?);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(&dir)?;
+
+    let files_dir = dir.join("files");
+    let mut generated_files = Vec::new();
+    for (path, content) in bf.generated_files.files() {
+        let native_path = files_dir.join(path.as_str().trim_start_matches('/'));
+        if let Some(parent) = native_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&native_path, content)?;
+        generated_files.push(path.as_str().to_owned());
+    }
+    let static_files = bf
+        .static_files
+        .mappings()
+        .map(|(new_path, orig_path)| (new_path.as_str().to_owned(), orig_path.as_str().to_owned()))
+        .collect();
+
+    // The manifest is written last, so an interrupted store is never used
+    let manifest = Manifest {
+        key,
+        map_name: map_name.to_owned(),
+        generated_files,
+        static_files,
+    };
+    std::fs::write(dir.join("manifest.json"), serde_json::to_vec(&manifest)?)?;
+
+    Ok(())
+}
+
+/// Remove all songs from the cache that are not in `song_dirs`
+///
+/// # Errors
+/// Will return an error if reading or removing from the cache fails
+pub fn prune(dir_tree: &DirectoryTree, song_dirs: &[&str]) -> Result<(), Error> {
+    if !dir_tree.cache().exists() {
+        return Ok(());
+    }
+    for entry in dir_tree.cache().read_dir()? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if !name.to_str().is_some_and(|name| song_dirs.contains(&name)) {
+            std::fs::remove_dir_all(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// The name of the cache directory for the song
+fn entry_name(dirs: &RelativeSongDirectoryTree) -> Result<&str, Error> {
+    dirs.song()
+        .file_name()
+        .ok_or_else(|| anyhow!("Song directory {} has no name!", dirs.song()))
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use dotstar_toolkit_utils::vfs::{
+        layeredfs::OverlayFs, native::NativeFs, symlinkfs::SymlinkFs, vecfs::VecFs,
+        VirtualFileSystem as _, VirtualPath, VirtualPathBuf,
+    };
+    use ubiart_toolkit::utils::{Game, Platform, UniqueGameId};
+
+    use super::{base_key, load, map_dirs, song_key, store};
+    use crate::{
+        build::{BuildFiles, BuildState},
+        types::{song::RelativeSongDirectoryTree, DirectoryTree, RelativeDirectoryTree},
+    };
+
+    const UGI: UniqueGameId = UniqueGameId {
+        game: Game::JustDance2022,
+        platform: Platform::Nx,
+        id: 0,
+    };
+
+    /// Create a mod with one song and a config
+    fn mod_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let dir_tree = DirectoryTree::new(dir.path());
+        dir_tree.create_all().unwrap();
+        std::fs::write(dir_tree.dot_mod().join("config.json"), b"{}").unwrap();
+        std::fs::create_dir(dir_tree.songs().join("Song")).unwrap();
+        std::fs::write(dir_tree.songs().join("Song/song.json"), b"{}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_base_key_uses_platform_bundles() {
+        let dir = mod_dir();
+        let native_vfs = NativeFs::new(dir.path()).unwrap();
+        let rel_tree = RelativeDirectoryTree::new();
+        let wiiu = UniqueGameId {
+            platform: Platform::WiiU,
+            ..UGI
+        };
+
+        let nx_key = base_key(&native_vfs, &rel_tree, UGI).unwrap();
+        let wiiu_key = base_key(&native_vfs, &rel_tree, wiiu).unwrap();
+        std::fs::write(dir.path().join(".mod/base/bundle_wiiu.ipk"), [0; 16]).unwrap();
+        let native_vfs = NativeFs::new(dir.path()).unwrap();
+        assert_eq!(
+            base_key(&native_vfs, &rel_tree, UGI).unwrap(),
+            nx_key,
+            "The bundle of another platform should not change the key"
+        );
+        assert_ne!(
+            base_key(&native_vfs, &rel_tree, wiiu).unwrap(),
+            wiiu_key,
+            "A changed bundle should change the key"
+        );
+    }
+
+    #[test]
+    fn test_cache_hit_and_miss() {
+        let dir = mod_dir();
+        let native_vfs = NativeFs::new(dir.path()).unwrap();
+        let empty = VecFs::new();
+        let patched_base_vfs = OverlayFs::new(&empty, &empty);
+        let rel_tree = RelativeDirectoryTree::new();
+        let bs = BuildState {
+            patched_base_vfs: &patched_base_vfs,
+            native_vfs: &native_vfs,
+            cache_key: base_key(&native_vfs, &rel_tree, UGI).unwrap(),
+            map_dirs: HashMap::new(),
+            rel_tree,
+            ugi: UGI,
+            engine_version: 0,
+            jd_version: 2022,
+        };
+        let dirs = RelativeSongDirectoryTree::new(&bs.rel_tree.songs().join("Song"));
+        let new_build_files = || BuildFiles {
+            generated_files: VecFs::new(),
+            static_files: SymlinkFs::with_capacity(&native_vfs, 1),
+        };
+        let path = VirtualPathBuf::from("cache/song.ckd");
+
+        let key = song_key(&bs, &dirs).unwrap();
+        let mut bf = new_build_files();
+        assert!(
+            load(&bs, &mut bf, &dirs, key).unwrap().is_none(),
+            "Empty cache should miss"
+        );
+
+        bf.generated_files
+            .add_file(path.clone(), vec![1, 2, 3])
+            .unwrap();
+        store(&bs, &bf, &dirs, key, "Song").unwrap();
+
+        let mut bf = new_build_files();
+        assert_eq!(
+            load(&bs, &mut bf, &dirs, key).unwrap().as_deref(),
+            Some("Song"),
+            "Stored song should hit"
+        );
+        assert_eq!(
+            &*bf.static_files.open(&path).unwrap(),
+            &[1, 2, 3],
+            "Cached file has the wrong content"
+        );
+        assert!(
+            !bf.generated_files.exists(&path),
+            "Cached files should not be loaded into memory"
+        );
+
+        // Changing a file in the song directory changes the key
+        std::fs::write(dir.path().join("songs/Song/song.json"), b"{ }").unwrap();
+        let native_vfs = NativeFs::new(dir.path()).unwrap();
+        let bs = BuildState {
+            native_vfs: &native_vfs,
+            rel_tree: RelativeDirectoryTree::new(),
+            ..bs
+        };
+        let new_key = song_key(&bs, &dirs).unwrap();
+        assert_ne!(new_key, key, "Changed song should have a new key");
+        let mut bf = BuildFiles {
+            generated_files: VecFs::new(),
+            static_files: SymlinkFs::with_capacity(&native_vfs, 1),
+        };
+        assert!(
+            load(&bs, &mut bf, &dirs, new_key).unwrap().is_none(),
+            "Changed song should miss"
+        );
+        assert!(
+            !bf.generated_files.exists(&path) && !bf.static_files.exists(&path),
+            "Nothing should be added on a miss"
+        );
+    }
+
+    #[test]
+    fn test_referenced_song_changes_key() {
+        let dir = mod_dir();
+        let songs = dir.path().join("songs");
+        let mashup = r#"{"map_name": "Song", "mashup": {"segments": [
+            {"start_time": 0, "duration": 100, "coach_id": 0, "source_map": "other"}
+        ]}}"#;
+        std::fs::write(songs.join("Song/song.json"), mashup).unwrap();
+        for (name, song) in [("Other", "{\"map_name\": \"Other\"}"), ("Unused", "{}")] {
+            std::fs::create_dir(songs.join(name)).unwrap();
+            std::fs::write(songs.join(name).join("song.json"), song).unwrap();
+        }
+        let dirs = RelativeSongDirectoryTree::new(VirtualPath::new("songs/Song"));
+        let empty = VecFs::new();
+        let patched_base_vfs = OverlayFs::new(&empty, &empty);
+
+        // The key of the mashup with the mod in its current state
+        let key = || {
+            let native_vfs = NativeFs::new(dir.path()).unwrap();
+            let song_dirs = ["songs/Song", "songs/Other", "songs/Unused"].map(VirtualPath::new);
+            let bs = BuildState {
+                patched_base_vfs: &patched_base_vfs,
+                native_vfs: &native_vfs,
+                rel_tree: RelativeDirectoryTree::new(),
+                ugi: UGI,
+                engine_version: 0,
+                jd_version: 2022,
+                cache_key: 0,
+                map_dirs: map_dirs(&native_vfs, &song_dirs).unwrap(),
+            };
+            song_key(&bs, &dirs).unwrap()
+        };
+
+        let old_key = key();
+        std::fs::write(songs.join("Unused/moves.msm"), [1]).unwrap();
+        assert_eq!(
+            key(),
+            old_key,
+            "A song that is not used should not change the key"
+        );
+        std::fs::write(songs.join("Other/moves.msm"), [1]).unwrap();
+        assert_ne!(key(), old_key, "A changed source map should change the key");
+    }
+}
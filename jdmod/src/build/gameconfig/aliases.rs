@@ -2,14 +2,10 @@
 //! Build aliases
 use anyhow::Error;
 use dotstar_toolkit_utils::vfs::VirtualFileSystem;
-use ubiart_toolkit::{
-    cooked,
-    cooked::{isg::GameManagerConfigV22, json::LocalAliasesV2022},
-    utils::UniqueGameId,
-};
+use ubiart_toolkit::{cooked, cooked::json::LocalAliasesV2022};
 
 use crate::{
-    build::{BuildFiles, BuildState},
+    build::{gameconfig::GameConfig, BuildFiles, BuildState},
     types::gameconfig::{aliases::Aliases, gachacontent::GachaItem},
     utils::cook_path,
 };
@@ -18,7 +14,7 @@ use crate::{
 pub fn build(
     bs: &BuildState,
     bf: &mut BuildFiles,
-    gameconfig: &mut GameManagerConfigV22,
+    gameconfig: &mut GameConfig<'_, '_>,
     gacha_items: &mut Vec<GachaItem>,
 ) -> Result<(), Error> {
     let aliases_file = bs
@@ -26,7 +22,7 @@ pub fn build(
         .open(&bs.rel_tree.config().join("aliases.json"))?;
     let aliases = serde_json::from_slice::<Aliases>(&aliases_file)?.into_owned();
 
-    let aliasesobjectives = &mut gameconfig.aliasesobjectives;
+    let aliasesobjectives = &mut *gameconfig.aliasesobjectives;
     aliasesobjectives.clear();
 
     let mut aliases_vec = Vec::with_capacity(aliases.aliases.len());
@@ -52,7 +48,7 @@ pub fn build(
 
     let local_aliases_vec = cooked::json::create_vec_with_capacity_hint(&local_aliases, 230_000)?;
     bf.generated_files.add_file(
-        cook_path(&gameconfig.alias_db_path, UniqueGameId::NX2022)?.into(),
+        cook_path(gameconfig.alias_db_path, bs.ugi)?.into(),
         local_aliases_vec,
     )?;
 
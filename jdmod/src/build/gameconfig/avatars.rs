@@ -13,10 +13,9 @@ use ubiart_toolkit::{
     cooked,
     cooked::{
         isc::AvatarDesc,
-        isg::GameManagerConfigV22,
         tpl::types::{AvatarDescription, AvatarDescription2022},
     },
-    utils::{SplitPath, UniqueGameId},
+    utils::SplitPath,
 };
 
 use crate::{
@@ -46,7 +45,7 @@ pub fn generate_avatar_id() -> u32 {
 pub fn build(
     bs: &BuildState,
     bf: &mut BuildFiles,
-    gameconfig: &mut GameManagerConfigV22,
+    gameconfig: &mut GameConfig<'_, '_>,
     gacha_items: &mut Vec<GachaItem>,
 ) -> Result<(), Error> {
     let avatars_file = bs
@@ -63,7 +62,7 @@ pub fn build(
         id_map.insert(name.clone(), id);
     }
 
-    let avatarsobjectives = &mut gameconfig.avatarsobjectives;
+    let avatarsobjectives = &mut *gameconfig.avatarsobjectives;
     avatarsobjectives.clear();
     let mut scene_actors = Vec::with_capacity(avatars.len());
 
@@ -82,11 +81,8 @@ pub fn build(
             bs.native_vfs,
             &bs.rel_tree.avatars().join(avatar.image_path.as_str()),
         )?;
-        let to = cook_path(
-            &format!("world/avatars/{id:04}/avatar.png"),
-            UniqueGameId::NX2022,
-        )?;
-        let cooked_image_vec = cooked::png::create_vec(cooked_image)?;
+        let to = cook_path(&format!("world/avatars/{id:04}/avatar.png"), bs.ugi)?;
+        let cooked_image_vec = cooked::png::create_vec_with_ugi(cooked_image, bs.ugi)?;
 
         // Add the phone image for copying
         let phone_image = format!("world/avatars/{id:04}/avatar_phone.png");
@@ -141,20 +137,14 @@ pub fn build(
         let desc_tpl_vec = cooked::json::create_vec_with_capacity_hint(&tpl, 3000)?;
 
         bf.generated_files.add_file(
-            cook_path(
-                &format!("world/avatars/{id:04}/desc.tpl"),
-                UniqueGameId::NX2022,
-            )?
-            .into(),
+            cook_path(&format!("world/avatars/{id:04}/desc.tpl"), bs.ugi)?.into(),
             desc_tpl_vec,
         )?;
 
         bf.generated_files.add_file(to.into(), cooked_image_vec)?;
 
-        bf.generated_files.add_file(
-            cook_path(&actor_path, UniqueGameId::NX2022)?.into(),
-            actor_vec,
-        )?;
+        bf.generated_files
+            .add_file(cook_path(&actor_path, bs.ugi)?.into(), actor_vec)?;
 
         let scene = desc_scene(id);
         scene_actors.push(scene);
@@ -163,7 +153,7 @@ pub fn build(
     let avatardb_scene_vec =
         cooked::isc::create_vec_with_capacity_hint(&avatardb_scene(bs, scene_actors), 940_000)?;
     bf.generated_files.add_file(
-        cook_path(&gameconfig.avatardb_scene, UniqueGameId::NX2022)?.into(),
+        cook_path(gameconfig.avatardb_scene, bs.ugi)?.into(),
         avatardb_scene_vec,
     )?;
 
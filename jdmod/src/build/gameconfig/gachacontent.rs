@@ -2,10 +2,10 @@
 //! Build the gacha machine
 use anyhow::Error;
 use dotstar_toolkit_utils::vfs::VirtualFileSystem;
-use ubiart_toolkit::{cooked, cooked::isg::GameManagerConfigV22, utils::UniqueGameId};
+use ubiart_toolkit::cooked;
 
 use crate::{
-    build::{BuildFiles, BuildState},
+    build::{gameconfig::GameConfig, BuildFiles, BuildState},
     types::gameconfig::gachacontent::{GachaConfig, GachaItem},
     utils::cook_path,
 };
@@ -14,7 +14,7 @@ use crate::{
 pub fn build(
     bs: &BuildState,
     bf: &mut BuildFiles,
-    gameconfig: &mut GameManagerConfigV22<'_>,
+    gameconfig: &mut GameConfig<'_, '_>,
     gacha_items: Vec<GachaItem>,
 ) -> Result<(), Error> {
     let gacha_config_file = bs
@@ -38,11 +38,7 @@ pub fn build(
     let gacha_content_database_vec =
         cooked::json::create_vec_with_capacity_hint(&gacha_content_database, 16_000)?;
     bf.generated_files.add_file(
-        cook_path(
-            &gameconfig.config_files_path.gachacontent,
-            UniqueGameId::NX2022,
-        )?
-        .into(),
+        cook_path(gameconfig.gachacontent_path, bs.ugi)?.into(),
         gacha_content_database_vec,
     )?;
 
@@ -3,18 +3,18 @@
 use anyhow::Error;
 use dotstar_toolkit_utils::vfs::VirtualFileSystem;
 use ownable::traits::IntoOwned;
-use ubiart_toolkit::cooked::isg::{GameManagerConfigV22, MapsGoals};
+use ubiart_toolkit::cooked::isg::MapsGoals;
 
-use crate::build::BuildState;
+use crate::build::{gameconfig::GameConfig, BuildState};
 
 /// Build map goals
-pub fn build(bs: &BuildState, gameconfig: &mut GameManagerConfigV22) -> Result<(), Error> {
+pub fn build(bs: &BuildState, gameconfig: &mut GameConfig<'_, '_>) -> Result<(), Error> {
     let maps_goals_file = bs
         .native_vfs
         .open(&bs.rel_tree.config().join("maps_goals.json"))?;
     let maps_goals = serde_json::from_slice::<MapsGoals>(&maps_goals_file)?.into_owned();
 
-    gameconfig.maps_goals = maps_goals;
+    *gameconfig.maps_goals = maps_goals;
 
     Ok(())
 }
@@ -2,10 +2,18 @@
 //! Build all gameconfig related components
 use std::collections::HashMap;
 
-use anyhow::{anyhow, Error};
+use anyhow::{anyhow, bail, Error};
 use dotstar_toolkit_utils::{bytes::read::BinaryDeserializeExt as _, vfs::VirtualFileSystem};
+use hipstr::HipStr;
 use ubiart_toolkit::{
-    alias8::Alias8, cooked, cooked::isg::GameManagerConfigV22, utils::UniqueGameId,
+    alias8::Alias8,
+    cooked,
+    cooked::isg::{
+        AliasesObjectives, AvatarsObjectives, GachaConfig, GameManagerConfigV20,
+        GameManagerConfigV21, GameManagerConfigV22, MapsGoals, MapsObjectives,
+        OfflineRecommendation, ScheduledQuestSetup, SongsSearchTags,
+    },
+    utils::Game,
 };
 
 use super::{BuildFiles, BuildState};
@@ -23,6 +31,67 @@ mod portraitborders;
 mod scheduled_quests;
 mod search_labels;
 
+/// The parts of the gameconfig that are used during the build
+///
+/// Just Dance 2020, 2021, and 2022 use the same types for these fields, so the build only needs to
+/// be written once.
+pub struct GameConfig<'b, 'a> {
+    /// Path to the alias database
+    pub alias_db_path: &'b str,
+    /// Path to the avatar database scene
+    pub avatardb_scene: &'b str,
+    /// Path to the carousel rules
+    pub carousel_rules: &'b str,
+    /// Path to the gacha content database
+    pub gachacontent_path: &'b str,
+    /// Path to the playlist database
+    pub playlist_path: &'b str,
+    /// Path to the portraitborder database
+    pub portraitborders_path: &'b str,
+    /// Objectives for unlocking aliases
+    pub aliasesobjectives: &'b mut AliasesObjectives<'a>,
+    /// Objectives for unlocking avatars
+    pub avatarsobjectives: &'b mut AvatarsObjectives<'a>,
+    /// Gacha machine configuration
+    pub gachaconfig: &'b mut GachaConfig<'a>,
+    /// Goals for maps
+    pub maps_goals: &'b mut MapsGoals<'a>,
+    /// Objectives for maps
+    pub mapsobjectives: &'b mut MapsObjectives<'a>,
+    /// Maps recommended when offline
+    pub offline_recommendation: &'b mut OfflineRecommendation<'a>,
+    /// Maps that can be redeemed
+    pub redeem_maps: &'b mut HashMap<HipStr<'a>, Vec<HipStr<'a>>>,
+    /// Scheduled quests configuration
+    pub scheduled_quest_setup: &'b mut ScheduledQuestSetup<'a>,
+    /// Search labels for maps
+    pub search_labels: &'b mut SongsSearchTags<'a>,
+}
+
+/// Create a [`GameConfig`] from a `GameManagerConfigV20`, `GameManagerConfigV21`, or
+/// `GameManagerConfigV22`
+macro_rules! gameconfig {
+    ($gameconfig:ident) => {
+        GameConfig {
+            alias_db_path: &$gameconfig.alias_db_path,
+            avatardb_scene: &$gameconfig.avatardb_scene,
+            carousel_rules: &$gameconfig.carousel_rules,
+            gachacontent_path: &$gameconfig.config_files_path.gachacontent,
+            playlist_path: &$gameconfig.config_files_path.playlist,
+            portraitborders_path: &$gameconfig.config_files_path.portraitborders,
+            aliasesobjectives: &mut $gameconfig.aliasesobjectives,
+            avatarsobjectives: &mut $gameconfig.avatarsobjectives,
+            gachaconfig: &mut $gameconfig.gachaconfig,
+            maps_goals: &mut $gameconfig.maps_goals,
+            mapsobjectives: &mut $gameconfig.mapsobjectives,
+            offline_recommendation: &mut $gameconfig.offline_recommendation,
+            redeem_maps: &mut $gameconfig.redeem_maps,
+            scheduled_quest_setup: &mut $gameconfig.scheduled_quest_setup,
+            search_labels: &mut $gameconfig.search_labels,
+        }
+    };
+}
+
 /// Build all gameconfig related components
 pub fn build(bs: &BuildState<'_>, bf: &mut BuildFiles) -> Result<(), Error> {
     println!("Building gameconfig...");
@@ -37,11 +106,41 @@ pub fn build(bs: &BuildState<'_>, bf: &mut BuildFiles) -> Result<(), Error> {
         &aliases
             .get_path_for_alias("gameconfig")
             .ok_or_else(|| anyhow!("gameconfig path not found in common.alias8!"))?,
-        UniqueGameId::NX2022,
+        bs.ugi,
     )?;
     let gameconfig_file = bs.patched_base_vfs.open(gameconfig_path.as_ref())?;
-    let mut gameconfig: GameManagerConfigV22 = cooked::isg::parse(&gameconfig_file, false)?;
 
+    let gameconfig_vec = match bs.ugi.game {
+        Game::JustDance2020 => {
+            let mut gameconfig: GameManagerConfigV20 = cooked::isg::parse(&gameconfig_file, false)?;
+            build_v20v22(bs, bf, gameconfig!(gameconfig))?;
+            cooked::json::create_vec(&gameconfig)?
+        }
+        Game::JustDance2021 => {
+            let mut gameconfig: GameManagerConfigV21 = cooked::isg::parse(&gameconfig_file, false)?;
+            build_v20v22(bs, bf, gameconfig!(gameconfig))?;
+            cooked::json::create_vec(&gameconfig)?
+        }
+        Game::JustDance2022 => {
+            let mut gameconfig: GameManagerConfigV22 = cooked::isg::parse(&gameconfig_file, false)?;
+            build_v20v22(bs, bf, gameconfig!(gameconfig))?;
+            cooked::json::create_vec(&gameconfig)?
+        }
+        _ => bail!("Building the gameconfig for {} is not supported!", bs.ugi),
+    };
+
+    bf.generated_files
+        .add_file(gameconfig_path.into(), gameconfig_vec)?;
+
+    Ok(())
+}
+
+/// Build all gameconfig related components (Just Dance 2020-2022)
+fn build_v20v22(
+    bs: &BuildState<'_>,
+    bf: &mut BuildFiles,
+    mut gameconfig: GameConfig<'_, '_>,
+) -> Result<(), Error> {
     scheduled_quests::build(bs, bf, &mut gameconfig)?;
     objectives::build(bs, bf)?;
     search_labels::build(bs, &mut gameconfig)?;
@@ -57,11 +156,7 @@ pub fn build(bs: &BuildState<'_>, bf: &mut BuildFiles) -> Result<(), Error> {
 
     gachacontent::build(bs, bf, &mut gameconfig, gacha_items)?;
 
-    gameconfig.redeem_maps = HashMap::new();
-
-    let gameconfig_vec = cooked::json::create_vec(&gameconfig)?;
-    bf.generated_files
-        .add_file(gameconfig_path.into(), gameconfig_vec)?;
+    *gameconfig.redeem_maps = HashMap::new();
 
     Ok(())
 }
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use anyhow::Error;
 use dotstar_toolkit_utils::vfs::VirtualFileSystem;
 use hipstr::HipStr;
-use ubiart_toolkit::{cooked, utils::UniqueGameId};
+use ubiart_toolkit::cooked;
 
 use crate::{
     build::{BuildFiles, BuildState},
@@ -31,7 +31,7 @@ pub fn build(bs: &BuildState, bf: &mut BuildFiles) -> Result<(), Error> {
 
     let objective_database_vec = cooked::json::create_vec(&objective_database)?;
     bf.generated_files.add_file(
-        cook_path("enginedata/gameconfig/objectives.isg", UniqueGameId::NX2022)?.into(),
+        cook_path("enginedata/gameconfig/objectives.isg", bs.ugi)?.into(),
         objective_database_vec,
     )?;
 
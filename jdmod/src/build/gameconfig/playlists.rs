@@ -6,14 +6,10 @@ use anyhow::{anyhow, Error};
 use dotstar_toolkit_utils::vfs::{VirtualFileSystem, VirtualPath};
 use hipstr::HipStr;
 use test_eq::test_eq;
-use ubiart_toolkit::{
-    cooked,
-    cooked::{isg::GameManagerConfigV22, json::CarouselRules},
-    utils::{SplitPath, UniqueGameId},
-};
+use ubiart_toolkit::{cooked, cooked::json::CarouselRules, utils::SplitPath};
 
 use crate::{
-    build::{BuildFiles, BuildState},
+    build::{gameconfig::GameConfig, BuildFiles, BuildState},
     types::gameconfig::playlists::Playlist,
     utils::{cook_path, encode_texture},
 };
@@ -22,7 +18,7 @@ use crate::{
 pub fn build(
     bs: &BuildState,
     bf: &mut BuildFiles,
-    gameconfig: &GameManagerConfigV22<'_>,
+    gameconfig: &GameConfig<'_, '_>,
 ) -> Result<(), Error> {
     let saved_playlists_file = bs
         .native_vfs
@@ -52,15 +48,15 @@ pub fn build(
 
         let cooked_cover =
             encode_texture(bs.native_vfs, &bs.rel_tree.playlists().join(cover.as_str()))?;
-        let cooked_cover_vec = cooked::png::create_vec(cooked_cover)?;
+        let cooked_cover_vec = cooked::png::create_vec_with_ugi(cooked_cover, bs.ugi)?;
         bf.generated_files.add_file(
-            cook_path(offline_playlist.cover_path.as_str(), UniqueGameId::NX2022)?.into(),
+            cook_path(offline_playlist.cover_path.as_str(), bs.ugi)?.into(),
             cover_actor_vec,
         )?;
         bf.generated_files.add_file(
             cook_path(
                 &format!("world/ui/textures/covers/playlists_offline/{tga}"),
-                UniqueGameId::NX2022,
+                bs.ugi,
             )?
             .into(),
             cooked_cover_vec,
@@ -76,11 +72,11 @@ pub fn build(
 
     let template_vec = cooked::json::create_vec(&template)?;
     bf.generated_files.add_file(
-        cook_path(&gameconfig.config_files_path.playlist, UniqueGameId::NX2022)?.into(),
+        cook_path(gameconfig.playlist_path, bs.ugi)?.into(),
         template_vec,
     )?;
 
-    build_carousel(bs, bf, requests, &gameconfig.carousel_rules)?;
+    build_carousel(bs, bf, requests, gameconfig.carousel_rules)?;
 
     Ok(())
 }
@@ -92,15 +88,16 @@ fn build_carousel(
     mut requests: Vec<cooked::json::CarouselRequestDesc<'_>>,
     carousel_rules: &str,
 ) -> Result<(), Error> {
-    let carousel_rules_path = cook_path(carousel_rules, UniqueGameId::NX2022)?;
+    let carousel_rules_path = cook_path(carousel_rules, bs.ugi)?;
     let template_file = bs.patched_base_vfs.open(carousel_rules_path.as_ref())?;
     let mut carousel_rules: CarouselRules = cooked::json::parse(&template_file, false)?;
 
     // Remove existing playlist carousels except for 'Recommended for me',
     // then remove any playlist that's in 'Recommended for me' from requests so that there are no dupes.
+    let rule_name = format!("/jd{}-playlists", bs.jd_version);
     let carousel_rule = carousel_rules
         .rules
-        .get_mut("/jd2022-playlists")
+        .get_mut(rule_name.as_str())
         .ok_or_else(|| anyhow!("Playlist rule not found in carousel"))?;
     carousel_rule
         .categories
@@ -4,10 +4,10 @@ use std::collections::HashMap;
 
 use anyhow::Error;
 use dotstar_toolkit_utils::vfs::{VirtualFileSystem, VirtualPathBuf};
-use ubiart_toolkit::{cooked, cooked::isg::GameManagerConfigV22, utils::UniqueGameId};
+use ubiart_toolkit::cooked;
 
 use crate::{
-    build::{BuildFiles, BuildState},
+    build::{gameconfig::GameConfig, BuildFiles, BuildState},
     types::gameconfig::{gachacontent::GachaItem, portraitborders::PortraitBorder},
     utils::{cook_path, encode_texture},
 };
@@ -16,7 +16,7 @@ use crate::{
 pub fn build(
     bs: &BuildState,
     bf: &mut BuildFiles,
-    gameconfig: &GameManagerConfigV22<'_>,
+    gameconfig: &GameConfig<'_, '_>,
     gacha_items: &mut Vec<GachaItem>,
 ) -> Result<(), Error> {
     let saved_portraitborders_file = bs
@@ -39,9 +39,10 @@ pub fn build(
                 .portraitborders()
                 .join(pb.background_texture_path.as_str()),
         )?;
-        let background_texture_vec = cooked::png::create_vec(background_texture_encoded)?;
+        let background_texture_vec =
+            cooked::png::create_vec_with_ugi(background_texture_encoded, bs.ugi)?;
         bf.generated_files.add_file(
-            cook_path(&desc.background_texture_path, UniqueGameId::NX2022)?.into(),
+            cook_path(&desc.background_texture_path, bs.ugi)?.into(),
             background_texture_vec,
         )?;
 
@@ -52,9 +53,10 @@ pub fn build(
                     .portraitborders()
                     .join(foreground_texture_path.as_str()),
             )?;
-            let foreground_texture_vec = cooked::png::create_vec(foreground_texture_encoded)?;
+            let foreground_texture_vec =
+                cooked::png::create_vec_with_ugi(foreground_texture_encoded, bs.ugi)?;
             bf.generated_files.add_file(
-                cook_path(&desc.foreground_texture_path, UniqueGameId::NX2022)?.into(),
+                cook_path(&desc.foreground_texture_path, bs.ugi)?.into(),
                 foreground_texture_vec,
             )?;
         }
@@ -85,11 +87,7 @@ pub fn build(
 
     let template_vec = cooked::json::create_vec(&template)?;
     bf.generated_files.add_file(
-        cook_path(
-            &gameconfig.config_files_path.portraitborders,
-            UniqueGameId::NX2022,
-        )?
-        .into(),
+        cook_path(gameconfig.portraitborders_path, bs.ugi)?.into(),
         template_vec,
     )?;
 
@@ -2,10 +2,10 @@
 //! Build the scheduled quests
 use anyhow::Error;
 use dotstar_toolkit_utils::vfs::VirtualFileSystem;
-use ubiart_toolkit::{cooked, cooked::isg::GameManagerConfigV22, utils::UniqueGameId};
+use ubiart_toolkit::cooked;
 
 use crate::{
-    build::{BuildFiles, BuildState},
+    build::{gameconfig::GameConfig, BuildFiles, BuildState},
     types::gameconfig::scheduled_quests::ScheduledQuests,
     utils::cook_path,
 };
@@ -14,7 +14,7 @@ use crate::{
 pub fn build(
     bs: &BuildState,
     bf: &mut BuildFiles,
-    gameconfig: &mut GameManagerConfigV22,
+    gameconfig: &mut GameConfig<'_, '_>,
 ) -> Result<(), Error> {
     let quest_config_file = bs
         .native_vfs
@@ -31,7 +31,7 @@ pub fn build(
     let first_discovery_quest_id = discovery_quest.id;
     scheduled_quests.push(discovery_quest);
 
-    let setup = &mut gameconfig.scheduled_quest_setup;
+    let setup = &mut *gameconfig.scheduled_quest_setup;
     setup.minimum_score = quest_config.minimum_score;
     setup.session_count_until_discovery_kill = quest_config.session_count_until_discovery_kill;
     setup.session_count_until_first_discovery_kill =
@@ -52,11 +52,7 @@ pub fn build(
 
     let quest_database_vec = cooked::json::create_vec(&quest_database)?;
     bf.generated_files.add_file(
-        cook_path(
-            "enginedata/gameconfig/scheduledquests.isg",
-            UniqueGameId::NX2022,
-        )?
-        .into(),
+        cook_path("enginedata/gameconfig/scheduledquests.isg", bs.ugi)?.into(),
         quest_database_vec,
     )?;
 
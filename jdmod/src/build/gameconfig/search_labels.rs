@@ -6,12 +6,15 @@ use anyhow::Error;
 use dotstar_toolkit_utils::vfs::VirtualFileSystem;
 use hipstr::HipStr;
 use ownable::traits::IntoOwned;
-use ubiart_toolkit::{cooked, cooked::isg::GameManagerConfigV22};
+use ubiart_toolkit::cooked;
 
-use crate::{build::BuildState, types::gameconfig::search_labels::SearchLabel};
+use crate::{
+    build::{gameconfig::GameConfig, BuildState},
+    types::gameconfig::search_labels::SearchLabel,
+};
 
 /// Build the search labels
-pub fn build(bs: &BuildState, gameconfig: &mut GameManagerConfigV22<'_>) -> Result<(), Error> {
+pub fn build(bs: &BuildState, gameconfig: &mut GameConfig<'_, '_>) -> Result<(), Error> {
     let search_labels_file = bs
         .native_vfs
         .open(&bs.rel_tree.config().join("search_labels.json"))?;
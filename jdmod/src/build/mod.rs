@@ -1,9 +1,12 @@
 //! # Build
 //! Logic for building the mod
 
-use anyhow::Error;
+use std::collections::HashMap;
+
+use anyhow::{bail, Error};
 use dotstar_toolkit_utils::vfs::{
     layeredfs::OverlayFs, native::NativeFs, symlinkfs::SymlinkFs, vecfs::VecFs, VirtualFileSystem,
+    VirtualPathBuf,
 };
 use hipstr::HipStr;
 use ubiart_toolkit::{
@@ -12,27 +15,55 @@ use ubiart_toolkit::{
         isc::{SongDatabase, SongDesc},
         sgs,
     },
-    utils::{Platform, UniqueGameId},
+    secure_fat::bundle_name_to_filename,
+    utils::{Game, Platform, UniqueGameId},
 };
 
 use crate::{types::RelativeDirectoryTree, utils::cook_path};
 
+pub mod cache;
 pub mod gameconfig;
 pub mod localisation;
 pub mod song;
 
 /// State that is used a lot during the build
 pub struct BuildState<'a> {
-    /// Filesystem containing the files in bundle_nx.ipk and patch_nx.ipk
+    /// Filesystem containing the files in the bundle and patch of the base game
     pub patched_base_vfs: &'a OverlayFs<'a>,
     /// Vfs with mod directory as the root
     pub native_vfs: &'a NativeFs,
     /// The directory tree
     pub rel_tree: RelativeDirectoryTree,
-    /// Export platform
-    pub platform: Platform,
+    /// Export game and platform
+    pub ugi: UniqueGameId,
     /// Export Engine version
     pub engine_version: u32,
+    /// The Just Dance version of the export game (like 2022)
+    pub jd_version: u32,
+    /// Hash of the jdmod version and the base game, see [`cache::base_key`]
+    pub cache_key: u64,
+    /// The song directories by lowercase map name, see [`cache::map_dirs`]
+    pub map_dirs: HashMap<String, VirtualPathBuf>,
+}
+
+/// Get the Just Dance version (like 2022) of `ugi`
+///
+/// # Errors
+/// Will return an error if the mod cannot be exported to `ugi`
+pub fn jd_version(ugi: UniqueGameId) -> Result<u32, Error> {
+    match (ugi.game, ugi.platform) {
+        (Game::JustDance2020, Platform::Nx) => Ok(2020),
+        (Game::JustDance2021, Platform::Nx) => Ok(2021),
+        (Game::JustDance2022, Platform::Nx) => Ok(2022),
+        _ => bail!("Exporting to {ugi} is not supported!"),
+    }
+}
+
+/// The filenames of the bundle and patch of the base game of `ugi`, which are stored in
+/// `.mod/base`
+#[must_use]
+pub fn base_ipk_names(ugi: UniqueGameId) -> [String; 2] {
+    ["bundle", "patch"].map(|name| bundle_name_to_filename(name, ugi))
 }
 
 /// Files collected during the build
@@ -64,7 +95,7 @@ pub fn song_database(
     song_names: &[String],
 ) -> Result<(), Error> {
     println!("Building song database...");
-    let sgscontainer_path = cook_path("sgscontainer", UniqueGameId::NX2022)?;
+    let sgscontainer_path = cook_path("sgscontainer", bs.ugi)?;
     let sgscontainer_file = bs.patched_base_vfs.open(sgscontainer_path.as_ref())?;
     let mut sgscontainer = sgs::parse(&sgscontainer_file)?.into_scene_config_manager()?;
 
@@ -165,36 +196,58 @@ pub fn song_database(
         .add_file(sgscontainer_path.into(), sgscontainer_vec)?;
 
     bf.generated_files.add_file(
-        cook_path(
-            "world/skuscenes/skuscene_maps_nx_all.sgs",
-            UniqueGameId::NX2022,
-        )?
-        .into(),
+        cook_path("world/skuscenes/skuscene_maps_nx_all.sgs", bs.ugi)?.into(),
         skuscene_maps_sgs_vec.clone(),
     )?;
     bf.generated_files.add_file(
-        cook_path(
-            "world/skuscenes/skuscene_maps_pc_all.sgs",
-            UniqueGameId::NX2022,
-        )?
-        .into(),
+        cook_path("world/skuscenes/skuscene_maps_pc_all.sgs", bs.ugi)?.into(),
         skuscene_maps_sgs_vec,
     )?;
 
     // Create world/skuscenes/skuscene_maps_{nx,pc}_all.isc
-    let isc_skuscene_nx = isc::Root {
+    let isc_skuscene_nx = song_database_scene(
+        bs.engine_version,
+        bs.jd_version,
+        actors,
+        isc_coverflow_sku_songs,
+    );
+
+    let skuscene_maps_isc_vec = isc::create_vec_with_capacity_hint(&isc_skuscene_nx, 230_000)?;
+
+    bf.generated_files.add_file(
+        cook_path("world/skuscenes/skuscene_maps_nx_all.isc", bs.ugi)?.into(),
+        skuscene_maps_isc_vec.clone(),
+    )?;
+    bf.generated_files.add_file(
+        cook_path("world/skuscenes/skuscene_maps_pc_all.isc", bs.ugi)?.into(),
+        skuscene_maps_isc_vec,
+    )?;
+
+    Ok(())
+}
+
+/// Build the song database scene with `actors` and the covers in `coverflow_sku_songs`
+///
+/// Just Dance 2020, 2021, and 2022 use the same layout, only the sku contains the version.
+fn song_database_scene<'a>(
+    engine_version: u32,
+    jd_version: u32,
+    actors: Vec<isc::WrappedActors<'a>>,
+    coverflow_sku_songs: Vec<isc::CoverflowSkuSongs<'a>>,
+) -> isc::Root<'a> {
+    isc::Root {
         scene: isc::Scene {
-            engine_version: bs.engine_version,
+            engine_version,
             actors,
             scene_configs: isc::SceneConfigs {
                 active_scene_config: 0,
                 jd_scene_config: vec![isc::WrappedJdSceneConfig::SongDatabase(
                     isc::SongDatabaseSceneConfig {
-                        sku: HipStr::borrowed("jd2022-nx-all"),
+                        sku: HipStr::from(format!("jd{jd_version}-nx-all")),
                         rating_ui: HipStr::borrowed(
                             "world/ui/screens/boot_warning/boot_warning_esrb.isc",
                         ),
-                        coverflow_sku_songs: isc_coverflow_sku_songs,
+                        coverflow_sku_songs,
                         ..Default::default()
                     }
                     .into(),
@@ -203,26 +256,45 @@ pub fn song_database(
             .into(),
             ..Default::default()
         },
-    };
+    }
+}
 
-    let skuscene_maps_isc_vec = isc::create_vec_with_capacity_hint(&isc_skuscene_nx, 230_000)?;
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use hipstr::HipStr;
+    use ubiart_toolkit::cooked::isc;
 
-    bf.generated_files.add_file(
-        cook_path(
-            "world/skuscenes/skuscene_maps_nx_all.isc",
-            UniqueGameId::NX2022,
-        )?
-        .into(),
-        skuscene_maps_isc_vec.clone(),
-    )?;
-    bf.generated_files.add_file(
-        cook_path(
-            "world/skuscenes/skuscene_maps_pc_all.isc",
-            UniqueGameId::NX2022,
-        )?
-        .into(),
-        skuscene_maps_isc_vec,
-    )?;
+    use super::song_database_scene;
 
-    Ok(())
+    /// The song database for `jd_version` with one song, with the sku replaced by `{sku}`
+    fn song_database(jd_version: u32) -> String {
+        let actors = vec![isc::WrappedActors::Actor(isc::WrappedActor {
+            actor: Box::new(isc::Actor {
+                userfriendly: HipStr::borrowed("Song"),
+                lua: HipStr::borrowed("world/maps/song/songdesc.tpl"),
+                ..Default::default()
+            }),
+        })];
+        let covers = vec![isc::CoverflowSkuSongs {
+            coverflow_song: isc::CoverflowSong {
+                name: HipStr::borrowed("Song"),
+                cover_path: HipStr::borrowed(
+                    "world/maps/song/menuart/actors/song_cover_generic.act",
+                ),
+            },
+        }];
+        let root = song_database_scene(0x0025_3FD7, jd_version, actors, covers);
+        let xml = String::from_utf8(isc::create_vec(&root).unwrap()).unwrap();
+        let sku = format!("jd{jd_version}-nx-all");
+        assert!(xml.contains(&sku), "The sku should contain the version");
+        xml.replace(&sku, "{sku}")
+    }
+
+    #[test]
+    fn test_song_database_layout() {
+        let jd2022 = song_database(2022);
+        assert_eq!(song_database(2021), jd2022, "Only the sku should differ");
+        assert_eq!(song_database(2020), jd2022, "Only the sku should differ");
+    }
 }
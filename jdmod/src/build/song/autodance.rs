@@ -91,8 +91,11 @@ fn autodance_scene(ses: &SongExportState<'_>) -> cooked::isc::Root<'static> {
 }
 
 /// Build the autodance template
+///
+/// Showtime maps use their own recording setup, other maps use the default setup.
 fn autodance_template(ses: &SongExportState<'_>) -> Result<Vec<u8>, Error> {
     let lower_map_name = ses.lower_map_name;
+    let showtime = ses.song.showtime.as_ref();
     let template = cooked::tpl::types::Actor {
         class: cooked::tpl::types::Actor::CLASS,
         wip: 0,
@@ -109,19 +112,37 @@ fn autodance_template(ses: &SongExportState<'_>) -> Result<Vec<u8>, Error> {
                     class: Some(cooked::tpl::types::AutodanceData::CLASS),
                     recording_structure: cooked::tpl::types::AutodanceRecordingStructure {
                         class: Some(cooked::tpl::types::AutodanceRecordingStructure::CLASS),
-                        records: Vec::new(),
+                        records: showtime
+                            .map(|showtime| {
+                                showtime
+                                    .records
+                                    .iter()
+                                    .map(cooked::tpl::types::Record::from)
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
                     },
                     video_structure: shared_json_types::AutodanceVideoStructure {
                         class: Some(shared_json_types::AutodanceVideoStructure::CLASS),
-                        song_start_position: 0.0,
-                        duration: 30.0,
-                        thumbnail_time: 0,
-                        fade_out_duration: 3.0,
+                        song_start_position: showtime
+                            .map_or(0.0, |showtime| showtime.song_start_position),
+                        duration: showtime.map_or(30.0, |showtime| showtime.duration),
+                        thumbnail_time: showtime.map_or(0, |showtime| showtime.thumbnail_time),
+                        fade_out_duration: showtime
+                            .map_or(3.0, |showtime| showtime.fade_out_duration),
                         ground_plane_path: HipStr::borrowed("invalid "),
                         first_layer_triple_background_path: HipStr::borrowed("invalid "),
                         second_layer_triple_background_path: HipStr::borrowed("invalid "),
                         third_layer_triple_background_path: HipStr::borrowed("invalid "),
-                        playback_events: Vec::new(),
+                        playback_events: showtime
+                            .map(|showtime| {
+                                showtime
+                                    .playback_events
+                                    .iter()
+                                    .map(shared_json_types::PlaybackEvent::from)
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
                         background_effect: Box::default(),
                         player_effect: Box::default(),
                         background_effect_events: Vec::new(),
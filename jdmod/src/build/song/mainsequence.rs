@@ -1,19 +1,19 @@
 //! # Mainsequence Building
-//! Build the mainsequence
+//! Build the mainsequence, the mashup sequence, and the Party Master tapes
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use dotstar_toolkit_utils::vfs::{VirtualFileSystem, VirtualPathBuf};
 use hipstr::HipStr;
 use ubiart_toolkit::{
     cooked,
     cooked::{isc::MasterTape, tape},
-    utils::{SplitPath, UniqueGameId},
+    utils::SplitPath,
 };
 
-use super::SongExportState;
+use super::{timeline, SongExportState};
 use crate::{
     build::BuildFiles,
-    types::song::{Clip, Timeline},
+    types::song::{Clip, PartyMaster, Timeline},
     utils::{self, cook_path},
 };
 
@@ -27,13 +27,20 @@ pub fn build(
     let cinematics_cache_dir = cache_map_path.join("cinematics");
 
     // main sequence actor
-    let mainsequence_actor_vec = mainsequence_actor(ses)?;
+    let mainsequence_actor_vec = sequence_actor(ses, "mainsequence")?;
 
     // main sequence template
-    let mainsequence_template_vec = mainsequence_template(ses)?;
+    let mainsequence_template_vec = sequence_template(ses, &[("master", "mainsequence")])?;
+
+    // Party Master maps have an extra actor with the puppet and choice tapes
+    let mut sequences = vec![("mainsequence", "MainSequence")];
+    if ses.song.party_master.is_some() {
+        build_party_master(ses, bf)?;
+        sequences.push(("partymaster_coach", "partymaster_coach"));
+    }
 
     // Cine scene
-    let cine_scene = cine_scene(ses);
+    let cine_scene = cine_scene(ses, &sequences);
     let cine_scene_vec = cooked::isc::create_vec_with_capacity_hint(&cine_scene, 900)?;
 
     bf.generated_files.add_file(
@@ -52,18 +59,94 @@ pub fn build(
     )?;
 
     // the timeline
-    mainsequence_timeline(ses, bf)?;
+    sequence_timeline(ses, bf, "mainsequence", false)?;
 
     Ok(cine_scene.scene.into())
 }
 
-/// Build the mainsequence actor
-fn mainsequence_actor(ses: &SongExportState<'_>) -> Result<Vec<u8>, Error> {
+/// Build the mashup sequence, if the song is a mashup
+///
+/// The mashup sequence is not part of the main scene, the game loads it separately.
+pub fn build_mashup(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Result<(), Error> {
+    if ses.song.mashup.is_none() {
+        return Ok(());
+    }
+    let cache_map_path = &ses.cache_map_path;
+    let lower_map_name = ses.lower_map_name;
+    let cinematics_cache_dir = cache_map_path.join("cinematics");
+
+    let mashup_actor_vec = sequence_actor(ses, "mashup")?;
+    let mashup_template_vec = sequence_template(ses, &[("master", "mashup")])?;
+    let mashup_scene = cine_scene(ses, &[("mashup", "Mashup")]);
+    let mashup_scene_vec = cooked::isc::create_vec_with_capacity_hint(&mashup_scene, 900)?;
+
+    bf.generated_files.add_file(
+        cinematics_cache_dir.join(format!("{lower_map_name}_mashup.act.ckd")),
+        mashup_actor_vec,
+    )?;
+
+    bf.generated_files.add_file(
+        cinematics_cache_dir.join(format!("{lower_map_name}_mashup.tpl.ckd")),
+        mashup_template_vec,
+    )?;
+
+    bf.generated_files.add_file(
+        cinematics_cache_dir.join(format!("{lower_map_name}_mashup.isc.ckd")),
+        mashup_scene_vec,
+    )?;
+
+    // The import skips missing mashup sequences
+    sequence_timeline(ses, bf, "mashup", true)?;
+
+    Ok(())
+}
+
+/// Build the `partymaster_coach` actor and the puppet and choice tapes
+fn build_party_master(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Result<(), Error> {
+    let Some(party_master) = &ses.song.party_master else {
+        return Ok(());
+    };
+    let cache_map_path = &ses.cache_map_path;
+    let lower_map_name = ses.lower_map_name;
+    let cinematics_cache_dir = cache_map_path.join("cinematics");
+
+    let tapes: Vec<_> = party_master
+        .tapes
+        .iter()
+        .map(|label| (label.as_str(), PartyMaster::tape_name(label)))
+        .collect();
+    let entries: Vec<_> = tapes
+        .iter()
+        .map(|(label, tape)| (*label, tape.as_str()))
+        .collect();
+
+    let coach_actor_vec = sequence_actor(ses, "partymaster_coach")?;
+    let coach_template_vec = sequence_template(ses, &entries)?;
+
+    bf.generated_files.add_file(
+        cinematics_cache_dir.join(format!("{lower_map_name}_partymaster_coach.act.ckd")),
+        coach_actor_vec,
+    )?;
+
+    bf.generated_files.add_file(
+        cinematics_cache_dir.join(format!("{lower_map_name}_partymaster_coach.tpl.ckd")),
+        coach_template_vec,
+    )?;
+
+    for (_, tape) in &entries {
+        sequence_timeline(ses, bf, tape, false)?;
+    }
+
+    Ok(())
+}
+
+/// Build the actor for `sequence`
+fn sequence_actor(ses: &SongExportState<'_>, sequence: &str) -> Result<Vec<u8>, Error> {
     let lower_map_name = ses.lower_map_name;
     let actor = cooked::act::Actor {
         lua: SplitPath::new(
             HipStr::from(format!("world/maps/{lower_map_name}/cinematics/")),
-            HipStr::from(format!("{lower_map_name}_mainsequence.tpl")),
+            HipStr::from(format!("{lower_map_name}_{sequence}.tpl")),
         )?,
         unk1: 0.0,
         unk2: 1.0,
@@ -75,8 +158,10 @@ fn mainsequence_actor(ses: &SongExportState<'_>) -> Result<Vec<u8>, Error> {
     Ok(cooked::act::create_vec(actor)?)
 }
 
-/// Build the cine scene
-fn cine_scene(ses: &SongExportState<'_>) -> cooked::isc::Root<'static> {
+/// Build the cine scene with an actor for every `(sequence, userfriendly)`
+///
+/// The actors are named `{map_name}_{userfriendly}`.
+fn cine_scene(ses: &SongExportState<'_>, sequences: &[(&str, &str)]) -> cooked::isc::Root<'static> {
     let lower_map_name = ses.lower_map_name;
     let map_name = ses.song.map_name.as_str();
     cooked::isc::Root {
@@ -99,21 +184,23 @@ fn cine_scene(ses: &SongExportState<'_>) -> cooked::isc::Root<'static> {
             view_family: false,
             is_popup: false,
             platform_filters: Vec::new(),
-            actors: vec![
+            actors: sequences.iter().map(|(sequence, userfriendly)| {
                 cooked::isc::WrappedActors::Actor(cooked::isc::WrappedActor { actor: Box::new(cooked::isc::Actor {
-                    userfriendly: HipStr::from(format!("{map_name}_MainSequence")),
-                    lua: HipStr::from(format!("world/maps/{lower_map_name}/cinematics/{lower_map_name}_mainsequence.tpl")),
+                    userfriendly: HipStr::from(format!("{map_name}_{userfriendly}")),
+                    lua: HipStr::from(format!("world/maps/{lower_map_name}/cinematics/{lower_map_name}_{sequence}.tpl")),
                     components: vec![cooked::isc::WrappedComponent::MasterTape(MasterTape::default())],
                     ..Default::default()
-                })}),
-            ],
+                })})
+            }).collect(),
             scene_configs: cooked::isc::SceneConfigs::default().into(),
         },
     }
 }
 
-/// Build the mainsequence template
-fn mainsequence_template(ses: &SongExportState<'_>) -> Result<Vec<u8>, Error> {
+/// Build a template with a tape entry for every `(label, tape)`
+///
+/// The tapes are stored at `cinematics/{lower_map_name}_{tape}.tape`.
+fn sequence_template(ses: &SongExportState<'_>, tapes: &[(&str, &str)]) -> Result<Vec<u8>, Error> {
     let lower_map_name = ses.lower_map_name;
     let template = cooked::tpl::types::Actor {
         class: cooked::tpl::types::Actor::CLASS,
@@ -128,13 +215,16 @@ fn mainsequence_template(ses: &SongExportState<'_>) -> Result<Vec<u8>, Error> {
                 class: None,
                 tapes_rack: vec![cooked::tpl::types::TapeGroup {
                     class: Some(cooked::tpl::types::TapeGroup::CLASS),
-                    entries: vec![cooked::tpl::types::TapeEntry {
-                        class: Some(cooked::tpl::types::TapeEntry::CLASS),
-                        label: HipStr::borrowed("master"),
-                        path: HipStr::from(format!(
-                        "world/maps/{lower_map_name}/cinematics/{lower_map_name}_mainsequence.tape"
-                    )),
-                    }],
+                    entries: tapes
+                        .iter()
+                        .map(|(label, tape)| cooked::tpl::types::TapeEntry {
+                            class: Some(cooked::tpl::types::TapeEntry::CLASS),
+                            label: HipStr::from(*label),
+                            path: HipStr::from(format!(
+                                "world/maps/{lower_map_name}/cinematics/{lower_map_name}_{tape}.tape"
+                            )),
+                        })
+                        .collect(),
                 }],
             },
         )],
@@ -143,12 +233,29 @@ fn mainsequence_template(ses: &SongExportState<'_>) -> Result<Vec<u8>, Error> {
     Ok(cooked::json::create_vec(&template)?)
 }
 
-/// Build the mainsequence timeline
-fn mainsequence_timeline(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Result<(), Error> {
-    let timeline_file = ses
-        .native_vfs
-        .open(&ses.dirs.song().join("mainsequence.json"))?;
-    let timeline: Timeline = serde_json::from_slice(&timeline_file)?;
+/// Build the timeline for `sequence` from `{sequence}.json`
+///
+/// A missing file is treated as an empty timeline if `optional` is set, otherwise it's an error.
+fn sequence_timeline(
+    ses: &SongExportState<'_>,
+    bf: &mut BuildFiles,
+    sequence: &str,
+    optional: bool,
+) -> Result<(), Error> {
+    let timeline_path = ses.dirs.song().join(format!("{sequence}.json"));
+    let timeline_file = if optional && !ses.native_vfs.exists(&timeline_path) {
+        None
+    } else {
+        Some(
+            ses.native_vfs
+                .open(&timeline_path)
+                .with_context(|| format!("Could not find the {sequence} sequence"))?,
+        )
+    };
+    let timeline: Timeline = match &timeline_file {
+        Some(file) => serde_json::from_slice(file)?,
+        None => Timeline::default(),
+    };
     let lower_map_name = ses.lower_map_name;
     let cache_map_path = &ses.cache_map_path;
     let map_path = &ses.map_path;
@@ -160,11 +267,14 @@ fn mainsequence_timeline(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Resu
             Clip::HideUserInterface(_) | Clip::GameplayEvent(_) | Clip::Vibration(_) => {
                 Some(orig_clip.into_tape(&ses.song)?)
             }
+            // The puppet tapes of Party Master maps have their own moves and pictos
+            Clip::Motion(orig_clip) => Some(timeline::motion_clip(ses, bf, &orig_clip)?),
+            Clip::Pictogram(orig_clip) => Some(timeline::pictogram_clip(ses, bf, &orig_clip)?),
             Clip::SoundSet(orig_clip) => {
                 let name = orig_clip.name.as_str();
                 let filename =
                     HipStr::from(map_path.join(format!("audio/amb/{name}.wav")).into_string());
-                let cooked_filename = cook_path(&filename, UniqueGameId::NX2022)?;
+                let cooked_filename = cook_path(&filename, ses.ugi)?;
 
                 // Add amb clip to copy list
                 let from = ses.dirs.audio().join(orig_clip.audio_filename.as_str());
@@ -194,7 +304,7 @@ fn mainsequence_timeline(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Resu
                     };
 
                     // Save the template
-                    let cooked_template_path = cook_path(&template_path, UniqueGameId::NX2022)?;
+                    let cooked_template_path = cook_path(&template_path, ses.ugi)?;
                     let cooked_template_vec = cooked::json::create_vec(&template)?;
                     bf.generated_files
                         .add_file(cooked_template_path.into(), cooked_template_vec)?;
@@ -206,7 +316,7 @@ fn mainsequence_timeline(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Resu
                 Some(tape::Clip::SoundSet(new_clip))
             }
             x => {
-                println!("Warning! Found non-dance clip in dance_timeline, ignoring! {x:?}");
+                println!("Warning! Found unsupported clip in {sequence}, ignoring! {x:?}");
                 None
             }
         };
@@ -225,10 +335,10 @@ fn mainsequence_timeline(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Resu
         soundwich_event: Some(HipStr::new()),
     };
 
-    let mainsequence_tape_vec = cooked::json::create_vec(&tape)?;
+    let tape_vec = cooked::json::create_vec(&tape)?;
     bf.generated_files.add_file(
-        cache_map_path.join(format!("cinematics/{lower_map_name}_mainsequence.tape.ckd")),
-        mainsequence_tape_vec,
+        cache_map_path.join(format!("cinematics/{lower_map_name}_{sequence}.tape.ckd")),
+        tape_vec,
     )?;
 
     Ok(())
@@ -7,9 +7,9 @@ use dotstar_toolkit_utils::{
     vfs::{native::NativeFs, VirtualFileSystem, VirtualPath, VirtualPathBuf},
 };
 use hipstr::HipStr;
-use ubiart_toolkit::{cooked, cooked::isc::SongDesc, utils::Platform};
+use ubiart_toolkit::{cooked, cooked::isc::SongDesc, utils::UniqueGameId};
 
-use super::{BuildFiles, BuildState};
+use super::{cache, BuildFiles, BuildState};
 use crate::types::song::{RelativeSongDirectoryTree, Song};
 
 mod audio;
@@ -29,22 +29,42 @@ pub struct SongExportState<'a> {
     /// Cache version of the map path
     pub cache_map_path: &'a VirtualPath,
     /// Song directory tree
-    pub dirs: RelativeSongDirectoryTree,
-    /// Export platform
-    pub platform: Platform,
+    pub dirs: &'a RelativeSongDirectoryTree,
+    /// Export game and platform
+    pub ugi: UniqueGameId,
     /// Export Engine version
     pub engine_version: u32,
+    /// The Just Dance version of the export game (like 2022)
+    pub jd_version: u32,
     /// The song metadata
     pub song: Song<'a>,
     /// Vfs with mod directory as the root
     pub native_vfs: &'a NativeFs,
 }
 
-/// Build the song at `dirs`
+/// Build the song at `dirs`, reusing the build files from the cache if the song did not change
 pub fn build(
     bs: &BuildState<'_>,
     bf: &mut BuildFiles,
     dirs: RelativeSongDirectoryTree,
+) -> Result<String, Error> {
+    let key = cache::song_key(bs, &dirs)?;
+    if let Some(map_name) = cache::load(bs, bf, &dirs, key)? {
+        println!("Reusing song '{map_name}' from the build cache...");
+        return Ok(map_name);
+    }
+
+    let map_name = build_song(bs, bf, &dirs)?;
+    cache::store(bs, bf, &dirs, key, &map_name)?;
+
+    Ok(map_name)
+}
+
+/// Build the song at `dirs`
+fn build_song(
+    bs: &BuildState<'_>,
+    bf: &mut BuildFiles,
+    dirs: &RelativeSongDirectoryTree,
 ) -> Result<String, Error> {
     let song_file = bs.native_vfs.open(&dirs.song().join("song.json"))?;
     let song: Song = serde_json::from_slice(&song_file)?;
@@ -57,11 +77,12 @@ pub fn build(
     let ses = SongExportState {
         lower_map_name: &lower_map_name,
         dirs,
-        platform: bs.platform,
+        ugi: bs.ugi,
         song,
         cache_map_path: &cache_map_path,
         map_path: &map_path,
         engine_version: bs.engine_version,
+        jd_version: bs.jd_version,
         native_vfs: bs.native_vfs,
     };
 
@@ -92,6 +113,9 @@ pub fn build(
     // Builds main sequence scene and adds the audio file for copying
     let mainsequence_scene = mainsequence::build(&ses, bf)?;
 
+    // Builds the mashup sequence if the song is a mashup
+    mainsequence::build_mashup(&ses, bf)?;
+
     // Builds autodance scene and adds the preview audio file for copying
     let autodance_scene = autodance::build(&ses, bf)?;
 
@@ -196,6 +220,8 @@ fn graph_scene(
 }
 
 /// Build the main scene from all the subscenes
+///
+/// Just Dance 2020, 2021, and 2022 use the same layout, so it does not depend on the version.
 #[allow(clippy::too_many_arguments, reason = "It's just easier this way")]
 fn main_scene<'a>(
     ses: &SongExportState<'a>,
@@ -261,12 +287,21 @@ fn main_scene<'a>(
         ));
     }
 
+    let mut song_desc_components =
+        vec![cooked::isc::WrappedComponent::SongDesc(SongDesc::default())];
+    if ses.song.dance_lab.is_some() {
+        song_desc_components.push(cooked::isc::WrappedComponent::BlockFlowComponent(
+            cooked::isc::BlockFlowComponent::default(),
+        ));
+    }
+
     actors.push(cooked::isc::WrappedActors::Actor(cooked::isc::WrappedActor { actor: Box::new(cooked::isc::Actor {
         // This is not an oversight, the JDVer, ID, Type, Flags, NbCoach, and Difficulty do not change per map
+        // or per version, every game uses this template text
         userfriendly: HipStr::from(format!("{map_name} : Template Artist - Template Title&#10;JDVer = 5, ID = 842776738, Type = 1 (Flags 0x00000000), NbCoach = 2, Difficulty = 2")),
         pos2d: (-3.531_976, -1.485_322),
         lua: HipStr::from(format!("world/maps/{lower_map_name}/songdesc.tpl")),
-        components: vec![cooked::isc::WrappedComponent::SongDesc(SongDesc::default())],
+        components: song_desc_components,
         ..Default::default()
     })}));
 
@@ -308,3 +343,97 @@ fn main_scene<'a>(
         },
     }
 }
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::vfs::{native::NativeFs, VirtualPath};
+    use hipstr::HipStr;
+    use ubiart_toolkit::{
+        cooked,
+        utils::{LocaleId, UniqueGameId},
+    };
+
+    use super::{main_scene, SongExportState};
+    use crate::types::song::{
+        Color, Difficulty, MapStatus, NumberOfCoaches, RelativeSongDirectoryTree, Song, SongColors,
+        SweatDifficulty,
+    };
+
+    /// A song without any special map data
+    fn song() -> Song<'static> {
+        Song {
+            map_name: HipStr::borrowed("Song"),
+            original_jd_version: 2022,
+            artist: HipStr::borrowed("Artist"),
+            dancer_name: HipStr::borrowed("Dancer"),
+            title: HipStr::borrowed("Title"),
+            credits: HipStr::borrowed("Credits"),
+            number_of_coaches: NumberOfCoaches::Solo,
+            main_coach: None,
+            difficulty: Difficulty::Easy,
+            sweat_difficulty: SweatDifficulty::Low,
+            related_songs: Vec::new(),
+            status: MapStatus::Unlocked,
+            tags: Vec::new(),
+            subtitle: LocaleId::default(),
+            default_colors: SongColors {
+                theme: Color::default(),
+                lyrics: Color::default(),
+                one_a: Color::default(),
+                one_b: Color::default(),
+                two_a: Color::default(),
+                two_b: Color::default(),
+            },
+            audiofile: HipStr::borrowed("song.ogg"),
+            videofile: HipStr::borrowed("song.webm"),
+            mashup: None,
+            dance_lab: None,
+            double_scoring: None,
+            showtime: None,
+            party_master: None,
+        }
+    }
+
+    #[test]
+    fn test_main_scene_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let native_vfs = NativeFs::new(dir.path()).unwrap();
+        let dirs = RelativeSongDirectoryTree::new(VirtualPath::new("songs/Song"));
+        let map_path = VirtualPath::new("world/maps/song");
+        let cache_map_path = VirtualPath::new("cache/itf_cooked/nx/world/maps/song");
+
+        let scenes = [
+            (2020, UniqueGameId::NX2020),
+            (2021, UniqueGameId::NX2021),
+            (2022, UniqueGameId::NX2022),
+        ]
+        .map(|(jd_version, ugi)| {
+            let ses = SongExportState {
+                lower_map_name: "song",
+                map_path,
+                cache_map_path,
+                dirs: &dirs,
+                ugi,
+                engine_version: 0x0025_3FD7,
+                jd_version,
+                song: song(),
+                native_vfs: &native_vfs,
+            };
+            let empty = || cooked::isc::Scene::default().into();
+            let root = main_scene(
+                &ses,
+                empty(),
+                empty(),
+                empty(),
+                empty(),
+                empty(),
+                empty(),
+                empty(),
+            );
+            cooked::isc::create_vec(&root).unwrap()
+        });
+        assert_eq!(scenes[0], scenes[2], "JD2020 should use the JD2022 layout");
+        assert_eq!(scenes[1], scenes[2], "JD2021 should use the JD2022 layout");
+    }
+}
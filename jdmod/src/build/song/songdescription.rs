@@ -36,12 +36,19 @@ pub fn build(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Result<(), Error
         );
     }
 
-    let song_desc_tpl = cooked::tpl::types::Actor {
+    // Double rumble maps are recognized by their tag
+    let mut tags = ses.song.tags.clone();
+    let double_scoring_tag = HipStr::borrowed("doublescoring");
+    if ses.song.double_scoring.is_some() && !tags.contains(&double_scoring_tag) {
+        tags.push(double_scoring_tag);
+    }
+
+    let mut song_desc_tpl = cooked::tpl::types::Actor {
         components: vec![cooked::tpl::types::Template::SongDescription(
             cooked::tpl::types::SongDescription {
                 class: None,
                 map_name: ses.song.map_name.clone(),
-                jd_version: 2022,
+                jd_version: ses.jd_version,
                 original_jd_version: ses.song.original_jd_version,
                 related_albums: ses.song.related_songs.clone(),
                 artist: ses.song.artist.clone(),
@@ -63,23 +70,46 @@ pub fn build(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Result<(), Error
                 sweat_difficulty: ses.song.sweat_difficulty.into(),
                 background_type: 0,
                 lyrics_type: 0,
-                tags: ses.song.tags.clone(),
+                tags,
                 status: ses.song.status.normalize().into(),
                 locale_id: ses.song.subtitle,
                 mojo_value: 0,
                 count_in_progression: 1,
                 default_colors: (&ses.song.default_colors).into(),
                 video_preview_path: HipStr::borrowed(""),
-                double_scoring_type: None,
+                // Only used before NX2020
+                double_scoring_type: ses
+                    .song
+                    .double_scoring
+                    .as_ref()
+                    .and_then(|double_scoring| double_scoring.scoring_type)
+                    .filter(|_| ses.jd_version < 2020),
                 paths: None,
                 energy: None,
                 score_with_both_controllers: None,
-                jdm_attributes: None,
+                jdm_attributes: ses
+                    .song
+                    .dance_lab
+                    .as_ref()
+                    .filter(|dance_lab| !dance_lab.jdm_attributes.is_empty())
+                    .map(|dance_lab| dance_lab.jdm_attributes.clone()),
             },
         )],
         ..Default::default()
     };
 
+    let mut song_desc_components = vec![cooked::act::Component::SongDescComponent];
+
+    // Dance Lab maps have their block flow in the songdesc actor
+    if let Some(dance_lab) = &ses.song.dance_lab {
+        song_desc_tpl
+            .components
+            .push(cooked::tpl::types::Template::BlockFlowTemplate(
+                dance_lab.to_template(),
+            ));
+        song_desc_components.push(cooked::act::Component::BlockFlowComponent);
+    }
+
     let song_desc_act = cooked::act::Actor {
         lua: SplitPath::new(
             HipStr::borrowed(ses.map_path.as_str()),
@@ -89,7 +119,7 @@ pub fn build(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Result<(), Error
         unk2: 1.0,
         unk2_5: 1.0,
         unk3_5: 0,
-        components: vec![cooked::act::Component::SongDescComponent],
+        components: song_desc_components,
     };
 
     let song_desc_tpl_vec = cooked::json::create_vec(&song_desc_tpl)?;
@@ -1,19 +1,23 @@
 //! # Timeline Building
 //! Builds the karaoke and dance timelines, and pictos
 
-use anyhow::Error;
-use dotstar_toolkit_utils::vfs::{VirtualFileSystem, VirtualPathBuf};
+use anyhow::{anyhow, Error};
+use dotstar_toolkit_utils::{
+    bytes::read::BinaryDeserializeExt as _,
+    vfs::{VirtualFileSystem, VirtualPathBuf},
+};
 use hipstr::HipStr;
 use ubiart_toolkit::{
     cooked,
     cooked::{isc::TapeCase, tape},
-    utils::{SplitPath, UniqueGameId},
+    msm::{self, MovementSpaceMove},
+    utils::SplitPath,
 };
 
 use super::SongExportState;
 use crate::{
     build::BuildFiles,
-    types::song::{Clip, MotionClip, Timeline},
+    types::song::{Clip, MotionClip, PictogramClip, Timeline},
     utils::{cook_path, encode_texture},
 };
 
@@ -99,50 +103,31 @@ fn build_dance(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Result<(), Err
             Clip::GoldEffect(orig_clip) => Some(tape::Clip::GoldEffect(
                 tape::GoldEffectClip::from(orig_clip),
             )),
-            Clip::Motion(orig_clip) => {
-                let new_clip = orig_clip.to_tape(&ses.song);
-
-                // TODO: Check the msm file
-                let from = ses
-                    .dirs
-                    .moves()
-                    .join(orig_clip.classifier_filename.as_str());
-                // Classifier path does not include platform specifier
-                let to = MotionClip::fix_classifier_path(&new_clip.classifier_path, ses.platform)?;
-
-                if ses.native_vfs.exists(&from) {
-                    bf.static_files.add_file(from, VirtualPathBuf::from(to))?;
-                } else {
-                    println!(
-                        "Warning! Missing {} for {lower_map_name}!",
-                        orig_clip.classifier_filename
-                    );
-                }
-
-                Some(tape::Clip::Motion(new_clip))
-            }
-            Clip::Pictogram(orig_clip) => {
-                let new_clip = orig_clip.to_tape(&ses.song);
-                let to = cook_path(&new_clip.picto_path, UniqueGameId::NX2022)?;
-
-                // A picto will be used multiple times, so only create it once
-                if !bf.generated_files.exists(to.as_ref()) {
-                    let from = ses.dirs.pictos().join(orig_clip.picto_filename.as_str());
-                    if ses.native_vfs.exists(&from) {
-                        let encoded = encode_texture(ses.native_vfs, &from)?;
-                        let encoded_vec = cooked::png::create_vec(encoded)?;
-
-                        bf.generated_files.add_file(to.into(), encoded_vec)?;
-                    } else {
-                        println!(
-                            "Warning! Missing {} for {lower_map_name}!",
-                            orig_clip.picto_filename
-                        );
-                    }
+            Clip::Motion(orig_clip) => Some(motion_clip(ses, bf, &orig_clip)?),
+            // Double rumble maps use gameplay events to target the teams
+            Clip::GameplayEvent(mut orig_clip) => {
+                if let Some(double_scoring) = ses
+                    .song
+                    .double_scoring
+                    .as_ref()
+                    .filter(|_| !orig_clip.actor_indices.is_empty())
+                {
+                    let team = double_scoring
+                        .team(&orig_clip.actor_indices)
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "Gameplay event at {} targets {:?}, which is not a team!",
+                                orig_clip.start_time,
+                                orig_clip.actor_indices
+                            )
+                        })?;
+                    orig_clip.actor_indices.clone_from(&team.coaches);
                 }
-
-                Some(tape::Clip::Pictogram(new_clip))
+                Some(tape::Clip::GameplayEvent(tape::GameplayEventClip::from(
+                    orig_clip,
+                )))
             }
+            Clip::Pictogram(orig_clip) => Some(pictogram_clip(ses, bf, &orig_clip)?),
             x => {
                 println!("Warning! Found non-dance clip in dance_timeline, ignoring! {x:?}");
                 None
@@ -183,6 +168,97 @@ fn build_dance(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Result<(), Err
     Ok(())
 }
 
+/// Build the tape clip for `orig_clip` and add its classifier to the build files
+pub(super) fn motion_clip(
+    ses: &SongExportState<'_>,
+    bf: &mut BuildFiles,
+    orig_clip: &MotionClip<'_>,
+) -> Result<tape::Clip<'static>, Error> {
+    let new_clip = orig_clip.to_tape(&ses.song);
+
+    let from = ses
+        .dirs
+        .moves()
+        .join(orig_clip.classifier_filename.as_str());
+    // Classifier path does not include platform specifier
+    let to = MotionClip::fix_classifier_path(&new_clip.classifier_path, ses.ugi.platform)?;
+
+    // Copied classifiers (like .gesture files) are static files, built .msm files are generated
+    if bf.generated_files.exists(to.as_ref()) || bf.static_files.exists(to.as_ref()) {
+        // The classifier is used by multiple clips and is already built
+    } else if ses.native_vfs.exists(&from) {
+        build_classifier(ses, bf, from, to)?;
+    } else {
+        println!(
+            "Warning! Missing {} for {}!",
+            orig_clip.classifier_filename, ses.lower_map_name
+        );
+    }
+
+    Ok(tape::Clip::Motion(new_clip))
+}
+
+/// Build the tape clip for `orig_clip` and add its picto to the build files
+pub(super) fn pictogram_clip(
+    ses: &SongExportState<'_>,
+    bf: &mut BuildFiles,
+    orig_clip: &PictogramClip<'_>,
+) -> Result<tape::Clip<'static>, Error> {
+    let new_clip = orig_clip.to_tape(&ses.song);
+    let to = cook_path(&new_clip.picto_path, ses.ugi)?;
+
+    // A picto will be used multiple times, so only create it once
+    if !bf.generated_files.exists(to.as_ref()) {
+        let from = ses.dirs.pictos().join(orig_clip.picto_filename.as_str());
+        if ses.native_vfs.exists(&from) {
+            let encoded = encode_texture(ses.native_vfs, &from)?;
+            let encoded_vec = cooked::png::create_vec_with_ugi(encoded, ses.ugi)?;
+
+            bf.generated_files.add_file(to.into(), encoded_vec)?;
+        } else {
+            println!(
+                "Warning! Missing {} for {}!",
+                orig_clip.picto_filename, ses.lower_map_name
+            );
+        }
+    }
+
+    Ok(tape::Clip::Pictogram(new_clip))
+}
+
+/// Check the classifier at `from` and add it to the build files at `to`
+///
+/// .msm files are parsed and written again, so any issues are found before the game
+/// tries to load them. Other classifiers (like .gesture files) are copied verbatim.
+fn build_classifier(
+    ses: &SongExportState<'_>,
+    bf: &mut BuildFiles,
+    from: VirtualPathBuf,
+    to: String,
+) -> Result<(), Error> {
+    if from.extension() != Some("msm") {
+        bf.static_files.add_file(from, VirtualPathBuf::from(to))?;
+        return Ok(());
+    }
+
+    let file = ses.native_vfs.open(&from)?;
+    match MovementSpaceMove::deserialize(&file) {
+        Ok(msm) => {
+            let msm_vec = msm::create_vec(msm)?;
+            bf.generated_files
+                .add_file(VirtualPathBuf::from(to), msm_vec)?;
+        }
+        Err(error) => {
+            println!(
+                "Warning! Failed to parse {from} for {}, copying it verbatim: {error:?}",
+                ses.lower_map_name
+            );
+            bf.static_files.add_file(from, VirtualPathBuf::from(to))?;
+        }
+    }
+    Ok(())
+}
+
 /// Build the karaoke timeline
 fn build_karaoke(ses: &SongExportState<'_>, bf: &mut BuildFiles) -> Result<(), Error> {
     let cache_map_path = ses.cache_map_path;
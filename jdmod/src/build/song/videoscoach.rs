@@ -254,7 +254,7 @@ fn video_map_preview_scene<'a>(ses: &SongExportState<'a>) -> cooked::isc::Root<'
     let lower_map_name = ses.lower_map_name;
     cooked::isc::Root {
         scene: cooked::isc::Scene {
-            engine_version: 326_704,
+            engine_version: ses.engine_version,
             gridunit: 0.5,
             depth_separator: 0,
             near_separator: [
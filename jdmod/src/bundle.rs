@@ -3,6 +3,7 @@
 use std::{
     collections::{hash_map::Entry, HashMap},
     fs::File,
+    num::{NonZeroU64, NonZeroUsize},
     path::{Path, PathBuf},
 };
 
@@ -14,7 +15,7 @@ use dotstar_toolkit_utils::{
 };
 use ubiart_toolkit::{
     ipk,
-    secure_fat::SecureFat,
+    secure_fat::{bundle_name_to_filename, SecureFat},
     utils::{PathId, UniqueGameId},
 };
 
@@ -45,6 +46,16 @@ pub struct Bundle {
     /// Only create a patch_nx.ipk
     #[arg(long)]
     patch: bool,
+    /// Maximum size of a song bundle in bytes
+    ///
+    /// Defaults to the maximum file size of the FAT32 filesystem
+    #[arg(long)]
+    max_bundle_size: Option<NonZeroU64>,
+    /// Use n threads for compressing the files
+    ///
+    /// Defaults to the amount of available cpus
+    #[arg(long)]
+    threads: Option<NonZeroUsize>,
 }
 
 /// Bundle the files at `source` to `destination`
@@ -69,17 +80,36 @@ pub fn main(data: &Bundle) -> Result<(), Error> {
             ipk_unk4: iu,
         }
     };
-    bundle(&data.source, &data.destination, &config, data.patch)
+    let max_bundle_size = data
+        .max_bundle_size
+        .map_or(MAX_BUNDLE_SIZE_FAT32, NonZeroU64::get);
+    let threads = if let Some(threads) = data.threads {
+        threads
+    } else {
+        std::thread::available_parallelism()?
+    };
+    bundle(
+        &data.source,
+        &data.destination,
+        &config,
+        data.patch,
+        max_bundle_size,
+        threads,
+    )
 }
 
 /// Bundle the files at `source` into .ipks at `destination`
 ///
-/// If `patch` is specified, only a "patch_nx.ipk" file is created.
+/// If `patch` is specified, only a "patch_nx.ipk" file is created. Otherwise the songs are
+/// divided over song bundles of at most `max_bundle_size` bytes, which are referenced in
+/// the generated secure_fat.gf. The files are compressed on `threads` threads.
 pub fn bundle(
     source: &Path,
     destination: &Path,
     config: &Config,
     patch: bool,
+    max_bundle_size: u64,
+    threads: NonZeroUsize,
 ) -> Result<(), Error> {
     // Check the source directory
     if !source.exists() {
@@ -104,7 +134,7 @@ pub fn bundle(
     let filenames: Vec<_> = files.collect::<Vec<_>>();
 
     if patch {
-        let file_path = destination.join("patch_nx.ipk");
+        let file_path = destination.join(bundle_name_to_filename("patch", config.game_platform));
         ipk::create(
             &file_path,
             ipk::Options {
@@ -112,12 +142,13 @@ pub fn bundle(
                 game_platform: config.game_platform,
                 unk4: config.ipk_unk4,
                 engine_version: config.engine_version,
+                threads,
             },
             &vfs,
             &filenames,
         )?;
         if file_path.metadata()?.len() >= MAX_BUNDLE_SIZE_FAT32 {
-            println!("Warning! patch_nx.ipk file is bigger than 4 GB and therefore not compatible with the FAT32 filesystem.");
+            println!("Warning! The patch file is bigger than 4 GB and therefore not compatible with the FAT32 filesystem.");
         }
     } else {
         // The main bundle that contains all the logic, but no songs
@@ -218,8 +249,10 @@ pub fn bundle(
             while index <= current_len {
                 // Extract the current song from the vec
                 let current_sorted = sorted_sizes[current_len - index];
-                // Check if it would fit in the bundle
-                if current_sorted.1 + current_bundle.0 <= MAX_BUNDLE_SIZE_FAT32 {
+                // Check if it would fit in the bundle, a song that is too big on its own gets its own bundle
+                if current_bundle.1.is_empty()
+                    || current_sorted.1 + current_bundle.0 <= max_bundle_size
+                {
                     // Remove the `DirEntry` from the entries
                     let entry = song_bundles
                         .get(current_sorted.0)
@@ -261,14 +294,16 @@ pub fn bundle(
 
         let mut sfat = SecureFat::with_capacity(config.game_platform, file_count);
 
-        println!("Creating bundle_nx.ipk");
+        let main_bundle_filename = bundle_name_to_filename("bundle", config.game_platform);
+        println!("Creating {main_bundle_filename}");
         ipk::create(
-            destination.join("bundle_nx.ipk"),
+            destination.join(main_bundle_filename),
             ipk::Options {
                 compression: ipk::CompressionEffort::Best,
                 game_platform: config.game_platform,
                 unk4: config.ipk_unk4,
                 engine_version: config.engine_version,
+                threads,
             },
             &vfs,
             &main_bundle_entries,
@@ -281,15 +316,21 @@ pub fn bundle(
         );
 
         for (i, (_, entries)) in other_bundles_entries.iter().enumerate() {
-            let name = format!("songs_{i}_nx.ipk");
-            println!("Creating {name}");
+            // Only happens when there are no songs
+            if entries.is_empty() {
+                continue;
+            }
+            let name = format!("songs_{i}");
+            let filename = bundle_name_to_filename(&name, config.game_platform);
+            println!("Creating {filename}");
             ipk::create(
-                destination.join(&name),
+                destination.join(filename),
                 ipk::Options {
                     compression: ipk::CompressionEffort::Best,
                     game_platform: config.game_platform,
                     unk4: config.ipk_unk4,
                     engine_version: config.engine_version,
+                    threads,
                 },
                 &vfs,
                 entries,
@@ -299,21 +340,23 @@ pub fn bundle(
             sfat.add_path_ids_to_bundle(bundle_id, entries.iter().map(|path| PathId::from(*path)));
         }
 
-        println!("Creating patch_nx.ipk");
+        let patch_filename = bundle_name_to_filename("patch", config.game_platform);
+        println!("Creating {patch_filename}");
         ipk::create(
-            destination.join("patch_nx.ipk"),
+            destination.join(patch_filename),
             ipk::Options {
                 compression: ipk::CompressionEffort::Best,
                 game_platform: config.game_platform,
                 unk4: config.ipk_unk4,
                 engine_version: config.engine_version,
+                threads,
             },
             &vfs,
             &[],
         )?;
 
         println!("Creating secure_fat.gf");
-        let mut file = File::open(destination.join("secure_fat.gf"))?;
+        let mut file = File::create(destination.join("secure_fat.gf"))?;
         file.write_at::<SecureFat>(&mut 0, sfat)?;
     }
 
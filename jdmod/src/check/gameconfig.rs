@@ -0,0 +1,247 @@
+//! # Gameconfig checks
+//! Checks the playlists, objectives, avatars and other configuration files
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use hipstr::HipStr;
+use ubiart_toolkit::cooked::isg::{MapsGoals, MapsObjectives};
+
+use super::{Checker, IssueKind};
+use crate::types::gameconfig::{
+    aliases::Aliases, avatars::Avatar, objectives::Objective, playlists::Playlist,
+    portraitborders::PortraitBorder, scheduled_quests::ScheduledQuests, search_labels::SearchLabel,
+};
+
+/// Check all the configuration files
+///
+/// Objectives are checked first, as the other files refer to them.
+pub fn check(checker: &mut Checker<'_>) {
+    objectives(checker);
+    maps_objectives(checker);
+    maps_goals(checker);
+    offline_recommendations(checker);
+    playlists(checker);
+    avatars(checker);
+    aliases(checker);
+    quests(checker);
+    search_labels(checker);
+    portraitborders(checker);
+}
+
+/// Check the objectives and collect their names
+fn objectives(checker: &mut Checker<'_>) {
+    let path = checker.dir_tree.config().join("objectives.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(objectives) = checker.parse::<BTreeMap<HipStr, Objective>>(&path, &data) else {
+        return;
+    };
+    for (name, objective) in objectives {
+        checker.require_locale_id(&path, objective.description, &format!("objective {name}"));
+        checker.objectives.insert(name.as_str().to_owned());
+    }
+}
+
+/// Check the objectives for maps
+fn maps_objectives(checker: &mut Checker<'_>) {
+    let path = checker.dir_tree.config().join("maps_objectives.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(maps_objectives) = checker.parse::<MapsObjectives>(&path, &data) else {
+        return;
+    };
+    let maps_objectives = maps_objectives.into_iter().collect::<BTreeMap<_, _>>();
+    for (map_name, objective) in maps_objectives {
+        checker.require_map_name(&path, &map_name, "a map objective");
+        checker.require_objective(&path, &objective, &format!("map {map_name}"));
+    }
+}
+
+/// Check the goals for maps
+fn maps_goals(checker: &mut Checker<'_>) {
+    let path = checker.dir_tree.config().join("maps_goals.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(maps_goals) = checker.parse::<MapsGoals>(&path, &data) else {
+        return;
+    };
+    let map_names = maps_goals.into_keys().collect::<BTreeSet<_>>();
+    for map_name in map_names {
+        checker.require_map_name(&path, &map_name, "a map goal");
+    }
+}
+
+/// Check the maps recommended when offline
+fn offline_recommendations(checker: &mut Checker<'_>) {
+    let path = checker
+        .dir_tree
+        .config()
+        .join("offline_recommendations.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(map_names) = checker.parse::<Vec<HipStr>>(&path, &data) else {
+        return;
+    };
+    for map_name in map_names {
+        checker.require_map_name(&path, &map_name, "an offline recommendation");
+    }
+}
+
+/// Check the playlists for unknown maps, missing covers, and unknown locale ids
+fn playlists(checker: &mut Checker<'_>) {
+    let dir_tree = checker.dir_tree;
+    let path = dir_tree.playlists().join("playlists.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(playlists) = checker.parse::<BTreeMap<HipStr, Playlist>>(&path, &data) else {
+        return;
+    };
+    for (name, playlist) in playlists {
+        let what = format!("playlist {name}");
+        checker.require_locale_id(&path, playlist.title, &what);
+        checker.require_locale_id(&path, playlist.description, &what);
+        checker.require_file(&path, &dir_tree.playlists().join(playlist.cover.as_str()));
+        for map_name in &playlist.maps {
+            checker.require_map_name(&path, map_name, &what);
+        }
+    }
+}
+
+/// Check the avatars for duplicate ids, unknown maps, and missing images
+fn avatars(checker: &mut Checker<'_>) {
+    let dir_tree = checker.dir_tree;
+    let path = dir_tree.avatars().join("avatars.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(avatars) = checker.parse::<BTreeMap<HipStr, Avatar>>(&path, &data) else {
+        return;
+    };
+    let mut ids: HashMap<u32, &str> = HashMap::with_capacity(avatars.len());
+    for (name, avatar) in &avatars {
+        let what = format!("avatar {name}");
+        if let Some(id) = avatar.id {
+            if let Some(other) = ids.insert(id, name.as_str()) {
+                let message = format!("Avatar id {id} is used by {name} and {other}");
+                checker.report(IssueKind::DuplicateId, &path, message);
+            }
+        }
+        checker.require_file(&path, &dir_tree.avatars().join(avatar.image_path.as_str()));
+        if !avatar.relative_song_name.is_empty() {
+            checker.require_map_name(&path, &avatar.relative_song_name, &what);
+        }
+        if !avatar.used_as_coach_map_name.is_empty() {
+            checker.require_map_name(&path, &avatar.used_as_coach_map_name, &what);
+        }
+        if let Some(main_avatar) = &avatar.main_avatar {
+            if !avatars.contains_key(main_avatar) {
+                let message = format!("Unknown main avatar {main_avatar} for {what}");
+                checker.report(IssueKind::DanglingReference, &path, message);
+            }
+        }
+    }
+}
+
+/// Check the aliases for invalid colours, unknown locale ids, and unknown objectives
+fn aliases(checker: &mut Checker<'_>) {
+    let path = checker.dir_tree.config().join("aliases.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(aliases) = checker.parse::<Aliases>(&path, &data) else {
+        return;
+    };
+    if !is_valid_color(&aliases.locked_color) {
+        let message = format!("Invalid locked color {}", aliases.locked_color);
+        checker.report(IssueKind::InvalidColor, &path, message);
+    }
+    for (rarity, color) in &aliases.rarity_color {
+        if !is_valid_color(color) {
+            let message = format!("Invalid color {color} for rarity {rarity:?}");
+            checker.report(IssueKind::InvalidColor, &path, message);
+        }
+    }
+    for (index, alias) in aliases.aliases.iter().enumerate() {
+        let what = format!("alias {index}");
+        checker.require_locale_id(&path, alias.name, &what);
+        checker.require_locale_id(&path, alias.name_female, &what);
+        checker.require_locale_id(&path, alias.description, &what);
+        if let Some(objective) = &alias.unlock_objective {
+            checker.require_objective(&path, objective, &what);
+        }
+    }
+}
+
+/// Check the quests for unknown objectives
+fn quests(checker: &mut Checker<'_>) {
+    let path = checker.dir_tree.config().join("quests.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(quests) = checker.parse::<ScheduledQuests>(&path, &data) else {
+        return;
+    };
+    let objectives = std::iter::once(&quests.first_discovery_quest)
+        .chain(&quests.quests)
+        .map(|quest| quest.objective.as_str())
+        .collect::<BTreeSet<_>>();
+    for objective in objectives {
+        checker.require_objective(&path, objective, "a quest");
+    }
+}
+
+/// Check the search labels for unknown maps and locale ids
+fn search_labels(checker: &mut Checker<'_>) {
+    let path = checker.dir_tree.config().join("search_labels.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(search_labels) = checker.parse::<BTreeMap<HipStr, HashSet<SearchLabel>>>(&path, &data)
+    else {
+        return;
+    };
+    for (map_name, labels) in search_labels {
+        let what = format!("the search labels of {map_name}");
+        checker.require_map_name(&path, &map_name, "search labels");
+        for label in labels {
+            checker.require_locale_id(&path, label.description, &what);
+        }
+    }
+}
+
+/// Check the portraitborders for missing textures
+fn portraitborders(checker: &mut Checker<'_>) {
+    let dir = checker.dir_tree.portraitborders();
+    let path = dir.join("portraitborders.json");
+    let Some(data) = checker.read(&path) else {
+        return;
+    };
+    let Some(portraitborders) = checker.parse::<BTreeMap<HipStr, PortraitBorder>>(&path, &data)
+    else {
+        return;
+    };
+    for portraitborder in portraitborders.values() {
+        let background = dir.join(portraitborder.background_texture_path.as_str());
+        let foreground = portraitborder
+            .foreground_texture_path
+            .as_ref()
+            .map(|path| dir.join(path.as_str()));
+        checker.require_file(&path, &background);
+        if let Some(foreground) = foreground {
+            checker.require_file(&path, &foreground);
+        }
+    }
+}
+
+/// Check if `color` is a RGBA hex string (with an optional `0x` or `#` prefix)
+fn is_valid_color(color: &str) -> bool {
+    let hex = color
+        .strip_prefix("0x")
+        .or_else(|| color.strip_prefix('#'))
+        .unwrap_or(color);
+    hex.len() == 8 && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
@@ -1,69 +1,216 @@
-//! Contains functions for verifying the mod
-
-use std::path::PathBuf;
+//! # Check
+//! Verifies the mod without exporting it, so problems can be found early (for example in CI)
+//!
+//! All songs, playlists, objectives, avatars and other configuration files are loaded and checked
+//! for missing media files, dangling map names, unknown locale ids, invalid colours and duplicate
+//! ids. The found issues are printed as JSON to stdout.
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 use anyhow::{bail, Error};
 use clap::Args;
-use serde_json::Value;
+use hipstr::HipStr;
+use serde::{Deserialize, Serialize};
 
-use crate::{check::mediawiki::MediaWiki, types::DirectoryTree};
+use crate::types::{
+    localisation::{LocaleId, LANGUAGE_FILES},
+    DirectoryTree,
+};
 
-mod mediawiki;
+mod gameconfig;
+mod songs;
 
-/// Extract a UbiArt archive
+/// Check the mod for missing files and broken references
 #[derive(Args, Clone)]
 pub struct Check {
     /// Mod directory
     mod_dir: PathBuf,
+    /// Pretty print the JSON output
+    #[arg(long)]
+    pretty: bool,
 }
 
 /// Wrapper around [`check`]
+///
+/// # Errors
+/// Will return an error if any issues are found, so it can be used as a gate before exporting
 pub fn main(data: &Check) -> Result<(), Error> {
     let dir_tree = DirectoryTree::new(&data.mod_dir);
     if !dir_tree.exists() {
         bail!("Did not find expected directories in the directory tree!")
     }
-    check(dir_tree)?;
+    let issues = check(&dir_tree);
+
+    let stdout = std::io::stdout().lock();
+    if data.pretty {
+        serde_json::to_writer_pretty(stdout, &issues)?;
+    } else {
+        serde_json::to_writer(stdout, &issues)?;
+    }
+    println!();
+
+    if !issues.is_empty() {
+        bail!("Found {} issues in the mod!", issues.len());
+    }
     Ok(())
 }
 
-/// Checks which songs are in the mod and which ones are missing
-///
-/// Maybe host a file on Github as the parsing is finicky, and some songs have multiple codenames
-///
-/// TODO: Also check song correctness (missing files)
-pub fn check(_dir_tree: DirectoryTree) -> Result<(), Error> {
-    let mediawiki = MediaWiki::new("justdance.fandom.com");
-    for song in mediawiki.category_members("Category:Songs") {
-        let title = song["title"].as_str().unwrap();
-        if let Some(pageprops) = song.get("pageprops") {
-            let infoboxes: Vec<Value> =
-                serde_json::from_str(pageprops["infoboxes"].as_str().unwrap())?;
-            let codenames = infoboxes
-                .first()
-                .and_then(Value::as_object)
-                .and_then(|o| o.get("data"))
-                .and_then(Value::as_array)
-                .and_then(|a| {
-                    a.iter()
-                        .filter_map(Value::as_object)
-                        .filter(|o| o["type"] == "data")
-                        .map(|o| &o["data"])
-                        .filter_map(Value::as_object)
-                        .find(|data| {
-                            let label = data["label"].as_str().unwrap();
-                            let mut label = label.to_ascii_lowercase();
-                            label.retain(|c| !c.is_whitespace());
-                            label.contains("codename")
-                        })
-                });
-            if let Some(codenames) = codenames {
-                let codenames = codenames["value"].as_str().unwrap().replace("<br>", " | ");
-                println!("{title}: {codenames}");
-            } else {
-                println!("{title}: No codename!");
+/// Check the mod at `dir_tree` and return all the issues that were found
+#[must_use]
+pub fn check(dir_tree: &DirectoryTree) -> Vec<Issue> {
+    let mut checker = Checker::new(dir_tree);
+
+    // Localisation and songs first, as everything else refers to them
+    check_localisation(&mut checker);
+    songs::check(&mut checker);
+    gameconfig::check(&mut checker);
+
+    checker.issues
+}
+
+/// A problem found in the mod
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    /// What kind of problem it is
+    pub kind: IssueKind,
+    /// The file that contains the problem, relative to the mod directory
+    pub file: PathBuf,
+    /// Description of the problem
+    pub message: String,
+}
+
+/// The kinds of problems that can be found in a mod
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// A file could not be read or parsed
+    UnreadableFile,
+    /// A file that is referenced does not exist
+    MissingFile,
+    /// A map name is referenced that is not in the mod
+    DanglingMapName,
+    /// An objective or avatar is referenced that is not in the mod
+    DanglingReference,
+    /// A locale id is referenced that is not in the translations
+    UnknownLocaleId,
+    /// A colour is not a valid RGBA hex string
+    InvalidColor,
+    /// An id or name is used more than once
+    DuplicateId,
+}
+
+/// State shared by all the checks
+struct Checker<'a> {
+    /// The mod being checked
+    dir_tree: &'a DirectoryTree,
+    /// All locale ids in the translations
+    locale_ids: HashSet<LocaleId>,
+    /// All map names in the mod (lowercase)
+    map_names: HashSet<String>,
+    /// All objective names in the mod
+    objectives: HashSet<String>,
+    /// The issues found so far
+    issues: Vec<Issue>,
+}
+
+impl<'a> Checker<'a> {
+    /// Create a new checker for the mod at `dir_tree`
+    fn new(dir_tree: &'a DirectoryTree) -> Self {
+        Self {
+            dir_tree,
+            locale_ids: HashSet::new(),
+            map_names: HashSet::new(),
+            objectives: HashSet::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    /// Add an issue for `file`
+    fn report(&mut self, kind: IssueKind, file: &Path, message: impl Into<String>) {
+        let file = file
+            .strip_prefix(self.dir_tree.root())
+            .unwrap_or(file)
+            .to_path_buf();
+        self.issues.push(Issue {
+            kind,
+            file,
+            message: message.into(),
+        });
+    }
+
+    /// Read the file at `path`, reporting an issue if that fails
+    fn read(&mut self, path: &Path) -> Option<Vec<u8>> {
+        match std::fs::read(path) {
+            Ok(data) => Some(data),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                self.report(IssueKind::MissingFile, path, "File does not exist");
+                None
+            }
+            Err(error) => {
+                self.report(IssueKind::UnreadableFile, path, error.to_string());
+                None
             }
         }
     }
-    Ok(())
+
+    /// Parse `data` read from `path`, reporting an issue if that fails
+    fn parse<'de, T: Deserialize<'de>>(&mut self, path: &Path, data: &'de [u8]) -> Option<T> {
+        match serde_json::from_slice(data) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.report(IssueKind::UnreadableFile, path, error.to_string());
+                None
+            }
+        }
+    }
+
+    /// Report an issue for `file` if the file at `path` does not exist
+    fn require_file(&mut self, file: &Path, path: &Path) {
+        if !path.is_file() {
+            let message = format!("Referenced file {} does not exist", path.display());
+            self.report(IssueKind::MissingFile, file, message);
+        }
+    }
+
+    /// Report an issue for `file` if `id` is not in the translations
+    fn require_locale_id(&mut self, file: &Path, id: LocaleId, what: &str) {
+        if id != LocaleId::EMPTY && !self.locale_ids.contains(&id) {
+            let message = format!("Unknown locale id {id} for {what}");
+            self.report(IssueKind::UnknownLocaleId, file, message);
+        }
+    }
+
+    /// Report an issue for `file` if `map_name` is not in the mod
+    fn require_map_name(&mut self, file: &Path, map_name: &str, what: &str) {
+        if !self.map_names.contains(&map_name.to_lowercase()) {
+            let message = format!("Unknown map {map_name} for {what}");
+            self.report(IssueKind::DanglingMapName, file, message);
+        }
+    }
+
+    /// Report an issue for `file` if `objective` is not in the mod
+    fn require_objective(&mut self, file: &Path, objective: &str, what: &str) {
+        if !self.objectives.contains(objective) {
+            let message = format!("Unknown objective {objective} for {what}");
+            self.report(IssueKind::DanglingReference, file, message);
+        }
+    }
+}
+
+/// Collect all locale ids in the translations
+fn check_localisation(checker: &mut Checker<'_>) {
+    for (_, file) in LANGUAGE_FILES {
+        let path = checker.dir_tree.translations().join(file);
+        if !path.exists() {
+            continue;
+        }
+        let Some(data) = checker.read(&path) else {
+            continue;
+        };
+        if let Some(translations) = checker.parse::<HashMap<LocaleId, HipStr<'_>>>(&path, &data) {
+            checker.locale_ids.extend(translations.into_keys());
+        }
+    }
 }
@@ -0,0 +1,258 @@
+//! # Song checks
+//! Checks every song for missing media files, unknown locale ids and unknown maps
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use super::{Checker, IssueKind};
+use crate::types::song::{Clip, DanceLab, Mashup, MenuArt, Song, SongDirectoryTree, Timeline};
+
+/// Check all songs in the mod and collect their map names
+pub fn check(checker: &mut Checker<'_>) {
+    let songs_dir = checker.dir_tree.songs().to_path_buf();
+    let mut song_names = match songs_dir.read_dir() {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>(),
+        Err(error) => {
+            checker.report(IssueKind::UnreadableFile, &songs_dir, error.to_string());
+            return;
+        }
+    };
+    // Sort for a stable output
+    song_names.sort_unstable();
+
+    let mut seen: HashMap<String, PathBuf> = HashMap::new();
+    // Songs can refer to songs that come later, so these are checked after all songs are known
+    let mut map_references = Vec::new();
+    for song_name in song_names {
+        let dirs = SongDirectoryTree::new(&songs_dir, &song_name);
+        let song_file = dirs.song_file().to_path_buf();
+        let Some(data) = checker.read(&song_file) else {
+            continue;
+        };
+        let Some(song) = checker.parse::<Song>(&song_file, &data) else {
+            continue;
+        };
+
+        let lower_map_name = song.map_name.to_lowercase();
+        if let Some(other) = seen.get(&lower_map_name) {
+            let message = format!(
+                "Map name {} is also used by {}",
+                song.map_name,
+                other.display()
+            );
+            checker.report(IssueKind::DuplicateId, &song_file, message);
+        } else {
+            seen.insert(lower_map_name.clone(), song_file.clone());
+        }
+        checker.map_names.insert(lower_map_name);
+
+        check_song(checker, &dirs, &song);
+        map_references.extend(
+            map_references_of(song.dance_lab.as_ref(), song.mashup.as_ref())
+                .into_iter()
+                .map(|(map_name, what)| (song_file.clone(), map_name, what)),
+        );
+    }
+
+    for (song_file, map_name, what) in map_references {
+        checker.require_map_name(&song_file, &map_name, &what);
+    }
+}
+
+/// Get the maps that are used by the Dance Lab blocks and mashup segments, with a description
+fn map_references_of(
+    dance_lab: Option<&DanceLab<'_>>,
+    mashup: Option<&Mashup<'_>>,
+) -> Vec<(String, String)> {
+    let mut references = Vec::new();
+    for block in dance_lab.iter().flat_map(|dance_lab| &dance_lab.blocks) {
+        for descriptor in std::iter::once(&block.base).chain(&block.alternatives) {
+            // Empty blocks don't have a song
+            if !descriptor.song_name.is_empty() {
+                references.push((
+                    descriptor.song_name.to_string(),
+                    format!("the Dance Lab block {}", descriptor.guid),
+                ));
+            }
+        }
+    }
+    for segment in mashup.iter().flat_map(|mashup| &mashup.segments) {
+        references.push((
+            segment.source_map.to_string(),
+            format!(
+                "coach {} of the mashup at {}",
+                segment.coach_id, segment.start_time
+            ),
+        ));
+    }
+    references
+}
+
+/// Check the files and locale ids used by `song`
+fn check_song(checker: &mut Checker<'_>, dirs: &SongDirectoryTree, song: &Song<'_>) {
+    let song_file = dirs.song_file();
+    checker.require_file(song_file, &dirs.audio().join(song.audiofile.as_str()));
+    checker.require_file(song_file, &dirs.song().join(song.videofile.as_str()));
+    checker.require_locale_id(song_file, song.subtitle, "the subtitle");
+    checker.require_file(song_file, &dirs.song().join("musictrack.json"));
+
+    // Menuart textures and phone images
+    let menuart_file = dirs.menuart().join("menuart.json");
+    if let Some(data) = checker.read(&menuart_file) {
+        if let Some(menuart) = checker.parse::<Vec<MenuArt>>(&menuart_file, &data) {
+            for menuart in menuart {
+                let filename = match menuart {
+                    MenuArt::Texture(texture) => texture.filename,
+                    MenuArt::Phone(phone) => phone.filename,
+                };
+                checker.require_file(&menuart_file, &dirs.menuart().join(filename.as_str()));
+            }
+        }
+    }
+
+    // Classifiers and pictos
+    check_timeline(
+        checker,
+        &dirs.song().join("dance_timeline.json"),
+        |clip| match clip {
+            Clip::Motion(clip) => Some(dirs.moves().join(clip.classifier_filename.as_str())),
+            Clip::Pictogram(clip) => Some(dirs.pictos().join(clip.picto_filename.as_str())),
+            _ => None,
+        },
+    );
+    check_timeline(checker, &dirs.song().join("karaoke_timeline.json"), |_| {
+        None
+    });
+
+    // Ambient sounds
+    check_timeline(
+        checker,
+        &dirs.song().join("mainsequence.json"),
+        |clip| match clip {
+            Clip::SoundSet(clip) => Some(dirs.audio().join(clip.audio_filename.as_str())),
+            _ => None,
+        },
+    );
+}
+
+/// Check that the timeline at `path` can be parsed and that all files returned by `referenced`
+/// exist
+fn check_timeline(
+    checker: &mut Checker<'_>,
+    path: &Path,
+    referenced: impl Fn(&Clip<'_>) -> Option<PathBuf>,
+) {
+    let Some(data) = checker.read(path) else {
+        return;
+    };
+    let Some(timeline) = checker.parse::<Timeline>(path, &data) else {
+        return;
+    };
+    // Pictos and classifiers are used many times, only report them once
+    let files = timeline
+        .timeline
+        .iter()
+        .filter_map(referenced)
+        .collect::<BTreeSet<_>>();
+    for file in files {
+        checker.require_file(path, &file);
+    }
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use hipstr::HipStr;
+
+    use super::{map_references_of, Checker, IssueKind};
+    use crate::types::{
+        song::{Block, BlockDescriptor, DanceLab, Mashup},
+        DirectoryTree,
+    };
+
+    fn descriptor(song_name: &'static str, guid: &'static str) -> BlockDescriptor<'static> {
+        BlockDescriptor {
+            song_name: HipStr::borrowed(song_name),
+            first_beat: 0,
+            last_beat: 16,
+            song_switch: false,
+            video_coach_offset: (0.0, 0.0),
+            video_coach_scale: 1.0,
+            dance_step_name: HipStr::new(),
+            playing_speed: 1.0,
+            is_entry_point: false,
+            is_empty_block: song_name.is_empty(),
+            is_no_score_block: false,
+            guid: HipStr::borrowed(guid),
+            force_display_last_pictos: false,
+        }
+    }
+
+    #[test]
+    fn test_map_references_dance_lab() {
+        let dance_lab = DanceLab {
+            blocks: vec![
+                Block {
+                    base: descriptor("Toxic", "a"),
+                    alternatives: vec![descriptor("Bang", "b")],
+                },
+                Block {
+                    base: descriptor("", "c"),
+                    alternatives: Vec::new(),
+                },
+            ],
+            ..Default::default()
+        };
+        let references = map_references_of(Some(&dance_lab), None);
+        let map_names: Vec<_> = references.iter().map(|(map, _)| map.as_str()).collect();
+        assert_eq!(
+            map_names,
+            ["Toxic", "Bang"],
+            "Base and alternative blocks should be referenced, empty blocks skipped"
+        );
+    }
+
+    #[test]
+    fn test_map_references_mashup() {
+        let mut mashup = Mashup::default();
+        mashup.add_move(0, Some(HipStr::borrowed("toxic")), 0, 100);
+        mashup.add_move(1, Some(HipStr::borrowed("bang")), 0, 100);
+        mashup.add_move(0, Some(HipStr::borrowed("toxic")), 100, 100);
+        let references = map_references_of(None, Some(&mashup));
+        let map_names: Vec<_> = references.iter().map(|(map, _)| map.as_str()).collect();
+        assert_eq!(
+            map_names,
+            ["toxic", "bang"],
+            "Every segment should be referenced once"
+        );
+    }
+
+    #[test]
+    fn test_require_map_name() {
+        let dir_tree = DirectoryTree::new(Path::new("mod"));
+        let mut checker = Checker::new(&dir_tree);
+        checker.map_names.insert(String::from("toxic"));
+        let song_file = dir_tree.songs().join("Mashup/song.json");
+        checker.require_map_name(&song_file, "Toxic", "the mashup");
+        assert!(checker.issues.is_empty(), "Map names are case insensitive");
+        checker.require_map_name(&song_file, "Bang", "the mashup");
+        assert_eq!(checker.issues.len(), 1, "Unknown map should be reported");
+        assert_eq!(
+            checker.issues[0].kind,
+            IssueKind::DanglingMapName,
+            "Wrong kind of issue"
+        );
+        assert_eq!(
+            checker.issues[0].file,
+            Path::new("songs/Mashup/song.json"),
+            "File should be relative to the mod"
+        );
+    }
+}
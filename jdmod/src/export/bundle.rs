@@ -2,6 +2,7 @@
 //! Contains the code for bundling files into .ipk files
 use std::{
     fs::File,
+    num::NonZeroUsize,
     path::Path,
     sync::{
         atomic::{AtomicU8, Ordering},
@@ -15,33 +16,88 @@ use dotstar_toolkit_utils::{
     bytes::write::WriteAt,
     vfs::{
         layeredfs::OverlayFs, native::NativeFs, symlinkfs::SymlinkFs, vecfs::VecFs,
-        VirtualFileSystem,
+        VirtualFileSystem, VirtualPath, VirtualPathBuf,
     },
 };
 use test_eq::test_eq;
 use ubiart_toolkit::{
-    ipk::{self, vfs::IpkFilesystem},
-    secure_fat::SecureFat,
+    ipk,
+    secure_fat::{bundle_name_to_filename, SecureFat},
     utils::PathId,
 };
 
-use super::FilesToAdd;
-use crate::{build::BuildFiles, types::Config};
+use super::{BundleOptions, FilesToAdd, SplitStrategy};
+use crate::{
+    build::BuildFiles,
+    types::{Config, DirectoryTree, RelativeDirectoryTree},
+};
 
 /// Maximum file size for FAT32
-const MAX_BUNDLE_SIZE_FAT32: u64 = 4_294_967_295;
+pub const MAX_BUNDLE_SIZE_FAT32: u64 = 4_294_967_295;
+
+/// Maximum amount of song bundles
+///
+/// A `secure_fat.gf` can reference 256 bundles, one of which is the main bundle
+pub const MAX_SONG_BUNDLES: usize = 255;
+
+/// Divides the threads for compressing files over the bundles that are created at the same time
+pub struct ThreadBudget {
+    /// The total amount of threads
+    threads: usize,
+    /// The threads that are not compressing and the amount of bundles that are being created
+    state: Mutex<(usize, usize)>,
+}
+
+impl ThreadBudget {
+    /// Create a budget of `threads` threads
+    #[must_use]
+    pub const fn new(threads: NonZeroUsize) -> Self {
+        Self {
+            threads: threads.get(),
+            state: Mutex::new((threads.get(), 0)),
+        }
+    }
+
+    /// Run `f` with the threads it may use for compressing a bundle
+    ///
+    /// Every bundle gets an equal share of the budget, as far as those threads are free. A bundle
+    /// that gets no free threads is compressed on the calling thread.
+    ///
+    /// # Panics
+    /// Will panic if the lock is poisoned
+    pub fn with_share<T>(&self, f: impl FnOnce(NonZeroUsize) -> T) -> T {
+        let claimed = {
+            let mut state = self.state.lock().expect("Poisoned lock, terminating");
+            let (free, running) = &mut *state;
+            *running = running.saturating_add(1);
+            let claimed = (*free).min(self.threads.div_ceil(*running));
+            *free = free.saturating_sub(claimed);
+            claimed
+        };
+
+        let result = f(NonZeroUsize::new(claimed).unwrap_or(NonZeroUsize::MIN));
+
+        let mut state = self.state.lock().expect("Poisoned lock, terminating");
+        let (free, running) = &mut *state;
+        *free = free.saturating_add(claimed);
+        *running = running.saturating_sub(1);
+        result
+    }
+}
 
 /// Receives files in `rx` and bundles them into .ipk files at `destination`
 ///
 /// # Panics
 /// Will panic if the lock is poisoned
+#[allow(clippy::too_many_arguments, reason = "It's just easier this way")]
 pub fn bundle<'fs: 'bf, 'bf>(
-    bundle_vfs: &IpkFilesystem<'_>,
-    patch_vfs: &IpkFilesystem<'_>,
+    patched_base_vfs: &OverlayFs<'_>,
     native_vfs: &'fs NativeFs,
     rx_files: &Receiver<FilesToAdd>,
     tx_bundle_job: Sender<(Arc<Mutex<SecureFat>>, BuildFiles<'bf>)>,
     config: Config,
+    options: BundleOptions,
+    thread_budget: &ThreadBudget,
     destination: &Path,
 ) -> Result<(), Error> {
     // Make sure the destination directory actually exists
@@ -59,10 +115,18 @@ pub fn bundle<'fs: 'bf, 'bf>(
     };
 
     // For files that are only used when playing a song
+    // The generated files are written to the staging directory, so this only contains static files
     let mut song_files = BuildFiles {
-        generated_files: VecFs::with_capacity(1000),
+        generated_files: VecFs::new(),
         static_files: SymlinkFs::with_capacity(native_vfs, 500),
     };
+    // The size of the files in `song_files`
+    let mut song_files_size = 0;
+    // The amount of songs in `song_files`
+    let mut song_files_count = 0;
+
+    let dir_tree = DirectoryTree::new(native_vfs.root());
+    let rel_tree = RelativeDirectoryTree::new();
 
     let mut sfat = Arc::new(Mutex::new(SecureFat::with_capacity(
         config.game_platform,
@@ -83,18 +147,36 @@ pub fn bundle<'fs: 'bf, 'bf>(
                 bundle_files.merge(new_bundle_files)?;
             }
             Ok(FilesToAdd::Song(new_song_files)) => {
-                // Check if the song bundle would be too big if this song is added
-                if song_files.size()? + new_song_files.size()? >= MAX_BUNDLE_SIZE_FAT32 {
+                let current_size = song_files_size;
+                let new_size = new_song_files.size()?;
+                if new_size >= options.max_bundle_size {
+                    println!(
+                        "Warning! A song is {new_size} bytes, which is more than the maximum bundle size of {} bytes",
+                        options.max_bundle_size
+                    );
+                }
+                // Check if the song needs to go into a new bundle
+                let start_new_bundle = match options.split {
+                    SplitStrategy::Size => current_size + new_size >= options.max_bundle_size,
+                    SplitStrategy::Song => song_files_count >= options.songs_per_bundle,
+                };
+                if start_new_bundle && current_size != 0 {
                     // Save this bundle and create a new one
                     tx_bundle_job
                         .send((sfat.clone(), song_files))
                         .expect("Broken jobs channel, terminating");
                     song_files = BuildFiles {
-                        generated_files: VecFs::with_capacity(1000),
+                        generated_files: VecFs::new(),
                         static_files: SymlinkFs::with_capacity(native_vfs, 500),
                     };
+                    song_files_size = 0;
+                    song_files_count = 0;
                 }
+                song_files_size += new_size;
+                song_files_count += 1;
                 for (path, content) in new_song_files.generated_files {
+                    // Write the file to the staging directory, so it's not kept in memory
+                    let staged_path = stage_file(&dir_tree, &rel_tree, &path, &content)?;
                     // Check if the file belongs to the main bundle
                     if path
                         .file_name()
@@ -116,10 +198,10 @@ pub fn bundle<'fs: 'bf, 'bf>(
                             .is_some_and(|s| s.ends_with("songdesc.tpl.ckd"))
                     {
                         // Add the file to the main bundle
-                        bundle_files.generated_files.add_file(path, content)?;
+                        bundle_files.static_files.add_file(staged_path, path)?;
                     } else {
                         // Add the file to the song bundle
-                        song_files.generated_files.add_file(path, content)?;
+                        song_files.static_files.add_file(staged_path, path)?;
                     }
                 }
                 for (new_path, orig_path) in new_song_files.static_files {
@@ -141,48 +223,61 @@ pub fn bundle<'fs: 'bf, 'bf>(
     }
 
     // Save last song bundle
-    tx_bundle_job
-        .send((sfat.clone(), song_files))
-        .expect("Broken jobs channel, terminating");
+    if song_files_size != 0 {
+        tx_bundle_job
+            .send((sfat.clone(), song_files))
+            .expect("Broken jobs channel, terminating");
+    }
     // Other threads will keep waiting for jobs until this channel is closed/dropped
     drop(tx_bundle_job);
 
     println!("Bundling jobs done!");
 
     // Create empty patch file
-    let mut patch_file = File::create(destination.join("patch_nx.ipk"))?;
-    ipk::write(
-        &mut patch_file,
-        &mut 0,
-        ipk::Options {
-            compression: ipk::CompressionEffort::Best,
-            game_platform: config.game_platform,
-            unk4: config.ipk_unk4,
-            engine_version: config.engine_version,
-        },
-        native_vfs,
-        &[],
-    )?;
+    let mut patch_file =
+        File::create(destination.join(bundle_name_to_filename("patch", config.game_platform)))?;
+    thread_budget.with_share(|threads| {
+        ipk::write(
+            &mut patch_file,
+            &mut 0,
+            ipk::Options {
+                compression: ipk::CompressionEffort::Best,
+                game_platform: config.game_platform,
+                unk4: config.ipk_unk4,
+                engine_version: config.engine_version,
+                threads,
+            },
+            native_vfs,
+            &[],
+        )
+    })?;
 
     // Create main bundle
     println!("Creating main bundle");
     let bundle_files_vfs =
         OverlayFs::new(&bundle_files.generated_files, &bundle_files.static_files);
-    let patched_bundle_vfs = OverlayFs::new(patch_vfs, bundle_vfs);
-    let vfs = OverlayFs::new(&bundle_files_vfs, &patched_bundle_vfs);
+    let vfs = OverlayFs::new(&bundle_files_vfs, patched_base_vfs);
     let filenames: Vec<_> = vfs.walk_filesystem("".as_ref())?.collect();
 
-    ipk::create(
-        destination.join("bundle_nx.ipk"),
-        ipk::Options {
-            compression: ipk::CompressionEffort::Best,
-            game_platform: config.game_platform,
-            unk4: config.ipk_unk4,
-            engine_version: config.engine_version,
-        },
-        &vfs,
-        &filenames,
-    )?;
+    let main_bundle_path =
+        destination.join(bundle_name_to_filename("bundle", config.game_platform));
+    thread_budget.with_share(|threads| {
+        ipk::create(
+            &main_bundle_path,
+            ipk::Options {
+                compression: ipk::CompressionEffort::Best,
+                game_platform: config.game_platform,
+                unk4: config.ipk_unk4,
+                engine_version: config.engine_version,
+                threads,
+            },
+            &vfs,
+            &filenames,
+        )
+    })?;
+    if main_bundle_path.metadata()?.len() >= MAX_BUNDLE_SIZE_FAT32 {
+        println!("Warning! The main bundle is bigger than 4 GB and therefore not compatible with the FAT32 filesystem.");
+    }
 
     {
         // Link all the file paths to the bundle
@@ -210,10 +305,28 @@ pub fn bundle<'fs: 'bf, 'bf>(
     Ok(())
 }
 
+/// Write a generated file to the staging directory
+///
+/// Returns the path of the staged file in the mod directory, which can be added to a [`SymlinkFs`].
+fn stage_file(
+    dir_tree: &DirectoryTree,
+    rel_tree: &RelativeDirectoryTree,
+    path: &VirtualPath,
+    content: &[u8],
+) -> Result<VirtualPathBuf, Error> {
+    let relative = path.as_str().trim_start_matches('/');
+    let native_path = dir_tree.export().join(relative);
+    if let Some(parent) = native_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&native_path, content).with_context(|| native_path.display().to_string())?;
+    Ok(rel_tree.export().join(relative))
+}
+
 /// Atomic counter for the song bundle nummer
 static BUNDLE_N: AtomicU8 = AtomicU8::new(0);
 
-/// Create the nth bundle file with songs
+/// Create the nth bundle file with songs, compressing the files on its share of `thread_budget`
 ///
 /// # Panics
 /// Will panic if the lock is poisoned
@@ -221,11 +334,14 @@ pub fn save_songs_bundle(
     sfat: &Arc<Mutex<SecureFat>>,
     bundle_files: &BuildFiles,
     config: Config,
+    thread_budget: &ThreadBudget,
     destination: &Path,
 ) -> Result<(), Error> {
     let bundle_n = BUNDLE_N.fetch_add(1, Ordering::AcqRel);
-    if bundle_n == u8::MAX {
-        return Err(anyhow!("Too many bundles! "));
+    if usize::from(bundle_n) >= MAX_SONG_BUNDLES {
+        return Err(anyhow!(
+            "Too many bundles! A secure_fat.gf can reference at most {MAX_SONG_BUNDLES} song bundles, use a bigger maximum bundle size"
+        ));
     }
 
     let name = format!("songs_{bundle_n}");
@@ -235,17 +351,20 @@ pub fn save_songs_bundle(
     let files = overlay_vfs.walk_filesystem("".as_ref())?;
     let filenames: Vec<_> = files.collect::<Vec<_>>();
 
-    ipk::create(
-        destination.join(format!("{name}_nx.ipk")),
-        ipk::Options {
-            compression: ipk::CompressionEffort::Best,
-            game_platform: config.game_platform,
-            unk4: config.ipk_unk4,
-            engine_version: config.engine_version,
-        },
-        &overlay_vfs,
-        &filenames,
-    )?;
+    thread_budget.with_share(|threads| {
+        ipk::create(
+            destination.join(bundle_name_to_filename(&name, config.game_platform)),
+            ipk::Options {
+                compression: ipk::CompressionEffort::Best,
+                game_platform: config.game_platform,
+                unk4: config.ipk_unk4,
+                engine_version: config.engine_version,
+                threads,
+            },
+            &overlay_vfs,
+            &filenames,
+        )
+    })?;
 
     {
         let mut sfat = sfat.lock().expect("Poisoned lock, terminating");
@@ -259,3 +378,33 @@ pub fn save_songs_bundle(
 
     Ok(())
 }
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::ThreadBudget;
+
+    #[test]
+    fn test_thread_budget() {
+        let budget = ThreadBudget::new(NonZeroUsize::new(4).unwrap());
+        budget.with_share(|outer| {
+            assert_eq!(outer.get(), 4, "A single bundle should get all threads");
+            budget.with_share(|inner| {
+                assert_eq!(
+                    inner.get(),
+                    1,
+                    "A bundle should use its own thread if no threads are free"
+                );
+            });
+        });
+        budget.with_share(|threads| {
+            assert_eq!(
+                threads.get(),
+                4,
+                "The threads should be returned to the budget"
+            );
+        });
+    }
+}
@@ -1,18 +1,18 @@
 //! # Export
-//! Builds the mod into a format that Just Dance 2022 can understand and then bundles it into .ipk files
+//! Builds the mod into a format that the base game can understand and then bundles it into .ipk files
 use std::{
-    num::NonZeroUsize,
+    num::{NonZeroU64, NonZeroUsize},
     path::{Path, PathBuf},
 };
 
 use anyhow::{bail, Error};
-use clap::Args;
+use clap::{Args, ValueEnum};
 use crossbeam::channel::TryRecvError;
 use dotstar_toolkit_utils::vfs::{
     layeredfs::OverlayFs, native::NativeFs, symlinkfs::SymlinkFs, vecfs::VecFs, VirtualFileSystem,
 };
 use tracing::instrument;
-use ubiart_toolkit::{ipk::vfs::IpkFilesystem, utils::Platform};
+use ubiart_toolkit::ipk::vfs::IpkFilesystem;
 
 use crate::{
     build::{self, BuildFiles, BuildState},
@@ -28,11 +28,76 @@ pub struct Build {
     source: PathBuf,
     /// Directory to put the bundles
     destination: PathBuf,
-    /// Use n threads
+    /// Use n threads for building the songs and compressing the bundles
     ///
     /// Note: 3 threads is the minimum, any number below that will be ignored
     #[arg(long)]
     threads: Option<NonZeroUsize>,
+    /// How to divide the songs over the song bundles
+    #[arg(long, value_enum, default_value_t = SplitStrategy::Size)]
+    split: SplitStrategy,
+    /// Maximum size of a song bundle in bytes
+    ///
+    /// Defaults to the maximum file size of the FAT32 filesystem
+    #[arg(long)]
+    max_bundle_size: Option<NonZeroU64>,
+    /// Ignore the build cache and rebuild every song
+    #[arg(long)]
+    clean: bool,
+}
+
+/// Strategies for dividing the songs over the song bundles
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SplitStrategy {
+    /// Fill every song bundle up to the maximum bundle size
+    Size,
+    /// Put every song in its own bundle
+    ///
+    /// If there are more songs than song bundles, the songs are divided evenly over the bundles
+    Song,
+}
+
+/// Options for dividing the songs over the song bundles
+#[derive(Clone, Copy, Debug)]
+pub struct BundleOptions {
+    /// How to divide the songs over the song bundles
+    pub split: SplitStrategy,
+    /// Maximum size of a song bundle in bytes
+    pub max_bundle_size: u64,
+    /// Amount of songs in a song bundle when splitting per song
+    pub songs_per_bundle: usize,
+    /// Amount of threads to use for compressing the bundles, divided over the bundles that are
+    /// created at the same time
+    pub threads: NonZeroUsize,
+}
+
+impl BundleOptions {
+    /// Make sure `n_songs` songs fit in the song bundles a `secure_fat.gf` can reference
+    ///
+    /// When splitting per song and there are more songs than song bundles, the songs are grouped.
+    #[must_use]
+    pub fn fit_songs(mut self, n_songs: usize) -> Self {
+        if self.split == SplitStrategy::Song && n_songs > bundle::MAX_SONG_BUNDLES {
+            self.songs_per_bundle = n_songs.div_ceil(bundle::MAX_SONG_BUNDLES);
+            println!(
+                "Warning! There are {n_songs} songs but only {} song bundles are possible, putting {} songs in every bundle",
+                bundle::MAX_SONG_BUNDLES,
+                self.songs_per_bundle
+            );
+        }
+        self
+    }
+}
+
+impl Default for BundleOptions {
+    fn default() -> Self {
+        Self {
+            split: SplitStrategy::Size,
+            max_bundle_size: bundle::MAX_BUNDLE_SIZE_FAT32,
+            songs_per_bundle: 1,
+            threads: NonZeroUsize::MIN,
+        }
+    }
 }
 
 /// Files that need to be added to bundle
@@ -45,10 +110,26 @@ pub enum FilesToAdd<'a> {
 
 /// Wrapper around [`export`]
 pub fn main(cli: &Build) -> Result<(), Error> {
-    export(&cli.source, &cli.destination, cli.threads)
+    let bundle_options = BundleOptions {
+        split: cli.split,
+        max_bundle_size: cli
+            .max_bundle_size
+            .map_or(bundle::MAX_BUNDLE_SIZE_FAT32, NonZeroU64::get),
+        ..Default::default()
+    };
+    export(
+        &cli.source,
+        &cli.destination,
+        cli.threads,
+        bundle_options,
+        cli.clean,
+    )
 }
 
-/// Builds the mod into a format that Just Dance 2022 can understand and then bundles it into .ipk files
+/// Builds the mod into a format that the base game can understand and then bundles it into .ipk files
+///
+/// Songs that did not change since the previous export are taken from the build cache, unless
+/// `clean` is set.
 ///
 /// # Panics
 /// Will panic if any of the threads it creates return an error
@@ -57,6 +138,8 @@ pub fn export(
     source: &Path,
     destination: &Path,
     n_threads: Option<NonZeroUsize>,
+    bundle_options: BundleOptions,
+    clean: bool,
 ) -> Result<(), Error> {
     // Check the directory structure
     let dir_tree = DirectoryTree::new(source);
@@ -74,14 +157,29 @@ pub fn export(
         std::fs::create_dir(destination)?;
     }
 
+    // Generated song files are staged here until they are bundled, remove leftovers of old exports
+    if dir_tree.export().exists() {
+        std::fs::remove_dir_all(dir_tree.export())?;
+    }
+    std::fs::create_dir(dir_tree.export())?;
+
+    // Throw away the build cache, so every song is rebuilt
+    if clean && dir_tree.cache().exists() {
+        std::fs::remove_dir_all(dir_tree.cache())?;
+    }
+
     // Do everything through a virtual filesystem with the mod directory as the root
     let native_vfs = NativeFs::new(dir_tree.root())?;
     let rel_tree = RelativeDirectoryTree::new();
 
-    // Load bundle_nx.ipk and patch_nx.ipk to use as a base
-    let bundle_nx_vfs = IpkFilesystem::new(&native_vfs, &rel_tree.base().join("bundle_nx.ipk"))?;
-    let patch_nx_vfs = IpkFilesystem::new(&native_vfs, &rel_tree.base().join("patch_nx.ipk"))?;
-    let patched_base_vfs = OverlayFs::new(&patch_nx_vfs, &bundle_nx_vfs);
+    let config: Config =
+        serde_json::from_slice(&native_vfs.open(&rel_tree.dot_mod().join("config.json"))?)?;
+
+    // Load the bundle and patch of the base game to use as a base
+    let [bundle_name, patch_name] = build::base_ipk_names(config.game_platform);
+    let bundle_vfs = IpkFilesystem::new(&native_vfs, &rel_tree.base().join(&bundle_name))?;
+    let patch_vfs = IpkFilesystem::new(&native_vfs, &rel_tree.base().join(&patch_name))?;
+    let patched_base_vfs = OverlayFs::new(&patch_vfs, &bundle_vfs);
 
     /*
      * 1 thread bundles build files into ipks. It will only bundle song files until the channel is dropped
@@ -93,33 +191,42 @@ pub fn export(
      * After bundle thread receives song database it will built the bundle_nx.ipk
      */
 
+    // Get a list of all songs in the directory
+    let mut paths: Vec<_> = native_vfs
+        .walk_filesystem(rel_tree.songs())?
+        .filter(|p| p.file_name() == Some("song.json"))
+        .filter_map(|p| p.parent())
+        .collect();
+    // Sort them, so we go through them alphabetically. This way the user can see how far we are in building the songs.
+    paths.sort();
+
     // Setup the (read-only) build state
-    let config: Config =
-        serde_json::from_slice(&native_vfs.open(&rel_tree.dot_mod().join("config.json"))?)?;
-    let platform = Platform::Nx;
+    let cache_key = build::cache::base_key(&native_vfs, &rel_tree, config.game_platform)?;
     let build_state = BuildState {
         patched_base_vfs: &patched_base_vfs,
         native_vfs: &native_vfs,
         rel_tree,
-        platform,
+        ugi: config.game_platform,
+        jd_version: build::jd_version(config.game_platform)?,
+        cache_key,
+        map_dirs: build::cache::map_dirs(&native_vfs, &paths)?,
         engine_version: config.engine_version,
     };
 
-    // Get a list of all songs in the directory
-    let mut paths: Vec<_> = build_state
-        .native_vfs
-        .walk_filesystem(build_state.rel_tree.songs())?
-        .filter(|p| p.file_name() == Some("song.json"))
-        .filter_map(|p| p.parent())
-        .collect();
-    // Sort them, so we go through them alphabetically. This way the user can see how far we are in building the songs.
-    paths.sort();
+    // Used to remove songs that are no longer in the mod from the build cache
+    let song_dirs: Vec<_> = paths.iter().filter_map(|p| p.file_name()).collect();
 
     let n_threads = if let Some(n_threads) = n_threads {
-        usize::from(n_threads)
+        n_threads
     } else {
-        usize::from(std::thread::available_parallelism()?)
+        std::thread::available_parallelism()?
     };
+    let bundle_options = BundleOptions {
+        threads: n_threads,
+        ..bundle_options.fit_songs(paths.len())
+    };
+    // Multiple bundles can be created at the same time, they share the threads
+    let thread_budget = bundle::ThreadBudget::new(bundle_options.threads);
 
     let (tx_name, rx_name) = crossbeam::channel::unbounded();
     let (tx_job, rx_job) = crossbeam::channel::unbounded();
@@ -136,17 +243,19 @@ pub fn export(
     std::thread::scope(|s| {
         let build_state = &build_state;
         let native_vfs = build_state.native_vfs;
+        let thread_budget = &thread_budget;
         {
             std::thread::Builder::new()
                 .name("Bundle".to_string())
                 .spawn_scoped(s, || {
                     bundle::bundle(
-                        &bundle_nx_vfs,
-                        &patch_nx_vfs,
+                        &patched_base_vfs,
                         native_vfs,
                         &rx_files,
                         tx_bundle_job,
                         config,
+                        bundle_options,
+                        thread_budget,
                         destination,
                     )
                     .unwrap();
@@ -244,6 +353,7 @@ pub fn export(
                                     &sfat,
                                     &bundle_files,
                                     config,
+                                    thread_budget,
                                     destination,
                                 )
                                 .unwrap();
@@ -258,6 +368,7 @@ pub fn export(
                                     &sfat,
                                     &bundle_files,
                                     config,
+                                    thread_budget,
                                     destination,
                                 )
                                 .unwrap();
@@ -274,7 +385,7 @@ pub fn export(
         }
 
         // Only start as many threads as there are cpus (excluding the main thread, which will be waiting and doing nothing the entire time)
-        for i in 0..n_threads.saturating_sub(3) {
+        for i in 0..n_threads.get().saturating_sub(3) {
             let tx_name = tx_name.clone();
             let rx_job = rx_job.clone();
             let tx_files = tx_files.clone();
@@ -308,6 +419,7 @@ pub fn export(
                                     &sfat,
                                     &bundle_files,
                                     config,
+                                    thread_budget,
                                     destination,
                                 )
                                 .unwrap();
@@ -322,6 +434,7 @@ pub fn export(
                                     &sfat,
                                     &bundle_files,
                                     config,
+                                    thread_budget,
                                     destination,
                                 )
                                 .unwrap();
@@ -342,6 +455,9 @@ pub fn export(
         drop(tx_name);
     });
 
+    std::fs::remove_dir_all(dir_tree.export())?;
+    build::cache::prune(&dir_tree, &song_dirs)?;
+
     println!("Done!");
 
     Ok(())
@@ -20,6 +20,56 @@ pub fn import(is: &ImportState<'_>, songdb_scene: &str) -> Result<(), Error> {
     let songdb_scene_file = is.vfs.open(cook_path(songdb_scene, is.ugi)?.as_ref())?;
     let songdb_scene = cooked::isc::parse(&songdb_scene_file, is.ugi)?;
 
+    let mut songdescs = Vec::new();
+    for actors in &songdb_scene.scene.actors {
+        let actor = actors.actor().unwrap();
+        if actor
+            .components
+            .iter()
+            .any(|c| matches!(c, WrappedComponent::SongDesc(_)))
+        {
+            songdescs.push(actor.lua.as_str());
+        }
+    }
+
+    if is.ugi.game == Game::JustDance2019
+        && is.ugi.platform != Platform::Wii
+        && is.ugi.platform != Platform::X360
+    {
+        // Nice For What by Drake was removed from the songdb on 8th gen consoles
+        songdescs.push("world/maps/niceforwhat/songdesc.tpl");
+    }
+
+    import_songdescs(is, &songdescs)
+}
+
+/// Imports every song in the maps directory using [`song::import`]
+///
+/// Used for the Wii versions, where the song database is not in a supported format.
+///
+/// # Panics
+/// See [`import`]
+pub fn import_maps_directory(is: &ImportState<'_>) -> Result<(), Error> {
+    println!("Importing songs...");
+    let maps = cook_path("world/maps/", is.ugi)?;
+    let songdescs: Vec<String> = is
+        .vfs
+        .walk_filesystem(maps.as_ref())?
+        .filter_map(|path| path.as_str().strip_prefix(maps.as_str()))
+        .filter_map(|path| path.strip_suffix("/songdesc.tpl.ckd"))
+        .filter(|map| !map.contains('/'))
+        .map(|map| format!("world/maps/{map}/songdesc.tpl"))
+        .collect();
+    let songdescs: Vec<&str> = songdescs.iter().map(String::as_str).collect();
+
+    import_songdescs(is, &songdescs)
+}
+
+/// Import the songs at `songdescs` on multiple threads
+///
+/// # Panics
+/// See [`import`]
+fn import_songdescs(is: &ImportState<'_>, songdescs: &[&str]) -> Result<(), Error> {
     let n_threads = if let Some(n_threads) = is.n_threads {
         usize::from(n_threads)
     } else {
@@ -30,25 +80,8 @@ pub fn import(is: &ImportState<'_>, songdb_scene: &str) -> Result<(), Error> {
     std::thread::scope(|s| {
         let (tx_job, rx_job) = crossbeam::channel::unbounded::<(&ImportState, &str)>();
 
-        for actors in &songdb_scene.scene.actors {
-            let actor = actors.actor().unwrap();
-            if actor
-                .components
-                .iter()
-                .any(|c| matches!(c, WrappedComponent::SongDesc(_)))
-            {
-                tx_job.send((is, &actor.lua)).unwrap();
-            }
-        }
-
-        if is.ugi.game == Game::JustDance2019
-            && is.ugi.platform != Platform::Wii
-            && is.ugi.platform != Platform::X360
-        {
-            // Nice For What by Drake was removed from the songdb on 8th gen consoles
-            tx_job
-                .send((is, "world/maps/niceforwhat/songdesc.tpl"))
-                .unwrap();
+        for songdesc in songdescs {
+            tx_job.send((is, *songdesc)).unwrap();
         }
 
         for i in 0..n_threads {
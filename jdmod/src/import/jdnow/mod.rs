@@ -1,6 +1,11 @@
 //! Import functionality for Just Dance Now data
 
-use std::{collections::BTreeSet, fs::File, ops::Deref, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    ops::Deref,
+    path::Path,
+};
 
 use anyhow::{anyhow, Context, Error};
 use bluestar_toolkit::{Moves, PictoAtlas, SongDetails};
@@ -97,6 +102,11 @@ pub fn import_song(
         default_colors: (&state.details.default_colors).into(),
         audiofile: HipStr::from(audiofile),
         videofile: HipStr::borrowed(videofile),
+        mashup: None,
+        dance_lab: None,
+        double_scoring: None,
+        showtime: None,
+        party_master: None,
     };
 
     let song_file = File::create(state.song.song_file())?;
@@ -234,6 +244,7 @@ fn parse_classifiers(state: &mut NowState, timeline: &mut Timeline) -> Result<i3
                 gold_move: class.gold_move.is_some(),
                 coach_id: coach,
                 color: Color::default(),
+                platform_specifics: BTreeMap::new(),
             });
 
             min_time = min_time.min(clip.start_time());
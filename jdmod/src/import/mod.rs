@@ -29,10 +29,11 @@ use ubiart_toolkit::{
     secure_fat::vfs::SfatFilesystem,
     utils::{Game, Platform, UniqueGameId},
 };
+use wii_toolkit::disc::vfs::DiscFilesystem;
 
 use crate::{
-    types::{localisation::LocaleIdMap, DirectoryTree, ImportState},
-    utils::cook_path,
+    types::{localisation::LocaleIdMap, DirectoryTree, ImportState, ImportSummary},
+    utils::{cook_path, is_switch_dump, is_wii_disc, load_switch_keys},
 };
 
 mod gameconfig;
@@ -45,7 +46,7 @@ mod song;
 pub struct Import {
     /// Path of the game to import
     ///
-    /// Supported files are: secure_fat.gf, dlcdescriptor.ckd
+    /// Supported files are: secure_fat.gf, dlcdescriptor.ckd, .nsp, .xci, .iso, .wbfs
     game_path: PathBuf,
     /// Mod directory
     mod_path: PathBuf,
@@ -61,6 +62,14 @@ pub struct Import {
     /// Use n threads for importing songs
     #[arg(long)]
     threads: Option<NonZeroUsize>,
+    /// Keys for decrypting .nsp and .xci files, defaults to ~/.switch/prod.keys
+    #[arg(long)]
+    keys: Option<PathBuf>,
+    /// Wii common key for decrypting .iso and .wbfs files
+    ///
+    /// The file contains the 16 byte key or the key encoded as 32 hex characters
+    #[arg(long)]
+    common_key: Option<PathBuf>,
     /// Transcode options
     #[clap(flatten)]
     transcode: TranscodeSettings,
@@ -99,12 +108,25 @@ pub fn main(cli: &Import) -> Result<(), Error> {
         cli.songs,
         cli.game,
         cli.threads,
+        cli.keys.as_deref(),
+        cli.common_key.as_deref(),
         cli.transcode,
     )
 }
 
 /// Import a game at `game_path` into the mod at `dir_root`
-#[tracing::instrument(skip(game_path, dir_root, lax, songs_only, game, n_threads, transcode))]
+#[allow(clippy::too_many_arguments, reason = "It's just easier this way")]
+#[tracing::instrument(skip(
+    game_path,
+    dir_root,
+    lax,
+    songs_only,
+    game,
+    n_threads,
+    keys_path,
+    common_key_path,
+    transcode
+))]
 pub fn import(
     game_path: &Path,
     dir_root: &Path,
@@ -112,6 +134,8 @@ pub fn import(
     songs_only: bool,
     game: Option<Game>,
     n_threads: Option<NonZeroUsize>,
+    keys_path: Option<&Path>,
+    common_key_path: Option<&Path>,
     transcode: TranscodeSettings,
 ) -> Result<(), Error> {
     // Check the directory structure
@@ -126,6 +150,20 @@ pub fn import(
 
     if game_path.ends_with("secure_fat.gf") {
         import_sfat(game_path, dir_tree, lax, songs_only, n_threads, transcode)?;
+    } else if is_switch_dump(game_path) {
+        import_switch_dump(
+            game_path, dir_tree, lax, songs_only, n_threads, keys_path, transcode,
+        )?;
+    } else if is_wii_disc(game_path) {
+        import_wii_disc(
+            game_path,
+            dir_tree,
+            lax,
+            songs_only,
+            n_threads,
+            common_key_path,
+            transcode,
+        )?;
     } else if game_path.ends_with("dlcdescriptor.ckd") {
         import_dlcdescriptor(game_path, dir_tree, lax, transcode)?;
     } else if game_path.ends_with("songdesc.tpl.ckd") {
@@ -197,7 +235,7 @@ pub fn import(
             }
         }
     } else {
-        bail!("Cannot import {game_path:?}! Input not recognized, currently only secure_fat.gf, JD Now .json files, and raw import are supported!");
+        bail!("Cannot import {game_path:?}! Input not recognized, currently only secure_fat.gf, .nsp, .xci, .iso, .wbfs, JD Now .json files, and raw import are supported!");
     }
 
     Ok(())
@@ -250,6 +288,88 @@ fn import_sfat(
     )
 }
 
+/// Import a game from a NSP or XCI
+fn import_switch_dump(
+    game_path: &Path,
+    dir_tree: DirectoryTree,
+    lax: bool,
+    songs_only: bool,
+    n_threads: Option<NonZeroUsize>,
+    keys_path: Option<&Path>,
+    transcode: TranscodeSettings,
+) -> Result<(), Error> {
+    let keys = load_switch_keys(keys_path)?;
+
+    // Init the native filesystem and load the RomFS and the securefat as virtual filesystems
+    let native_vfs = NativeFs::new(
+        game_path
+            .parent()
+            .ok_or_else(|| anyhow!("No parent directory for {}!", game_path.display()))?,
+    )?;
+    let file_name = game_path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| anyhow!("Invalid game path {}!", game_path.display()))?;
+    let romfs = nx_toolkit::open_romfs(native_vfs.open(VirtualPath::new(file_name))?, &keys)?;
+    let sfat_vfs = SfatFilesystem::new(&romfs, VirtualPath::new("secure_fat.gf"))?;
+
+    // TODO: Check engine version and warn user they're missing an update
+    let unique_game_id = sfat_vfs.unique_game_id();
+
+    // Import songs and other content from the game
+    import_full_game_vfs(
+        &sfat_vfs,
+        dir_tree,
+        unique_game_id,
+        lax,
+        songs_only,
+        n_threads,
+        transcode,
+    )
+}
+
+/// Import a game from a Wii ISO or WBFS image
+fn import_wii_disc(
+    game_path: &Path,
+    dir_tree: DirectoryTree,
+    lax: bool,
+    songs_only: bool,
+    n_threads: Option<NonZeroUsize>,
+    common_key_path: Option<&Path>,
+    transcode: TranscodeSettings,
+) -> Result<(), Error> {
+    let common_key_path = common_key_path
+        .ok_or_else(|| anyhow!("Wii discs are encrypted, use --common-key to decrypt them!"))?;
+    let common_key = wii_toolkit::parse_key(&std::fs::read(common_key_path)?)?;
+
+    // Init the native filesystem and load the disc and the securefat as virtual filesystems
+    let native_vfs = NativeFs::new(
+        game_path
+            .parent()
+            .ok_or_else(|| anyhow!("No parent directory for {}!", game_path.display()))?,
+    )?;
+    let file_name = game_path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| anyhow!("Invalid game path {}!", game_path.display()))?;
+    let disc = DiscFilesystem::new(native_vfs.open(VirtualPath::new(file_name))?, &common_key)?;
+    let sfat_vfs = SfatFilesystem::new(&disc, VirtualPath::new("secure_fat.gf"))?;
+
+    // TODO: Check engine version and warn user they're missing an update
+    let unique_game_id = sfat_vfs.unique_game_id();
+
+    // Import songs and other content from the game
+    import_full_game_vfs(
+        &sfat_vfs,
+        dir_tree,
+        unique_game_id,
+        lax,
+        songs_only,
+        n_threads,
+        transcode,
+    )
+}
+
 /// Import a song from a dlcdescriptor.ckd
 fn import_dlcdescriptor(
     game_path: &Path,
@@ -279,6 +399,7 @@ fn import_dlcdescriptor(
         lax,
         n_threads: NonZeroUsize::new(1),
         transcode,
+        summary: ImportSummary::default(),
     };
 
     let dlcdescriptor_file = native_vfs.open(VirtualPath::new("dlcdescriptor.ckd"))?;
@@ -286,6 +407,9 @@ fn import_dlcdescriptor(
     let mapname = dlcdescriptor.name;
 
     song::import(&is, &format!("world/jd2015/{mapname}/songdesc.tpl"))?;
+
+    is.summary.print();
+
     Ok(())
 }
 
@@ -325,6 +449,7 @@ fn import_songdesc(
         "nx" => Platform::Nx,
         "wiiu" => Platform::WiiU,
         "pc" => Platform::Win,
+        "wii" => Platform::Wii,
         platform => bail!("Unsupported platform {platform}"),
     };
 
@@ -354,11 +479,15 @@ fn import_songdesc(
         lax: true,
         n_threads: NonZeroUsize::new(1),
         transcode,
+        summary: ImportSummary::default(),
     };
 
     if let Err(err) = song::import(&is, &new_path) {
         println!("Warning: Failed to import {mapname}: {err}");
     }
+
+    is.summary.print();
+
     Ok(())
 }
 
@@ -391,6 +520,7 @@ fn import_gameconfig(
         "nx" => Platform::Nx,
         "wiiu" => Platform::WiiU,
         "pc" => Platform::Win,
+        "wii" => Platform::Wii,
         platform => bail!("Unsupported platform {platform}"),
     };
 
@@ -402,6 +532,8 @@ fn import_gameconfig(
 
     let game = if let Some(game) = game {
         game
+    } else if platform == Platform::Wii {
+        bail!("The game cannot be detected for the Wii, use --game to specify it!");
     } else {
         let new_path = cook_path(
             "enginedata/gameconfig/gameconfig.isg",
@@ -486,13 +618,17 @@ pub fn import_full_game_vfs(
         lax,
         n_threads,
         transcode,
+        summary: ImportSummary::default(),
     };
 
-    if is.ugi.game <= Game::JustDance2015 {
+    if is.ugi.game <= Game::JustDance2015 || is.ugi.platform == Platform::Wii {
         println!("Warning! Only importing songs. Avatars and other extras are not supported.");
     }
 
-    if songs_only || is.ugi.game <= Game::JustDance2015 {
+    if is.ugi.platform == Platform::Wii {
+        // The song database of the Wii versions is not supported, so look in the maps directory
+        gameconfig::songdb::import_maps_directory(&is)?;
+    } else if songs_only || is.ugi.game <= Game::JustDance2015 {
         // Get the gameconfig path
         let gameconfig_path = cook_path(
             &is.aliases
@@ -562,6 +698,9 @@ pub fn import_full_game_vfs(
         // Import gameconfig (& songs)
         gameconfig::import(&is)?;
     };
+
+    is.summary.print();
+
     Ok(())
 }
 
@@ -651,6 +790,16 @@ fn find_sources(path: &Path, sources: &mut Vec<Source>) -> Result<(), Error> {
         sources.push(Source::GameConfig(path.join(
             "cache/itf_cooked/pc/enginedata/gameconfig/gameconfig.isg.ckd",
         )));
+    } else if path.join("cache/itf_cooked/wii/world/maps").exists() {
+        // The gameconfig of the Wii versions is not supported, so always import the maps
+        let maps = path.join("cache/itf_cooked/wii/world/maps");
+        trace!("Looking for maps: {}", maps.display());
+        for dir in maps.read_dir()? {
+            let path = dir?.path().join("songdesc.tpl.ckd");
+            if path.exists() {
+                sources.push(Source::SongDesc(path));
+            }
+        }
     } else if path.join("cache/itf_cooked/nx/world/maps").exists() {
         let maps = path.join("cache/itf_cooked/nx/world/maps");
         trace!("Looking for maps: {}", maps.display());
@@ -0,0 +1,60 @@
+//! # Dance Lab import
+//! Imports the block flow of Dance Lab (dance machine) maps
+use anyhow::{anyhow, Error};
+use hipstr::HipStr;
+use ownable::traits::IntoOwned;
+use ubiart_toolkit::{cooked, cooked::tpl::types::Template};
+
+use super::SongImportState;
+use crate::{types::song::DanceLab, utils::cook_path};
+
+/// Imports the block flow of a Dance Lab map
+///
+/// The block flow is usually part of the songdesc template (`templates`), otherwise the main scene
+/// is searched for an actor with a block flow component.
+pub fn import(
+    sis: &SongImportState<'_>,
+    main_scene: &cooked::isc::Scene<'_>,
+    templates: Vec<Template<'_>>,
+    jdm_attributes: &[HipStr<'_>],
+) -> Result<DanceLab<'static>, Error> {
+    let mut dance_lab = if let Some(template) = templates.into_iter().find_map(|template| {
+        if let Template::BlockFlowTemplate(template) = template {
+            Some(template)
+        } else {
+            None
+        }
+    }) {
+        DanceLab::from(&template).into_owned()
+    } else {
+        let actor = main_scene
+            .actors
+            .iter()
+            .map(cooked::isc::WrappedActors::actor)
+            .filter_map(Result::ok)
+            .find(|actor| {
+                actor.components.iter().any(|component| {
+                    matches!(
+                        component,
+                        cooked::isc::WrappedComponent::BlockFlowComponent(_)
+                    )
+                })
+            })
+            .ok_or_else(|| anyhow!("Could not find the block flow for {}!", sis.map_name))?;
+        let template_file = sis.vfs.open(cook_path(&actor.lua, sis.ugi)?.as_ref())?;
+        let template = cooked::tpl::parse(&template_file, sis.ugi, sis.lax)?;
+        let block_flow = template
+            .components
+            .into_iter()
+            .find_map(|template| template.into_block_flow_template().ok())
+            .ok_or_else(|| anyhow!("Could not find the block flow in {}!", actor.lua))?;
+        DanceLab::from(&block_flow).into_owned()
+    };
+
+    dance_lab.jdm_attributes = jdm_attributes
+        .iter()
+        .map(|attribute| attribute.clone().into_owned())
+        .collect();
+
+    Ok(dance_lab)
+}
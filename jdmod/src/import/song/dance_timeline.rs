@@ -1,24 +1,43 @@
 //! # Dance Timeline
 //! Imports the dance timeline, pictos, and classifiers
 use std::{
+    borrow::Cow,
     collections::BTreeSet,
     fs::File,
     io::{ErrorKind, Write},
 };
 
-use anyhow::{anyhow, Error};
+use anyhow::{anyhow, Context, Error};
+use dotstar_toolkit_utils::bytes::{endian::Endian, read::BinaryDeserializeExt as _};
+use hipstr::HipStr;
 use test_eq::test_eq;
 use tracing::{debug, trace, warn};
-use ubiart_toolkit::{cooked, cooked::tape};
+use ubiart_toolkit::{
+    cooked,
+    cooked::tape,
+    msm::{self, MovementSpaceMove},
+    utils::Platform,
+};
 
 use super::{montage, SongImportState};
 use crate::{
-    types::song::{Clip, MotionClip, PictogramClip, Timeline},
+    types::song::{Clip, Mashup, MotionClip, PictogramClip, Timeline},
     utils::{cook_path, decode_texture},
 };
 
+/// Information from the dance timeline that is needed for the song metadata
+pub struct DanceTimelineInfo {
+    /// The moves that use classifiers of other maps, which is the case for mashups
+    pub mashup: Mashup<'static>,
+    /// The coaches targeted by the gameplay events, which are the teams in double rumble maps
+    pub event_targets: Vec<Vec<u8>>,
+}
+
 /// Imports the dance timeline, pictos, and classifiers
-pub fn import(sis: &SongImportState<'_>, dance_timeline_path: &str) -> Result<(), Error> {
+pub fn import(
+    sis: &SongImportState<'_>,
+    dance_timeline_path: &str,
+) -> Result<DanceTimelineInfo, Error> {
     let dance_timeline_file = sis
         .vfs
         .open(cook_path(dance_timeline_path, sis.ugi)?.as_ref())?;
@@ -51,6 +70,11 @@ pub fn import(sis: &SongImportState<'_>, dance_timeline_path: &str) -> Result<()
 
     let mut montage_vec = sis.vfs.exists(montage_path.as_ref()).then(Vec::new);
 
+    // The moves with the map their classifier is from, `None` if it's from this map
+    let mut moves = Vec::new();
+    // Coaches targeted by gameplay events
+    let mut event_targets = Vec::new();
+
     for clip in tape.clips {
         let new_clip = match clip {
             tape::Clip::GoldEffect(goldeffect) => Clip::GoldEffect(goldeffect.into()),
@@ -58,30 +82,18 @@ pub fn import(sis: &SongImportState<'_>, dance_timeline_path: &str) -> Result<()
                 let classifier_path = motion.classifier_path.clone();
                 let new_motion: MotionClip = motion.try_into()?;
 
-                // Classifier path does not include platform specifier
-                let classifier_path =
-                    MotionClip::fix_classifier_path(&classifier_path, sis.ugi.platform)?;
-
-                // Save the classifier
-                if let Ok(from) = sis.vfs.open(classifier_path.as_ref()) {
-                    let mut to = File::create(
-                        sis.dirs
-                            .moves()
-                            .join(new_motion.classifier_filename.as_str()),
-                    )?;
-                    to.write_all(&from)?;
-                } else if !new_motion.classifier_filename.ends_with(".msm") {
-                    // We don't care about any other classifiers than msm
-                    trace!(
-                        "Did not find classifier {} at {classifier_path}!",
-                        new_motion.classifier_filename
-                    );
-                } else {
-                    warn!(
-                        "Did not find classifier {} at {classifier_path}!",
-                        new_motion.classifier_filename
-                    );
-                }
+                // Mashups use the classifiers of the maps the coaches come from
+                let source_map = MotionClip::classifier_map(&classifier_path)
+                    .filter(|map| *map != sis.lower_map_name)
+                    .map(|map| HipStr::from(map.to_string()));
+                moves.push((
+                    new_motion.start_time,
+                    new_motion.coach_id,
+                    source_map,
+                    new_motion.duration,
+                ));
+
+                import_classifier(sis, &classifier_path, &new_motion.classifier_filename)?;
                 Clip::Motion(new_motion)
             }
             tape::Clip::Pictogram(pictogram) => {
@@ -91,35 +103,15 @@ pub fn import(sis: &SongImportState<'_>, dance_timeline_path: &str) -> Result<()
                     if !vec.contains(&new_picto.picto_filename) {
                         vec.push(new_picto.picto_filename.clone());
                     }
-                } else {
-                    let cooked_path = cook_path(&picto_path, sis.ugi)?;
-                    match (sis.vfs.open(cooked_path.as_ref()), sis.lax) {
-                        (Ok(from), _) => {
-                            match (decode_texture(&from, sis.ugi), sis.lax) {
-                                (Ok(decooked_picto), _) => {
-                                    let path =
-                                        sis.dirs.pictos().join(new_picto.picto_filename.as_str());
-                                    decooked_picto.save(path)?;
-                                }
-                                (Err(err), true) => {
-                                    warn!(
-                                        "Failed to import {cooked_path}, texture decoding failed"
-                                    );
-                                    debug!("{err}");
-                                    continue;
-                                }
-                                (Err(err), _) => return Err(err),
-                            };
-                        }
-                        (Err(error), true) if error.kind() == ErrorKind::NotFound => {
-                            warn!("Failed to import {cooked_path}, file does not exist");
-                            trace!("{error}");
-                        }
-                        (Err(error), _) => return Err(error.into()),
-                    };
+                } else if !import_picto(sis, &picto_path, &new_picto.picto_filename)? {
+                    continue;
                 }
                 Clip::Pictogram(new_picto)
             }
+            tape::Clip::GameplayEvent(gameplay_event) => {
+                event_targets.push(gameplay_event.actor_indices.clone());
+                Clip::GameplayEvent(gameplay_event.into())
+            }
             _ => return Err(anyhow!("Unexpected Clip in Dance Timeline Tape! {clip:?}")),
         };
         timeline.timeline.insert(new_clip);
@@ -136,5 +128,166 @@ pub fn import(sis: &SongImportState<'_>, dance_timeline_path: &str) -> Result<()
     let timeline_file = File::create(dance_timeline_path)?;
     serde_json::to_writer_pretty(timeline_file, &timeline)?;
 
+    let mut mashup = Mashup::default();
+    moves.sort();
+    for (start_time, coach_id, source_map, duration) in moves {
+        mashup.add_move(coach_id, source_map, start_time, duration);
+    }
+    mashup.sort();
+
+    Ok(DanceTimelineInfo {
+        mashup,
+        event_targets,
+    })
+}
+
+/// Imports the classifier at `classifier_path` into the moves directory as `classifier_filename`
+///
+/// Missing classifiers are skipped, with a warning if it is a .msm file.
+pub fn import_classifier(
+    sis: &SongImportState<'_>,
+    classifier_path: &str,
+    classifier_filename: &str,
+) -> Result<(), Error> {
+    // Classifier path does not include platform specifier
+    let classifier_path = MotionClip::fix_classifier_path(classifier_path, sis.ugi.platform)?;
+
+    if let Ok(from) = sis.vfs.open(classifier_path.as_ref()) {
+        let data = if classifier_filename.ends_with(".msm") {
+            little_endian_msm(&from, sis.ugi.platform)
+                .with_context(|| format!("Failed to import {classifier_path}!"))?
+        } else {
+            Cow::Borrowed(&*from)
+        };
+        let mut to = File::create(sis.dirs.moves().join(classifier_filename))?;
+        to.write_all(&data)?;
+    } else if !classifier_filename.ends_with(".msm") {
+        // We don't care about any other classifiers than msm
+        trace!("Did not find classifier {classifier_filename} at {classifier_path}!");
+    } else {
+        warn!("Did not find classifier {classifier_filename} at {classifier_path}!");
+    }
     Ok(())
 }
+
+/// Imports the picto at `picto_path` into the pictos directory as `picto_filename`
+///
+/// Returns `false` if the picto could not be decoded in lax mode, the clip should then be skipped.
+pub fn import_picto(
+    sis: &SongImportState<'_>,
+    picto_path: &str,
+    picto_filename: &str,
+) -> Result<bool, Error> {
+    let cooked_path = cook_path(picto_path, sis.ugi)?;
+    match (sis.vfs.open(cooked_path.as_ref()), sis.lax) {
+        (Ok(from), _) => match (decode_texture(&from, sis.ugi), sis.lax) {
+            (Ok(decooked_picto), _) => {
+                decooked_picto.save(sis.dirs.pictos().join(picto_filename))?;
+            }
+            (Err(err), true) => {
+                warn!("Failed to import {cooked_path}, texture decoding failed");
+                debug!("{err}");
+                return Ok(false);
+            }
+            (Err(err), _) => return Err(err),
+        },
+        (Err(error), true) if error.kind() == ErrorKind::NotFound => {
+            warn!("Failed to import {cooked_path}, file does not exist");
+            trace!("{error}");
+        }
+        (Err(error), _) => return Err(error.into()),
+    }
+    Ok(true)
+}
+
+/// Convert the .msm classifier in `data` to little endian
+///
+/// The Wii uses big endian .msm files, the mod stores the little endian files of the other
+/// platforms. Files that are already little endian or cannot be parsed are kept as is, except on
+/// the Wii where they would end up in the mod as big endian.
+fn little_endian_msm(data: &[u8], platform: Platform) -> Result<Cow<'_, [u8]>, Error> {
+    match MovementSpaceMove::deserialize(data) {
+        Ok(msm) if msm.endianness() == Endian::Big => Ok(Cow::Owned(
+            msm::create_vec_with_endianness(msm, Endian::Little)?,
+        )),
+        Err(error) if platform == Platform::Wii => Err(anyhow!(
+            "Could not parse the big endian classifier: {error:?}"
+        )),
+        Ok(_) | Err(_) => Ok(Cow::Borrowed(data)),
+    }
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::bytes::{endian::Endian, read::BinaryDeserializeExt as _};
+    use hipstr::HipStr;
+    use ubiart_toolkit::{
+        msm::{self, MovementSpaceMove},
+        utils::Platform,
+    };
+
+    use super::little_endian_msm;
+
+    /// A move as found on the Wii
+    fn movement_space_move() -> MovementSpaceMove<'static> {
+        MovementSpaceMove {
+            name: HipStr::borrowed("testmove"),
+            map: HipStr::borrowed("TestMap"),
+            device: HipStr::borrowed("Acc_Dev_Dir_NP"),
+            data: vec![(0.5, 0.25), (1.0, 0.5)],
+            version: 7,
+            unk3: 1.5,
+            unk4: 1.0,
+            unk5: 2.0,
+            unk6: Some(1.0),
+            unk7: Some(0.5),
+            unk10: Some(1),
+            unk11: 2,
+            unk13: 0,
+            unk14: 0.0,
+            unk15: 0.0,
+            unk16: None,
+            unk17: None,
+            data2: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_big_endian_is_converted() {
+        let big = msm::create_vec(movement_space_move()).unwrap();
+        let converted = little_endian_msm(&big, Platform::Wii).unwrap();
+        let msm = MovementSpaceMove::deserialize(converted.as_ref()).unwrap();
+        assert_eq!(
+            msm.endianness(),
+            Endian::Little,
+            "Move should be little endian"
+        );
+        assert_eq!(msm.name.as_str(), "testmove", "Name should be kept");
+        assert_eq!(msm.data, [(0.5, 0.25), (1.0, 0.5)], "Data should be kept");
+    }
+
+    #[test]
+    fn test_little_endian_is_kept() {
+        let little =
+            msm::create_vec_with_endianness(movement_space_move(), Endian::Little).unwrap();
+        let converted = little_endian_msm(&little, Platform::Nx).unwrap();
+        assert_eq!(
+            converted.as_ref(),
+            little,
+            "Little endian move should be copied"
+        );
+    }
+
+    #[test]
+    fn test_broken_move() {
+        assert!(
+            little_endian_msm(b"garbage", Platform::Nx).is_ok(),
+            "Broken moves are copied on other platforms"
+        );
+        assert!(
+            little_endian_msm(b"garbage", Platform::Wii).is_err(),
+            "Broken moves cannot be converted to little endian"
+        );
+    }
+}
@@ -6,21 +6,29 @@ use anyhow::Error;
 use dotstar_toolkit_utils::vfs::VirtualFileSystem;
 use hipstr::HipStr;
 use tracing::error;
-use ubiart_toolkit::{cooked, cooked::tpl::types::SongDescription, utils::UniqueGameId};
+use ubiart_toolkit::{
+    cooked,
+    cooked::tpl::types::{SongDescription, Template},
+    utils::UniqueGameId,
+};
 
+mod dance_lab;
 mod dance_timeline;
 mod karaoke_timeline;
 mod mainsequence;
 mod menuart;
 mod montage;
 mod musictrack;
+mod party_master;
+mod sequence;
+mod showtime;
 mod video;
 
 use crate::{
     import::TranscodeSettings,
     types::{
         localisation::LocaleIdMap,
-        song::{Song, SongDirectoryTree},
+        song::{DoubleScoring, Song, SongDirectoryTree, SongKind},
         ImportState,
     },
     utils::cook_path,
@@ -58,30 +66,6 @@ pub fn import(is: &ImportState<'_>, songdesc_path: &str) -> Result<(), Error> {
     if dirs.exists() {
         println!("Skipping {map_name}, song already imported!");
         return Ok(());
-    } else if songdesc.jdm_attributes.is_some()
-        || songdesc.tags.contains(&HipStr::borrowed("dancemachine"))
-    {
-        // TODO: Support dance machine/dance lab
-        println!("Warning! {map_name} is a dance machine map! Skipping!");
-        return Ok(());
-    } else if songdesc.tags.contains(&HipStr::borrowed("MASHUP"))
-        || songdesc.tags.contains(&HipStr::borrowed("COMMUNITYMASHUP"))
-    {
-        // TODO: Support mashups
-        println!("Warning! {map_name} is a mashup! Skipping!");
-        return Ok(());
-    } else if songdesc.tags.contains(&HipStr::borrowed("doublescoring")) {
-        // TODO: Support double rumble
-        println!("Warning! {map_name} is a double rumble map! Skipping!");
-        return Ok(());
-    } else if songdesc.tags.contains(&HipStr::borrowed("JUSTSHINE")) {
-        // TODO: Support showtime maps
-        println!("Warning! {map_name} is a showtime map! Skipping!");
-        return Ok(());
-    } else if songdesc.tags.contains(&HipStr::borrowed("PARTYMASTER")) {
-        // TODO: Support party master maps
-        println!("Warning! {map_name} is a party (puppet) master map! Skipping!");
-        return Ok(());
     }
 
     let sis = SongImportState {
@@ -95,8 +79,8 @@ pub fn import(is: &ImportState<'_>, songdesc_path: &str) -> Result<(), Error> {
         transcode: is.transcode,
     };
 
-    match actual_import(&sis, songdesc) {
-        Ok(()) => {}
+    match actual_import(&sis, songdesc, actor.components) {
+        Ok(kind) => is.summary.add(kind, &sis.map_name),
         Err(err) => {
             error!("Failed to import {}: {err}", sis.map_name);
             sis.dirs.remove_dir_all()?;
@@ -109,7 +93,13 @@ pub fn import(is: &ImportState<'_>, songdesc_path: &str) -> Result<(), Error> {
 }
 
 /// The actual import logic for a song, so that the [`import`] function can catch any errors
-fn actual_import(sis: &SongImportState<'_>, songdesc: SongDescription<'_>) -> Result<(), Error> {
+///
+/// `templates` are the other templates in the songdesc actor.
+fn actual_import(
+    sis: &SongImportState<'_>,
+    songdesc: SongDescription<'_>,
+    templates: Vec<Template<'_>>,
+) -> Result<SongKind, Error> {
     println!("Parsing {}", sis.map_name);
     let lower_map_name = sis.lower_map_name;
 
@@ -131,7 +121,7 @@ fn actual_import(sis: &SongImportState<'_>, songdesc: SongDescription<'_>) -> Re
     let dance_timeline_path = &timeline_scene
         .get_actor_by_userfriendly_end("_tml_dance", sis.lax)?
         .lua;
-    dance_timeline::import(sis, dance_timeline_path)?;
+    let dance_timeline_info = dance_timeline::import(sis, dance_timeline_path)?;
 
     // Import the karaoke timeline
     let karaoke_timeline_path = &timeline_scene
@@ -140,14 +130,71 @@ fn actual_import(sis: &SongImportState<'_>, songdesc: SongDescription<'_>) -> Re
     karaoke_timeline::import(sis, karaoke_timeline_path)?;
 
     // Import the mainsequence
-    let mainsequence_path = &main_scene
+    let cine_scene = main_scene
         .get_subscene_by_userfriendly_end("_CINE", sis.lax)?
         .wrapped_scene
-        .as_ref()
+        .as_ref();
+    let mainsequence_path = &cine_scene
         .get_actor_by_userfriendly_end("_MainSequence", sis.lax)?
         .lua;
     mainsequence::import(sis, mainsequence_path)?;
 
+    // Import the puppet and choice tapes
+    let party_master = if songdesc.tags.contains(&HipStr::borrowed("PARTYMASTER")) {
+        Some(party_master::import(sis, cine_scene)?)
+    } else {
+        None
+    };
+
+    // Import the mashup sequence
+    let is_mashup = songdesc.tags.contains(&HipStr::borrowed("MASHUP"))
+        || songdesc.tags.contains(&HipStr::borrowed("COMMUNITYMASHUP"));
+    if is_mashup {
+        sequence::import_mashup(sis)?;
+    }
+
+    // Import the block flow
+    let is_dance_lab = songdesc.jdm_attributes.is_some()
+        || songdesc.tags.contains(&HipStr::borrowed("dancemachine"));
+    let dance_lab = if is_dance_lab {
+        Some(dance_lab::import(
+            sis,
+            &main_scene,
+            templates,
+            songdesc.jdm_attributes.as_deref().unwrap_or_default(),
+        )?)
+    } else {
+        None
+    };
+
+    // Import the teams
+    let double_scoring = if songdesc.tags.contains(&HipStr::borrowed("doublescoring")) {
+        let double_scoring = DoubleScoring::from_actor_indices(
+            songdesc.double_scoring_type,
+            dance_timeline_info.event_targets,
+        );
+        if double_scoring.is_none() {
+            println!(
+                "Warning! Could not find the teams of {}, not importing the double scoring!",
+                sis.map_name
+            );
+        }
+        double_scoring
+    } else {
+        None
+    };
+
+    // Import the recording setup
+    let showtime = if songdesc.tags.contains(&HipStr::borrowed("JUSTSHINE")) {
+        let autodance_scene = main_scene
+            .get_subscene_by_userfriendly_end("_AUTODANCE", sis.lax)?
+            .wrapped_scene
+            .as_ref();
+        Some(showtime::import(sis, autodance_scene)?)
+    } else {
+        None
+    };
+
     // Import the audio
     let musictrack_path = &main_scene
         .get_subscene_by_userfriendly_end("_AUDIO", sis.lax)?
@@ -210,10 +257,15 @@ fn actual_import(sis: &SongImportState<'_>, songdesc: SongDescription<'_>) -> Re
         default_colors: (&songdesc.default_colors).into(),
         audiofile: HipStr::from(audiofile),
         videofile: HipStr::borrowed(videofile),
+        mashup: is_mashup.then_some(dance_timeline_info.mashup),
+        dance_lab,
+        double_scoring,
+        showtime,
+        party_master,
     };
 
     let song_file = File::create(sis.dirs.song_file())?;
     serde_json::to_writer_pretty(song_file, &song)?;
 
-    Ok(())
+    Ok(song.kind())
 }
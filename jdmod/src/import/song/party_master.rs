@@ -0,0 +1,54 @@
+//! # Party Master
+//! Imports the puppet and choice tapes of Party Master maps
+use anyhow::{anyhow, Error};
+use test_eq::test_eq;
+use tracing::warn;
+use ubiart_toolkit::{cooked, cooked::tpl::types::Template};
+
+use super::{sequence, SongImportState};
+use crate::{types::song::PartyMaster, utils::cook_path};
+
+/// Imports the tapes of the `partymaster_coach` actor in `cine_scene`
+///
+/// Every tape is imported as [`PartyMaster::tape_name`]`.json`.
+pub fn import(
+    sis: &SongImportState<'_>,
+    cine_scene: &cooked::isc::Scene<'_>,
+) -> Result<PartyMaster<'static>, Error> {
+    let coach_path = &cine_scene
+        .get_actor_by_userfriendly_end("partymaster_coach", true)?
+        .lua;
+    let coach_file = sis.vfs.open(cook_path(coach_path, sis.ugi)?.as_ref())?;
+    let mut actor = cooked::tpl::parse(&coach_file, sis.ugi, sis.lax)?;
+    test_eq!(actor.components.len(), 1)?;
+    let master_tape = match actor.components.remove(0) {
+        Template::MasterTape(master_tape) | Template::TapeCase(master_tape) => master_tape,
+        template => {
+            return Err(anyhow!(
+                "Unexpected template for partymaster_coach! {template:?}"
+            ))
+        }
+    };
+
+    let mut party_master = PartyMaster::default();
+    for entry in master_tape
+        .tapes_rack
+        .iter()
+        .flat_map(|group| &group.entries)
+    {
+        let label = &entry.label;
+        let tape_name = PartyMaster::tape_name(label);
+        if party_master
+            .tapes
+            .iter()
+            .any(|tape| PartyMaster::tape_name(tape) == tape_name)
+        {
+            warn!("Skipping duplicate Party Master tape {label}!");
+            continue;
+        }
+        sequence::import(sis, &entry.path, &tape_name)?;
+        party_master.tapes.push(label.clone().into_owned());
+    }
+
+    Ok(party_master)
+}
@@ -0,0 +1,97 @@
+//! # Sequences
+//! Imports the extra sequences of mashups and Party Master maps
+use std::{collections::BTreeSet, fs::File};
+
+use anyhow::{Context, Error};
+use tracing::{debug, warn};
+use ubiart_toolkit::cooked::tape;
+
+use super::{
+    dance_timeline::{import_classifier, import_picto},
+    mainsequence::parse_soundset,
+    SongImportState,
+};
+use crate::{
+    types::song::{Clip, MotionClip, PictogramClip, Timeline},
+    utils::cook_path,
+};
+
+/// Imports the mashup sequence, which is stored next to the mainsequence
+///
+/// A missing mashup sequence is skipped, the build treats it as an empty timeline.
+pub fn import_mashup(sis: &SongImportState<'_>) -> Result<(), Error> {
+    let lower_map_name = sis.lower_map_name;
+    let tape_path = format!("world/maps/{lower_map_name}/cinematics/{lower_map_name}_mashup.tape");
+    if sis.vfs.exists(cook_path(&tape_path, sis.ugi)?.as_ref()) {
+        import(sis, &tape_path, "mashup")
+    } else {
+        warn!("Could not find the mashup sequence at {tape_path}");
+        Ok(())
+    }
+}
+
+/// Imports the tape at `tape_path` into `{name}.json`
+///
+/// The classifiers and pictos of motion and pictogram clips are imported with the clips.
+/// Clips that are not understood are skipped with a warning.
+///
+/// # Errors
+/// Will return an error if the tape does not exist or cannot be parsed
+pub fn import(sis: &SongImportState<'_>, tape_path: &str, name: &str) -> Result<(), Error> {
+    let tape_path = cook_path(tape_path, sis.ugi)?;
+    let tape_file = sis
+        .vfs
+        .open(tape_path.as_ref())
+        .with_context(|| format!("Could not find the {name} sequence at {tape_path}"))?;
+
+    let mut timeline = Timeline {
+        timeline: BTreeSet::new(),
+    };
+
+    let tape = tape::parse(&tape_file, sis.ugi, sis.lax)?;
+    for clip in tape.clips {
+        let new_clip = match clip {
+            tape::Clip::HideUserInterface(hui) => Clip::HideUserInterface(hui.into()),
+            tape::Clip::GameplayEvent(gameplay) => Clip::GameplayEvent(gameplay.into()),
+            tape::Clip::Vibration(vib) => Clip::Vibration(vib.into()),
+            tape::Clip::Motion(motion) => {
+                let classifier_path = motion.classifier_path.clone();
+                let new_motion: MotionClip = motion.try_into()?;
+                import_classifier(sis, &classifier_path, &new_motion.classifier_filename)?;
+                Clip::Motion(new_motion)
+            }
+            tape::Clip::Pictogram(pictogram) => {
+                let picto_path = pictogram.picto_path.clone();
+                let new_picto: PictogramClip = pictogram.try_into()?;
+                // Pictos that are not cooked separately are part of the montage of the dance
+                // timeline, which is imported with the dance timeline
+                if sis.vfs.exists(cook_path(&picto_path, sis.ugi)?.as_ref())
+                    && !import_picto(sis, &picto_path, &new_picto.picto_filename)?
+                {
+                    continue;
+                }
+                Clip::Pictogram(new_picto)
+            }
+            tape::Clip::SoundSet(soundset) => match (parse_soundset(sis, &soundset), sis.lax) {
+                (Ok(clip), _) => clip,
+                (Err(error), true) => {
+                    warn!("Failed to import soundset");
+                    debug!("{error}");
+                    continue;
+                }
+                (Err(error), false) => return Err(error),
+            },
+            _ => {
+                warn!("Skipping unsupported clip in {name} tape! {clip:?}");
+                continue;
+            }
+        };
+        timeline.timeline.insert(new_clip);
+    }
+
+    let timeline_path = sis.dirs.song().join(format!("{name}.json"));
+    let timeline_file = File::create(timeline_path)?;
+    serde_json::to_writer_pretty(timeline_file, &timeline)?;
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+//! # Showtime
+//! Imports the recording setup of Showtime maps
+use anyhow::Error;
+use test_eq::test_eq;
+use ubiart_toolkit::cooked;
+
+use super::SongImportState;
+use crate::{types::song::Showtime, utils::cook_path};
+
+/// Imports the recording and autodance setup from the autodance template in `autodance_scene`
+pub fn import(
+    sis: &SongImportState<'_>,
+    autodance_scene: &cooked::isc::Scene<'_>,
+) -> Result<Showtime, Error> {
+    let autodance_path = &autodance_scene
+        .get_actor_by_userfriendly_end("_autodance", true)?
+        .lua;
+    let autodance_file = sis.vfs.open(cook_path(autodance_path, sis.ugi)?.as_ref())?;
+    let mut actor = cooked::tpl::parse(&autodance_file, sis.ugi, sis.lax)?;
+    test_eq!(actor.components.len(), 1)?;
+    let autodance = actor.components.remove(0).into_autodance_component()?;
+
+    Ok(Showtime::from_autodance(&autodance.autodance_data))
+}
@@ -3,7 +3,10 @@
 use std::{fs::File, io::Write};
 
 use anyhow::{anyhow, Error};
-use ubiart_toolkit::{cooked, utils::Game};
+use ubiart_toolkit::{
+    cooked,
+    utils::{Game, Platform},
+};
 
 use super::SongImportState;
 use crate::utils::transcode_replace;
@@ -41,7 +44,7 @@ pub fn import(
 
     to.flush()?;
 
-    if sis.ugi.game <= Game::JustDance2015 {
+    if sis.ugi.game <= Game::JustDance2015 || sis.ugi.platform == Platform::Wii {
         println!("Transcoding video for {}", sis.map_name);
         transcode_replace(&to_path, sis.transcode)?;
     }
@@ -13,11 +13,12 @@ use clap::ValueEnum;
 
 pub mod build;
 pub mod bundle;
-// pub mod check;
+pub mod check;
 pub mod export;
 pub mod extract;
 pub mod import;
 pub mod new;
+pub mod song;
 pub mod types;
 pub mod unlock;
 pub mod utils;
@@ -11,10 +11,10 @@
 
 use std::process::ExitCode;
 
-// use jdmod::check::Check;
 use clap::{Parser, Subcommand};
 use jdmod::{
-    bundle::Bundle, export::Build, extract::Extract, import::Import, new::New, unlock::Unlock,
+    bundle::Bundle, check::Check, export::Build, extract::Extract, import::Import, new::New,
+    song::SongCommand, unlock::Unlock,
 };
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _};
@@ -35,12 +35,15 @@ enum Commands {
     New(New),
     /// Import Just Dance files
     Import(Import),
+    /// Create and manage songs in the mod
+    #[command(subcommand)]
+    Song(SongCommand),
     /// Extract Just Dance files
     Extract(Extract),
     /// Export the mod
     Export(Build),
-    // /// Check the completeness of the mod
-    // Check(Check),
+    /// Check the mod for missing files and broken references
+    Check(Check),
     /// Bundle files into a .ipk
     Bundle(Bundle),
     /// Unlock all songs, avatars, etc…
@@ -80,9 +83,10 @@ fn main() -> ExitCode {
     let result = match cli.commands {
         Commands::New(data) => jdmod::new::main(&data),
         Commands::Import(data) => jdmod::import::main(&data),
+        Commands::Song(data) => jdmod::song::main(&data),
         Commands::Extract(data) => jdmod::extract::main(data),
         Commands::Export(data) => jdmod::export::main(&data),
-        // Commands::Check(data) => jdmod::check::main(&data),
+        Commands::Check(data) => jdmod::check::main(&data),
         Commands::Bundle(data) => jdmod::bundle::main(&data),
         Commands::Unlock(data) => jdmod::unlock::main(&data),
     };
@@ -3,55 +3,65 @@
 use std::{
     ffi::OsStr,
     fs::File,
-    io::ErrorKind,
     num::NonZeroUsize,
     path::{Path, PathBuf},
 };
 
 use anyhow::{anyhow, Context, Error};
 use clap::Args;
-use dotstar_toolkit_utils::vfs::{native::NativeFs, VirtualPathBuf};
-use tracing::instrument;
-use ubiart_toolkit::{
-    secure_fat::vfs::SfatFilesystem,
-    utils::{Game, Platform},
+use dotstar_toolkit_utils::{
+    bytes::read::BinaryDeserializeExt as _,
+    vfs::{layeredfs::OverlayFs, native::NativeFs, VirtualFileSystem, VirtualPath},
 };
+use tracing::instrument;
+use ubiart_toolkit::secure_fat::{vfs::SfatFilesystem, SecureFat};
 
 use crate::{
-    import,
+    build, import,
     import::TranscodeSettings,
     types::{Config, DirectoryTree},
+    utils,
 };
 
-/// Create a new mod at <mod_path> using <sfat_path> as a base
+/// Create a new mod at <mod_path> using <game_path> as a base
 #[derive(Args, Clone)]
 pub struct New {
-    /// secure_fat.gf of the game to mod
-    sfat_path: PathBuf,
+    /// secure_fat.gf, .nsp, or .xci of the game to mod
+    game_path: PathBuf,
     /// Directory to place the mod
     mod_path: PathBuf,
     /// Use n threads for importing songs
     #[arg(long)]
     threads: Option<NonZeroUsize>,
+    /// Keys for decrypting .nsp and .xci files, defaults to ~/.switch/prod.keys
+    #[arg(long)]
+    keys: Option<PathBuf>,
 }
 
 /// Wrapper around [`new`]
 pub fn main(cli: &New) -> Result<(), Error> {
-    new(&cli.sfat_path, &cli.mod_path, cli.threads)
+    new(
+        &cli.game_path,
+        &cli.mod_path,
+        cli.threads,
+        cli.keys.as_deref(),
+    )
 }
 
-/// Create a new game at `dir_root` with the secure_fat.gf at `game_path`
+/// Create a new game at `dir_root` with the secure_fat.gf, NSP, or XCI at `game_path`
 ///
 /// # Errors
 /// - When `dir_root` exists and is not empty or not a directory
-/// - When `game_path` is not a secure_fat.gf
+/// - When `game_path` is not a secure_fat.gf, NSP, or XCI
+/// - When the NSP or XCI cannot be decrypted with the keys at `keys_path`
 /// - Invalid secure_fat.gf or .ipks or missing .ipks
-/// - The secure_fat.gf is not for the Nintendo Switch
+/// - When the secure_fat.gf is not from a game that can be exported to
 #[instrument]
 pub fn new(
     game_path: &Path,
     dir_root: &Path,
     n_threads: Option<NonZeroUsize>,
+    keys_path: Option<&Path>,
 ) -> Result<(), Error> {
     // Check that the target directory either doesn't exist yet or is empty
     // We do not want to potentially override existing files and/or directories
@@ -65,35 +75,53 @@ pub fn new(
         return Err(anyhow!("{dir_root:?} exists and is not empty!"));
     }
 
-    // TODO: import directly from XCI/NSP?
-    if game_path.file_name().and_then(OsStr::to_str) != Some("secure_fat.gf") {
-        return Err(anyhow!(
-            "Expected path to 'secure_fat.gf', got {game_path:?} instead!"
-        ));
-    }
-
-    // Init the native filesystem and load the securefat as a virtual filesystem
+    let file_name = game_path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| anyhow!("Invalid game path {game_path:?}!"))?;
     let native_vfs = NativeFs::new(
         game_path
             .parent()
-            .ok_or_else(|| anyhow!("No parent directory for secure_fat.gf!"))?,
+            .ok_or_else(|| anyhow!("No parent directory for {file_name}!"))?,
     )?;
-    let sfat_vfs = SfatFilesystem::new(&native_vfs, &VirtualPathBuf::from("secure_fat.gf"))?;
 
-    // Check that the sfat is from the right game
-    let game_platform = sfat_vfs.unique_game_id();
-    if game_platform.game != Game::JustDance2022 {
-        return Err(anyhow!(
-            "The secure_fat.gf is from {} instead of {}!",
-            game_platform.game,
-            Game::JustDance2022
-        ));
+    if utils::is_switch_dump(game_path) {
+        // Read the game files directly from the RomFS in the dump
+        let keys = utils::load_switch_keys(keys_path)?;
+        let romfs = nx_toolkit::open_romfs(native_vfs.open(VirtualPath::new(file_name))?, &keys)?;
+        // The bundles are several GB, so they're decrypted in chunks instead of all at once
+        new_from_vfs(&romfs, dir_root, n_threads, |path, destination| {
+            romfs.extract(path, &mut File::create(destination)?)?;
+            Ok(())
+        })
+    } else if file_name == "secure_fat.gf" {
+        new_from_vfs(&native_vfs, dir_root, n_threads, |path, destination| {
+            std::fs::copy(native_vfs.root().join(path.as_str()), destination)?;
+            Ok(())
+        })
+    } else {
+        Err(anyhow!(
+            "Expected path to 'secure_fat.gf', a .nsp, or a .xci, got {game_path:?} instead!"
+        ))
     }
-    if game_platform.platform != Platform::Nx {
+}
+
+/// Create a new game at `dir_root` from the game files in `vfs`
+///
+/// `vfs` should have the secure_fat.gf and the .ipks in the root. `copy` copies a file from `vfs`
+/// to a path on disk, the bundle and patch are copied to `.mod/base` and imported from there.
+fn new_from_vfs(
+    vfs: &dyn VirtualFileSystem,
+    dir_root: &Path,
+    n_threads: Option<NonZeroUsize>,
+    copy: impl Fn(&VirtualPath, &Path) -> std::io::Result<()>,
+) -> Result<(), Error> {
+    // Check that the sfat is from the right game, without opening the bundles
+    let sfat_path = VirtualPath::new("secure_fat.gf");
+    let game_platform = SecureFat::deserialize(&vfs.open(sfat_path)?)?.game_platform();
+    if build::jd_version(game_platform).is_err() {
         return Err(anyhow!(
-            "The secure_fat.gf is for {} instead of {}!",
-            game_platform.platform,
-            Platform::Nx
+            "The secure_fat.gf is from {game_platform}, which is not supported!"
         ));
     }
     // TODO: Check engine version and warn user they're missing an update
@@ -102,34 +130,40 @@ pub fn new(
     let dir_tree = DirectoryTree::new(dir_root);
     dir_tree.create_all()?;
 
+    // Copy the bundle and patch to .mod/base
+    let [bundle_name, patch_name] = build::base_ipk_names(game_platform);
+    copy(
+        VirtualPath::new(&bundle_name),
+        &dir_tree.base().join(&bundle_name),
+    )
+    .with_context(|| format!("Failed to copy {bundle_name}"))?;
+    if vfs.exists(VirtualPath::new(&patch_name)) {
+        copy(
+            VirtualPath::new(&patch_name),
+            &dir_tree.base().join(&patch_name),
+        )
+        .with_context(|| format!("Failed to copy {patch_name}"))?;
+    } else {
+        println!("Warning! You're missing {patch_name}. This means you're missing an update!");
+    }
+
+    // Load the securefat as a virtual filesystem, with the bundle and patch from .mod/base
+    let base_vfs = NativeFs::new(dir_tree.base())?;
+    let game_vfs = OverlayFs::new(&base_vfs, vfs);
+    let sfat_vfs = SfatFilesystem::new(&game_vfs, sfat_path)?;
+
     // Write the config file
     let path_root_mod_config = dir_tree.dot_mod().join("config.json");
     let file_root_mod_config = File::create(path_root_mod_config)?;
     serde_json::to_writer_pretty(
         file_root_mod_config,
         &Config {
-            game_platform,
+            game_platform: sfat_vfs.unique_game_id(),
             engine_version: sfat_vfs.engine_version(),
             ipk_unk4: sfat_vfs.ipk_unk4(),
         },
     )?;
 
-    // Copy bundle_nx and patch_nx to .mod/base
-    std::fs::copy(
-        game_path.with_file_name("bundle_nx.ipk"),
-        dir_tree.base().join("bundle_nx.ipk"),
-    )?;
-    if let Err(e) = std::fs::copy(
-        game_path.with_file_name("patch_nx.ipk"),
-        dir_tree.base().join("patch_nx.ipk"),
-    ) {
-        if e.kind() == ErrorKind::NotFound {
-            println!("Warning! You're missing patch_nx.ipk. This means you're missing an update!");
-        } else {
-            return Err(e.into());
-        }
-    }
-
     // Import songs and other content from the game
     import::import_full_game_vfs(
         &sfat_vfs,
@@ -138,7 +172,7 @@ pub fn new(
         false,
         false,
         n_threads,
-        TranscodeSettings::default(), // NX2022 does not need transcoding
+        TranscodeSettings::default(), // Switch games do not need transcoding
     )?;
 
     Ok(())
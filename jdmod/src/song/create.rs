@@ -0,0 +1,864 @@
+//! # Create
+//! Creates a new song from raw assets, so original maps can be made without importing a game.
+//!
+//! The song is described by a JSON file, all paths in it are relative to the description:
+//! ```json
+//! {
+//!     "map_name": "MyOriginalSong",
+//!     "title": "My Original Song",
+//!     "artist": "Ferris",
+//!     "video": "video.mp4",
+//!     "cover": "cover.png",
+//!     "coaches": ["coach_1.png"],
+//!     "beats": { "bpm": 120.0, "offset": 250, "length": 180000 },
+//!     "sections": [{ "beat": 16, "section_type": 1 }],
+//!     "pictos": [{ "file": "pictos/clap.png", "start": 16.0, "duration": 2.0, "gold": false }],
+//!     "lyrics": [{ "text": "Hello", "start": 16.0, "duration": 1.0, "end_of_line": true }],
+//!     "moves": [{ "file": "moves/clap.msm", "start": 16.0, "duration": 2.0 }]
+//! }
+//! ```
+//! All times in the timelines are in beats, where beat 0 is the first beat of `beats`.
+//! `beats` can also be a list with the time of every beat in milliseconds.
+//!
+//! The audio is extracted from the video, unless `audio` is set to a .wav (48kHz), .ogg, or .opus.
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    ffi::OsStr,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Error};
+use clap::Args;
+use hipstr::HipStr;
+use image::{imageops, ImageFormat};
+use serde::Deserialize;
+
+use super::{find_song, is_valid_map_name};
+use crate::{
+    import::TranscodeSettings,
+    types::{
+        localisation::LocaleId,
+        song::{
+            Clip, Color, Difficulty, GoldEffectClip, HideUserInterfaceClip, KaraokeClip, MapStatus,
+            MenuArt, MenuArtTexture, MotionClip, MusicTrack, NumberOfCoaches, PhoneImage,
+            PictogramClip, Section, Signature, Song, SongColors, SongDirectoryTree,
+            SweatDifficulty, Timeline,
+        },
+        DirectoryTree,
+    },
+    utils::{extract_audio, transcode},
+};
+
+/// Create a new song in the mod at <mod_path> from the description at <description>
+#[derive(Args, Clone)]
+pub struct Create {
+    /// JSON file describing the song
+    description: PathBuf,
+    /// Mod directory
+    mod_path: PathBuf,
+    /// Transcode options
+    #[clap(flatten)]
+    transcode: TranscodeSettings,
+}
+
+/// Wrapper around [`create`]
+pub fn main(cli: &Create) -> Result<(), Error> {
+    create(&cli.description, &cli.mod_path, cli.transcode)
+}
+
+/// Description of a new song
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Description {
+    /// Codename for the song, only letters and numbers
+    map_name: String,
+    /// Song name
+    title: String,
+    /// Artist
+    artist: String,
+    /// Coach name
+    #[serde(default)]
+    dancer_name: String,
+    /// Writing credits
+    #[serde(default)]
+    credits: String,
+    /// Original Just Dance version
+    #[serde(default = "default_jd_version")]
+    original_jd_version: u32,
+    /// Difficulty of the song
+    #[serde(default = "default_difficulty")]
+    difficulty: Difficulty,
+    /// Intensity of the song
+    #[serde(default = "default_sweat_difficulty")]
+    sweat_difficulty: SweatDifficulty,
+    /// Tags related to this song
+    #[serde(default = "default_tags")]
+    tags: Vec<String>,
+    /// Theme colors of the song, all white if not set
+    #[serde(default)]
+    colors: Option<SongColors>,
+    /// The audio file, extracted from the video if not set
+    #[serde(default)]
+    audio: Option<PathBuf>,
+    /// The video file
+    video: PathBuf,
+    /// The cover art
+    cover: PathBuf,
+    /// An image of every coach, also decides the number of coaches
+    coaches: Vec<PathBuf>,
+    /// Background for the song in the menus
+    #[serde(default)]
+    map_bkg: Option<PathBuf>,
+    /// The beats of the song
+    beats: Beats,
+    /// Time signatures, defaults to four beats per bar from the start
+    #[serde(default = "default_signatures")]
+    signatures: Vec<BeatSignature>,
+    /// Sections of the song
+    #[serde(default)]
+    sections: Vec<BeatSection>,
+    /// The part of the song used as preview in the menus
+    #[serde(default)]
+    preview: Option<Preview>,
+    /// The pictograms
+    #[serde(default)]
+    pictos: Vec<PictoDescription>,
+    /// The lyrics
+    #[serde(default)]
+    lyrics: Vec<LyricDescription>,
+    /// The moves (classifiers)
+    #[serde(default)]
+    moves: Vec<MoveDescription>,
+}
+
+/// The beats of a song
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Beats {
+    /// The time of every beat in milliseconds
+    List(Vec<u32>),
+    /// A constant tempo
+    Tempo {
+        /// Beats per minute
+        bpm: f32,
+        /// Time of the first beat in milliseconds
+        #[serde(default)]
+        offset: u32,
+        /// Length of the song in milliseconds
+        length: u32,
+    },
+}
+
+/// Time signature starting at `beat`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BeatSignature {
+    /// Beat the signature starts at
+    beat: i32,
+    /// Beats per bar
+    beats: u32,
+}
+
+/// Section of the song starting at `beat`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BeatSection {
+    /// Beat the section starts at
+    beat: i32,
+    /// Type of the section
+    section_type: u32,
+}
+
+/// The part of the song used as preview in the menus
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Preview {
+    /// First beat of the preview
+    start: u32,
+    /// Last beat of the preview
+    end: u32,
+}
+
+/// A pictogram in the description
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PictoDescription {
+    /// The image
+    file: PathBuf,
+    /// When to show the picto in beats
+    start: f32,
+    /// How long to show the picto in beats
+    duration: f32,
+    /// Is this a gold move
+    #[serde(default)]
+    gold: bool,
+}
+
+/// A lyric in the description
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LyricDescription {
+    /// The lyric
+    text: String,
+    /// When to show the lyric in beats
+    start: f32,
+    /// How long to show the lyric in beats
+    duration: f32,
+    /// Should the next lyric be on a new line
+    #[serde(default)]
+    end_of_line: bool,
+    /// Expected pitch of the lyric
+    #[serde(default)]
+    pitch: f32,
+}
+
+/// A move in the description
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MoveDescription {
+    /// The classifier (.msm)
+    file: PathBuf,
+    /// When the move starts in beats
+    start: f32,
+    /// Duration of the move in beats
+    duration: f32,
+    /// Which coach this move is tracking
+    #[serde(default)]
+    coach: u8,
+    /// Is this a gold move
+    #[serde(default)]
+    gold: bool,
+}
+
+/// Default original Just Dance version
+const fn default_jd_version() -> u32 {
+    2022
+}
+
+/// Default difficulty
+const fn default_difficulty() -> Difficulty {
+    Difficulty::Medium
+}
+
+/// Default intensity
+const fn default_sweat_difficulty() -> SweatDifficulty {
+    SweatDifficulty::Moderate
+}
+
+/// Default tags
+fn default_tags() -> Vec<String> {
+    vec![String::from("main")]
+}
+
+/// Default time signature, four beats per bar
+fn default_signatures() -> Vec<BeatSignature> {
+    vec![BeatSignature { beat: 0, beats: 4 }]
+}
+
+/// Create the song described by `description_path` in the mod at `dir_root`
+///
+/// # Errors
+/// - When the mod directory does not exist
+/// - When the description is invalid or a song with the same map name exists
+/// - When any of the files in the description cannot be read or converted
+pub fn create(
+    description_path: &Path,
+    dir_root: &Path,
+    transcode_settings: TranscodeSettings,
+) -> Result<(), Error> {
+    let dir_tree = DirectoryTree::new(dir_root);
+    if !dir_tree.exists() {
+        bail!("Mod directory does not exist or is missing vital subdirectories!");
+    }
+
+    let description_file = std::fs::read(description_path)
+        .with_context(|| format!("Could not open {}", description_path.display()))?;
+    let description: Description = serde_json::from_slice(&description_file)?;
+    let root = description_path
+        .parent()
+        .ok_or_else(|| anyhow!("No parent directory for {}", description_path.display()))?;
+
+    let map_name = description.map_name.as_str();
+    if !is_valid_map_name(map_name) {
+        bail!("Map name '{map_name}' should only contain letters and numbers!");
+    }
+    if let Some(existing) = find_song(&dir_tree, map_name)? {
+        bail!("There is already a song named {existing} in the mod!");
+    }
+
+    let tree_song = SongDirectoryTree::new(dir_tree.songs(), map_name);
+    tree_song.create_dir_all()?;
+    // Don't leave a half created song behind, it would break the export
+    if let Err(error) = create_song(&description, root, &tree_song, transcode_settings) {
+        tree_song.remove_dir_all()?;
+        return Err(error);
+    }
+
+    println!("Created {map_name}");
+
+    Ok(())
+}
+
+/// Create all files of the song
+fn create_song(
+    description: &Description,
+    root: &Path,
+    tree_song: &SongDirectoryTree,
+    transcode_settings: TranscodeSettings,
+) -> Result<(), Error> {
+    let number_of_coaches =
+        NumberOfCoaches::try_from(u32::try_from(description.coaches.len()).unwrap_or(u32::MAX))?;
+
+    let timing = Timing::new(&description.beats)?;
+    let mut first_clip = create_dance_timeline(description, root, tree_song, &timing)?;
+    first_clip = first_clip.min(create_karaoke_timeline(description, tree_song, &timing)?);
+    create_mainsequence(tree_song, first_clip)?;
+    create_musictrack(description, tree_song, &timing)?;
+    create_menuart(description, root, tree_song)?;
+
+    println!("Transcoding video for {}", description.map_name);
+    let videofile = "main_video.webm";
+    transcode(
+        &root.join(&description.video),
+        &tree_song.song().join(videofile),
+        transcode_settings,
+    )?;
+    let audiofile = create_audio(description, root, tree_song)?;
+
+    let song = Song {
+        map_name: HipStr::from(description.map_name.as_str()),
+        original_jd_version: description.original_jd_version,
+        artist: HipStr::from(description.artist.as_str()),
+        dancer_name: HipStr::from(description.dancer_name.as_str()),
+        title: HipStr::from(description.title.as_str()),
+        credits: HipStr::from(description.credits.as_str()),
+        number_of_coaches,
+        main_coach: None,
+        difficulty: description.difficulty,
+        sweat_difficulty: description.sweat_difficulty,
+        related_songs: vec![],
+        status: MapStatus::Unlocked,
+        tags: description
+            .tags
+            .iter()
+            .map(|tag| HipStr::from(tag.as_str()))
+            .collect(),
+        subtitle: LocaleId::default(),
+        default_colors: description.colors.unwrap_or(SongColors {
+            theme: Color::default(),
+            lyrics: Color::default(),
+            one_a: Color::default(),
+            one_b: Color::default(),
+            two_a: Color::default(),
+            two_b: Color::default(),
+        }),
+        audiofile: HipStr::from(audiofile),
+        videofile: HipStr::borrowed(videofile),
+        mashup: None,
+        dance_lab: None,
+        double_scoring: None,
+        showtime: None,
+        party_master: None,
+    };
+
+    let song_file = File::create(tree_song.song_file())?;
+    serde_json::to_writer_pretty(song_file, &song)?;
+
+    Ok(())
+}
+
+/// Converts the beats in the description to the UbiArt markers and times
+struct Timing {
+    /// Every beat as the sample at 48kHz
+    markers: Vec<u32>,
+    /// Index of the marker that is beat 0 in the description
+    first_beat: u32,
+}
+
+impl Timing {
+    /// Samples per millisecond, the markers are at 48kHz
+    const SAMPLES_PER_MS: u32 = 48;
+    /// UbiArt time units per beat
+    const TICKS_PER_BEAT: f32 = 24.0;
+
+    /// Create the markers for `beats`
+    fn new(beats: &Beats) -> Result<Self, Error> {
+        match beats {
+            Beats::List(beats) => {
+                if beats.len() < 2 {
+                    bail!("The song needs at least two beats!");
+                }
+                if beats.windows(2).any(|pair| pair[0] >= pair[1]) {
+                    bail!("The beats should be in ascending order!");
+                }
+                let markers = beats
+                    .iter()
+                    .map(|beat| beat.checked_mul(Self::SAMPLES_PER_MS))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| anyhow!("Beat is too far into the song!"))?;
+                Ok(Self {
+                    markers,
+                    first_beat: 0,
+                })
+            }
+            Beats::Tempo {
+                bpm,
+                offset,
+                length,
+            } => {
+                if !bpm.is_normal() || *bpm <= 0.0 {
+                    bail!("Invalid bpm {bpm}!");
+                }
+                let interval = 60_000.0 / f64::from(*bpm);
+                let offset = f64::from(*offset);
+                // Add the beats before the first beat, so the markers cover the whole song
+                let first_beat = (offset / interval).floor();
+                let start = first_beat.mul_add(-interval, offset);
+                let mut markers = Vec::new();
+                let mut time = start;
+                while time <= f64::from(*length) {
+                    markers.push(ms_to_marker(time));
+                    time += interval;
+                }
+                if markers.len() < 2 {
+                    bail!("The song needs at least two beats!");
+                }
+                Ok(Self {
+                    markers,
+                    first_beat: saturating_f64_to_u32(first_beat),
+                })
+            }
+        }
+    }
+
+    /// The index of the last marker
+    fn end_beat(&self) -> u32 {
+        u32::try_from(self.markers.len())
+            .unwrap_or(u32::MAX)
+            .saturating_sub(1)
+    }
+
+    /// Convert `beat` in the description to a marker index
+    fn marker(&self, beat: i32) -> i32 {
+        i32::try_from(self.first_beat)
+            .unwrap_or(i32::MAX)
+            .saturating_add(beat)
+    }
+
+    /// Convert the time `beat` in the description to the UbiArt time
+    fn time(&self, beat: f32) -> i32 {
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::as_conversions,
+            reason = "The first beat is always small enough"
+        )]
+        let first_beat = self.first_beat as f32;
+        ticks((beat + first_beat) * Self::TICKS_PER_BEAT)
+    }
+
+    /// Convert the duration `beats` in the description to the UbiArt duration
+    fn duration(beats: f32) -> i32 {
+        ticks(beats * Self::TICKS_PER_BEAT)
+    }
+}
+
+/// Convert a time in milliseconds to a marker
+fn ms_to_marker(ms: f64) -> u32 {
+    saturating_f64_to_u32(ms * f64::from(Timing::SAMPLES_PER_MS))
+}
+
+/// Round `value` to the nearest u32
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::as_conversions,
+    reason = "Value is clamped to the range of u32"
+)]
+fn saturating_f64_to_u32(value: f64) -> u32 {
+    value.round().clamp(0.0, f64::from(u32::MAX)) as u32
+}
+
+/// Round UbiArt ticks to an integer
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::as_conversions,
+    reason = "Saturating conversion is intended"
+)]
+fn ticks(value: f32) -> i32 {
+    value.round() as i32
+}
+
+/// Create the dance timeline with the pictos and moves, returns the start of the first clip
+fn create_dance_timeline(
+    description: &Description,
+    root: &Path,
+    tree_song: &SongDirectoryTree,
+    timing: &Timing,
+) -> Result<i32, Error> {
+    let mut timeline = Timeline {
+        timeline: BTreeSet::new(),
+    };
+    let mut first_clip = i32::MAX;
+
+    let mut converted = HashSet::new();
+    for picto in &description.pictos {
+        let picto_filename = file_name(&picto.file, "png")?;
+        // A picto can be used multiple times, only convert it once
+        if converted.insert(picto_filename.clone()) {
+            let image = image::open(root.join(&picto.file))
+                .with_context(|| format!("Could not open {}", picto.file.display()))?;
+            let image = image.resize(1024, 512, imageops::FilterType::Lanczos3);
+            image.save_with_format(tree_song.pictos().join(&picto_filename), ImageFormat::Png)?;
+        }
+
+        let start_time = timing.time(picto.start);
+        let duration = Timing::duration(picto.duration);
+        first_clip = first_clip.min(start_time);
+        timeline.timeline.insert(Clip::Pictogram(PictogramClip {
+            is_active: true,
+            start_time,
+            duration,
+            picto_filename: HipStr::from(picto_filename),
+        }));
+        if picto.gold {
+            timeline.timeline.insert(Clip::GoldEffect(GoldEffectClip {
+                is_active: true,
+                start_time,
+                duration,
+                effect_type: 1,
+            }));
+        }
+    }
+
+    for mov in &description.moves {
+        if usize::from(mov.coach) >= description.coaches.len() {
+            bail!(
+                "Move {} is for coach {}, but the song only has {} coaches!",
+                mov.file.display(),
+                mov.coach,
+                description.coaches.len()
+            );
+        }
+        if mov.file.extension() != Some(OsStr::new("msm")) {
+            bail!("Moves should be .msm files: {}", mov.file.display());
+        }
+        let classifier_filename = file_name(&mov.file, "msm")?;
+        if converted.insert(classifier_filename.clone()) {
+            std::fs::copy(
+                root.join(&mov.file),
+                tree_song.moves().join(&classifier_filename),
+            )
+            .with_context(|| format!("Could not copy {}", mov.file.display()))?;
+        }
+
+        let start_time = timing.time(mov.start);
+        first_clip = first_clip.min(start_time);
+        timeline.timeline.insert(Clip::Motion(MotionClip {
+            is_active: true,
+            start_time,
+            duration: Timing::duration(mov.duration),
+            classifier_filename: HipStr::from(classifier_filename),
+            gold_move: mov.gold,
+            coach_id: mov.coach,
+            color: Color::default(),
+            platform_specifics: BTreeMap::new(),
+        }));
+    }
+
+    let timeline_file = File::create(tree_song.song().join("dance_timeline.json"))?;
+    serde_json::to_writer_pretty(timeline_file, &timeline)?;
+
+    Ok(first_clip)
+}
+
+/// The filename of `path` with the extension replaced by `extension`
+///
+/// Pictos are always converted to png, so their original extension is not used.
+fn file_name(path: &Path, extension: &str) -> Result<String, Error> {
+    let stem = path
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| anyhow!("Invalid filename: {}", path.display()))?;
+    Ok(format!("{stem}.{extension}"))
+}
+
+/// Create the karaoke timeline with the lyrics, returns the start of the first clip
+fn create_karaoke_timeline(
+    description: &Description,
+    tree_song: &SongDirectoryTree,
+    timing: &Timing,
+) -> Result<i32, Error> {
+    let mut timeline = Timeline {
+        timeline: BTreeSet::new(),
+    };
+    let mut first_clip = i32::MAX;
+
+    for lyric in &description.lyrics {
+        let start_time = timing.time(lyric.start);
+        first_clip = first_clip.min(start_time);
+        timeline.timeline.insert(Clip::Karaoke(KaraokeClip {
+            is_active: true,
+            start_time,
+            duration: Timing::duration(lyric.duration),
+            pitch: lyric.pitch,
+            lyrics: HipStr::from(lyric.text.as_str()),
+            is_end_of_line: lyric.end_of_line,
+            content_type: 1,
+        }));
+    }
+
+    let timeline_file = File::create(tree_song.song().join("karaoke_timeline.json"))?;
+    serde_json::to_writer_pretty(timeline_file, &timeline)?;
+
+    Ok(first_clip)
+}
+
+/// Create the mainsequence, hiding the user interface until the dancing starts
+fn create_mainsequence(tree_song: &SongDirectoryTree, first_clip: i32) -> Result<(), Error> {
+    let mut timeline = Timeline {
+        timeline: BTreeSet::new(),
+    };
+
+    if first_clip != i32::MAX && first_clip > 100 {
+        timeline
+            .timeline
+            .insert(Clip::HideUserInterface(HideUserInterfaceClip {
+                is_active: true,
+                start_time: 0,
+                duration: first_clip - 100,
+                event_type: 18,
+            }));
+    }
+
+    let mainsequence_file = File::create(tree_song.song().join("mainsequence.json"))?;
+    serde_json::to_writer_pretty(mainsequence_file, &timeline)?;
+
+    Ok(())
+}
+
+/// Create the musictrack with the markers, signatures, and sections
+fn create_musictrack(
+    description: &Description,
+    tree_song: &SongDirectoryTree,
+    timing: &Timing,
+) -> Result<(), Error> {
+    let end_beat = timing.end_beat();
+    let (preview_start, preview_end) = description.preview.as_ref().map_or_else(
+        || {
+            let start = timing.first_beat;
+            (start, end_beat.min(start.saturating_add(64)))
+        },
+        |preview| {
+            (
+                timing.first_beat.saturating_add(preview.start),
+                timing.first_beat.saturating_add(preview.end),
+            )
+        },
+    );
+    if preview_start >= preview_end || preview_end > end_beat {
+        bail!("The preview should be inside the song and end after it starts!");
+    }
+
+    let musictrack = MusicTrack {
+        start_beat: 0,
+        end_beat,
+        video_start_time: 0.0,
+        preview_entry: preview_start,
+        preview_loop_start: preview_start,
+        preview_loop_end: preview_end,
+        signatures: description
+            .signatures
+            .iter()
+            .map(|signature| Signature {
+                marker: timing.marker(signature.beat),
+                beats: signature.beats,
+            })
+            .collect(),
+        sections: description
+            .sections
+            .iter()
+            .map(|section| Section {
+                marker: timing.marker(section.beat),
+                section_type: section.section_type,
+            })
+            .collect(),
+        markers: timing.markers.clone(),
+    };
+
+    let musictrack_file = File::create(tree_song.song().join("musictrack.json"))?;
+    serde_json::to_writer_pretty(musictrack_file, &musictrack)?;
+
+    Ok(())
+}
+
+/// Copy or extract the audio and put it in the song directory
+fn create_audio(
+    description: &Description,
+    root: &Path,
+    tree_song: &SongDirectoryTree,
+) -> Result<String, Error> {
+    let map_name = &description.map_name;
+    if let Some(audio) = &description.audio {
+        let extension = audio
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_lowercase);
+        if let Some(extension @ ("wav" | "ogg" | "opus")) = extension.as_deref() {
+            let filename = format!("{map_name}.{extension}");
+            std::fs::copy(root.join(audio), tree_song.audio().join(&filename))
+                .with_context(|| format!("Could not copy {}", audio.display()))?;
+            return Ok(filename);
+        }
+    }
+
+    let from = root.join(description.audio.as_ref().unwrap_or(&description.video));
+    let filename = format!("{map_name}.opus");
+    extract_audio(&from, &tree_song.audio().join(&filename))?;
+    Ok(filename)
+}
+
+/// Position of the menuart of every coach, the coaches are next to each other
+const COACH_POSITIONS: [(f32, f32); 6] = [
+    (212.7845, 663.6802),
+    (524.3811, 670.82983),
+    (835.9777, 670.82983),
+    (1147.5743, 670.82983),
+    (1459.1709, 670.82983),
+    (1770.7675, 670.82983),
+];
+
+/// Create the menuart from the cover, the coaches, and the map background
+fn create_menuart(
+    description: &Description,
+    root: &Path,
+    tree_song: &SongDirectoryTree,
+) -> Result<(), Error> {
+    let mut menuart = Vec::new();
+    let menuart_dir = tree_song.menuart();
+
+    let orig_cover = image::open(root.join(&description.cover))
+        .with_context(|| format!("Could not open {}", description.cover.display()))?;
+    let cover = orig_cover.resize(1024, 1024, imageops::FilterType::Lanczos3);
+    for (name, pos2d) in [
+        ("cover_generic", (266.08755, 197.62996)),
+        ("cover_online_Kids", (-150.0, 0.0)),
+        ("cover_online", (-150.0, 0.0)),
+    ] {
+        let filename = format!("{name}.png");
+        cover.save(menuart_dir.join(&filename))?;
+        menuart.push(MenuArt::Texture(MenuArtTexture {
+            name: HipStr::borrowed(name),
+            filename: HipStr::from(filename),
+            scale: (0.3, 0.3),
+            pos2d,
+            disable_shadow: 4_294_967_295,
+            anchor: 1,
+        }));
+    }
+    // JPEG has no alpha channel
+    cover.to_rgb8().save(menuart_dir.join("cover_phone.jpg"))?;
+    menuart.push(MenuArt::Phone(PhoneImage {
+        name: HipStr::borrowed("cover"),
+        filename: HipStr::borrowed("cover_phone.jpg"),
+    }));
+
+    for (i, coach_path) in description.coaches.iter().enumerate() {
+        let coach = i.saturating_add(1);
+        let orig_coach = image::open(root.join(coach_path))
+            .with_context(|| format!("Could not open {}", coach_path.display()))?;
+        let coach_image = orig_coach.resize(1024, 1024, imageops::FilterType::Lanczos3);
+        coach_image.save(menuart_dir.join(format!("coach_{coach}.png")))?;
+        let coach_image = orig_coach.resize(256, 256, imageops::FilterType::Lanczos3);
+        coach_image.save(menuart_dir.join(format!("coach{coach}_phone.png")))?;
+
+        menuart.push(MenuArt::Texture(MenuArtTexture {
+            name: HipStr::from(format!("coach_{coach}")),
+            filename: HipStr::from(format!("coach_{coach}.png")),
+            scale: (0.290_211, 0.290_211),
+            pos2d: COACH_POSITIONS[i],
+            disable_shadow: 4_294_967_295,
+            anchor: 6,
+        }));
+        menuart.push(MenuArt::Phone(PhoneImage {
+            name: HipStr::from(format!("coach{coach}")),
+            filename: HipStr::from(format!("coach{coach}_phone.png")),
+        }));
+
+        if coach == 1 {
+            let cover_albumcoach = orig_coach.resize(1024, 1024, imageops::FilterType::Lanczos3);
+            cover_albumcoach.save(menuart_dir.join("cover_albumcoach.png"))?;
+            menuart.push(MenuArt::Texture(MenuArtTexture {
+                name: HipStr::borrowed("cover_albumcoach"),
+                filename: HipStr::borrowed("cover_albumcoach.png"),
+                scale: (0.3, 0.3),
+                pos2d: (738.1063, 359.61203),
+                disable_shadow: 4_294_967_295,
+                anchor: 1,
+            }));
+        }
+    }
+
+    if let Some(map_bkg_path) = &description.map_bkg {
+        let map_bkg = image::open(root.join(map_bkg_path))
+            .with_context(|| format!("Could not open {}", map_bkg_path.display()))?;
+        map_bkg.save(menuart_dir.join("map_bkg.png"))?;
+        menuart.push(MenuArt::Texture(MenuArtTexture {
+            name: HipStr::borrowed("map_bkg"),
+            filename: HipStr::borrowed("map_bkg.png"),
+            scale: (256.0, 128.0),
+            pos2d: (1487.41, 350.0),
+            disable_shadow: 1,
+            anchor: 1,
+        }));
+    }
+
+    let menuart_file = File::create(menuart_dir.join("menuart.json"))?;
+    serde_json::to_writer_pretty(menuart_file, &menuart)?;
+
+    Ok(())
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use super::{Beats, Timing};
+
+    #[test]
+    fn test_timing_tempo() {
+        let timing = Timing::new(&Beats::Tempo {
+            bpm: 120.0,
+            offset: 1250,
+            length: 3000,
+        })
+        .unwrap();
+        assert_eq!(
+            timing.first_beat, 2,
+            "Two beats should be added before the offset"
+        );
+        assert_eq!(
+            timing.markers,
+            [12_000, 36_000, 60_000, 84_000, 108_000, 132_000],
+            "Markers should be every 500 ms at 48 kHz"
+        );
+        assert_eq!(timing.time(0.0), 48, "Beat 0 is after the two added beats");
+        assert_eq!(timing.time(1.5), 84, "A beat should be 24 ticks");
+        assert_eq!(timing.marker(-1), 1, "Beat -1 is the second added beat");
+        assert_eq!(timing.end_beat(), 5, "The last marker is the end");
+    }
+
+    #[test]
+    fn test_timing_list() {
+        let timing = Timing::new(&Beats::List(vec![0, 500, 1000])).unwrap();
+        assert_eq!(timing.first_beat, 0, "No beats should be added");
+        assert_eq!(
+            timing.markers,
+            [0, 24_000, 48_000],
+            "Markers should be the beats at 48 kHz"
+        );
+        assert!(
+            Timing::new(&Beats::List(vec![0, 500, 400])).is_err(),
+            "Beats that go back in time should be an error"
+        );
+    }
+}
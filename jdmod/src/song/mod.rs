@@ -0,0 +1,49 @@
+//! # Song
+//! Commands for managing the songs in a mod
+use anyhow::Error;
+use clap::Subcommand;
+
+use crate::types::DirectoryTree;
+
+pub mod create;
+mod references;
+pub mod remove;
+pub mod rename;
+
+/// Manage the songs in a mod
+#[derive(Subcommand, Clone)]
+pub enum SongCommand {
+    /// Create a new song from an audio file, a video, cover art, and a description
+    Create(create::Create),
+    /// Remove a song and all references to it
+    Remove(remove::Remove),
+    /// Give a song a new map name and update all references to it
+    Rename(rename::Rename),
+}
+
+/// Run the song command
+pub fn main(cli: &SongCommand) -> Result<(), Error> {
+    match cli {
+        SongCommand::Create(data) => create::main(data),
+        SongCommand::Remove(data) => remove::main(data),
+        SongCommand::Rename(data) => rename::main(data),
+    }
+}
+
+/// Check if `map_name` can be used as a map name, it should only contain letters and numbers
+fn is_valid_map_name(map_name: &str) -> bool {
+    !map_name.is_empty() && map_name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Find a song in the mod with the same map name, ignoring case
+fn find_song(dir_tree: &DirectoryTree, map_name: &str) -> Result<Option<String>, Error> {
+    for entry in dir_tree.songs().read_dir()? {
+        let name = entry?.file_name();
+        if let Some(name) = name.to_str() {
+            if name.eq_ignore_ascii_case(map_name) {
+                return Ok(Some(name.to_owned()));
+            }
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,928 @@
+//! # References
+//! Updates everything in the mod that refers to a song, used for removing and renaming songs.
+//!
+//! Songs are referred to by other songs (related songs, mashups, and Dance Lab blocks), playlists,
+//! objectives, maps objectives and goals, offline recommendations, search labels, and avatars.
+//! The content of the gacha machine is generated from the avatars during the export, so it is
+//! updated by updating the avatars.
+//!
+//! When removing a song, entries that only existed for that song (like its avatars or an objective
+//! for only that map) are removed too. The locale ids used by removed entries are removed from the
+//! translations if nothing else uses them.
+//!
+//! All changes are collected in a [`Transaction`], which only touches the mod when every file was
+//! updated successfully.
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Error};
+use hipstr::HipStr;
+use serde::{Deserialize, Serialize};
+use ubiart_toolkit::cooked::isg::{MapsGoals, MapsObjectives};
+
+use crate::types::{
+    gameconfig::{
+        aliases::Aliases,
+        avatars::{Avatar, UnlockType},
+        objectives::{ComponentType, Objective},
+        playlists::Playlist,
+        scheduled_quests::ScheduledQuests,
+        search_labels::SearchLabel,
+    },
+    localisation::{LocaleId, LANGUAGE_FILES},
+    song::{Song, SongDirectoryTree},
+    DirectoryTree,
+};
+
+/// What happens to the song
+#[derive(Debug, Clone, Copy)]
+pub enum Change<'a> {
+    /// The song is removed from the mod
+    Remove,
+    /// The song gets a new map name
+    Rename(&'a str),
+}
+
+/// Changes to the files in a mod that are applied together
+#[derive(Default)]
+pub struct Transaction {
+    /// Files with their new contents
+    writes: Vec<(PathBuf, Vec<u8>)>,
+    /// Files and directories to move, done after the writes
+    moves: Vec<(PathBuf, PathBuf)>,
+    /// Files and directories to remove, done after the moves
+    removals: Vec<PathBuf>,
+}
+
+impl Transaction {
+    /// Write `value` as JSON to `path`
+    pub fn write(&mut self, path: PathBuf, value: &impl Serialize) -> Result<(), Error> {
+        self.writes.push((path, serde_json::to_vec_pretty(value)?));
+        Ok(())
+    }
+
+    /// Move the file or directory at `from` to `to`
+    pub fn rename(&mut self, from: PathBuf, to: PathBuf) {
+        self.moves.push((from, to));
+    }
+
+    /// Remove the file or directory at `path`
+    pub fn remove(&mut self, path: PathBuf) {
+        self.removals.push(path);
+    }
+
+    /// Apply all changes
+    ///
+    /// The new contents are first written next to the original files, so a failing write leaves
+    /// the mod untouched. Then the files are replaced, moved and removed while keeping a journal.
+    /// Replaced and removed files are kept aside until everything succeeded, so if any of these
+    /// steps fails the completed steps are undone.
+    ///
+    /// # Errors
+    /// Will return an error if any of the IO fails
+    pub fn commit(self) -> Result<(), Error> {
+        let mut staged = Vec::with_capacity(self.writes.len());
+        for (path, data) in self.writes {
+            let staging = with_suffix(&path, "tmp");
+            if let Err(error) = std::fs::write(&staging, data) {
+                let _ = std::fs::remove_file(&staging);
+                for (staging, _) in &staged {
+                    let _ = std::fs::remove_file(staging);
+                }
+                return Err(error).with_context(|| format!("Could not write {}", path.display()));
+            }
+            staged.push((staging, path));
+        }
+
+        let mut journal = Vec::new();
+        if let Err(error) = apply(staged, self.moves, self.removals, &mut journal) {
+            for step in journal.into_iter().rev() {
+                step.undo();
+            }
+            return Err(error);
+        }
+
+        // Everything succeeded, the kept aside files are no longer needed
+        for step in journal {
+            step.finish();
+        }
+        Ok(())
+    }
+}
+
+/// A completed step of [`Transaction::commit`]
+enum Step {
+    /// A file was replaced
+    Replaced {
+        /// The replaced file
+        path: PathBuf,
+        /// Where the original file is kept
+        backup: PathBuf,
+    },
+    /// A file was created
+    Created {
+        /// The new file
+        path: PathBuf,
+    },
+    /// A file or directory was moved
+    Moved {
+        /// The old location
+        from: PathBuf,
+        /// The new location
+        to: PathBuf,
+    },
+    /// A file or directory was removed
+    Removed {
+        /// The removed file or directory
+        path: PathBuf,
+        /// Where it is kept until the commit is finished
+        trash: PathBuf,
+    },
+}
+
+impl Step {
+    /// Undo this step, failures are ignored as there is already an error
+    fn undo(self) {
+        match self {
+            Self::Replaced { path, backup } => {
+                let _ = std::fs::rename(backup, path);
+            }
+            Self::Created { path } => {
+                let _ = std::fs::remove_file(path);
+            }
+            Self::Moved { from, to } => {
+                let _ = std::fs::rename(to, from);
+            }
+            Self::Removed { path, trash } => {
+                let _ = std::fs::rename(trash, path);
+            }
+        }
+    }
+
+    /// Remove what was kept aside for this step, failures are ignored as the changes are done
+    fn finish(self) {
+        match self {
+            Self::Replaced { backup: trash, .. } | Self::Removed { trash, .. } => {
+                if trash.is_dir() {
+                    let _ = std::fs::remove_dir_all(trash);
+                } else {
+                    let _ = std::fs::remove_file(trash);
+                }
+            }
+            Self::Created { .. } | Self::Moved { .. } => {}
+        }
+    }
+}
+
+/// Replace the files with the `staged` files, then do the `moves` and the `removals`
+///
+/// Every completed step is added to `journal`. On an error, the staged files that were not used
+/// yet are removed.
+fn apply(
+    staged: Vec<(PathBuf, PathBuf)>,
+    moves: Vec<(PathBuf, PathBuf)>,
+    removals: Vec<PathBuf>,
+    journal: &mut Vec<Step>,
+) -> Result<(), Error> {
+    let mut staged = staged.into_iter();
+    while let Some((staging, path)) = staged.next() {
+        if let Err(error) = replace(&staging, path, journal) {
+            let _ = std::fs::remove_file(staging);
+            for (staging, _) in staged {
+                let _ = std::fs::remove_file(staging);
+            }
+            return Err(error);
+        }
+    }
+    for (from, to) in moves {
+        std::fs::rename(&from, &to)
+            .with_context(|| format!("Could not move {} to {}", from.display(), to.display()))?;
+        journal.push(Step::Moved { from, to });
+    }
+    for path in removals {
+        if !path.exists() {
+            continue;
+        }
+        let trash = with_suffix(&path, "removed");
+        std::fs::rename(&path, &trash)
+            .with_context(|| format!("Could not remove {}", path.display()))?;
+        journal.push(Step::Removed { path, trash });
+    }
+    Ok(())
+}
+
+/// Replace the file at `path` with `staging`, keeping the original aside
+fn replace(staging: &Path, path: PathBuf, journal: &mut Vec<Step>) -> Result<(), Error> {
+    if path.exists() {
+        let backup = with_suffix(&path, "bak");
+        std::fs::rename(&path, &backup)
+            .with_context(|| format!("Could not replace {}", path.display()))?;
+        if let Err(error) = std::fs::rename(staging, &path) {
+            let _ = std::fs::rename(&backup, &path);
+            return Err(error).with_context(|| format!("Could not replace {}", path.display()));
+        }
+        journal.push(Step::Replaced { path, backup });
+    } else {
+        std::fs::rename(staging, &path)
+            .with_context(|| format!("Could not create {}", path.display()))?;
+        journal.push(Step::Created { path });
+    }
+    Ok(())
+}
+
+/// Add `.{suffix}` to the filename of `path`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Collect the changes to all files that refer to the song in `song_dir`
+///
+/// When renaming, this includes the new map name in the song itself but not moving its directory.
+///
+/// # Errors
+/// Will return an error if a file cannot be read or parsed, or if the song is used in a way that
+/// cannot be removed (like a mashup)
+pub fn update(
+    dir_tree: &DirectoryTree,
+    song_dir: &str,
+    change: Change<'_>,
+) -> Result<Transaction, Error> {
+    let song_file = SongDirectoryTree::new(dir_tree.songs(), song_dir)
+        .song_file()
+        .to_path_buf();
+    let data = read(&song_file)?.ok_or_else(|| anyhow!("{} is missing", song_file.display()))?;
+    let map_name = parse::<Song>(&song_file, &data)?.map_name.to_string();
+
+    let mut updater = Updater {
+        dir_tree,
+        map_name,
+        change,
+        transaction: Transaction::default(),
+        removed_objectives: HashSet::new(),
+        orphaned_ids: HashSet::new(),
+        used_ids: HashSet::new(),
+    };
+
+    updater.songs(song_dir)?;
+    // Objectives first, as removing them affects the other files
+    updater.objectives()?;
+    updater.maps_objectives()?;
+    updater.maps_goals()?;
+    updater.offline_recommendations()?;
+    updater.playlists()?;
+    updater.search_labels()?;
+    updater.avatars()?;
+    updater.aliases()?;
+    updater.quests()?;
+    // Translations last, as all the other files decide which locale ids are still used
+    updater.translations()?;
+
+    Ok(updater.transaction)
+}
+
+/// State shared by all the updates
+struct Updater<'a> {
+    /// The mod being updated
+    dir_tree: &'a DirectoryTree,
+    /// Map name of the song
+    map_name: String,
+    /// What happens to the song
+    change: Change<'a>,
+    /// The changes so far
+    transaction: Transaction,
+    /// Objectives that were removed because they were only for this song
+    removed_objectives: HashSet<String>,
+    /// Locale ids used by removed entries
+    orphaned_ids: HashSet<LocaleId>,
+    /// Locale ids used by the remaining entries
+    used_ids: HashSet<LocaleId>,
+}
+
+impl Updater<'_> {
+    /// Does `name` refer to the song
+    fn matches(&self, name: &str) -> bool {
+        name.eq_ignore_ascii_case(&self.map_name)
+    }
+
+    /// The updated reference for `name`, `None` if the reference should be removed
+    ///
+    /// References that are lowercase stay lowercase.
+    fn replacement<'b>(&self, name: &HipStr<'b>) -> Option<HipStr<'b>> {
+        if !self.matches(name) {
+            return Some(name.clone());
+        }
+        match self.change {
+            Change::Remove => None,
+            Change::Rename(new) if !name.chars().any(|c| c.is_ascii_uppercase()) => {
+                Some(HipStr::from(new.to_lowercase()))
+            }
+            Change::Rename(new) => Some(HipStr::from(new)),
+        }
+    }
+
+    /// Update the references in `names`, returns true if anything changed
+    fn update_names(&self, names: &mut Vec<HipStr<'_>>) -> bool {
+        if !names.iter().any(|name| self.matches(name)) {
+            return false;
+        }
+        *names = names
+            .iter()
+            .filter_map(|name| self.replacement(name))
+            .collect();
+        true
+    }
+
+    /// Update a reference that cannot be removed, returns true if it changed
+    fn update_required(
+        &self,
+        name: &mut HipStr<'_>,
+        owner: &str,
+        what: &str,
+    ) -> Result<bool, Error> {
+        if !self.matches(name) {
+            return Ok(false);
+        }
+        match self.replacement(name) {
+            Some(new) => {
+                *name = new;
+                Ok(true)
+            }
+            None => bail!(
+                "{} is still used by the {what} of {owner}, remove it from there first!",
+                self.map_name
+            ),
+        }
+    }
+
+    /// Update the map names used as keys in `map`, returns the values that were removed
+    fn update_keys<V>(&self, map: &mut HashMap<HipStr<'_>, V>) -> Vec<V> {
+        let keys = map
+            .keys()
+            .filter(|key| self.matches(key))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut removed = Vec::new();
+        for key in keys {
+            if let Some(value) = map.remove(&key) {
+                match self.replacement(&key) {
+                    Some(new_key) => {
+                        map.insert(new_key, value);
+                    }
+                    None => removed.push(value),
+                }
+            }
+        }
+        removed
+    }
+
+    /// Update the references in all songs, and the map name of the song itself when renaming
+    fn songs(&mut self, song_dir: &str) -> Result<(), Error> {
+        let dir_tree = self.dir_tree;
+        let songs_dir = dir_tree.songs();
+        for entry in songs_dir.read_dir()? {
+            let entry = entry?;
+            if !entry.path().is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let dirs = SongDirectoryTree::new(songs_dir, &name);
+            let path = dirs.song_file();
+            let Some(data) = read(path)? else {
+                continue;
+            };
+            let mut song: Song = parse(path, &data)?;
+
+            let mut changed = false;
+            if name == song_dir {
+                match self.change {
+                    Change::Remove => {
+                        self.orphaned_ids.insert(song.subtitle);
+                        continue;
+                    }
+                    Change::Rename(new) => {
+                        song.map_name = HipStr::from(new);
+                        changed = true;
+                    }
+                }
+            }
+
+            changed |= self.update_names(&mut song.related_songs);
+            let owner = song.map_name.clone();
+            if let Some(mashup) = &mut song.mashup {
+                for segment in &mut mashup.segments {
+                    changed |= self.update_required(&mut segment.source_map, &owner, "mashup")?;
+                }
+            }
+            if let Some(dance_lab) = &mut song.dance_lab {
+                let descriptors = dance_lab.blocks.iter_mut().flat_map(|block| {
+                    std::iter::once(&mut block.base).chain(&mut block.alternatives)
+                });
+                for descriptor in descriptors {
+                    changed |=
+                        self.update_required(&mut descriptor.song_name, &owner, "Dance Lab")?;
+                }
+            }
+            self.used_ids.insert(song.subtitle);
+
+            if changed {
+                self.transaction.write(path.to_path_buf(), &song)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the map name requirements of the objectives
+    ///
+    /// An empty requirement accepts every map, so objectives that only accepted this song are
+    /// removed instead.
+    fn objectives(&mut self) -> Result<(), Error> {
+        let path = self.dir_tree.config().join("objectives.json");
+        let Some(data) = read(&path)? else {
+            return Ok(());
+        };
+        let mut objectives: HashMap<HipStr, Objective> = parse(&path, &data)?;
+
+        let mut changed = false;
+        let mut removed = Vec::new();
+        for (name, objective) in &mut objectives {
+            let Some(components) = objective.objective_type.components_mut() else {
+                continue;
+            };
+            for component in components {
+                if let ComponentType::MapNameRequirement(requirement) = &mut component.c_type {
+                    let was_empty = requirement.acceptable_map_names.is_empty();
+                    if self.update_names(&mut requirement.acceptable_map_names) {
+                        changed = true;
+                        if !was_empty && requirement.acceptable_map_names.is_empty() {
+                            removed.push(name.clone());
+                        }
+                    }
+                }
+            }
+        }
+        for name in removed {
+            if let Some(objective) = objectives.remove(&name) {
+                self.orphaned_ids.insert(objective.description);
+                self.removed_objectives.insert(name.as_str().to_owned());
+            }
+        }
+
+        for objective in objectives.values_mut() {
+            self.used_ids.insert(objective.description);
+            let Some(components) = objective.objective_type.components_mut() else {
+                continue;
+            };
+            for component in components {
+                if let ComponentType::SearchLabelsRequirement(requirement) = &component.c_type {
+                    self.used_ids
+                        .extend(requirement.acceptable_labels.iter().copied());
+                    self.used_ids
+                        .extend(requirement.forbidden_labels.iter().copied());
+                }
+            }
+        }
+
+        if changed {
+            self.transaction.write(path, &objectives)?;
+        }
+        Ok(())
+    }
+
+    /// Update the objectives for maps
+    fn maps_objectives(&mut self) -> Result<(), Error> {
+        let path = self.dir_tree.config().join("maps_objectives.json");
+        let Some(data) = read(&path)? else {
+            return Ok(());
+        };
+        let mut maps_objectives: MapsObjectives = parse(&path, &data)?;
+
+        let mut changed = maps_objectives.keys().any(|key| self.matches(key));
+        self.update_keys(&mut maps_objectives);
+        let before = maps_objectives.len();
+        maps_objectives
+            .retain(|_, objective| !self.removed_objectives.contains(objective.as_str()));
+        changed |= maps_objectives.len() != before;
+
+        if changed {
+            self.transaction.write(path, &maps_objectives)?;
+        }
+        Ok(())
+    }
+
+    /// Update the goals for maps
+    fn maps_goals(&mut self) -> Result<(), Error> {
+        let path = self.dir_tree.config().join("maps_goals.json");
+        let Some(data) = read(&path)? else {
+            return Ok(());
+        };
+        let mut maps_goals: MapsGoals = parse(&path, &data)?;
+
+        if maps_goals.keys().any(|key| self.matches(key)) {
+            self.update_keys(&mut maps_goals);
+            self.transaction.write(path, &maps_goals)?;
+        }
+        Ok(())
+    }
+
+    /// Update the maps recommended when offline
+    fn offline_recommendations(&mut self) -> Result<(), Error> {
+        let path = self.dir_tree.config().join("offline_recommendations.json");
+        let Some(data) = read(&path)? else {
+            return Ok(());
+        };
+        let mut map_names: Vec<HipStr> = parse(&path, &data)?;
+
+        if self.update_names(&mut map_names) {
+            self.transaction.write(path, &map_names)?;
+        }
+        Ok(())
+    }
+
+    /// Update the maps in the playlists, playlists that are now empty are removed
+    fn playlists(&mut self) -> Result<(), Error> {
+        let dir_tree = self.dir_tree;
+        let dir = dir_tree.playlists();
+        let path = dir.join("playlists.json");
+        let Some(data) = read(&path)? else {
+            return Ok(());
+        };
+        let mut playlists: HashMap<HipStr, Playlist> = parse(&path, &data)?;
+
+        let mut changed = false;
+        let mut removed = Vec::new();
+        for (name, playlist) in &mut playlists {
+            if self.update_names(&mut playlist.maps) {
+                changed = true;
+                if playlist.maps.is_empty() {
+                    removed.push(name.clone());
+                }
+            }
+        }
+        let mut covers = HashSet::new();
+        for name in removed {
+            if let Some(playlist) = playlists.remove(&name) {
+                self.orphaned_ids.insert(playlist.title);
+                self.orphaned_ids.insert(playlist.description);
+                covers.insert(playlist.cover);
+            }
+        }
+
+        for playlist in playlists.values() {
+            self.used_ids.insert(playlist.title);
+            self.used_ids.insert(playlist.description);
+            covers.remove(&playlist.cover);
+        }
+        for cover in covers {
+            self.transaction.remove(dir.join(cover.as_str()));
+        }
+
+        if changed {
+            self.transaction.write(path, &playlists)?;
+        }
+        Ok(())
+    }
+
+    /// Update the search labels for maps
+    fn search_labels(&mut self) -> Result<(), Error> {
+        let path = self.dir_tree.config().join("search_labels.json");
+        let Some(data) = read(&path)? else {
+            return Ok(());
+        };
+        let mut search_labels: HashMap<HipStr, HashSet<SearchLabel>> = parse(&path, &data)?;
+
+        let changed = search_labels.keys().any(|key| self.matches(key));
+        for labels in self.update_keys(&mut search_labels) {
+            self.orphaned_ids
+                .extend(labels.iter().map(|label| label.description));
+        }
+        for labels in search_labels.values() {
+            self.used_ids
+                .extend(labels.iter().map(|label| label.description));
+        }
+
+        if changed {
+            self.transaction.write(path, &search_labels)?;
+        }
+        Ok(())
+    }
+
+    /// Update the avatars, avatars based on a removed song are removed
+    fn avatars(&mut self) -> Result<(), Error> {
+        let dir_tree = self.dir_tree;
+        let dir = dir_tree.avatars();
+        let path = dir.join("avatars.json");
+        let Some(data) = read(&path)? else {
+            return Ok(());
+        };
+        let mut avatars: HashMap<HipStr, Avatar> = parse(&path, &data)?;
+
+        let mut changed = false;
+        let mut removed = HashSet::new();
+        for (name, avatar) in &mut avatars {
+            if !self.matches(&avatar.relative_song_name)
+                && !self.matches(&avatar.used_as_coach_map_name)
+            {
+                continue;
+            }
+            changed = true;
+            match (
+                self.replacement(&avatar.relative_song_name),
+                self.replacement(&avatar.used_as_coach_map_name),
+            ) {
+                (Some(relative_song_name), Some(used_as_coach_map_name)) => {
+                    avatar.relative_song_name = relative_song_name;
+                    avatar.used_as_coach_map_name = used_as_coach_map_name;
+                }
+                _ => {
+                    removed.insert(name.clone());
+                }
+            }
+        }
+        let mut images = HashSet::new();
+        for name in &removed {
+            if let Some(avatar) = avatars.remove(name) {
+                images.insert(avatar.image_path);
+            }
+        }
+
+        for avatar in avatars.values_mut() {
+            images.remove(&avatar.image_path);
+            if avatar
+                .main_avatar
+                .as_ref()
+                .is_some_and(|main_avatar| removed.contains(main_avatar))
+            {
+                avatar.main_avatar = None;
+                changed = true;
+            }
+            if let UnlockType::Quest(objective) = &avatar.unlock_type {
+                if self.removed_objectives.contains(objective.as_str()) {
+                    avatar.unlock_type = UnlockType::Unlocked;
+                    changed = true;
+                }
+            }
+        }
+        for image in images {
+            self.transaction.remove(dir.join(image.as_str()));
+        }
+
+        if changed {
+            self.transaction.write(path, &avatars)?;
+        }
+        Ok(())
+    }
+
+    /// Update the aliases that are unlocked by a removed objective
+    fn aliases(&mut self) -> Result<(), Error> {
+        let path = self.dir_tree.config().join("aliases.json");
+        let Some(data) = read(&path)? else {
+            return Ok(());
+        };
+        let mut aliases: Aliases = parse(&path, &data)?;
+
+        let mut changed = false;
+        for alias in &mut aliases.aliases {
+            if alias
+                .unlock_objective
+                .as_ref()
+                .is_some_and(|objective| self.removed_objectives.contains(objective.as_str()))
+            {
+                alias.unlock_objective = None;
+                changed = true;
+            }
+            self.used_ids.insert(alias.name);
+            self.used_ids.insert(alias.name_female);
+            self.used_ids.insert(alias.description);
+        }
+
+        if changed {
+            self.transaction.write(path, &aliases)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the quests for removed objectives
+    fn quests(&mut self) -> Result<(), Error> {
+        if self.removed_objectives.is_empty() {
+            return Ok(());
+        }
+        let path = self.dir_tree.config().join("quests.json");
+        let Some(data) = read(&path)? else {
+            return Ok(());
+        };
+        let mut quests: ScheduledQuests = parse(&path, &data)?;
+        let removed = &self.removed_objectives;
+
+        let first_objective = quests.first_discovery_quest.objective.as_str();
+        if removed.contains(first_objective) {
+            bail!(
+                "The first discovery quest uses objective {first_objective}, which is only for {}!",
+                self.map_name
+            );
+        }
+
+        let before = quests.quests.len();
+        quests
+            .quests
+            .retain(|quest| !removed.contains(quest.objective.as_str()));
+        let mut changed = quests.quests.len() != before;
+
+        let has_removed_precondition = std::iter::once(&quests.first_discovery_quest)
+            .chain(&quests.quests)
+            .any(|quest| {
+                quest
+                    .preconditions
+                    .iter()
+                    .any(|objective| removed.contains(objective.as_str()))
+            });
+        if has_removed_precondition {
+            quests
+                .first_discovery_quest
+                .preconditions
+                .retain(|objective| !removed.contains(objective.as_str()));
+            quests.quests = std::mem::take(&mut quests.quests)
+                .into_iter()
+                .map(|mut quest| {
+                    quest
+                        .preconditions
+                        .retain(|objective| !removed.contains(objective.as_str()));
+                    quest
+                })
+                .collect();
+            changed = true;
+        }
+
+        if changed {
+            self.transaction.write(path, &quests)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the locale ids that were only used by removed entries from the translations
+    fn translations(&mut self) -> Result<(), Error> {
+        let orphaned = self
+            .orphaned_ids
+            .difference(&self.used_ids)
+            .copied()
+            .filter(|id| *id != LocaleId::EMPTY)
+            .collect::<HashSet<_>>();
+        if orphaned.is_empty() {
+            return Ok(());
+        }
+
+        for (_, file) in LANGUAGE_FILES {
+            let path = self.dir_tree.translations().join(file);
+            let Some(data) = read(&path)? else {
+                continue;
+            };
+            let mut translations: HashMap<LocaleId, HipStr> = parse(&path, &data)?;
+            let before = translations.len();
+            translations.retain(|id, _| !orphaned.contains(id));
+            if translations.len() != before {
+                self.transaction.write(path, &translations)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read the file at `path`, returns `None` if it does not exist
+fn read(path: &Path) -> Result<Option<Vec<u8>>, Error> {
+    match std::fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error).with_context(|| format!("Could not read {}", path.display())),
+    }
+}
+
+/// Parse `data` read from `path`
+fn parse<'de, T: Deserialize<'de>>(path: &Path, data: &'de [u8]) -> Result<T, Error> {
+    serde_json::from_slice(data).with_context(|| format!("Could not parse {}", path.display()))
+}
+
+#[allow(clippy::missing_panics_doc)]
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, path::Path};
+
+    use hipstr::HipStr;
+
+    use super::{Change, Transaction, Updater};
+    use crate::types::DirectoryTree;
+
+    fn updater<'a>(dir_tree: &'a DirectoryTree, change: Change<'a>) -> Updater<'a> {
+        Updater {
+            dir_tree,
+            map_name: String::from("MapName"),
+            change,
+            transaction: Transaction::default(),
+            removed_objectives: HashSet::new(),
+            orphaned_ids: HashSet::new(),
+            used_ids: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn test_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("existing.json");
+        let removed = dir.path().join("removed.json");
+        std::fs::write(&existing, b"1").unwrap();
+        std::fs::write(&removed, b"2").unwrap();
+
+        let mut transaction = Transaction::default();
+        transaction.write(existing.clone(), &3).unwrap();
+        transaction.write(dir.path().join("new.json"), &4).unwrap();
+        transaction.rename(removed.clone(), dir.path().join("moved.json"));
+        transaction.remove(dir.path().join("moved.json"));
+        transaction.commit().unwrap();
+
+        let mut files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            ["existing.json", "new.json"],
+            "Only the changed files should be left"
+        );
+        assert_eq!(
+            std::fs::read(&existing).unwrap(),
+            b"3",
+            "File should be replaced"
+        );
+    }
+
+    #[test]
+    fn test_commit_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("existing.json");
+        let removed = dir.path().join("removed.json");
+        std::fs::write(&existing, b"1").unwrap();
+        std::fs::write(&removed, b"2").unwrap();
+
+        let mut transaction = Transaction::default();
+        transaction.write(existing.clone(), &3).unwrap();
+        transaction.write(dir.path().join("new.json"), &4).unwrap();
+        transaction.remove(removed.clone());
+        // Moving a file that does not exist fails after the writes are done
+        transaction.rename(dir.path().join("missing"), dir.path().join("moved"));
+        assert!(transaction.commit().is_err(), "Move should fail");
+
+        let mut files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            ["existing.json", "removed.json"],
+            "The mod should be untouched"
+        );
+        assert_eq!(
+            std::fs::read(&existing).unwrap(),
+            b"1",
+            "Replaced file should be restored"
+        );
+    }
+
+    #[test]
+    fn test_update_names_rename() {
+        let dir_tree = DirectoryTree::new(Path::new("mod"));
+        let updater = updater(&dir_tree, Change::Rename("NewName"));
+        let mut names = vec![
+            HipStr::from("MapName"),
+            HipStr::from("mapname"),
+            HipStr::from("Other"),
+        ];
+        assert!(updater.update_names(&mut names), "Names should be changed");
+        assert_eq!(
+            names.iter().map(HipStr::as_str).collect::<Vec<_>>(),
+            ["NewName", "newname", "Other"],
+            "Case is not kept"
+        );
+    }
+
+    #[test]
+    fn test_update_names_remove() {
+        let dir_tree = DirectoryTree::new(Path::new("mod"));
+        let updater = updater(&dir_tree, Change::Remove);
+        let mut names = vec![HipStr::from("Other"), HipStr::from("MAPNAME")];
+        assert!(updater.update_names(&mut names), "Names should be changed");
+        assert_eq!(
+            names.iter().map(HipStr::as_str).collect::<Vec<_>>(),
+            ["Other"],
+            "Map name is not removed"
+        );
+        assert!(
+            !updater.update_names(&mut names),
+            "Names without the map should not be changed"
+        );
+    }
+}
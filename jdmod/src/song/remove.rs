@@ -0,0 +1,52 @@
+//! # Remove
+//! Removes a song from the mod, together with everything that refers to it.
+//!
+//! See the `references` module for what is updated.
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Error};
+use clap::Args;
+
+use super::{
+    find_song,
+    references::{self, Change},
+};
+use crate::types::DirectoryTree;
+
+/// Remove the song <map_name> from the mod at <mod_path>
+#[derive(Args, Clone)]
+pub struct Remove {
+    /// Map name of the song
+    map_name: String,
+    /// Mod directory
+    mod_path: PathBuf,
+}
+
+/// Wrapper around [`remove`]
+pub fn main(cli: &Remove) -> Result<(), Error> {
+    remove(&cli.map_name, &cli.mod_path)
+}
+
+/// Remove the song `map_name` from the mod at `dir_root`
+///
+/// # Errors
+/// - When the mod directory or the song does not exist
+/// - When the song is used in a mashup or Dance Lab map
+/// - When any of the files that refer to the song cannot be read or written
+pub fn remove(map_name: &str, dir_root: &Path) -> Result<(), Error> {
+    let dir_tree = DirectoryTree::new(dir_root);
+    if !dir_tree.exists() {
+        bail!("Mod directory does not exist or is missing vital subdirectories!");
+    }
+    let Some(song_dir) = find_song(&dir_tree, map_name)? else {
+        bail!("There is no song named {map_name} in the mod!");
+    };
+
+    let mut transaction = references::update(&dir_tree, &song_dir, Change::Remove)?;
+    transaction.remove(dir_tree.songs().join(&song_dir));
+    transaction.commit()?;
+
+    println!("Removed {song_dir}");
+
+    Ok(())
+}
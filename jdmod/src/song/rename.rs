@@ -0,0 +1,66 @@
+//! # Rename
+//! Gives a song a new map name and updates everything that refers to it.
+//!
+//! See the `references` module for what is updated.
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Error};
+use clap::Args;
+
+use super::{
+    find_song, is_valid_map_name,
+    references::{self, Change},
+};
+use crate::types::DirectoryTree;
+
+/// Rename the song <map_name> to <new_map_name> in the mod at <mod_path>
+#[derive(Args, Clone)]
+pub struct Rename {
+    /// Current map name of the song
+    map_name: String,
+    /// New map name for the song, only letters and numbers
+    new_map_name: String,
+    /// Mod directory
+    mod_path: PathBuf,
+}
+
+/// Wrapper around [`rename`]
+pub fn main(cli: &Rename) -> Result<(), Error> {
+    rename(&cli.map_name, &cli.new_map_name, &cli.mod_path)
+}
+
+/// Rename the song `map_name` to `new_map_name` in the mod at `dir_root`
+///
+/// # Errors
+/// - When the mod directory or the song does not exist
+/// - When the new map name is invalid or already used by another song
+/// - When any of the files that refer to the song cannot be read or written
+pub fn rename(map_name: &str, new_map_name: &str, dir_root: &Path) -> Result<(), Error> {
+    let dir_tree = DirectoryTree::new(dir_root);
+    if !dir_tree.exists() {
+        bail!("Mod directory does not exist or is missing vital subdirectories!");
+    }
+    if !is_valid_map_name(new_map_name) {
+        bail!("Map name '{new_map_name}' should only contain letters and numbers!");
+    }
+    let Some(song_dir) = find_song(&dir_tree, map_name)? else {
+        bail!("There is no song named {map_name} in the mod!");
+    };
+    // Only changing the case of the map name is allowed
+    if let Some(existing) = find_song(&dir_tree, new_map_name)? {
+        if existing != song_dir {
+            bail!("There is already a song named {existing} in the mod!");
+        }
+    }
+
+    let mut transaction = references::update(&dir_tree, &song_dir, Change::Rename(new_map_name))?;
+    transaction.rename(
+        dir_tree.songs().join(&song_dir),
+        dir_tree.songs().join(new_map_name),
+    );
+    transaction.commit()?;
+
+    println!("Renamed {song_dir} to {new_map_name}");
+
+    Ok(())
+}
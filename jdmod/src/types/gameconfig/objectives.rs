@@ -1331,6 +1331,41 @@ impl<'a> ObjectiveType<'a> {
             }
         }
     }
+
+    /// The additional generic requirements, `None` if this type does not support them
+    pub fn components_mut(&mut self) -> Option<&mut Vec<Component<'a>>> {
+        match self {
+            Self::AccumulateXCal(data) => Some(&mut data.components),
+            Self::AccumulateXMoves(data) => Some(&mut data.components),
+            Self::AddXSongsToAPlaylist(data) => Some(&mut data.components),
+            Self::ChangeCustoItemXTimes(data) => Some(&mut data.components),
+            Self::FinishXPlaylist(data) => Some(&mut data.components),
+            Self::GatherXStars(data) => Some(&mut data.components),
+            Self::PlayGachaXTimes(data) => Some(&mut data.components),
+            Self::PlayXMaps(data) => Some(&mut data.components),
+            Self::PlayXWDFTournamentRounds(data) => Some(&mut data.components),
+            Self::UnlockXStickers(data) => Some(&mut data.components),
+            Self::ActivateCoopMode
+            | Self::BeatWDFBoss
+            | Self::CompleteXQuests(_)
+            | Self::DanceXSeconds(_)
+            | Self::LinkedToUplay
+            | Self::OpenAnthologyMode
+            | Self::OpenPostcardsGallery
+            | Self::OpenStickerAlbum
+            | Self::OpenVideoGallery
+            | Self::PlayDailyQuestsForXDays(_)
+            | Self::PlayPreviousJD
+            | Self::PlayWDFTournament(_)
+            | Self::ReachRankX(_)
+            | Self::RenewJDUSub
+            | Self::SwitchSweatMode
+            | Self::UnlockUplayRewardAliasPack1
+            | Self::UnlockUplayRewardAliasPack2
+            | Self::UnlockXPortraitBorders(_)
+            | Self::WinWDFTeamBattle => None,
+        }
+    }
 }
 
 /// Grading of a move
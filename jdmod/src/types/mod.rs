@@ -2,8 +2,10 @@
 //! This module contains all the types that are shared between the various portions of this application
 
 use std::{
+    collections::BTreeMap,
     num::NonZeroUsize,
     path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
 };
 
 use dotstar_toolkit_utils::vfs::{VirtualFileSystem, VirtualPath, VirtualPathBuf};
@@ -11,7 +13,7 @@ use path_clean::PathClean;
 use serde::{Deserialize, Serialize};
 use ubiart_toolkit::{alias8::Alias8, utils::UniqueGameId};
 
-use self::localisation::LocaleIdMap;
+use self::{localisation::LocaleIdMap, song::SongKind};
 use crate::import::TranscodeSettings;
 
 pub mod gameconfig;
@@ -47,6 +49,42 @@ pub struct ImportState<'a> {
     pub n_threads: Option<NonZeroUsize>,
     /// Settings for transcoding
     pub transcode: TranscodeSettings,
+    /// The songs that have been imported
+    pub summary: ImportSummary,
+}
+
+/// Keeps track of the imported songs, so an overview can be shown at the end of the import
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// The names of the imported songs, by kind of song
+    songs: Mutex<BTreeMap<SongKind, Vec<String>>>,
+}
+
+impl ImportSummary {
+    /// Add an imported song to the summary
+    pub fn add(&self, kind: SongKind, map_name: &str) {
+        self.songs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(kind)
+            .or_default()
+            .push(map_name.to_string());
+    }
+
+    /// Print the summary, songs that are not regular songs are listed by name
+    pub fn print(&self) {
+        let mut songs = self.songs.lock().unwrap_or_else(PoisonError::into_inner);
+        let total: usize = songs.values().map(Vec::len).sum();
+        println!("Imported {total} songs");
+        for (kind, map_names) in songs.iter_mut() {
+            map_names.sort();
+            if *kind == SongKind::Regular {
+                println!("  {kind}: {}", map_names.len());
+            } else {
+                println!("  {kind}: {} ({})", map_names.len(), map_names.join(", "));
+            }
+        }
+    }
 }
 
 /// The directory tree of a mod
@@ -56,8 +94,12 @@ pub struct DirectoryTree {
     dir_root: PathBuf,
     /// The .mod directory, used for non-user editable config files
     dir_root_mod: PathBuf,
-    /// The .mod/base directory, used for storing base_nx.ipk and patch_nx.ipk
+    /// The .mod/base directory, used for storing the bundle and patch of the base game
     dir_root_mod_base: PathBuf,
+    /// The .mod/export directory, used for storing generated files during an export
+    dir_root_mod_export: PathBuf,
+    /// The .mod/cache directory, used for storing the build cache
+    dir_root_mod_cache: PathBuf,
     /// The songs directory
     dir_root_songs: PathBuf,
     /// The config directory, used for user editable config files
@@ -81,6 +123,8 @@ impl DirectoryTree {
         let dir_root = dir_root.clean();
         let dir_root_mod = dir_root.join(".mod");
         let dir_root_mod_base = dir_root_mod.join("base");
+        let dir_root_mod_export = dir_root_mod.join("export");
+        let dir_root_mod_cache = dir_root_mod.join("cache");
         let dir_root_songs = dir_root.join("songs");
         let dir_root_config = dir_root.join("config");
         let dir_root_translations = dir_root.join("translations");
@@ -91,6 +135,8 @@ impl DirectoryTree {
             dir_root,
             dir_root_mod,
             dir_root_mod_base,
+            dir_root_mod_export,
+            dir_root_mod_cache,
             dir_root_songs,
             dir_root_config,
             dir_root_translations,
@@ -148,6 +194,22 @@ impl DirectoryTree {
         &self.dir_root_mod_base
     }
 
+    /// Used to store generated files during an export until they are bundled.
+    ///
+    /// This directory is not part of the mod and only exists during an export.
+    #[must_use]
+    pub fn export(&self) -> &Path {
+        &self.dir_root_mod_export
+    }
+
+    /// Used to store the build cache, so songs that did not change are not rebuilt.
+    ///
+    /// This directory is not part of the mod and can be removed at any time.
+    #[must_use]
+    pub fn cache(&self) -> &Path {
+        &self.dir_root_mod_cache
+    }
+
     /// Used to store all the parsed songs.
     #[must_use]
     pub fn songs(&self) -> &Path {
@@ -189,8 +251,12 @@ impl DirectoryTree {
 pub struct RelativeDirectoryTree {
     /// The .mod directory, used for non-user editable config files
     dir_root_mod: VirtualPathBuf,
-    /// The .mod/base directory, used for storing base_nx.ipk and patch_nx.ipk
+    /// The .mod/base directory, used for storing the bundle and patch of the base game
     dir_root_mod_base: VirtualPathBuf,
+    /// The .mod/export directory, used for storing generated files during an export
+    dir_root_mod_export: VirtualPathBuf,
+    /// The .mod/cache directory, used for storing the build cache
+    dir_root_mod_cache: VirtualPathBuf,
     /// The songs directory
     dir_root_songs: VirtualPathBuf,
     /// The config directory, used for user editable config files
@@ -220,6 +286,8 @@ impl RelativeDirectoryTree {
         let dir_root = VirtualPathBuf::from("/");
         let dir_root_mod = dir_root.join(".mod");
         let dir_root_mod_base = dir_root_mod.join("base");
+        let dir_root_mod_export = dir_root_mod.join("export");
+        let dir_root_mod_cache = dir_root_mod.join("cache");
         let dir_root_songs = dir_root.join("songs");
         let dir_root_config = dir_root.join("config");
         let dir_root_translations = dir_root.join("translations");
@@ -229,6 +297,8 @@ impl RelativeDirectoryTree {
         Self {
             dir_root_mod,
             dir_root_mod_base,
+            dir_root_mod_export,
+            dir_root_mod_cache,
             dir_root_songs,
             dir_root_config,
             dir_root_translations,
@@ -250,6 +320,22 @@ impl RelativeDirectoryTree {
         &self.dir_root_mod_base
     }
 
+    /// Used to store generated files during an export until they are bundled.
+    ///
+    /// This directory is not part of the mod and only exists during an export.
+    #[must_use]
+    pub fn export(&self) -> &VirtualPath {
+        &self.dir_root_mod_export
+    }
+
+    /// Used to store the build cache, so songs that did not change are not rebuilt.
+    ///
+    /// This directory is not part of the mod and can be removed at any time.
+    #[must_use]
+    pub fn cache(&self) -> &VirtualPath {
+        &self.dir_root_mod_cache
+    }
+
     /// Used to store all the parsed songs.
     #[must_use]
     pub fn songs(&self) -> &VirtualPath {
@@ -3,7 +3,8 @@
 
 use std::{
     cmp::Ordering,
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
     hash::Hash,
     path::{Path, PathBuf},
 };
@@ -282,6 +283,445 @@ pub struct Song<'a> {
     /// The videofile for the song
     #[serde(borrow)]
     pub videofile: HipStr<'a>,
+    /// Where the coaches come from if this song is a mashup
+    #[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+    pub mashup: Option<Mashup<'a>>,
+    /// The block flow if this song is a Dance Lab (dance machine) map
+    #[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+    pub dance_lab: Option<DanceLab<'a>>,
+    /// The teams if this song is a double rumble (doublescoring) map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub double_scoring: Option<DoubleScoring>,
+    /// The recording setup if this song is a Showtime (JUSTSHINE) map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub showtime: Option<Showtime>,
+    /// The puppet and choice tapes if this song is a Party Master map
+    #[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+    pub party_master: Option<PartyMaster<'a>>,
+}
+
+impl Song<'_> {
+    /// The kind of song, based on the special map data
+    #[must_use]
+    pub const fn kind(&self) -> SongKind {
+        if self.mashup.is_some() {
+            SongKind::Mashup
+        } else if self.dance_lab.is_some() {
+            SongKind::DanceLab
+        } else if self.double_scoring.is_some() {
+            SongKind::DoubleRumble
+        } else if self.showtime.is_some() {
+            SongKind::Showtime
+        } else if self.party_master.is_some() {
+            SongKind::PartyMaster
+        } else {
+            SongKind::Regular
+        }
+    }
+}
+
+/// The kind of song
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SongKind {
+    /// A regular song
+    Regular,
+    /// A mashup (MASHUP/COMMUNITYMASHUP)
+    Mashup,
+    /// A Dance Lab (dance machine) map
+    DanceLab,
+    /// A double rumble (doublescoring) map
+    DoubleRumble,
+    /// A Showtime (JUSTSHINE) map
+    Showtime,
+    /// A Party Master (PARTYMASTER) map
+    PartyMaster,
+}
+
+impl Display for SongKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Regular => write!(f, "Regular"),
+            Self::Mashup => write!(f, "Mashup"),
+            Self::DanceLab => write!(f, "Dance Lab"),
+            Self::DoubleRumble => write!(f, "Double Rumble"),
+            Self::Showtime => write!(f, "Showtime"),
+            Self::PartyMaster => write!(f, "Party Master"),
+        }
+    }
+}
+
+/// Describes which maps the coaches of a mashup come from
+#[derive(Serialize, Deserialize, Clone, Debug, Default, IntoOwned)]
+pub struct Mashup<'a> {
+    /// The segments of the mashup, sorted by start time and coach
+    #[serde(borrow)]
+    pub segments: Vec<MashupSegment<'a>>,
+    /// Coaches whose last segment was ended by a move of the mashup itself
+    #[serde(skip)]
+    ended: Vec<u8>,
+}
+
+impl<'a> Mashup<'a> {
+    /// Add a move of `coach_id` to the segments, `source_map` is `None` for a move of the mashup
+    ///
+    /// A move from another map is added to the last segment of the coach if that segment is from
+    /// the same map and was not ended, otherwise a new segment is started. A move of the mashup
+    /// itself ends the last segment of the coach. Moves need to be added in chronological order.
+    pub fn add_move(
+        &mut self,
+        coach_id: u8,
+        source_map: Option<HipStr<'a>>,
+        start_time: i32,
+        duration: i32,
+    ) {
+        let Some(source_map) = source_map else {
+            if !self.ended.contains(&coach_id) {
+                self.ended.push(coach_id);
+            }
+            return;
+        };
+        let ended = self.ended.contains(&coach_id);
+        self.ended.retain(|id| *id != coach_id);
+
+        let end_time = start_time + duration;
+        let last = self
+            .segments
+            .iter_mut()
+            .rev()
+            .find(|segment| segment.coach_id == coach_id);
+        match last {
+            Some(segment) if !ended && segment.source_map == source_map => {
+                segment.duration = segment.duration.max(end_time - segment.start_time);
+            }
+            _ => self.segments.push(MashupSegment {
+                start_time,
+                duration,
+                coach_id,
+                source_map,
+            }),
+        }
+    }
+
+    /// Sort the segments by start time and coach
+    pub fn sort(&mut self) {
+        self.segments
+            .sort_by_key(|segment| (segment.start_time, segment.coach_id));
+    }
+
+    /// Get the map that `coach_id` comes from at `time`, if the coach is replaced at that time
+    #[must_use]
+    pub fn source_map(&self, coach_id: u8, time: i32) -> Option<&str> {
+        self.segments
+            .iter()
+            .find(|segment| {
+                segment.coach_id == coach_id
+                    && segment.start_time <= time
+                    && time < segment.start_time + segment.duration
+            })
+            .map(|segment| segment.source_map.as_str())
+    }
+}
+
+/// A part of a mashup where a coach is taken from another map
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, IntoOwned)]
+pub struct MashupSegment<'a> {
+    /// When the segment starts
+    pub start_time: i32,
+    /// Duration of the segment
+    pub duration: i32,
+    /// Which coach is replaced
+    pub coach_id: u8,
+    /// Codename of the map the coach comes from (lowercase)
+    #[serde(borrow)]
+    pub source_map: HipStr<'a>,
+}
+
+/// Describes how the coaches of a double rumble map are paired into teams
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, IntoOwned)]
+pub struct DoubleScoring {
+    /// The double scoring type from the song description, only available before NX2020
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoring_type: Option<i8>,
+    /// The teams, every team scores together
+    pub teams: Vec<Team>,
+}
+
+impl DoubleScoring {
+    /// Create the double scoring from the actor indices of the gameplay events
+    ///
+    /// Every distinct set of coaches is a team. Returns `None` if there are not at least two teams.
+    #[must_use]
+    pub fn from_actor_indices(
+        scoring_type: Option<i8>,
+        actor_indices: impl IntoIterator<Item = Vec<u8>>,
+    ) -> Option<Self> {
+        let teams: Vec<Team> = actor_indices
+            .into_iter()
+            .filter(|coaches| !coaches.is_empty())
+            .map(|mut coaches| {
+                coaches.sort_unstable();
+                coaches.dedup();
+                Team { coaches }
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        (teams.len() >= 2).then_some(Self {
+            scoring_type,
+            teams,
+        })
+    }
+
+    /// Find the team that consists of exactly `coaches`, in any order
+    #[must_use]
+    pub fn team(&self, coaches: &[u8]) -> Option<&Team> {
+        self.teams.iter().find(|team| {
+            team.coaches.len() == coaches.len()
+                && coaches.iter().all(|coach| team.coaches.contains(coach))
+        })
+    }
+}
+
+/// A team of coaches in a double rumble map
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, IntoOwned)]
+pub struct Team {
+    /// The coaches in this team
+    pub coaches: Vec<u8>,
+}
+
+/// The recording and autodance setup of a Showtime map
+#[derive(Serialize, Deserialize, Clone, IntoOwned)]
+pub struct Showtime {
+    /// The parts of the song that are recorded
+    pub records: Vec<Record>,
+    /// Where in the song the autodance video starts
+    pub song_start_position: f32,
+    /// Duration of the autodance video
+    pub duration: f32,
+    /// Time of the thumbnail in the autodance video
+    pub thumbnail_time: u32,
+    /// Duration of the fade out at the end of the autodance video
+    pub fade_out_duration: f32,
+    /// How the recordings are played back in the autodance video
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub playback_events: Vec<PlaybackEvent>,
+}
+
+impl Showtime {
+    /// Create the showtime setup from an autodance template
+    #[must_use]
+    pub fn from_autodance(autodance: &cooked::tpl::types::AutodanceData<'_>) -> Self {
+        let video = &autodance.video_structure;
+        Self {
+            records: autodance
+                .recording_structure
+                .records
+                .iter()
+                .map(Record::from)
+                .collect(),
+            song_start_position: video.song_start_position,
+            duration: video.duration,
+            thumbnail_time: video.thumbnail_time,
+            fade_out_duration: video.fade_out_duration,
+            playback_events: video
+                .playback_events
+                .iter()
+                .map(PlaybackEvent::from)
+                .collect(),
+        }
+    }
+}
+
+/// The extra tapes of a Party Master map
+///
+/// Every tape is stored in the song directory as [`PartyMaster::tape_name`]`.json`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, IntoOwned)]
+pub struct PartyMaster<'a> {
+    /// The labels of the puppet and choice tapes, in the order of the tapes rack
+    #[serde(borrow)]
+    pub tapes: Vec<HipStr<'a>>,
+}
+
+impl PartyMaster<'_> {
+    /// The name of the tape with `label`, which is used for its files
+    ///
+    /// The label comes from the game data, so anything but ASCII letters, digits, `_` and `-`
+    /// is replaced with `_` to keep it a valid filename.
+    #[must_use]
+    pub fn tape_name(label: &str) -> String {
+        let label: String = label
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("partymaster_{label}")
+    }
+}
+
+/// Describes how a Dance Lab (dance machine) map is put together from blocks
+#[derive(Serialize, Deserialize, Clone, Debug, Default, IntoOwned)]
+pub struct DanceLab<'a> {
+    /// Dance Lab attributes from the song description
+    #[serde(borrow, default, skip_serializing_if = "Vec::is_empty")]
+    pub jdm_attributes: Vec<HipStr<'a>>,
+    /// Is the block flow a mashup
+    pub is_mashup: bool,
+    /// Is the block flow a party master
+    pub is_party_master: bool,
+    /// The blocks in order of play
+    #[serde(borrow)]
+    pub blocks: Vec<Block<'a>>,
+}
+
+impl<'a> DanceLab<'a> {
+    /// Convert to the block flow template used by the game
+    #[must_use]
+    pub fn to_template(&self) -> cooked::tpl::types::BlockFlowTemplate<'a> {
+        cooked::tpl::types::BlockFlowTemplate {
+            class: None,
+            is_mash_up: u32::from(self.is_mashup),
+            is_party_master: u32::from(self.is_party_master),
+            block_descriptor_vector: self.blocks.iter().map(Block::to_template).collect(),
+        }
+    }
+}
+
+impl<'a> From<&cooked::tpl::types::BlockFlowTemplate<'a>> for DanceLab<'a> {
+    fn from(value: &cooked::tpl::types::BlockFlowTemplate<'a>) -> Self {
+        Self {
+            jdm_attributes: Vec::new(),
+            is_mashup: value.is_mash_up != 0,
+            is_party_master: value.is_party_master != 0,
+            blocks: value
+                .block_descriptor_vector
+                .iter()
+                .map(Block::from)
+                .collect(),
+        }
+    }
+}
+
+/// A block in the block flow with the blocks that can replace it
+#[derive(Serialize, Deserialize, Clone, Debug, IntoOwned)]
+pub struct Block<'a> {
+    /// The block that is played by default
+    #[serde(borrow)]
+    pub base: BlockDescriptor<'a>,
+    /// Blocks that can be played instead of the base block
+    #[serde(borrow, default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<BlockDescriptor<'a>>,
+}
+
+impl<'a> Block<'a> {
+    /// Convert to the block replacements used by the game
+    #[must_use]
+    pub fn to_template(&self) -> cooked::tpl::types::BlockReplacements<'a> {
+        cooked::tpl::types::BlockReplacements {
+            class: None,
+            base_block: self.base.to_template(),
+            alternative_blocks: self
+                .alternatives
+                .iter()
+                .map(BlockDescriptor::to_template)
+                .collect(),
+        }
+    }
+}
+
+impl<'a> From<&cooked::tpl::types::BlockReplacements<'a>> for Block<'a> {
+    fn from(value: &cooked::tpl::types::BlockReplacements<'a>) -> Self {
+        Self {
+            base: BlockDescriptor::from(&value.base_block),
+            alternatives: value
+                .alternative_blocks
+                .iter()
+                .map(BlockDescriptor::from)
+                .collect(),
+        }
+    }
+}
+
+/// A part of another song that is played in the block flow
+#[derive(Serialize, Deserialize, Clone, Debug, IntoOwned)]
+pub struct BlockDescriptor<'a> {
+    /// Codename of the song the block comes from
+    #[serde(borrow)]
+    pub song_name: HipStr<'a>,
+    /// The first beat of the block
+    pub first_beat: u32,
+    /// The last beat of the block
+    pub last_beat: u32,
+    /// Does the block switch to another song
+    pub song_switch: bool,
+    /// Offset of the coach video
+    pub video_coach_offset: (f32, f32),
+    /// Scale of the coach video
+    pub video_coach_scale: f32,
+    /// Name of the dance step
+    #[serde(borrow)]
+    pub dance_step_name: HipStr<'a>,
+    /// Speed at which the block is played
+    pub playing_speed: f32,
+    /// Can the block flow start at this block
+    pub is_entry_point: bool,
+    /// Is this an empty block
+    pub is_empty_block: bool,
+    /// Is this block not scored
+    pub is_no_score_block: bool,
+    /// Unique identifier of the block
+    #[serde(borrow)]
+    pub guid: HipStr<'a>,
+    /// Should the last pictos of the block be displayed
+    pub force_display_last_pictos: bool,
+}
+
+impl<'a> BlockDescriptor<'a> {
+    /// Convert to the block descriptor used by the game
+    #[must_use]
+    pub fn to_template(&self) -> cooked::tpl::types::BlockDescriptor<'a> {
+        cooked::tpl::types::BlockDescriptor {
+            class: None,
+            song_name: self.song_name.clone(),
+            frst_beat: self.first_beat,
+            last_beat: self.last_beat,
+            song_switch: u32::from(self.song_switch),
+            video_coach_offset: self.video_coach_offset,
+            video_coach_scale: self.video_coach_scale,
+            dance_step_name: self.dance_step_name.clone(),
+            playing_speed: self.playing_speed,
+            is_entry_point: u32::from(self.is_entry_point),
+            is_empty_block: u32::from(self.is_empty_block),
+            is_no_score_block: u32::from(self.is_no_score_block),
+            guid: self.guid.clone(),
+            force_display_last_pictos: u32::from(self.force_display_last_pictos),
+        }
+    }
+}
+
+impl<'a> From<&cooked::tpl::types::BlockDescriptor<'a>> for BlockDescriptor<'a> {
+    fn from(value: &cooked::tpl::types::BlockDescriptor<'a>) -> Self {
+        Self {
+            song_name: value.song_name.clone(),
+            first_beat: value.frst_beat,
+            last_beat: value.last_beat,
+            song_switch: value.song_switch != 0,
+            video_coach_offset: value.video_coach_offset,
+            video_coach_scale: value.video_coach_scale,
+            dance_step_name: value.dance_step_name.clone(),
+            playing_speed: value.playing_speed,
+            is_entry_point: value.is_entry_point != 0,
+            is_empty_block: value.is_empty_block != 0,
+            is_no_score_block: value.is_no_score_block != 0,
+            guid: value.guid.clone(),
+            force_display_last_pictos: value.force_display_last_pictos != 0,
+        }
+    }
 }
 
 /// Image used in the menus
@@ -326,7 +766,7 @@ pub struct PhoneImage<'a> {
 }
 
 /// Unknown
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, IntoOwned)]
 pub struct Record {
     /// Start of the record?
     pub start: f32,
@@ -354,7 +794,7 @@ impl From<&cooked::tpl::types::Record<'_>> for Record {
 }
 
 /// Unknown
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, IntoOwned)]
 pub struct PlaybackEvent {
     /// Clip to play?
     pub clip_number: u32,
@@ -1412,6 +1852,9 @@ pub struct MotionClip<'a> {
     pub coach_id: u8,
     /// The color of something?
     pub color: Color,
+    /// Scoring settings for specific platforms
+    #[serde(borrow, default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub platform_specifics: BTreeMap<HipStr<'a>, MotionPlatformSpecific>,
 }
 
 impl MotionClip<'_> {
@@ -1424,6 +1867,13 @@ impl MotionClip<'_> {
 
         let lower_map_name = song.map_name.to_lowercase();
         let filename = &self.classifier_filename;
+        // Mashups use the classifiers of the maps the coaches come from, they're prefixed with
+        // that map so they don't conflict with the classifiers of the source map itself
+        let prefix = song
+            .mashup
+            .as_ref()
+            .and_then(|mashup| mashup.source_map(self.coach_id, self.start_time))
+            .unwrap_or(&lower_map_name);
 
         tape::MotionClip {
             class: None,
@@ -1433,17 +1883,34 @@ impl MotionClip<'_> {
             start_time: self.start_time,
             duration: self.duration,
             classifier_path: HipStr::from(format!(
-                "world/maps/{lower_map_name}/timeline/moves/{lower_map_name}_{filename}"
+                "world/maps/{lower_map_name}/timeline/moves/{prefix}_{filename}"
             )),
             gold_move: u8::from(self.gold_move),
             coach_id: self.coach_id,
             move_type: 0,
             color: (&self.color).into(),
-            motion_platform_specifics: HashMap::new(),
+            motion_platform_specifics: self
+                .platform_specifics
+                .iter()
+                .map(|(platform, specific)| (platform.clone().into_owned(), specific.into()))
+                .collect(),
         }
     }
 
-    /// The classifier path needs to be changed to include the /wiiu/ component
+    /// Get the (lowercase) codename of the map the classifier at `classifier_path` belongs to
+    ///
+    /// For mashups this is the map the coach comes from.
+    #[must_use]
+    pub fn classifier_map(classifier_path: &str) -> Option<&str> {
+        classifier_path
+            .strip_prefix("world/maps/")
+            .and_then(|path| path.split_once('/'))
+            .map(|(map, _)| map)
+    }
+
+    /// The classifier path needs to be changed to include the platform component
+    ///
+    /// This is /wii/ for the Wii, /wiiu/ for the other platforms, and /orbis/ for gestures.
     ///
     /// # Errors
     /// Will return an error if the platform is not supported or the path is broken
@@ -1459,6 +1926,7 @@ impl MotionClip<'_> {
             (false, Platform::Nx | Platform::WiiU | Platform::Win) => {
                 classifier_path.push_str("/wiiu");
             }
+            (false, Platform::Wii) => classifier_path.push_str("/wii"),
             (true, _) => classifier_path.push_str("/orbis"),
             _ => unimplemented!("Not implemented for {}", platform),
         }
@@ -1483,10 +1951,97 @@ impl<'a> TryFrom<tape::MotionClip<'a>> for MotionClip<'a> {
             gold_move: value.gold_move == 1,
             coach_id: value.coach_id,
             color: (&value.color).into(),
+            platform_specifics: value
+                .motion_platform_specifics
+                .iter()
+                .map(|(platform, specific)| (platform.clone(), specific.into()))
+                .collect(),
         })
     }
 }
 
+/// Scoring settings for a move on a specific platform
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct MotionPlatformSpecific {
+    /// Unknown
+    pub score_scale: f32,
+    /// Unknown
+    pub score_smoothing: f32,
+    /// Unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoring_mode: Option<f32>,
+    /// Not used in nx2019 or later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_threshold: Option<f32>,
+    /// Not used in nx2019 or later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high_threshold: Option<f32>,
+}
+
+impl MotionPlatformSpecific {
+    /// Get all the values as bits, for comparing and hashing
+    fn to_bits(self) -> [Option<u32>; 5] {
+        [
+            Some(self.score_scale.to_bits()),
+            Some(self.score_smoothing.to_bits()),
+            self.scoring_mode.map(f32::to_bits),
+            self.low_threshold.map(f32::to_bits),
+            self.high_threshold.map(f32::to_bits),
+        ]
+    }
+}
+
+impl From<&tape::MotionPlatformSpecific<'_>> for MotionPlatformSpecific {
+    fn from(value: &tape::MotionPlatformSpecific<'_>) -> Self {
+        Self {
+            score_scale: value.score_scale,
+            score_smoothing: value.score_smoothing,
+            scoring_mode: value.scoring_mode,
+            low_threshold: value.low_threshold,
+            high_threshold: value.high_threshold,
+        }
+    }
+}
+
+impl From<&MotionPlatformSpecific> for tape::MotionPlatformSpecific<'static> {
+    fn from(value: &MotionPlatformSpecific) -> Self {
+        Self {
+            class: Some(HipStr::borrowed("MotionPlatformSpecific")),
+            score_scale: value.score_scale,
+            score_smoothing: value.score_smoothing,
+            scoring_mode: value.scoring_mode,
+            low_threshold: value.low_threshold,
+            high_threshold: value.high_threshold,
+        }
+    }
+}
+
+impl Hash for MotionPlatformSpecific {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_bits().hash(state);
+    }
+}
+
+impl PartialEq for MotionPlatformSpecific {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_bits() == other.to_bits()
+    }
+}
+
+impl Eq for MotionPlatformSpecific {}
+
+impl Ord for MotionPlatformSpecific {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_bits().cmp(&other.to_bits())
+    }
+}
+
+impl PartialOrd for MotionPlatformSpecific {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 /// Show a pictogram
 #[derive(Hash, Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PictogramClip<'a> {
@@ -2089,3 +2644,180 @@ impl PartialOrd for KeyFloat {
         Some(self.cmp(other))
     }
 }
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use hipstr::HipStr;
+
+    use super::{DoubleScoring, Mashup, MashupSegment, PartyMaster, Team};
+
+    /// A segment of `coach_id` from `source_map`
+    fn segment(
+        start_time: i32,
+        duration: i32,
+        coach_id: u8,
+        source_map: &'static str,
+    ) -> MashupSegment<'static> {
+        MashupSegment {
+            start_time,
+            duration,
+            coach_id,
+            source_map: HipStr::borrowed(source_map),
+        }
+    }
+
+    #[test]
+    fn test_mashup_add_move() {
+        let mut mashup = Mashup::default();
+        mashup.add_move(0, Some(HipStr::borrowed("toxic")), 0, 100);
+        mashup.add_move(1, Some(HipStr::borrowed("bang")), 50, 100);
+        mashup.add_move(0, Some(HipStr::borrowed("toxic")), 100, 100);
+        mashup.add_move(0, Some(HipStr::borrowed("bang")), 200, 100);
+        mashup.sort();
+        assert_eq!(
+            mashup.segments,
+            [
+                segment(0, 200, 0, "toxic"),
+                segment(50, 100, 1, "bang"),
+                segment(200, 100, 0, "bang"),
+            ],
+            "Moves from the same map should be merged per coach"
+        );
+    }
+
+    #[test]
+    fn test_mashup_own_move_ends_segment() {
+        let mut mashup = Mashup::default();
+        mashup.add_move(0, Some(HipStr::borrowed("toxic")), 0, 100);
+        mashup.add_move(0, None, 100, 100);
+        mashup.add_move(1, Some(HipStr::borrowed("bang")), 150, 100);
+        mashup.add_move(0, Some(HipStr::borrowed("toxic")), 200, 100);
+        mashup.sort();
+        assert_eq!(
+            mashup.segments,
+            [
+                segment(0, 100, 0, "toxic"),
+                segment(150, 100, 1, "bang"),
+                segment(200, 100, 0, "toxic"),
+            ],
+            "A move of the mashup itself should end the segment of that coach only"
+        );
+
+        assert_eq!(
+            mashup.source_map(0, 50),
+            Some("toxic"),
+            "Coach 0 starts in toxic"
+        );
+        assert_eq!(
+            mashup.source_map(0, 150),
+            None,
+            "The move of the mashup should not be in a segment"
+        );
+        assert_eq!(
+            mashup.source_map(0, 250),
+            Some("toxic"),
+            "Coach 0 goes back to toxic"
+        );
+        assert_eq!(
+            mashup.source_map(1, 50),
+            None,
+            "Coach 1 only has a segment later"
+        );
+        assert_eq!(
+            mashup.source_map(1, 249),
+            Some("bang"),
+            "The end of a segment is exclusive"
+        );
+        assert_eq!(
+            mashup.source_map(1, 250),
+            None,
+            "The end of a segment is exclusive"
+        );
+    }
+
+    #[test]
+    fn test_mashup_only_own_moves() {
+        let mut mashup = Mashup::default();
+        mashup.add_move(0, None, 0, 100);
+        mashup.add_move(1, None, 0, 100);
+        assert!(mashup.segments.is_empty(), "No moves are borrowed");
+        assert_eq!(mashup.source_map(0, 50), None, "No moves are borrowed");
+    }
+
+    #[test]
+    fn test_party_master_tape_name() {
+        assert_eq!(
+            PartyMaster::tape_name("Puppet_01-b"),
+            "partymaster_Puppet_01-b",
+            "Valid labels should be kept"
+        );
+        assert_eq!(
+            PartyMaster::tape_name("../choice 1"),
+            "partymaster____choice_1",
+            "Path separators and spaces should be replaced"
+        );
+    }
+
+    #[test]
+    fn test_double_scoring_from_actor_indices() {
+        let double_scoring = DoubleScoring::from_actor_indices(
+            Some(1),
+            [vec![1, 0], vec![], vec![2, 3, 3], vec![0, 1], vec![3, 2]],
+        )
+        .unwrap();
+        assert_eq!(double_scoring.scoring_type, Some(1), "Type should be kept");
+        assert_eq!(
+            double_scoring.teams,
+            [
+                Team {
+                    coaches: vec![0, 1]
+                },
+                Team {
+                    coaches: vec![2, 3]
+                },
+            ],
+            "Every distinct set of coaches should be one sorted team"
+        );
+    }
+
+    #[test]
+    fn test_double_scoring_needs_two_teams() {
+        assert!(
+            DoubleScoring::from_actor_indices(None, [vec![0, 1], vec![1, 0], vec![]]).is_none(),
+            "One team is not double scoring"
+        );
+        assert!(
+            DoubleScoring::from_actor_indices(None, Vec::new()).is_none(),
+            "No teams is not double scoring"
+        );
+    }
+
+    #[test]
+    fn test_double_scoring_team() {
+        let double_scoring =
+            DoubleScoring::from_actor_indices(None, [vec![0, 1], vec![2, 3]]).unwrap();
+        assert_eq!(
+            double_scoring.team(&[3, 2]),
+            Some(&Team {
+                coaches: vec![2, 3]
+            }),
+            "The order of the coaches should not matter"
+        );
+        assert_eq!(
+            double_scoring.team(&[0]),
+            None,
+            "Part of a team is not a team"
+        );
+        assert_eq!(
+            double_scoring.team(&[0, 1, 2]),
+            None,
+            "More coaches than a team is not a team"
+        );
+        assert_eq!(
+            double_scoring.team(&[1, 2]),
+            None,
+            "Coaches of different teams are not a team"
+        );
+    }
+}
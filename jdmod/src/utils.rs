@@ -1,11 +1,12 @@
 //! Various utilities like texture encoding/decoding and dealing with paths
 use std::{
+    ffi::OsStr,
     io::{Seek, Write},
-    path::Path,
+    path::{Path, PathBuf},
     sync::LazyLock,
 };
 
-use anyhow::{anyhow, bail, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use dotstar_toolkit_utils::{
     bytes::{
         primitives::i16le,
@@ -20,6 +21,7 @@ use hipstr::HipStr;
 use hound::SampleFormat;
 use image::{imageops, RgbaImage};
 use nx_opus::{mux_from_opus, mux_to_opus};
+use nx_toolkit::keys::Keys;
 use regex::Regex;
 use rubato::Resampler;
 use test_eq::test_eq;
@@ -55,6 +57,7 @@ pub fn cook_path(path: &str, ugi: UniqueGameId) -> Result<String, Error> {
         Platform::Nx => cooked.push_str("nx/"),
         Platform::WiiU => cooked.push_str("wiiu/"),
         Platform::Win => cooked.push_str("pc/"),
+        Platform::Wii => cooked.push_str("wii/"),
         _ => Err(anyhow!("Not yet implemented for {path}"))?,
     };
 
@@ -248,7 +251,7 @@ pub fn decode_audio(
 
             Ok(false)
         }
-        (WavPlatform::WiiU, Codec::Adpc) => {
+        (WavPlatform::Wii | WavPlatform::WiiU, Codec::Adpc) => {
             let spec = hound::WavSpec {
                 channels: fmt.channel_count,
                 sample_rate: 48000,
@@ -268,9 +271,9 @@ pub fn decode_audio(
                     coefficients: dsp_left.coefficients,
                 };
                 let right_state = gc_adpcm::Dsp {
-                    hist1: dsp_left.initial_sample_history_1,
-                    hist2: dsp_left.initial_sample_history_2,
-                    coefficients: dsp_left.coefficients,
+                    hist1: dsp_right.initial_sample_history_1,
+                    hist2: dsp_right.initial_sample_history_2,
+                    coefficients: dsp_right.coefficients,
                 };
                 let total_frames = dsp_left.sample_count.div_ceil(gc_adpcm::SAMPLES_PER_FRAME);
                 test_eq!(
@@ -381,7 +384,7 @@ pub fn decode_audio(
                 }
                 Ok(false)
             } else {
-                Err(anyhow!("Unexpected WiiU/ADPC configuration: {wav:?}"))
+                Err(anyhow!("Unexpected Wii(U)/ADPC configuration: {wav:?}"))
             }
         }
         _ => Err(anyhow!(
@@ -749,3 +752,166 @@ pub fn extract_audio(source: &Path, destination: &Path) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Is `path` a NSP or XCI dump of a Switch game
+#[must_use]
+pub fn is_switch_dump(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("nsp") || ext.eq_ignore_ascii_case("xci"))
+}
+
+/// Is `path` an ISO or WBFS image of a Wii game
+#[must_use]
+pub fn is_wii_disc(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("iso") || ext.eq_ignore_ascii_case("wbfs"))
+}
+
+/// Load the keys for decrypting Switch dumps
+///
+/// If `path` is `None`, `~/.switch/prod.keys` is used. If there is a `title.keys` in the same
+/// directory, it's also loaded.
+///
+/// # Errors
+/// Will return an error if the keys file cannot be found or cannot be parsed
+pub fn load_switch_keys(path: Option<&Path>) -> Result<Keys, Error> {
+    let path = if let Some(path) = path {
+        path.to_path_buf()
+    } else {
+        let home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .ok_or_else(|| anyhow!("Could not find the home directory, use --keys instead!"))?;
+        PathBuf::from(home).join(".switch").join("prod.keys")
+    };
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Could not read the keys at {}", path.display()))?;
+    let mut keys = Keys::parse(&text)?;
+
+    let title_keys_path = path.with_file_name("title.keys");
+    if title_keys_path.exists() {
+        keys.add_title_keys(&std::fs::read_to_string(title_keys_path)?)?;
+    }
+
+    Ok(keys)
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::bytes::read::BinaryDeserializeExt as _;
+
+    use super::*;
+
+    /// A triangle wave for every channel, each with a different period
+    fn triangle(channel_count: u16, frames: i32) -> Vec<i16> {
+        (0..frames)
+            .flat_map(|i| {
+                (0..i32::from(channel_count)).map(move |channel| {
+                    let period = 200 + channel * 70;
+                    let value = ((i % period) - period / 2).abs() * 8000 / period - 2000;
+                    i16::try_from(value).unwrap()
+                })
+            })
+            .collect()
+    }
+
+    /// Write the samples as a Wii audio file, check the header and decode it again
+    fn roundtrip(channel_count: u16) {
+        let samples = triangle(channel_count, 1000);
+        let fmt = Fmt {
+            unk1: 2,
+            channel_count,
+            sample_rate: 48000,
+            total_samples_hz: 48000 * 2 * u32::from(channel_count),
+            block_align: 2 * channel_count,
+            bits_per_sample: 16,
+            unk3: None,
+        };
+        let mut file = Vec::new();
+        wav::Writer::create_dsp(&mut file, &mut 0, fmt, &samples, true).unwrap();
+
+        assert_eq!(&file[..4], b"RAKI", "Wrong magic");
+        assert_eq!(
+            &file[4..8],
+            &[0, 0, 0, 9],
+            "The version should be big endian 9"
+        );
+        assert_eq!(&file[8..12], b"Wii ", "Wrong platform");
+        assert_eq!(&file[12..16], b"adpc", "Wrong codec");
+
+        let wav = Wav::deserialize(&file).unwrap();
+        assert_eq!(wav.unk1, 9, "The version should be parsed as 9");
+        assert_eq!(wav.unk2, 3, "A main song should have unk2 = 3");
+        assert_eq!(wav.platform, WavPlatform::Wii, "Wrong platform");
+        assert_eq!(wav.codec, Codec::Adpc, "Wrong codec");
+
+        let fmt = wav.chunks[&Fmt::MAGIC].as_fmt().unwrap();
+        assert_eq!(fmt.unk1, 2, "Wrong fmt unk1");
+        assert_eq!(fmt.channel_count, channel_count, "Wrong channel count");
+        assert_eq!(fmt.sample_rate, 48000, "Wrong sample rate");
+        assert_eq!(
+            fmt.total_samples_hz,
+            48000 * 2 * u32::from(channel_count),
+            "Wrong total samples"
+        );
+        assert_eq!(fmt.block_align, 2 * channel_count, "Wrong block align");
+        assert_eq!(fmt.bits_per_sample, 16, "Wrong bits per sample");
+        assert!(fmt.unk3.is_none(), "Only the WiiU has unk3");
+
+        let dsp_left = wav.chunks[&Dsp::MAGIC_LEFT].as_dsp().unwrap();
+        assert_eq!(dsp_left.sample_count, 1000, "Wrong sample count");
+        assert!(
+            wav.chunks.contains_key(&Data::MAGIC_LEFT),
+            "The left channel is missing"
+        );
+        let stereo = channel_count == 2;
+        assert_eq!(
+            wav.chunks.contains_key(&Dsp::MAGIC_RIGHT),
+            stereo,
+            "Only stereo has a right channel"
+        );
+        assert_eq!(
+            wav.chunks.contains_key(&Data::MAGIC_RIGHT),
+            stereo,
+            "Only stereo has a right channel"
+        );
+
+        let mut decoded = Vec::new();
+        let opus = decode_audio(&file, &mut decoded, false).unwrap();
+        assert!(!opus, "The audio is not opus encoded");
+
+        let reader = hound::WavReader::new(decoded.as_slice()).unwrap();
+        assert_eq!(
+            reader.spec().channels,
+            channel_count,
+            "Wrong decoded channel count"
+        );
+        let decoded = reader
+            .into_samples::<i16>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(decoded.len(), samples.len(), "Wrong amount of samples");
+        let max_error = samples
+            .iter()
+            .zip(&decoded)
+            .map(|(a, b)| (i32::from(*a) - i32::from(*b)).abs())
+            .max()
+            .unwrap();
+        assert!(
+            max_error < 256,
+            "Decoded audio is too different: {max_error}"
+        );
+    }
+
+    #[test]
+    fn test_dsp_mono() {
+        roundtrip(1);
+    }
+
+    #[test]
+    fn test_dsp_stereo() {
+        roundtrip(2);
+    }
+}
@@ -0,0 +1,146 @@
+//! # Crypto
+//! The AES modes used by the Switch, built on top of the AES block cipher
+
+use aes::Aes128;
+use cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit};
+
+/// Size of an AES block
+pub const BLOCK_SIZE: usize = 0x10;
+
+/// Decrypt a single block with AES-128-ECB
+pub fn aes_128_ecb_decrypt(key: &[u8; 16], block: &mut [u8; 16]) {
+    let cipher = Aes128::new(GenericArray::from_slice(key));
+    cipher.decrypt_block(GenericArray::from_mut_slice(block));
+}
+
+/// Decrypt `data` inplace with AES-128-XTS
+///
+/// Unlike the standard, the Switch uses the sector number as a big endian tweak.
+/// `data` must be a multiple of `sector_size` and `sector_size` must be a multiple of the block
+/// size.
+pub fn aes_128_xts_decrypt(key: &[u8; 32], data: &mut [u8], first_sector: u64, sector_size: usize) {
+    let (data_key, tweak_key) = key.split_at(16);
+    let data_cipher = Aes128::new(GenericArray::from_slice(data_key));
+    let tweak_cipher = Aes128::new(GenericArray::from_slice(tweak_key));
+
+    let mut sector_n = first_sector;
+    for sector in data.chunks_mut(sector_size) {
+        let mut tweak = GenericArray::from(u128::from(sector_n).to_be_bytes());
+        tweak_cipher.encrypt_block(&mut tweak);
+
+        for block in sector.chunks_exact_mut(BLOCK_SIZE) {
+            xor(block, &tweak);
+            data_cipher.decrypt_block(GenericArray::from_mut_slice(block));
+            xor(block, &tweak);
+
+            // Multiply the tweak by x in GF(2^128)
+            let mut carry = 0;
+            for byte in &mut tweak {
+                let next_carry = byte.wrapping_shr(7);
+                *byte = byte.wrapping_shl(1) | carry;
+                carry = next_carry;
+            }
+            if carry != 0 {
+                tweak[0] ^= 0x87;
+            }
+        }
+        sector_n = sector_n.wrapping_add(1);
+    }
+}
+
+/// Decrypt (or encrypt) `data` inplace with AES-128-CTR
+///
+/// `offset` is the position of `data` in the encrypted storage and must be aligned to the block
+/// size. The upper half of the counter is `nonce`, the lower half is the block number.
+pub fn aes_128_ctr_apply(key: &[u8; 16], nonce: [u8; 8], offset: u64, data: &mut [u8]) {
+    let cipher = Aes128::new(GenericArray::from_slice(key));
+
+    let mut block_n = offset.wrapping_shr(4);
+    for block in data.chunks_mut(BLOCK_SIZE) {
+        let mut counter = [0; BLOCK_SIZE];
+        counter[..8].copy_from_slice(&nonce);
+        counter[8..].copy_from_slice(&block_n.to_be_bytes());
+        let mut keystream = GenericArray::from(counter);
+        cipher.encrypt_block(&mut keystream);
+        xor(block, &keystream);
+        block_n = block_n.wrapping_add(1);
+    }
+}
+
+/// Xor `data` with `other`, `other` must be at least as long as `data`
+fn xor(data: &mut [u8], other: &[u8]) {
+    for (byte, other) in data.iter_mut().zip(other) {
+        *byte ^= other;
+    }
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[allow(
+    clippy::arithmetic_side_effects,
+    reason = "The indices in the tests are small"
+)]
+#[cfg(test)]
+mod tests {
+    use super::{aes_128_ctr_apply, aes_128_xts_decrypt};
+
+    /// Decode a hex string
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&string[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_xts_ieee_vector_1() {
+        // IEEE 1619 vector 1, with a tweak of zero the byte order does not matter
+        let mut data = hex("917cf69ebd68b2ec9b9fe9a3eadda692cd43d2f59598ed858c02c2652fbf922e");
+        aes_128_xts_decrypt(&[0; 32], &mut data, 0, 0x20);
+        assert_eq!(data, [0; 32], "Decryption failed");
+    }
+
+    #[test]
+    fn test_xts_big_endian_tweak() {
+        // The keys and plaintext of IEEE 1619 vector 2, but with two sectors and a big endian tweak
+        let mut key = [0x11; 32];
+        key[16..].fill(0x22);
+        let mut data = hex(concat!(
+            "44bec82ffb76aefdfbc96dfe61e192ccfa2213677c8f4fd6e4f18f7ebb69382f",
+            "5a2cf8ce76a49709468e07cd8f05b5fb296e36d00e5cd93bc4583f3d2cc05ae5",
+        ));
+        let mut second_sector = data[0x20..].to_vec();
+        aes_128_xts_decrypt(&key, &mut data, 0x33_3333_3333, 0x20);
+        assert_eq!(data, [0x44; 0x40], "Decryption failed");
+
+        aes_128_xts_decrypt(&key, &mut second_sector, 0x33_3333_3334, 0x20);
+        assert_eq!(
+            second_sector, [0x44; 0x20],
+            "Decrypting from the middle failed"
+        );
+    }
+
+    #[test]
+    fn test_ctr_rfc3686_vector_1() {
+        let key: [u8; 16] = hex("ae6852f8121067cc4bf7a5765577f39e").try_into().unwrap();
+        let nonce = [0, 0, 0, 0x30, 0, 0, 0, 0];
+        let mut data = hex("e4095d4fb7a7b3792d6175a3261311b8");
+        // The counter block ends in 1, so the data is at offset 0x10
+        aes_128_ctr_apply(&key, nonce, 0x10, &mut data);
+        assert_eq!(data, b"Single block msg", "Decryption failed");
+    }
+
+    #[test]
+    fn test_ctr_offset() {
+        let key = [0x2B; 16];
+        let nonce = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut whole = vec![0; 0x40];
+        aes_128_ctr_apply(&key, nonce, 0x100, &mut whole);
+        let mut part = vec![0; 0x20];
+        aes_128_ctr_apply(&key, nonce, 0x120, &mut part);
+        assert_eq!(
+            whole[0x20..],
+            part,
+            "Keystream at an offset should match the keystream of the whole"
+        );
+    }
+}
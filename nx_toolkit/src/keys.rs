@@ -0,0 +1,214 @@
+//! # Keys
+//! Parses the keys files (like `prod.keys` and `title.keys`) that are dumped by tools like
+//! Lockpick_RCM.
+//!
+//! Both files use the same format, every line contains a name and a hex encoded key separated by
+//! `=`. For `title.keys` the name is the hex encoded rights id.
+
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind},
+};
+
+use dotstar_toolkit_utils::bytes::{
+    primitives::u32le,
+    read::{ReadAtExt, ReadError},
+};
+use test_eq::test_eq;
+
+use crate::{crypto, invalid_data};
+
+/// The keys used for decrypting game dumps
+#[derive(Debug, Clone, Default)]
+pub struct Keys {
+    /// Named keys, like `header_key` and `titlekek_00`
+    keys: HashMap<String, Vec<u8>>,
+    /// Encrypted title keys indexed by rights id
+    title_keys: HashMap<[u8; 16], [u8; 16]>,
+}
+
+/// The key area keys that can be used by a NCA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAreaKey {
+    /// Used by applications
+    Application,
+    /// Used by ocean (system applets)
+    Ocean,
+    /// Used by the system
+    System,
+}
+
+impl KeyAreaKey {
+    /// The name of the key in the keys file
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Application => "application",
+            Self::Ocean => "ocean",
+            Self::System => "system",
+        }
+    }
+}
+
+impl TryFrom<u8> for KeyAreaKey {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Application),
+            1 => Ok(Self::Ocean),
+            2 => Ok(Self::System),
+            _ => Err(invalid_data(format!("Unknown key area key index {value}"))),
+        }
+    }
+}
+
+impl Keys {
+    /// Offset of the title key block in a ticket signed with RSA-2048
+    const TICKET_TITLE_KEY_OFFSET: u64 = 0x180;
+    /// Offset of the rights id in a ticket signed with RSA-2048
+    const TICKET_RIGHTS_ID_OFFSET: u64 = 0x2A0;
+    /// Signature type of a ticket signed with RSA-2048 and SHA-256
+    const TICKET_SIGNATURE_RSA2048_SHA256: u32 = 0x1_0004;
+
+    /// Parse a keys file like `prod.keys`
+    ///
+    /// # Errors
+    /// Will return an error if a line is not a name and a hex encoded key
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut keys = Self::default();
+        keys.add_keys(text)?;
+        Ok(keys)
+    }
+
+    /// Add the keys in the keys file `text`
+    ///
+    /// # Errors
+    /// Will return an error if a line is not a name and a hex encoded key
+    pub fn add_keys(&mut self, text: &str) -> Result<(), Error> {
+        for (name, key) in parse_lines(text)? {
+            self.keys.insert(name.to_lowercase(), key);
+        }
+        Ok(())
+    }
+
+    /// Add the title keys in a `title.keys` file
+    ///
+    /// # Errors
+    /// Will return an error if a line is not a rights id and a title key
+    pub fn add_title_keys(&mut self, text: &str) -> Result<(), Error> {
+        for (rights_id, title_key) in parse_lines(text)? {
+            let rights_id = decode_hex(rights_id)?
+                .try_into()
+                .map_err(|_| invalid_data(format!("Rights id {rights_id} is not 16 bytes")))?;
+            let title_key = title_key
+                .try_into()
+                .map_err(|_| invalid_data("Title key is not 16 bytes"))?;
+            self.title_keys.insert(rights_id, title_key);
+        }
+        Ok(())
+    }
+
+    /// Add the title key from the ticket `data`
+    ///
+    /// Only common tickets signed with RSA-2048 are supported.
+    ///
+    /// # Errors
+    /// Will return an error if the ticket cannot be parsed
+    pub fn add_ticket(&mut self, data: &[u8]) -> Result<(), Error> {
+        let (rights_id, title_key) = Self::parse_ticket(data).map_err(invalid_data)?;
+        self.title_keys.entry(rights_id).or_insert(title_key);
+        Ok(())
+    }
+
+    /// Read the rights id and title key from the ticket `data`
+    fn parse_ticket(data: &[u8]) -> Result<([u8; 16], [u8; 16]), ReadError> {
+        let signature_type = data.read_at::<u32le>(&mut 0)?;
+        test_eq!(signature_type, Self::TICKET_SIGNATURE_RSA2048_SHA256)?;
+        let mut position = Self::TICKET_TITLE_KEY_OFFSET;
+        let title_key = data.read_at::<[u8; 16]>(&mut position)?;
+        let mut position = Self::TICKET_RIGHTS_ID_OFFSET;
+        let rights_id = data.read_at::<[u8; 16]>(&mut position)?;
+        Ok((rights_id, title_key))
+    }
+
+    /// The key for decrypting NCA headers
+    ///
+    /// # Errors
+    /// Will return an error if the key is missing
+    pub fn header_key(&self) -> Result<[u8; 32], Error> {
+        self.get("header_key")
+    }
+
+    /// The key area key for `key_generation`
+    ///
+    /// # Errors
+    /// Will return an error if the key is missing
+    pub fn key_area_key(&self, kind: KeyAreaKey, key_generation: u8) -> Result<[u8; 16], Error> {
+        self.get(&format!(
+            "key_area_key_{}_{key_generation:02x}",
+            kind.name()
+        ))
+    }
+
+    /// The decrypted title key for `rights_id`
+    ///
+    /// # Errors
+    /// Will return an error if the title key or the title kek is missing
+    pub fn title_key(&self, rights_id: [u8; 16], key_generation: u8) -> Result<[u8; 16], Error> {
+        let mut title_key = *self.title_keys.get(&rights_id).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Missing title key for rights id {}", encode_hex(&rights_id)),
+            )
+        })?;
+        let title_kek = self.get(&format!("titlekek_{key_generation:02x}"))?;
+        crypto::aes_128_ecb_decrypt(&title_kek, &mut title_key);
+        Ok(title_key)
+    }
+
+    /// Get the key with `name`
+    fn get<const N: usize>(&self, name: &str) -> Result<[u8; N], Error> {
+        let key = self
+            .keys
+            .get(name)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("Missing key {name}")))?;
+        key.as_slice()
+            .try_into()
+            .map_err(|_| invalid_data(format!("Key {name} is not {N} bytes")))
+    }
+}
+
+/// Split every line of a keys file into a name and a decoded key
+fn parse_lines(text: &str) -> Result<Vec<(&str, Vec<u8>)>, Error> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', ';']))
+        .map(|line| {
+            let (name, key) = line
+                .split_once('=')
+                .ok_or_else(|| invalid_data(format!("Invalid line in keys file: {line}")))?;
+            Ok((name.trim(), decode_hex(key.trim())?))
+        })
+        .collect()
+}
+
+/// Decode a hex string
+fn decode_hex(hex: &str) -> Result<Vec<u8>, Error> {
+    let pairs = hex.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err(invalid_data(format!("Odd number of hex digits in {hex}")));
+    }
+    pairs
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| invalid_data(format!("Invalid hex digits in {hex}")))
+        })
+        .collect()
+}
+
+/// Encode `data` as a hex string
+fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,82 @@
+// Everything should be documented
+#![deny(missing_docs)]
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(clippy::arithmetic_side_effects)]
+
+//! # NX Toolkit
+//!
+//! This is a library for reading Nintendo Switch game dumps.
+//! Currently supported are:
+//! | File format | Extension | Supported                              |
+//! | PFS0        | .nsp      | yes                                    |
+//! | HFS0        | .xci      | yes, only the secure partition         |
+//! | NCA         | .nca      | Partially, only RomFS sections (NCA3)  |
+//! | BKTR        | .nca      | no, update NCAs are rejected           |
+//! | RomFS       |           | yes                                    |
+//!
+//! Decrypting NCAs requires the console keys, which can be dumped with tools like Lockpick_RCM.
+//!
+//! ## Features
+//! This crate has no features that can be enabled
+//!
+
+use std::io::{Error, ErrorKind};
+
+use dotstar_toolkit_utils::vfs::{VirtualFile, VirtualFileSystem};
+
+mod crypto;
+pub mod keys;
+pub mod nca;
+pub mod partition;
+pub mod romfs;
+
+use keys::Keys;
+use nca::{ContentType, Nca};
+use partition::PartitionFs;
+use romfs::RomFsFilesystem;
+
+/// Open the RomFS of the game in the NSP or XCI `file`
+///
+/// The RomFS of the first program NCA is used. For NSPs, the title keys are read from the tickets
+/// in the NSP if they're not in `keys`.
+///
+/// # Errors
+/// Will return an error if the file is not a NSP or XCI, if no program NCA is found, or if
+/// the keys needed for decryption are missing.
+pub fn open_romfs<'f>(file: VirtualFile<'f>, keys: &Keys) -> Result<RomFsFilesystem<'f>, Error> {
+    let partition = if file.starts_with(&partition::PFS0_MAGIC) {
+        PartitionFs::new(file)?
+    } else {
+        PartitionFs::xci_secure(file)?
+    };
+
+    // Add the title keys from the tickets, eShop dumps need these to decrypt the NCAs
+    let mut keys = keys.clone();
+    for path in partition.paths() {
+        if path.extension() == Some("tik") {
+            let ticket = partition.open(path)?;
+            keys.add_ticket(&ticket)?;
+        }
+    }
+
+    for path in partition.paths() {
+        if path.extension() != Some("nca") {
+            continue;
+        }
+        let (offset, size) = partition.location(path)?;
+        let nca = Nca::new(partition.file().clone(), offset, size, &keys)?;
+        if nca.content_type() == ContentType::Program && nca.has_romfs() {
+            return RomFsFilesystem::new(nca.romfs(&keys)?);
+        }
+    }
+
+    Err(Error::new(
+        ErrorKind::NotFound,
+        "Could not find a program NCA with a RomFS!",
+    ))
+}
+
+/// Convert a parsing error into an I/O error, so it can be returned by the filesystems
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Error {
+    Error::new(ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,393 @@
+//! # NCA
+//! Nintendo Content Archives contain the actual content of a title. They have an encrypted header
+//! followed by up to four sections, every section is a PFS0 or RomFS and is usually encrypted.
+//!
+//! Only NCA3 is supported and only the RomFS sections can be read. The RomFS of update NCAs is a
+//! patch (BKTR) on the RomFS of the base game, these are rejected.
+//!
+//! This parser implementation is based on the documentation on [SwitchBrew](https://switchbrew.org/wiki/NCA).
+
+use std::io::{Error, ErrorKind};
+
+use dotstar_toolkit_utils::{
+    bytes::{
+        primitives::{u32le, u64le},
+        read::{ReadAtExt, ReadError},
+    },
+    vfs::VirtualFile,
+};
+use test_eq::test_eq;
+
+use crate::{
+    crypto::{self, BLOCK_SIZE},
+    invalid_data,
+    keys::{KeyAreaKey, Keys},
+};
+
+/// Size of the (encrypted) header, including the section headers
+const HEADER_SIZE: usize = 0xC00;
+/// Size of a sector of the header
+const HEADER_SECTOR_SIZE: usize = 0x200;
+/// Size of a media unit, the section offsets are in media units
+const MEDIA_UNIT_SIZE: u64 = 0x200;
+/// Magic of a NCA3
+const NCA3_MAGIC: [u8; 4] = *b"NCA3";
+/// The maximum amount of sections in a NCA
+const MAX_SECTIONS: u64 = 4;
+
+/// The type of content in a NCA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentType {
+    /// The program, contains the executables and the game files
+    Program,
+    /// Metadata about the title
+    Meta,
+    /// Control data, like the icon and title
+    Control,
+    /// Manual
+    Manual,
+    /// Data
+    Data,
+    /// Public data
+    PublicData,
+}
+
+impl TryFrom<u8> for ContentType {
+    type Error = ReadError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Program),
+            1 => Ok(Self::Meta),
+            2 => Ok(Self::Control),
+            3 => Ok(Self::Manual),
+            4 => Ok(Self::Data),
+            5 => Ok(Self::PublicData),
+            _ => Err(ReadError::custom(format!("Unknown content type {value}"))),
+        }
+    }
+}
+
+/// A NCA in a file
+pub struct Nca<'f> {
+    /// The file that contains the NCA
+    file: VirtualFile<'f>,
+    /// Offset of the NCA in `file`
+    offset: u64,
+    /// Type of the content
+    content_type: ContentType,
+    /// Index of the key generation, used for selecting the keys
+    key_generation: u8,
+    /// Which key area key is used for the key area
+    key_area_key: KeyAreaKey,
+    /// Rights id, if not all zeroes the title key is used instead of the key area
+    rights_id: [u8; 16],
+    /// The (still encrypted) key area
+    key_area: [[u8; 16]; 4],
+    /// The RomFS section, if there is one
+    romfs: Option<SectionInfo>,
+}
+
+/// Location and encryption of a section
+#[derive(Debug, Clone, Copy)]
+struct SectionInfo {
+    /// Offset of the data from the start of the NCA
+    offset: u64,
+    /// Size of the data
+    size: u64,
+    /// Is the section encrypted with AES-CTR
+    encrypted: bool,
+    /// The upper half of the AES-CTR counter
+    nonce: [u8; 8],
+}
+
+impl<'f> Nca<'f> {
+    /// Parse the header of the NCA at `offset` in `file`
+    ///
+    /// # Errors
+    /// Will return an error if the header key is missing or the header cannot be parsed
+    pub fn new(file: VirtualFile<'f>, offset: u64, size: u64, keys: &Keys) -> Result<Self, Error> {
+        let start = usize::try_from(offset).map_err(invalid_data)?;
+        let end = start
+            .checked_add(HEADER_SIZE)
+            .ok_or_else(|| invalid_data("NCA is too large"))?;
+        let mut header = file
+            .get(start..end)
+            .ok_or_else(|| invalid_data("NCA header is outside the file"))?
+            .to_vec();
+        crypto::aes_128_xts_decrypt(&keys.header_key()?, &mut header, 0, HEADER_SECTOR_SIZE);
+
+        // A wrong header key results in garbage instead of the magic
+        if header.get(0x200..0x204) != Some(NCA3_MAGIC.as_slice()) {
+            return Err(invalid_data(
+                "NCA header could not be decrypted or is not a NCA3, check the header key",
+            ));
+        }
+        let header = parse_header(&header, size).map_err(invalid_data)?;
+
+        Ok(Self {
+            file,
+            offset,
+            content_type: header.content_type,
+            key_generation: header.key_generation,
+            key_area_key: KeyAreaKey::try_from(header.key_area_key)?,
+            rights_id: header.rights_id,
+            key_area: header.key_area,
+            romfs: header.romfs,
+        })
+    }
+
+    /// The type of content in this NCA
+    #[must_use]
+    pub const fn content_type(&self) -> ContentType {
+        self.content_type
+    }
+
+    /// Does this NCA have a RomFS section
+    #[must_use]
+    pub const fn has_romfs(&self) -> bool {
+        self.romfs.is_some()
+    }
+
+    /// Get the RomFS section of this NCA
+    ///
+    /// # Errors
+    /// Will return an error if there is no RomFS section or if the keys are missing
+    pub fn romfs(&self, keys: &Keys) -> Result<NcaSection<'f>, Error> {
+        let info = self
+            .romfs
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "NCA has no RomFS section"))?;
+        let key = if info.encrypted {
+            Some(self.section_key(keys)?)
+        } else {
+            None
+        };
+        Ok(NcaSection {
+            file: self.file.clone(),
+            nca_offset: self.offset,
+            info,
+            key,
+        })
+    }
+
+    /// The key used for decrypting the sections
+    fn section_key(&self, keys: &Keys) -> Result<[u8; 16], Error> {
+        if self.rights_id == [0; 16] {
+            // The AES-CTR key is the third key in the key area
+            let mut key = self.key_area[2];
+            let key_area_key = keys.key_area_key(self.key_area_key, self.key_generation)?;
+            crypto::aes_128_ecb_decrypt(&key_area_key, &mut key);
+            Ok(key)
+        } else {
+            keys.title_key(self.rights_id, self.key_generation)
+        }
+    }
+}
+
+/// A section of a NCA, decrypted on read
+pub struct NcaSection<'f> {
+    /// The file that contains the NCA
+    file: VirtualFile<'f>,
+    /// Offset of the NCA in `file`
+    nca_offset: u64,
+    /// Location and encryption of the section
+    info: SectionInfo,
+    /// The AES-CTR key, `None` if the section is not encrypted
+    key: Option<[u8; 16]>,
+}
+
+impl<'f> NcaSection<'f> {
+    /// Create an unencrypted section that is all of `file`
+    #[cfg(test)]
+    pub(crate) fn unencrypted(file: VirtualFile<'f>) -> Self {
+        let size = u64::try_from(file.len()).unwrap_or(u64::MAX);
+        Self {
+            file,
+            nca_offset: 0,
+            info: SectionInfo {
+                offset: 0,
+                size,
+                encrypted: false,
+                nonce: [0; 8],
+            },
+            key: None,
+        }
+    }
+
+    /// Size of the section
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.info.size
+    }
+
+    /// Read and decrypt `len` bytes at `offset` in the section
+    ///
+    /// # Errors
+    /// Will return an error if the range is outside the section
+    pub fn read(&self, offset: u64, len: u64) -> Result<Vec<u8>, Error> {
+        let out_of_range = || {
+            Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("Range {offset:#x}+{len:#x} is outside the section"),
+            )
+        };
+        if offset.checked_add(len).ok_or_else(out_of_range)? > self.info.size {
+            return Err(out_of_range());
+        }
+
+        // AES-CTR works on blocks, so read from the start of the block
+        let block_size = u64::try_from(BLOCK_SIZE).map_err(invalid_data)?;
+        let nca_position = self
+            .info
+            .offset
+            .checked_add(offset)
+            .ok_or_else(out_of_range)?;
+        let padding = nca_position
+            .checked_rem(block_size)
+            .ok_or_else(out_of_range)?;
+        let aligned_position = nca_position.checked_sub(padding).ok_or_else(out_of_range)?;
+
+        let start = self
+            .nca_offset
+            .checked_add(aligned_position)
+            .and_then(|start| usize::try_from(start).ok())
+            .ok_or_else(out_of_range)?;
+        let padded_len = padding
+            .checked_add(len)
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or_else(out_of_range)?;
+        let end = start.checked_add(padded_len).ok_or_else(out_of_range)?;
+        let mut data = self.file.get(start..end).ok_or_else(out_of_range)?.to_vec();
+
+        if let Some(key) = &self.key {
+            crypto::aes_128_ctr_apply(key, self.info.nonce, aligned_position, &mut data);
+        }
+
+        let padding = usize::try_from(padding).map_err(invalid_data)?;
+        data.drain(..padding);
+        Ok(data)
+    }
+}
+
+/// The parts of the decrypted header that are used
+struct Header {
+    /// Type of the content
+    content_type: ContentType,
+    /// Index of the key generation
+    key_generation: u8,
+    /// Index of the key area key
+    key_area_key: u8,
+    /// Rights id
+    rights_id: [u8; 16],
+    /// The encrypted key area
+    key_area: [[u8; 16]; 4],
+    /// The RomFS section, if there is one
+    romfs: Option<SectionInfo>,
+}
+
+/// Parse the decrypted header
+fn parse_header(header: &[u8], nca_size: u64) -> Result<Header, ReadError> {
+    let mut position = 0x200;
+    let magic = header.read_at::<[u8; 4]>(&mut position)?;
+    test_eq!(magic, NCA3_MAGIC)?;
+    let _distribution_type = header.read_at::<u8>(&mut position)?;
+    let content_type = ContentType::try_from(header.read_at::<u8>(&mut position)?)?;
+    let key_generation_old = header.read_at::<u8>(&mut position)?;
+    let key_area_key = header.read_at::<u8>(&mut position)?;
+    let size = header.read_at::<u64le>(&mut 0x208)?;
+    test_eq!(size, nca_size)?;
+    let key_generation = header.read_at::<u8>(&mut 0x220)?;
+    // Generation 0 and 1 both use the first keys
+    let key_generation = key_generation_old.max(key_generation).saturating_sub(1);
+    let rights_id = header.read_at::<[u8; 16]>(&mut 0x230)?;
+
+    let mut key_area = [[0; 16]; 4];
+    let mut position = 0x300;
+    for key in &mut key_area {
+        *key = header.read_at::<[u8; 16]>(&mut position)?;
+    }
+
+    let mut romfs = None;
+    for index in 0..MAX_SECTIONS {
+        let mut position = index
+            .checked_mul(0x10)
+            .and_then(|offset| offset.checked_add(0x240))
+            .ok_or_else(ReadError::int_under_overflow)?;
+        let start = header.read_at::<u32le>(&mut position)?;
+        let end = header.read_at::<u32le>(&mut position)?;
+        if start == 0 && end == 0 {
+            continue;
+        }
+        let fs_header_offset = index
+            .checked_mul(0x200)
+            .and_then(|offset| offset.checked_add(0x400))
+            .ok_or_else(ReadError::int_under_overflow)?;
+        if let Some(info) = parse_fs_header(header, fs_header_offset, start)? {
+            romfs = Some(info);
+            break;
+        }
+    }
+
+    Ok(Header {
+        content_type,
+        key_generation,
+        key_area_key,
+        rights_id,
+        key_area,
+        romfs,
+    })
+}
+
+/// Parse the filesystem header at `offset`, returns `None` if the section is not a RomFS
+fn parse_fs_header(
+    header: &[u8],
+    offset: u64,
+    start: u32,
+) -> Result<Option<SectionInfo>, ReadError> {
+    let at = |relative: u64| {
+        offset
+            .checked_add(relative)
+            .ok_or_else(ReadError::int_under_overflow)
+    };
+    let fs_type = header.read_at::<u8>(&mut at(0x2)?)?;
+    let hash_type = header.read_at::<u8>(&mut at(0x3)?)?;
+    let encryption_type = header.read_at::<u8>(&mut at(0x4)?)?;
+    // Only RomFS with hierarchical integrity hashing is supported
+    if fs_type != 0 || hash_type != 3 {
+        return Ok(None);
+    }
+    // Update NCAs patch the RomFS of the base game with a BKTR (indirect storage)
+    let indirect_size = header.read_at::<u64le>(&mut at(0x108)?)?;
+    if encryption_type == 4 || indirect_size != 0 {
+        return Err(ReadError::custom(String::from(
+            "Update NCAs (BKTR) are not supported, use the NCA of the base game",
+        )));
+    }
+    let encrypted = match encryption_type {
+        1 => false,
+        3 => true,
+        _ => {
+            return Err(ReadError::custom(format!(
+                "Unsupported section encryption {encryption_type}"
+            )))
+        }
+    };
+
+    // The actual RomFS is the last level of the integrity hashes
+    let level_offset = header.read_at::<u64le>(&mut at(0x90)?)?;
+    let size = header.read_at::<u64le>(&mut at(0x98)?)?;
+    let mut nonce = header.read_at::<[u8; 8]>(&mut at(0x140)?)?;
+    nonce.reverse();
+
+    let offset = u64::from(start)
+        .checked_mul(MEDIA_UNIT_SIZE)
+        .and_then(|start| start.checked_add(level_offset))
+        .ok_or_else(ReadError::int_under_overflow)?;
+
+    Ok(Some(SectionInfo {
+        offset,
+        size,
+        encrypted,
+        nonce,
+    }))
+}
@@ -0,0 +1,287 @@
+//! # Partition filesystems
+//! PFS0 (used by NSPs) and HFS0 (used by XCIs) are simple archives with a list of files followed by
+//! the data. The only difference is that the file entries of HFS0 also contain a hash.
+//!
+//! This parser implementation is based on the documentation on [SwitchBrew](https://switchbrew.org/wiki/NCA#PFS0).
+
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind},
+};
+
+use dotstar_toolkit_utils::{
+    bytes::{
+        primitives::{u32le, u64le},
+        read::{ReadAtExt, ReadError},
+    },
+    vfs::{VirtualFile, VirtualFileSystem, VirtualMetadata, VirtualPath, VirtualPathBuf, WalkFs},
+};
+use test_eq::test_eq;
+
+use crate::invalid_data;
+
+/// Magic of PFS0 partitions
+pub const PFS0_MAGIC: [u8; 4] = *b"PFS0";
+/// Magic of HFS0 partitions
+pub const HFS0_MAGIC: [u8; 4] = *b"HFS0";
+/// Magic of the XCI header
+const XCI_MAGIC: [u8; 4] = *b"HEAD";
+/// Offset of the XCI magic
+const XCI_MAGIC_OFFSET: u64 = 0x100;
+/// Offset of the offset of the root HFS0 partition in a XCI
+const XCI_ROOT_PARTITION_OFFSET: u64 = 0x130;
+
+/// A PFS0 or HFS0 partition as a virtual filesystem
+pub struct PartitionFs<'f> {
+    /// The file that contains the partition
+    file: VirtualFile<'f>,
+    /// The offset and size of every file, the offset is from the start of `file`
+    files: HashMap<VirtualPathBuf, (u64, u64)>,
+    /// All paths in the partition
+    list: Vec<VirtualPathBuf>,
+}
+
+impl<'f> PartitionFs<'f> {
+    /// Parse the PFS0 or HFS0 partition at the start of `file`
+    ///
+    /// # Errors
+    /// Will return an error if the partition cannot be parsed
+    pub fn new(file: VirtualFile<'f>) -> Result<Self, Error> {
+        Self::at_offset(file, 0)
+    }
+
+    /// Parse the secure partition of the XCI `file`
+    ///
+    /// # Errors
+    /// Will return an error if the file is not a XCI or if it has no secure partition
+    pub fn xci_secure(file: VirtualFile<'f>) -> Result<Self, Error> {
+        let root_offset = parse_xci_header(&file).map_err(invalid_data)?;
+        let root = Self::at_offset(file, root_offset)?;
+        let (secure_offset, _) = root.location(VirtualPath::new("secure"))?;
+        Self::at_offset(root.file, secure_offset)
+    }
+
+    /// Parse the partition at `offset` in `file`
+    fn at_offset(file: VirtualFile<'f>, offset: u64) -> Result<Self, Error> {
+        let files = parse_partition(&file, offset).map_err(invalid_data)?;
+        let list = files.keys().cloned().collect();
+        Ok(Self { file, files, list })
+    }
+
+    /// The file that contains the partition
+    #[must_use]
+    pub const fn file(&self) -> &VirtualFile<'f> {
+        &self.file
+    }
+
+    /// All paths in the partition
+    pub fn paths(&self) -> impl Iterator<Item = &VirtualPath> {
+        self.list.iter().map(VirtualPathBuf::as_path)
+    }
+
+    /// The offset (from the start of [`Self::file`]) and size of the file at `path`
+    ///
+    /// # Errors
+    /// Will return an error if the file does not exist
+    pub fn location(&self, path: &VirtualPath) -> Result<(u64, u64), Error> {
+        let path = path.clean_relative();
+        self.files.get(&path).copied().ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Could not find {path:?} in partition!"),
+            )
+        })
+    }
+}
+
+impl VirtualFileSystem for PartitionFs<'_> {
+    fn open(&self, path: &VirtualPath) -> std::io::Result<VirtualFile> {
+        let (offset, size) = self.location(path)?;
+        let start = usize::try_from(offset).map_err(invalid_data)?;
+        let end = offset
+            .checked_add(size)
+            .and_then(|end| usize::try_from(end).ok())
+            .ok_or_else(|| invalid_data("File is too large"))?;
+        let data = self
+            .file
+            .get(start..end)
+            .ok_or_else(|| invalid_data(format!("{path:?} is outside the partition")))?;
+        Ok(VirtualFile::Slice(data))
+    }
+
+    fn metadata(&self, path: &VirtualPath) -> std::io::Result<VirtualMetadata> {
+        let (_, size) = self.location(path)?;
+        Ok(VirtualMetadata {
+            file_size: size,
+            created: Err(ErrorKind::Unsupported),
+        })
+    }
+
+    fn walk_filesystem<'rf>(&'rf self, path: &VirtualPath) -> std::io::Result<WalkFs<'rf>> {
+        Ok(WalkFs::filtered(&self.list, path))
+    }
+
+    fn exists(&self, path: &VirtualPath) -> bool {
+        self.files.contains_key(&path.clean_relative())
+    }
+}
+
+/// Parse the XCI header and return the offset of the root partition
+fn parse_xci_header(reader: &[u8]) -> Result<u64, ReadError> {
+    let mut position = XCI_MAGIC_OFFSET;
+    let magic = reader.read_at::<[u8; 4]>(&mut position)?;
+    test_eq!(magic, XCI_MAGIC)?;
+    let mut position = XCI_ROOT_PARTITION_OFFSET;
+    reader.read_at::<u64le>(&mut position)
+}
+
+/// Parse the partition at `offset` and return the offset and size of every file
+fn parse_partition(
+    reader: &[u8],
+    offset: u64,
+) -> Result<HashMap<VirtualPathBuf, (u64, u64)>, ReadError> {
+    let mut position = offset;
+    let magic = reader.read_at::<[u8; 4]>(&mut position)?;
+    let entry_size: u64 = match magic {
+        PFS0_MAGIC => 0x18,
+        HFS0_MAGIC => 0x40,
+        _ => {
+            return Err(ReadError::custom(format!(
+                "Unknown partition magic {magic:x?}"
+            )))
+        }
+    };
+    let n_files = reader.read_at::<u32le>(&mut position)?;
+    let string_table_size = reader.read_at::<u32le>(&mut position)?;
+    let _reserved = reader.read_at::<u32le>(&mut position)?;
+
+    let string_table_offset = u64::from(n_files)
+        .checked_mul(entry_size)
+        .and_then(|size| size.checked_add(position))
+        .ok_or_else(ReadError::int_under_overflow)?;
+    let data_offset = string_table_offset
+        .checked_add(u64::from(string_table_size))
+        .ok_or_else(ReadError::int_under_overflow)?;
+
+    let mut files = HashMap::with_capacity(usize::try_from(n_files)?);
+    for _ in 0..n_files {
+        let mut entry_position = position;
+        let file_offset = reader.read_at::<u64le>(&mut entry_position)?;
+        let size = reader.read_at::<u64le>(&mut entry_position)?;
+        let name_offset = reader.read_at::<u32le>(&mut entry_position)?;
+        position = position
+            .checked_add(entry_size)
+            .ok_or_else(ReadError::int_under_overflow)?;
+
+        let mut name_position = string_table_offset
+            .checked_add(u64::from(name_offset))
+            .ok_or_else(ReadError::int_under_overflow)?;
+        let name = reader.read_null_terminated_string_at(&mut name_position)?;
+        let file_offset = data_offset
+            .checked_add(file_offset)
+            .ok_or_else(ReadError::int_under_overflow)?;
+        files.insert(VirtualPathBuf::from(name.as_str()), (file_offset, size));
+    }
+
+    Ok(files)
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[allow(
+    clippy::arithmetic_side_effects,
+    reason = "The offsets in the tests are small"
+)]
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::vfs::{VirtualFile, VirtualFileSystem, VirtualPath};
+
+    use super::{PartitionFs, HFS0_MAGIC, PFS0_MAGIC, XCI_MAGIC};
+
+    /// Create a PFS0 or HFS0 partition containing `files`
+    fn partition(magic: [u8; 4], files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut string_table = Vec::new();
+        let mut entries = Vec::new();
+        let mut data = Vec::new();
+        for (name, content) in files {
+            entries.extend(u64::try_from(data.len()).unwrap().to_le_bytes());
+            entries.extend(u64::try_from(content.len()).unwrap().to_le_bytes());
+            entries.extend(u32::try_from(string_table.len()).unwrap().to_le_bytes());
+            if magic == HFS0_MAGIC {
+                // Hashed size, reserved, and the hash
+                entries.extend([0; 0x2C]);
+            } else {
+                entries.extend([0; 4]);
+            }
+            string_table.extend(name.as_bytes());
+            string_table.push(0);
+            data.extend(*content);
+        }
+        string_table.resize(string_table.len().next_multiple_of(0x20), 0);
+
+        let mut partition = magic.to_vec();
+        partition.extend(u32::try_from(files.len()).unwrap().to_le_bytes());
+        partition.extend(u32::try_from(string_table.len()).unwrap().to_le_bytes());
+        partition.extend([0; 4]);
+        partition.extend(entries);
+        partition.extend(string_table);
+        partition.extend(data);
+        partition
+    }
+
+    #[test]
+    fn test_pfs0() {
+        let pfs0 = partition(
+            PFS0_MAGIC,
+            &[("a.nca", b"abc".as_slice()), ("b.tik", b"defg")],
+        );
+        let fs = PartitionFs::new(VirtualFile::Slice(&pfs0)).unwrap();
+        assert_eq!(fs.paths().count(), 2, "Partition should have two files");
+        assert_eq!(
+            &*fs.open(VirtualPath::new("a.nca")).unwrap(),
+            b"abc",
+            "First file is wrong"
+        );
+        assert_eq!(
+            &*fs.open(VirtualPath::new("/b.tik")).unwrap(),
+            b"defg",
+            "Second file is wrong"
+        );
+        assert!(
+            fs.open(VirtualPath::new("c.cnmt")).is_err(),
+            "File should not exist"
+        );
+    }
+
+    #[test]
+    fn test_xci_secure() {
+        let secure = partition(HFS0_MAGIC, &[("game.nca", b"game".as_slice())]);
+        let root = partition(
+            HFS0_MAGIC,
+            &[("update", b"update".as_slice()), ("secure", &secure)],
+        );
+        let mut xci = vec![0; 0x200];
+        xci[0x100..0x104].copy_from_slice(&XCI_MAGIC);
+        xci[0x130..0x138].copy_from_slice(&0x200u64.to_le_bytes());
+        xci.extend(root);
+
+        let fs = PartitionFs::xci_secure(VirtualFile::Slice(&xci)).unwrap();
+        assert_eq!(
+            &*fs.open(VirtualPath::new("game.nca")).unwrap(),
+            b"game",
+            "File in the secure partition is wrong"
+        );
+        assert!(
+            !fs.exists(VirtualPath::new("update")),
+            "Files of the root partition should not be in the secure partition"
+        );
+    }
+
+    #[test]
+    fn test_unknown_magic() {
+        let data = partition(*b"PFS1", &[]);
+        assert!(
+            PartitionFs::new(VirtualFile::Slice(&data)).is_err(),
+            "Unknown magic should be an error"
+        );
+    }
+}
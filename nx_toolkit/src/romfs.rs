@@ -0,0 +1,429 @@
+//! # RomFS
+//! The filesystem that contains the game files. It consists of a directory table and a file table,
+//! where every entry refers to its siblings and children by their offset in the table.
+//!
+//! The RomFS is read from a [`NcaSection`] and every file is decrypted when it's opened. Big files
+//! can be decrypted in chunks with [`RomFsFilesystem::extract`] instead.
+//!
+//! This parser implementation is based on the documentation on [SwitchBrew](https://switchbrew.org/wiki/RomFS).
+
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    io::{Error, ErrorKind, Write},
+    sync::{Arc, Mutex, Weak},
+};
+
+use dotstar_toolkit_utils::{
+    bytes::{
+        primitives::{u32le, u64le},
+        read::{ReadAtExt, ReadError},
+    },
+    vfs::{VirtualFile, VirtualFileSystem, VirtualMetadata, VirtualPath, VirtualPathBuf, WalkFs},
+};
+use test_eq::test_eq;
+
+use crate::{invalid_data, nca::NcaSection};
+
+/// Size of the RomFS header
+const HEADER_SIZE: u64 = 0x50;
+/// Offset used for an empty entry in the tables
+const EMPTY: u32 = 0xFFFF_FFFF;
+/// Amount of bytes that are decrypted at once when extracting a file
+const CHUNK_SIZE: u64 = 0x10_0000;
+
+/// A RomFS as a virtual filesystem
+pub struct RomFsFilesystem<'f> {
+    /// The section that contains the RomFS
+    section: NcaSection<'f>,
+    /// The offset and size of every file, the offset is from the start of `section`
+    files: HashMap<VirtualPathBuf, (u64, u64)>,
+    /// All paths in the RomFS
+    list: Vec<VirtualPathBuf>,
+    /// Recently opened files, so they're not decrypted twice
+    cache: Mutex<HashMap<VirtualPathBuf, Weak<Vec<u8>>>>,
+}
+
+impl<'f> RomFsFilesystem<'f> {
+    /// Parse the RomFS in `section`
+    ///
+    /// # Errors
+    /// Will return an error if the RomFS cannot be parsed
+    pub fn new(section: NcaSection<'f>) -> Result<Self, Error> {
+        let header = section.read(0, HEADER_SIZE)?;
+        let header = parse_header(&header).map_err(invalid_data)?;
+        let dir_table = section.read(header.dir_meta_offset, header.dir_meta_size)?;
+        let file_table = section.read(header.file_meta_offset, header.file_meta_size)?;
+        let files =
+            parse_tables(&dir_table, &file_table, header.data_offset).map_err(invalid_data)?;
+        let list = files.keys().cloned().collect();
+        Ok(Self {
+            section,
+            files,
+            list,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Decrypt the file at `path` to `writer`, without keeping the whole file in memory
+    ///
+    /// Returns the size of the file.
+    ///
+    /// # Errors
+    /// Will return an error if the file does not exist or if writing fails
+    pub fn extract(&self, path: &VirtualPath, writer: &mut impl Write) -> Result<u64, Error> {
+        let (_, offset, size) = self.location(path)?;
+        let mut done = 0;
+        while done < size {
+            let len = CHUNK_SIZE.min(size.saturating_sub(done));
+            let position = offset.checked_add(done).ok_or_else(|| {
+                Error::new(ErrorKind::InvalidData, format!("{path:?} is too big"))
+            })?;
+            writer.write_all(&self.section.read(position, len)?)?;
+            done = done.saturating_add(len);
+        }
+        Ok(size)
+    }
+
+    /// The offset and size of the file at `path`
+    fn location(&self, path: &VirtualPath) -> Result<(VirtualPathBuf, u64, u64), Error> {
+        let path = path.clean_relative();
+        let (offset, size) = self.files.get(&path).copied().ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Could not find {path:?} in RomFS!"),
+            )
+        })?;
+        Ok((path, offset, size))
+    }
+}
+
+impl VirtualFileSystem for RomFsFilesystem<'_> {
+    fn open(&self, path: &VirtualPath) -> std::io::Result<VirtualFile> {
+        let (path, offset, size) = self.location(path)?;
+        let mut cache = self
+            .cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let entry = cache.entry(path);
+        if let Entry::Occupied(entry) = &entry {
+            if let Some(arc) = entry.get().upgrade() {
+                return Ok(VirtualFile::Vec(arc));
+            }
+        }
+        let arc = Arc::new(self.section.read(offset, size)?);
+        match entry {
+            Entry::Occupied(mut entry) => {
+                entry.insert(Arc::downgrade(&arc));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Arc::downgrade(&arc));
+            }
+        }
+        Ok(VirtualFile::Vec(arc))
+    }
+
+    fn metadata(&self, path: &VirtualPath) -> std::io::Result<VirtualMetadata> {
+        let (_, _, size) = self.location(path)?;
+        Ok(VirtualMetadata {
+            file_size: size,
+            created: Err(ErrorKind::Unsupported),
+        })
+    }
+
+    fn walk_filesystem<'rf>(&'rf self, path: &VirtualPath) -> std::io::Result<WalkFs<'rf>> {
+        Ok(WalkFs::filtered(&self.list, path))
+    }
+
+    fn exists(&self, path: &VirtualPath) -> bool {
+        self.files.contains_key(&path.clean_relative())
+    }
+}
+
+/// The parts of the header that are used
+struct Header {
+    /// Offset of the directory table
+    dir_meta_offset: u64,
+    /// Size of the directory table
+    dir_meta_size: u64,
+    /// Offset of the file table
+    file_meta_offset: u64,
+    /// Size of the file table
+    file_meta_size: u64,
+    /// Offset of the file data
+    data_offset: u64,
+}
+
+/// Parse the RomFS header
+fn parse_header(reader: &[u8]) -> Result<Header, ReadError> {
+    let mut position = 0;
+    let header_size = reader.read_at::<u64le>(&mut position)?;
+    test_eq!(header_size, HEADER_SIZE)?;
+    let _dir_hash_offset = reader.read_at::<u64le>(&mut position)?;
+    let _dir_hash_size = reader.read_at::<u64le>(&mut position)?;
+    let dir_meta_offset = reader.read_at::<u64le>(&mut position)?;
+    let dir_meta_size = reader.read_at::<u64le>(&mut position)?;
+    let _file_hash_offset = reader.read_at::<u64le>(&mut position)?;
+    let _file_hash_size = reader.read_at::<u64le>(&mut position)?;
+    let file_meta_offset = reader.read_at::<u64le>(&mut position)?;
+    let file_meta_size = reader.read_at::<u64le>(&mut position)?;
+    let data_offset = reader.read_at::<u64le>(&mut position)?;
+    Ok(Header {
+        dir_meta_offset,
+        dir_meta_size,
+        file_meta_offset,
+        file_meta_size,
+        data_offset,
+    })
+}
+
+/// Walk the directory and file tables and return the offset and size of every file
+fn parse_tables(
+    dir_table: &[u8],
+    file_table: &[u8],
+    data_offset: u64,
+) -> Result<HashMap<VirtualPathBuf, (u64, u64)>, ReadError> {
+    let mut files = HashMap::new();
+    // A corrupt table can refer to an entry twice, which would never stop
+    let mut visited_dirs = HashSet::from([0]);
+    let mut visited_files = HashSet::new();
+    // The root directory is always the first entry
+    let mut todo = vec![(0, String::new())];
+    while let Some((dir_offset, dir_path)) = todo.pop() {
+        let mut position = u64::from(dir_offset);
+        let _parent = dir_table.read_at::<u32le>(&mut position)?;
+        let _sibling = dir_table.read_at::<u32le>(&mut position)?;
+        let mut child_dir = dir_table.read_at::<u32le>(&mut position)?;
+        let mut child_file = dir_table.read_at::<u32le>(&mut position)?;
+
+        while child_file != EMPTY {
+            if !visited_files.insert(child_file) {
+                return Err(ReadError::custom(format!(
+                    "File entry at {child_file:#x} is referenced twice"
+                )));
+            }
+            let mut position = u64::from(child_file);
+            let _parent = file_table.read_at::<u32le>(&mut position)?;
+            child_file = file_table.read_at::<u32le>(&mut position)?;
+            let offset = file_table.read_at::<u64le>(&mut position)?;
+            let size = file_table.read_at::<u64le>(&mut position)?;
+            let _hash = file_table.read_at::<u32le>(&mut position)?;
+            let name = read_name(file_table, &mut position)?;
+            let offset = data_offset
+                .checked_add(offset)
+                .ok_or_else(ReadError::int_under_overflow)?;
+            files.insert(
+                VirtualPathBuf::from(format!("{dir_path}{name}")),
+                (offset, size),
+            );
+        }
+
+        while child_dir != EMPTY {
+            if !visited_dirs.insert(child_dir) {
+                return Err(ReadError::custom(format!(
+                    "Directory entry at {child_dir:#x} is referenced twice"
+                )));
+            }
+            let mut position = u64::from(child_dir);
+            let _parent = dir_table.read_at::<u32le>(&mut position)?;
+            let sibling = dir_table.read_at::<u32le>(&mut position)?;
+            let _child_dir = dir_table.read_at::<u32le>(&mut position)?;
+            let _child_file = dir_table.read_at::<u32le>(&mut position)?;
+            let _hash = dir_table.read_at::<u32le>(&mut position)?;
+            let name = read_name(dir_table, &mut position)?;
+            todo.push((child_dir, format!("{dir_path}{name}/")));
+            child_dir = sibling;
+        }
+    }
+    Ok(files)
+}
+
+/// Read the length prefixed name of an entry
+fn read_name(reader: &[u8], position: &mut u64) -> Result<String, ReadError> {
+    let len = reader.read_at::<u32le>(position)?;
+    let start = usize::try_from(*position)?;
+    let end = start
+        .checked_add(usize::try_from(len)?)
+        .ok_or_else(ReadError::int_under_overflow)?;
+    let name = reader
+        .get(start..end)
+        .ok_or_else(|| ReadError::custom(format!("Name at {start:#x} is outside the table")))?;
+    Ok(std::str::from_utf8(name)?.to_owned())
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[allow(
+    clippy::arithmetic_side_effects,
+    reason = "The offsets in the tests are small"
+)]
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dotstar_toolkit_utils::vfs::{VirtualFile, VirtualFileSystem, VirtualPath};
+
+    use super::{parse_tables, RomFsFilesystem, CHUNK_SIZE, EMPTY, HEADER_SIZE};
+    use crate::nca::NcaSection;
+
+    /// Create a table entry with the fields in `fields` followed by the padded `name`
+    fn entry(fields: &[&[u8]], name: &str) -> Vec<u8> {
+        let mut entry: Vec<u8> = fields.concat();
+        entry.extend(u32::try_from(name.len()).unwrap().to_le_bytes());
+        entry.extend(name.as_bytes());
+        entry.resize(entry.len().next_multiple_of(4), 0);
+        entry
+    }
+
+    /// Create a directory entry
+    fn dir_entry(sibling: u32, child_dir: u32, child_file: u32, name: &str) -> Vec<u8> {
+        entry(
+            &[
+                &0u32.to_le_bytes(),
+                &sibling.to_le_bytes(),
+                &child_dir.to_le_bytes(),
+                &child_file.to_le_bytes(),
+                &EMPTY.to_le_bytes(),
+            ],
+            name,
+        )
+    }
+
+    /// Create a file entry
+    fn file_entry(sibling: u32, offset: u64, size: u64, name: &str) -> Vec<u8> {
+        entry(
+            &[
+                &0u32.to_le_bytes(),
+                &sibling.to_le_bytes(),
+                &offset.to_le_bytes(),
+                &size.to_le_bytes(),
+                &EMPTY.to_le_bytes(),
+            ],
+            name,
+        )
+    }
+
+    /// A RomFS with `a.txt` in the root and `b.bin` in `sub`
+    fn romfs() -> Vec<u8> {
+        let a = file_entry(EMPTY, 0, 5, "a.txt");
+        let b = file_entry(EMPTY, 8, 3, "b.bin");
+        let b_offset = u32::try_from(a.len()).unwrap();
+        let file_table = [a, b].concat();
+
+        let root = dir_entry(EMPTY, 0x18, 0, "");
+        let sub = dir_entry(EMPTY, EMPTY, b_offset, "sub");
+        let dir_table = [root, sub].concat();
+
+        let data = b"hello\0\0\0abc";
+
+        let dir_offset = HEADER_SIZE;
+        let dir_size = u64::try_from(dir_table.len()).unwrap();
+        let file_offset = dir_offset + dir_size;
+        let file_size = u64::try_from(file_table.len()).unwrap();
+        let data_offset = file_offset + file_size;
+        let header = [
+            HEADER_SIZE,
+            dir_offset,
+            0,
+            dir_offset,
+            dir_size,
+            file_offset,
+            0,
+            file_offset,
+            file_size,
+            data_offset,
+        ];
+        let mut romfs: Vec<u8> = header.iter().flat_map(|v| v.to_le_bytes()).collect();
+        romfs.extend(dir_table);
+        romfs.extend(file_table);
+        romfs.extend(data);
+        romfs
+    }
+
+    #[test]
+    fn test_romfs() {
+        let section = NcaSection::unencrypted(VirtualFile::Vec(Arc::new(romfs())));
+        let romfs = RomFsFilesystem::new(section).unwrap();
+        assert_eq!(
+            &*romfs.open(VirtualPath::new("a.txt")).unwrap(),
+            b"hello",
+            "File in the root is wrong"
+        );
+        assert_eq!(
+            &*romfs.open(VirtualPath::new("/sub/b.bin")).unwrap(),
+            b"abc",
+            "File in a subdirectory is wrong"
+        );
+        assert_eq!(
+            romfs
+                .walk_filesystem(VirtualPath::new("sub"))
+                .unwrap()
+                .count(),
+            1,
+            "Walking a subdirectory should only list its files"
+        );
+        assert!(
+            !romfs.exists(VirtualPath::new("sub")),
+            "Directories are not files"
+        );
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut data = romfs();
+        // Make the file in the root bigger than a chunk
+        let size = usize::try_from(CHUNK_SIZE).unwrap() * 2 + 3;
+        let a_size = u64::try_from(size).unwrap();
+        let file_table_offset =
+            usize::try_from(u64::from_le_bytes(data[0x38..0x40].try_into().unwrap())).unwrap();
+        data[file_table_offset + 0x10..file_table_offset + 0x18]
+            .copy_from_slice(&a_size.to_le_bytes());
+        let content: Vec<u8> = (0..size).map(|i| u8::try_from(i % 251).unwrap()).collect();
+        data.truncate(data.len() - 11);
+        data.extend(&content);
+
+        let section = NcaSection::unencrypted(VirtualFile::Vec(Arc::new(data)));
+        let romfs = RomFsFilesystem::new(section).unwrap();
+        let mut extracted = Vec::new();
+        let extracted_size = romfs
+            .extract(VirtualPath::new("a.txt"), &mut extracted)
+            .unwrap();
+        assert_eq!(extracted_size, a_size, "Wrong size returned");
+        assert!(extracted == content, "Extracted file is wrong");
+        assert!(
+            romfs
+                .extract(VirtualPath::new("missing"), &mut Vec::new())
+                .is_err(),
+            "A missing file should be an error"
+        );
+    }
+
+    #[test]
+    fn test_cyclic_directories() {
+        // The subdirectory is its own sibling
+        let root = dir_entry(EMPTY, 0x18, EMPTY, "");
+        let sub = dir_entry(0x18, EMPTY, EMPTY, "sub");
+        let dir_table = [root, sub].concat();
+        assert!(
+            parse_tables(&dir_table, &[], 0).is_err(),
+            "A cycle in the directories should be an error"
+        );
+
+        // The subdirectory is the parent of the root
+        let root = dir_entry(EMPTY, 0x18, EMPTY, "");
+        let sub = dir_entry(EMPTY, 0, EMPTY, "sub");
+        let dir_table = [root, sub].concat();
+        assert!(
+            parse_tables(&dir_table, &[], 0).is_err(),
+            "A directory that contains the root should be an error"
+        );
+    }
+
+    #[test]
+    fn test_cyclic_files() {
+        let dir_table = dir_entry(EMPTY, EMPTY, 0, "");
+        let file_table = file_entry(0, 0, 0, "a.txt");
+        assert!(
+            parse_tables(&dir_table, &file_table, 0).is_err(),
+            "A cycle in the files should be an error"
+        );
+    }
+}
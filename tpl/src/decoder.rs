@@ -0,0 +1,253 @@
+use std::num::TryFromIntError;
+
+use dotstar_toolkit_utils::{
+    bytes::{
+        primitives::{u16be, u32be},
+        read::{BinaryDeserialize, ReadAtExt, ReadError},
+    },
+    texture,
+};
+use image::{
+    error::{DecodingError, ImageFormatHint},
+    ColorType, ImageDecoder, ImageError, ImageResult,
+};
+use test_eq::{test_eq, TestFailure};
+use thiserror::Error;
+
+use crate::{
+    formats,
+    types::{Format, ImageHeader, TplHeader},
+};
+
+/// Errors returned when the decoder fails
+#[derive(Error, Debug)]
+pub enum DecoderError {
+    /// Encountered unknown texture format
+    #[error("Unknown texture format found: 0x{0:x}")]
+    UnknownTextureFormat(u32),
+    /// Encountered a texture format that needs a palette
+    #[error("Paletted texture formats are not supported: {0:?}")]
+    PalettedFormat(Format),
+    /// No image in the texture file
+    #[error("No image in the texture")]
+    NoImages,
+    /// Read failure
+    #[error("Read error")]
+    Read(#[from] ReadError),
+    /// Test failure
+    #[error("Value test failed")]
+    Test(#[from] TestFailure),
+    /// Integer conversion failed
+    #[error("Integer conversion failed")]
+    TryFromInt(#[from] TryFromIntError),
+}
+
+impl From<DecoderError> for ImageError {
+    fn from(err: DecoderError) -> Self {
+        Self::Decoding(DecodingError::new(ImageFormatHint::Name("TPL".into()), err))
+    }
+}
+
+/// Decoder for the first image in a TPL file
+///
+/// Any other images and the mipmaps are ignored.
+pub struct TplDecoder<R: ReadAtExt> {
+    reader: R,
+    /// Position of the start of the TPL file, all offsets are relative to this
+    start: u64,
+    header: ImageHeader,
+}
+
+impl<R: ReadAtExt> TplDecoder<R> {
+    pub fn new(reader: R, position: &mut u64) -> Result<Self, DecoderError> {
+        let start = *position;
+        let tpl = reader.read_at::<TplHeader>(position)?;
+        if tpl.image_count == 0 {
+            return Err(DecoderError::NoImages);
+        }
+
+        let mut table_position = start + u64::from(tpl.image_table_offset);
+        let image_header_offset = reader.read_at::<u32be>(&mut table_position)?;
+        let _palette_header_offset = reader.read_at::<u32be>(&mut table_position)?;
+
+        let mut header_position = start + u64::from(image_header_offset);
+        let header = reader.read_at::<ImageHeader>(&mut header_position)?;
+        if header.format.is_paletted() {
+            return Err(DecoderError::PalettedFormat(header.format));
+        }
+
+        Ok(Self {
+            reader,
+            start,
+            header,
+        })
+    }
+}
+
+impl<R: ReadAtExt> ImageDecoder for TplDecoder<R> {
+    fn dimensions(&self) -> (u32, u32) {
+        (u32::from(self.header.width), u32::from(self.header.height))
+    }
+
+    fn color_type(&self) -> ColorType {
+        // everything is decoded to RGBA8, even if there is no alpha channel
+        ColorType::Rgba8
+    }
+
+    fn read_image(self, buf: &mut [u8]) -> ImageResult<()>
+    where
+        Self: Sized,
+    {
+        assert_eq!(
+            u64::try_from(buf.len()).expect("Image is too big for u64"),
+            self.total_bytes(),
+            "Buffer is too small or too big for the image"
+        );
+
+        let hdr = self.header;
+        let width = usize::from(hdr.width);
+        let height = usize::from(hdr.height);
+        let (block_width, block_height) = hdr.format.block_dimensions();
+        let size =
+            width.div_ceil(block_width) * height.div_ceil(block_height) * hdr.format.block_size();
+
+        let mut position = self.start + u64::from(hdr.data_offset);
+        let data = self
+            .reader
+            .read_slice_at(&mut position, size)
+            .map_err(DecoderError::from)?;
+
+        let decode_block: fn(&[u8]) -> formats::Block = match hdr.format {
+            Format::I4 => formats::i4,
+            Format::I8 => formats::i8,
+            Format::Ia4 => formats::ia4,
+            Format::Ia8 => formats::ia8,
+            Format::Rgb565 => formats::rgb565,
+            Format::Rgb5a3 => formats::rgb5a3,
+            Format::Rgba32 => formats::rgba32,
+            Format::Cmpr => formats::cmpr,
+            Format::C4 | Format::C8 | Format::C14x2 => {
+                return Err(DecoderError::PalettedFormat(hdr.format).into());
+            }
+        };
+        texture::decode_blocks(
+            &data,
+            width,
+            height,
+            buf,
+            hdr.format.block_dimensions(),
+            hdr.format.block_size(),
+            decode_block,
+        )
+        .map_err(DecoderError::from)?;
+
+        Ok(())
+    }
+
+    fn read_image_boxed(self: Box<Self>, buf: &mut [u8]) -> ImageResult<()> {
+        (*self).read_image(buf)
+    }
+}
+
+impl BinaryDeserialize<'_> for TplHeader {
+    type Ctx = ();
+    type Output = Self;
+
+    fn deserialize_at_with(
+        reader: &(impl ReadAtExt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<Self::Output, ReadError> {
+        let magic = reader.read_at::<u32be>(position)?;
+        test_eq!(magic, Self::MAGIC)?;
+        let image_count = reader.read_at::<u32be>(position)?;
+        let image_table_offset = reader.read_at::<u32be>(position)?;
+
+        Ok(Self {
+            image_count,
+            image_table_offset,
+        })
+    }
+}
+
+impl BinaryDeserialize<'_> for ImageHeader {
+    type Ctx = ();
+    type Output = Self;
+
+    fn deserialize_at_with(
+        reader: &(impl ReadAtExt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<Self::Output, ReadError> {
+        let height = reader.read_at::<u16be>(position)?;
+        let width = reader.read_at::<u16be>(position)?;
+        let format = reader.read_at::<Format>(position)?;
+        let data_offset = reader.read_at::<u32be>(position)?;
+        // Wrapping, filtering and level of detail settings, not needed for decoding
+        let _slice = reader.read_slice_at(position, 0x14)?;
+
+        Ok(Self {
+            width,
+            height,
+            format,
+            data_offset,
+        })
+    }
+}
+
+impl BinaryDeserialize<'_> for Format {
+    type Ctx = ();
+    type Output = Self;
+
+    fn deserialize_at_with(
+        reader: &(impl ReadAtExt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<Self::Output, ReadError> {
+        let value = reader.read_at::<u32be>(position)?;
+        Self::try_from(value).map_err(|_| ReadError::custom(format!("Unknown format: 0x{value:x}")))
+    }
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView as _};
+
+    use super::TplDecoder;
+    use crate::types::TplHeader;
+
+    #[test]
+    fn test_partial_blocks() {
+        // A 3x5 I8 image is two 8x4 blocks, which are both partially outside the image
+        let mut tpl = Vec::new();
+        tpl.extend(TplHeader::MAGIC.to_be_bytes());
+        // One image, the image table is right after the header
+        tpl.extend(1u32.to_be_bytes());
+        tpl.extend(0xCu32.to_be_bytes());
+        tpl.extend(0x14u32.to_be_bytes());
+        tpl.extend(0u32.to_be_bytes());
+        // Height, width, format (I8) and data offset
+        tpl.extend(5u16.to_be_bytes());
+        tpl.extend(3u16.to_be_bytes());
+        tpl.extend(1u32.to_be_bytes());
+        tpl.extend(0x40u32.to_be_bytes());
+        tpl.resize(0x40, 0);
+        tpl.extend([0x80; 32]);
+        tpl.extend([0x40; 32]);
+
+        let decoder = TplDecoder::new(tpl.as_slice(), &mut 0).unwrap();
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.dimensions(), (3, 5), "Dimensions are wrong");
+        assert_eq!(
+            image.get_pixel(2, 3).0,
+            [0x80, 0x80, 0x80, 0xFF],
+            "Last pixel of the first block is wrong"
+        );
+        assert_eq!(
+            image.get_pixel(0, 4).0,
+            [0x40, 0x40, 0x40, 0xFF],
+            "First pixel of the second block is wrong"
+        );
+    }
+}
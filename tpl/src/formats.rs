@@ -0,0 +1,211 @@
+//! Conversion of the GX texture formats to RGBA8
+//!
+//! The textures are stored in blocks of 32 bytes, from left to right and top to bottom. The
+//! pixels in a block are also stored from left to right and top to bottom. Every value is big
+//! endian.
+
+use dotstar_toolkit_utils::texture::expand;
+
+/// The decoded pixels of a block, the largest blocks are 8x8
+///
+/// Smaller blocks only use the first `width * height` pixels.
+pub type Block = [[u8; 4]; 64];
+
+/// Decode a block of 8x8 4-bit intensities
+pub fn i4(block: &[u8]) -> Block {
+    let mut pixels = [[0; 4]; 64];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let byte = block[i / 2];
+        let value = if i % 2 == 0 { byte >> 4 } else { byte & 0xF };
+        let intensity = value * 0x11;
+        *pixel = [intensity, intensity, intensity, 0xFF];
+    }
+    pixels
+}
+
+/// Decode a block of 8x4 8-bit intensities
+pub fn i8(block: &[u8]) -> Block {
+    let mut pixels = [[0; 4]; 64];
+    for (pixel, intensity) in pixels.iter_mut().zip(block) {
+        *pixel = [*intensity, *intensity, *intensity, 0xFF];
+    }
+    pixels
+}
+
+/// Decode a block of 8x4 4-bit alphas and intensities
+pub fn ia4(block: &[u8]) -> Block {
+    let mut pixels = [[0; 4]; 64];
+    for (pixel, byte) in pixels.iter_mut().zip(block) {
+        let alpha = (byte >> 4) * 0x11;
+        let intensity = (byte & 0xF) * 0x11;
+        *pixel = [intensity, intensity, intensity, alpha];
+    }
+    pixels
+}
+
+/// Decode a block of 4x4 8-bit alphas and intensities
+pub fn ia8(block: &[u8]) -> Block {
+    let mut pixels = [[0; 4]; 64];
+    for (pixel, value) in pixels.iter_mut().zip(block.chunks_exact(2)) {
+        let (alpha, intensity) = (value[0], value[1]);
+        *pixel = [intensity, intensity, intensity, alpha];
+    }
+    pixels
+}
+
+/// Decode a RGB565 value
+fn rgb565_pixel(value: u16) -> [u8; 4] {
+    [
+        expand(u32::from(value >> 11), 5),
+        expand(u32::from(value >> 5), 6),
+        expand(u32::from(value), 5),
+        0xFF,
+    ]
+}
+
+/// Decode a block of 4x4 RGB565 pixels
+pub fn rgb565(block: &[u8]) -> Block {
+    let mut pixels = [[0; 4]; 64];
+    for (pixel, value) in pixels.iter_mut().zip(block.chunks_exact(2)) {
+        *pixel = rgb565_pixel(u16::from_be_bytes([value[0], value[1]]));
+    }
+    pixels
+}
+
+/// Decode a RGB5A3 value
+///
+/// If the top bit is set the pixel is RGB555 without alpha, otherwise it's ARGB3444.
+fn rgb5a3_pixel(value: u16) -> [u8; 4] {
+    if value & 0x8000 == 0x8000 {
+        [
+            expand(u32::from(value >> 10), 5),
+            expand(u32::from(value >> 5), 5),
+            expand(u32::from(value), 5),
+            0xFF,
+        ]
+    } else {
+        [
+            expand(u32::from(value >> 8), 4),
+            expand(u32::from(value >> 4), 4),
+            expand(u32::from(value), 4),
+            expand(u32::from(value >> 12), 3),
+        ]
+    }
+}
+
+/// Decode a block of 4x4 RGB5A3 pixels
+pub fn rgb5a3(block: &[u8]) -> Block {
+    let mut pixels = [[0; 4]; 64];
+    for (pixel, value) in pixels.iter_mut().zip(block.chunks_exact(2)) {
+        *pixel = rgb5a3_pixel(u16::from_be_bytes([value[0], value[1]]));
+    }
+    pixels
+}
+
+/// Decode a block of 4x4 RGBA8 pixels
+///
+/// The first 32 bytes contain the alpha and red components, the last 32 bytes the green and blue
+/// components.
+pub fn rgba32(block: &[u8]) -> Block {
+    let (ar, gb) = block.split_at(32);
+    let mut pixels = [[0; 4]; 64];
+    for ((pixel, ar), gb) in pixels
+        .iter_mut()
+        .zip(ar.chunks_exact(2))
+        .zip(gb.chunks_exact(2))
+    {
+        *pixel = [ar[1], gb[0], gb[1], ar[0]];
+    }
+    pixels
+}
+
+/// Decode a block of 8x8 CMPR pixels
+///
+/// A CMPR block consists of four big endian DXT1 blocks in the order top left, top right, bottom
+/// left, bottom right.
+pub fn cmpr(block: &[u8]) -> Block {
+    let mut pixels = [[0; 4]; 64];
+    for (i, sub_block) in block.chunks_exact(8).enumerate() {
+        let offset_x = (i % 2) * 4;
+        let offset_y = (i / 2) * 4;
+        for (j, pixel) in dxt1_block(sub_block).into_iter().enumerate() {
+            let x = offset_x + j % 4;
+            let y = offset_y + j / 4;
+            pixels[y * 8 + x] = pixel;
+        }
+    }
+    pixels
+}
+
+/// Decode the 16 pixels of a big endian DXT1 block
+fn dxt1_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let color0 = u16::from_be_bytes([block[0], block[1]]);
+    let color1 = u16::from_be_bytes([block[2], block[3]]);
+    let c0 = rgb565_pixel(color0);
+    let c1 = rgb565_pixel(color1);
+    let mix = |left: u16, right: u16, divisor: u16| {
+        let mut color = [0xFF; 4];
+        for (i, component) in color.iter_mut().take(3).enumerate() {
+            let value = (u16::from(c0[i]) * left + u16::from(c1[i]) * right) / divisor;
+            *component = u8::try_from(value).unwrap_or(u8::MAX);
+        }
+        color
+    };
+    let palette = if color0 > color1 {
+        [c0, c1, mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [c0, c1, mix(1, 1, 2), [0; 4]]
+    };
+
+    let mut pixels = [[0; 4]; 16];
+    for (row, indices) in block[4..8].iter().enumerate() {
+        for column in 0..4 {
+            let index = (indices >> (6 - column * 2)) & 0b11;
+            pixels[row * 4 + column] = palette[usize::from(index)];
+        }
+    }
+    pixels
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgb5a3() {
+        assert_eq!(
+            rgb5a3_pixel(0xFC00),
+            [255, 0, 0, 255],
+            "Top bit set is opaque RGB555"
+        );
+        assert_eq!(
+            rgb5a3_pixel(0x0F00),
+            [255, 0, 0, 0],
+            "Top bit unset is ARGB3444"
+        );
+        assert_eq!(
+            rgb5a3_pixel(0x700F),
+            [0, 0, 255, 255],
+            "Alpha is the three bits after the top bit"
+        );
+    }
+
+    #[test]
+    fn test_cmpr() {
+        let mut block = [0; 32];
+        // Top left: red and blue, first pixel is index 1 and the last pixel is index 2
+        block[..8].copy_from_slice(&[0xF8, 0x00, 0x00, 0x1F, 0b0100_0000, 0, 0, 0b0000_0010]);
+        // Bottom right: color0 <= color1, so index 3 is transparent
+        block[24..].copy_from_slice(&[0x00, 0x00, 0xFF, 0xFF, 0b1100_0000, 0, 0, 0]);
+        let pixels = cmpr(&block);
+        assert_eq!(pixels[0], [0, 0, 255, 255], "Index 1 is color1");
+        assert_eq!(pixels[1], [255, 0, 0, 255], "Index 0 is color0");
+        assert_eq!(
+            pixels[27],
+            [170, 0, 85, 255],
+            "Index 2 is 2/3 color0 + 1/3 color1"
+        );
+        assert_eq!(pixels[36], [0, 0, 0, 0], "Index 3 is transparent");
+    }
+}
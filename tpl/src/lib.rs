@@ -0,0 +1,6 @@
+mod decoder;
+mod formats;
+mod types;
+
+pub use decoder::TplDecoder;
+pub use types::Format;
@@ -0,0 +1,94 @@
+use crate::decoder::DecoderError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TplHeader {
+    pub image_count: u32,
+    pub image_table_offset: u32,
+}
+
+impl TplHeader {
+    pub const MAGIC: u32 = 0x0020_AF30;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub width: u16,
+    pub height: u16,
+    pub format: Format,
+    /// Offset from the start of the TPL file
+    pub data_offset: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Format {
+    I4 = 0x0,
+    I8 = 0x1,
+    Ia4 = 0x2,
+    Ia8 = 0x3,
+    Rgb565 = 0x4,
+    Rgb5a3 = 0x5,
+    Rgba32 = 0x6,
+    C4 = 0x8,
+    C8 = 0x9,
+    C14x2 = 0xA,
+    Cmpr = 0xE,
+}
+
+impl Format {
+    /// Width and height of a block in pixels
+    #[must_use]
+    pub const fn block_dimensions(self) -> (usize, usize) {
+        match self {
+            Self::I4 | Self::C4 | Self::Cmpr => (8, 8),
+            Self::I8 | Self::Ia4 | Self::C8 => (8, 4),
+            Self::Ia8 | Self::Rgb565 | Self::Rgb5a3 | Self::Rgba32 | Self::C14x2 => (4, 4),
+        }
+    }
+
+    /// Size of a block in bytes
+    ///
+    /// RGBA32 blocks are stored as two blocks, one with the alpha and red components and one with
+    /// the green and blue components.
+    #[must_use]
+    pub const fn block_size(self) -> usize {
+        match self {
+            Self::Rgba32 => 64,
+            _ => 32,
+        }
+    }
+
+    /// Does the format need a palette
+    #[must_use]
+    pub const fn is_paletted(self) -> bool {
+        matches!(self, Self::C4 | Self::C8 | Self::C14x2)
+    }
+}
+
+impl TryFrom<u32> for Format {
+    type Error = DecoderError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0x0 => Ok(Self::I4),
+            0x1 => Ok(Self::I8),
+            0x2 => Ok(Self::Ia4),
+            0x3 => Ok(Self::Ia8),
+            0x4 => Ok(Self::Rgb565),
+            0x5 => Ok(Self::Rgb5a3),
+            0x6 => Ok(Self::Rgba32),
+            0x8 => Ok(Self::C4),
+            0x9 => Ok(Self::C8),
+            0xA => Ok(Self::C14x2),
+            0xE => Ok(Self::Cmpr),
+            _ => Err(DecoderError::UnknownTextureFormat(value)),
+        }
+    }
+}
+
+impl From<Format> for u32 {
+    #[allow(clippy::as_conversions, reason = "Format is repr(u32)")]
+    fn from(value: Format) -> Self {
+        value as Self
+    }
+}
@@ -5,9 +5,11 @@
 )]
 
 use std::{
+    borrow::Cow,
     collections::HashSet,
     fs::{create_dir_all, File},
     io::Write,
+    num::NonZeroUsize,
     path::{Path, PathBuf},
 };
 
@@ -22,7 +24,7 @@ use ubiart_toolkit::{
     ipk::{self, Bundle},
     utils::{
         errors::{ParserError, WriterError},
-        UniqueGameId,
+        PathId, UniqueGameId,
     },
 };
 
@@ -44,6 +46,12 @@ struct Cli {
     overwrite: bool,
     #[arg(long, default_value_t = false)]
     lax: bool,
+    /// After compressing, check that the bundle extracts to exactly the source files
+    #[arg(long, default_value_t = false)]
+    verify: bool,
+    /// Use n threads for compressing the files, defaults to the amount of available cpus
+    #[arg(long)]
+    threads: Option<NonZeroUsize>,
 }
 
 fn main() {
@@ -76,7 +84,21 @@ fn main() {
             temp.push_str(".ipk");
             PathBuf::from(temp)
         });
-        create_ipk(source, &destination).unwrap();
+        let threads = cli
+            .threads
+            .unwrap_or_else(|| std::thread::available_parallelism().unwrap());
+        let statistics = create_ipk(source, &destination, threads).unwrap();
+        info!(
+            "{} files, {} bytes uncompressed, {} bytes stored",
+            statistics.files, statistics.uncompressed_size, statistics.stored_size
+        );
+        info!(
+            "Deduplicated {} files, saving {} bytes",
+            statistics.deduplicated_files, statistics.saved_size
+        );
+        if cli.verify {
+            verify_ipk(source, &destination).unwrap();
+        }
     } else {
         let file = File::open(source).unwrap();
         let ipk = Bundle::deserialize_with(&file, cli.lax).unwrap();
@@ -132,24 +154,7 @@ pub fn unpack_ipk(ipk: &Bundle, destination: &Path, overwrite: bool) -> Result<(
         let filepath = &path.join(fil.path.filename());
         if overwrite || !filepath.exists() {
             let mut file = File::create(filepath)?;
-            match &fil.data {
-                ipk::Data::Uncompressed(unc) => {
-                    // Copy all the packed data into the file
-                    file.write_all(unc.data.as_ref())?;
-                }
-                ipk::Data::Compressed(data) => {
-                    let mut vec = Vec::with_capacity(data.uncompressed_size + 1);
-                    let mut decompress = flate2::Decompress::new(true);
-                    decompress
-                        .decompress_vec(
-                            data.data.as_ref(),
-                            &mut vec,
-                            flate2::FlushDecompress::Finish,
-                        )
-                        .unwrap();
-                    file.write_all(&vec)?;
-                }
-            }
+            file.write_all(&file_content(&fil.data)?)?;
         } else {
             warn!("File already exists!: {filepath:?}");
         }
@@ -157,6 +162,61 @@ pub fn unpack_ipk(ipk: &Bundle, destination: &Path, overwrite: bool) -> Result<(
     Ok(())
 }
 
+/// Get the (decompressed) content of a file in a IPK bundle
+pub fn file_content<'a>(data: &'a ipk::Data<'_>) -> Result<Cow<'a, [u8]>, ParserError> {
+    match data {
+        ipk::Data::Uncompressed(unc) => Ok(Cow::Borrowed(unc.data.as_ref())),
+        ipk::Data::Compressed(data) => {
+            let mut vec = Vec::with_capacity(data.uncompressed_size + 1);
+            let mut decompress = flate2::Decompress::new(true);
+            decompress
+                .decompress_vec(
+                    data.data.as_ref(),
+                    &mut vec,
+                    flate2::FlushDecompress::Finish,
+                )
+                .map_err(|error| ParserError::custom(format!("Decompression failed: {error}")))?;
+            Ok(Cow::Owned(vec))
+        }
+    }
+}
+
+/// Check that the IPK bundle at `destination` contains all files in `source` byte-for-byte
+pub fn verify_ipk(source: &Path, destination: &Path) -> Result<(), ParserError> {
+    let vfs = NativeFs::new(source)?;
+    let file = File::open(destination)?;
+    let ipk = Bundle::deserialize_with(&file, false)?;
+
+    let mut n: usize = 0;
+    let mut mismatches: usize = 0;
+    for path in vfs.walk_filesystem(&VirtualPathBuf::from(""))? {
+        n += 1;
+        let Some(packed_file) = ipk.files.get(&PathId::from(path)) else {
+            warn!("{path} is missing from the bundle!");
+            mismatches += 1;
+            continue;
+        };
+        let expected = vfs.open(path)?;
+        if *file_content(&packed_file.data)? != *expected {
+            warn!("{path} does not match the source file!");
+            mismatches += 1;
+        }
+    }
+    if ipk.files.len() != n {
+        warn!("Bundle contains {} files, expected {n}", ipk.files.len());
+        mismatches += 1;
+    }
+
+    if mismatches == 0 {
+        info!("Verified {n} files");
+        Ok(())
+    } else {
+        Err(ParserError::custom(format!(
+            "Verification failed: {mismatches} problems found in {n} files"
+        )))
+    }
+}
+
 pub fn check_ipk(ipk: &Bundle, filename: &Path, lax: bool) {
     info!("GamePlatform: {:#?}", ipk.game_platform);
     if ipk.version != 5 {
@@ -197,13 +257,17 @@ pub fn check_ipk(ipk: &Bundle, filename: &Path, lax: bool) {
 }
 
 /// Create a IPK bundle from all files and directories in `source`
-pub fn create_ipk(source: &Path, destination: &Path) -> Result<(), WriterError> {
+pub fn create_ipk(
+    source: &Path,
+    destination: &Path,
+    threads: NonZeroUsize,
+) -> Result<ipk::Statistics, WriterError> {
     let vfs = NativeFs::new(source)?;
     let root = VirtualPathBuf::from("");
     let file_list = vfs.walk_filesystem(&root)?;
     let files: Vec<_> = file_list.collect();
     let mut file = File::create(destination)?;
-    ipk::write(
+    let statistics = ipk::write(
         &mut file,
         &mut 0,
         ipk::Options {
@@ -211,10 +275,11 @@ pub fn create_ipk(source: &Path, destination: &Path) -> Result<(), WriterError>
             game_platform: UniqueGameId::try_from(0x1DDB_2268)?,
             unk4: 0x937D0,
             engine_version: 0x4FD39,
+            threads,
         },
         &vfs,
         &files,
     )
     .unwrap();
-    Ok(())
+    Ok(statistics)
 }
@@ -1,26 +1,41 @@
 #![allow(clippy::missing_panics_doc, reason = "Tool not a library")]
 
-use std::{fs::File, path::PathBuf};
+use std::{fs::File, io::Write, path::PathBuf};
 
 use clap::Parser;
-use dotstar_toolkit_utils::bytes::read::BinaryDeserializeExt as _;
-use ubiart_toolkit::msm::MovementSpaceMove;
+use dotstar_toolkit_utils::bytes::{read::BinaryDeserializeExt as _, read_to_vec};
+use ubiart_toolkit::msm::{self, MovementSpaceMove};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Path to the .msm file
+    /// Path to the .msm file (or the JSON file with `--from-json`)
     source: PathBuf,
-    /// Where to save the parsed (JSON) file
+    /// Where to save the parsed (JSON) file (or the rebuilt .msm file with `--from-json`)
     output: Option<PathBuf>,
     /// Don't print information about the file
     #[arg(short, long, default_value_t = false)]
     quiet: bool,
+    /// Rebuild a .msm file from a JSON file created by this tool
+    #[arg(long, default_value_t = false)]
+    from_json: bool,
 }
 
 fn main() {
     let cli = Cli::parse();
 
+    if cli.from_json {
+        let output = cli
+            .output
+            .expect("An output path is required when using --from-json");
+        let data = read_to_vec(cli.source).unwrap();
+        let msm: MovementSpaceMove = serde_json::from_slice(&data).unwrap();
+        let vec = msm::create_vec(msm).unwrap();
+        let mut file = File::create(output).unwrap();
+        file.write_all(&vec).unwrap();
+        return;
+    }
+
     let file = File::open(cli.source).unwrap();
     let msm = MovementSpaceMove::deserialize(&file).unwrap();
 
@@ -39,6 +54,8 @@ fn main() {
         println!("unk13: {}", msm.unk13);
         println!("unk14: {}", msm.unk14);
         println!("unk15: {}", msm.unk15);
+        println!("unk16: {:?}", msm.unk16);
+        println!("unk17: {:?}", msm.unk17);
         println!("Pairs: {}", msm.data.len());
 
         let x_min = msm
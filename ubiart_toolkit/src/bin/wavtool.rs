@@ -26,6 +26,9 @@ use ubiart_toolkit::cooked::wav::{
 struct Cli {
     source: PathBuf,
     output_dir: Option<PathBuf>,
+    /// Encode .wav files as Wii DSP-ADPCM instead of PCM
+    #[arg(long, default_value_t = false)]
+    dsp: bool,
 }
 
 pub fn main() {
@@ -69,7 +72,7 @@ pub fn main() {
             std::fs::rename(&output_file_path, output_file_path.with_extension("opus")).unwrap();
         }
     } else {
-        let content = encode_audio(source_file).unwrap();
+        let content = encode_audio(source_file, args.dsp).unwrap();
         let filename = args.source.file_name().unwrap();
         let output_file_path = output_dir.join(filename).with_extension("wav.ckd");
         let mut output_file = File::create(&output_file_path).unwrap();
@@ -140,12 +143,13 @@ pub fn decode_audio(reader: &File, writer: &mut File) -> Result<bool, Error> {
             )?;
             Ok(true)
         }
-        (WavPlatform::WiiU, Codec::Adpc) => {
+        (WavPlatform::Wii | WavPlatform::WiiU, Codec::Adpc) => {
             let writer = BufWriter::new(writer);
             let spec = hound::WavSpec {
                 channels: fmt.channel_count,
                 sample_rate: fmt.sample_rate,
-                bits_per_sample: fmt.bits_per_sample,
+                // The decoder always produces 16-bit samples
+                bits_per_sample: 16,
                 sample_format: SampleFormat::Int,
             };
 
@@ -165,9 +169,9 @@ pub fn decode_audio(reader: &File, writer: &mut File) -> Result<bool, Error> {
                     coefficients: dsp_left.coefficients,
                 };
                 let right_state = gc_adpcm::Dsp {
-                    hist1: dsp_left.initial_sample_history_1,
-                    hist2: dsp_left.initial_sample_history_2,
-                    coefficients: dsp_left.coefficients,
+                    hist1: dsp_right.initial_sample_history_1,
+                    hist2: dsp_right.initial_sample_history_2,
+                    coefficients: dsp_right.coefficients,
                 };
                 let total_frames = dsp_left.sample_count.div_ceil(gc_adpcm::SAMPLES_PER_FRAME) * 2;
                 assert_eq!(
@@ -259,7 +263,7 @@ pub fn decode_audio(reader: &File, writer: &mut File) -> Result<bool, Error> {
                 writer.finalize()?;
                 Ok(false)
             } else {
-                Err(anyhow!("Unexpected WiiU/ADPC configuration: {wav:?}"))
+                Err(anyhow!("Unexpected Wii(U)/ADPC configuration: {wav:?}"))
             }
         }
         _ => Err(anyhow!(
@@ -271,7 +275,9 @@ pub fn decode_audio(reader: &File, writer: &mut File) -> Result<bool, Error> {
 }
 
 /// Encode a JD audio file
-pub fn encode_audio(file: File) -> Result<Vec<u8>, Error> {
+///
+/// If `dsp` is true, .wav files are encoded as DSP-ADPCM for the Wii
+pub fn encode_audio(file: File, dsp: bool) -> Result<Vec<u8>, Error> {
     let magic = file.read_at::<[u8; 4]>(&mut 0)?;
     match &magic {
         b"OggS" => {
@@ -303,19 +309,31 @@ pub fn encode_audio(file: File) -> Result<Vec<u8>, Error> {
             test_eq!(spec.sample_format, SampleFormat::Int)
                 .and(test_eq!(spec.bits_per_sample, 16))?;
 
-            let fmt = Fmt {
-                unk1: 1,
-                channel_count: spec.channels,
-                sample_rate: spec.sample_rate,
-                total_samples_hz: 192_000,
-                block_align: 4,
-                bits_per_sample: 16,
-                unk3: None,
-            };
-
             let samples = decoder.into_samples().collect::<Result<Vec<_>, _>>()?;
 
-            wav::Writer::create_pcm(&mut vec, &mut 0, fmt, &samples, true)?;
+            if dsp {
+                let fmt = Fmt {
+                    unk1: 2,
+                    channel_count: spec.channels,
+                    sample_rate: spec.sample_rate,
+                    total_samples_hz: spec.sample_rate * 2 * u32::from(spec.channels),
+                    block_align: 2 * spec.channels,
+                    bits_per_sample: 16,
+                    unk3: None,
+                };
+                wav::Writer::create_dsp(&mut vec, &mut 0, fmt, &samples, true)?;
+            } else {
+                let fmt = Fmt {
+                    unk1: 1,
+                    channel_count: spec.channels,
+                    sample_rate: spec.sample_rate,
+                    total_samples_hz: 192_000,
+                    block_align: 4,
+                    bits_per_sample: 16,
+                    unk3: None,
+                };
+                wav::Writer::create_pcm(&mut vec, &mut 0, fmt, &samples, true)?;
+            }
 
             Ok(vec)
         }
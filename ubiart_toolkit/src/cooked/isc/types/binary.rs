@@ -19,10 +19,11 @@ use crate::{
         GFXPrimitiveParam, MapSceneConfig, MasterTape, Material, MaterialGraphicComponent,
         MaterialParams, MusicTrackComponent, PleoComponent, PleoTextureGraphicComponent,
         PrimitiveParameters, Scene, SceneConfigs, SongDatabase, SongDatabaseSceneConfig, SongDesc,
-        SubSceneActor, TapeCase, TextureSet, WrappedActor, WrappedActors, WrappedComponent,
-        WrappedJdSceneConfig, WrappedMapSceneConfig, WrappedMaterialGraphicComponent,
-        WrappedPleoComponent, WrappedPleoTextureGraphicComponent, WrappedScene,
-        WrappedSceneConfigs, WrappedSongDatabaseSceneConfig, WrappedSubSceneActor,
+        SubSceneActor, TapeCase, TextureSet, TransitionSceneConfig, UIBannerSceneConfig,
+        WrappedActor, WrappedActors, WrappedComponent, WrappedJdSceneConfig, WrappedMapSceneConfig,
+        WrappedMaterialGraphicComponent, WrappedPleoComponent, WrappedPleoTextureGraphicComponent,
+        WrappedScene, WrappedSceneConfigs, WrappedSongDatabaseSceneConfig, WrappedSubSceneActor,
+        WrappedTransitionSceneConfig, WrappedUIBannerSceneConfig,
     },
     utils::{Game, InternedString, SplitPath, UniqueGameId},
 };
@@ -149,8 +150,8 @@ impl<'de> BinaryDeserialize<'de> for Actor<'de> {
         test_any!(xflipped, 0..=1)?;
         let userfriendly = reader.read_len_string_at::<u32be>(position)?;
         let pos2d = reader.read_at::<(f32be, f32be)>(position)?;
-        let unk3 = reader.read_at::<f32be>(position)?;
-        test_any!(unk3, 0.0..=6.283_185_5, "Position: {position}")?;
+        let angle = reader.read_at::<f32be>(position)?;
+        test_any!(angle, 0.0..=6.283_185_5, "Position: {position}")?;
         let unk4 = reader.read_at::<u32be>(position)?;
         test_eq!(unk4, 0)?;
         let unk5 = reader.read_at::<u32be>(position)?;
@@ -176,7 +177,7 @@ impl<'de> BinaryDeserialize<'de> for Actor<'de> {
             defaultenable: None,
             is_enabled: None,
             pos2d,
-            angle: 0.0,
+            angle,
             instancedatafile: HipStr::default(),
             lua: HipStr::from(lua.to_string()),
             components,
@@ -244,10 +245,10 @@ impl<'de> BinaryDeserialize<'de> for SubSceneActor<'de> {
             instancedatafile: HipStr::default(),
             lua: HipStr::from(lua.to_string()),
             relativepath: HipStr::from(relative_path.to_string()),
-            embed_scene: false,
-            is_single_piece: false,
-            zforced: false,
-            direct_picking: false,
+            embed_scene: embed_scene == 1,
+            is_single_piece: is_single_piece == 1,
+            zforced: zforced == 1,
+            direct_picking: direct_picking == 1,
             ignore_save: false,
             enums: vec![Enum {
                 name: HipStr::borrowed("viewType"),
@@ -347,7 +348,7 @@ impl<'de> BinaryDeserialize<'de> for WrappedComponent<'de> {
                 },
             )),
             "TapeCase_Component" => Ok(WrappedComponent::TapeCase(TapeCase { wrapped: () })),
-            _ => todo!("{name}"),
+            _ => Err(ReadError::custom(format!("Unknown component: {name}"))),
         }
     }
 }
@@ -400,10 +401,9 @@ impl<'de> BinaryDeserialize<'de> for MaterialGraphicComponent<'de> {
         test_eq!(unk4, 0)?;
         let anchor = reader.read_at::<i32be>(position)?;
         test_any!(anchor, 0..=9)?;
-        let unk5 = reader.read_at::<f32be>(position)?;
-        test_any!(unk5, -0.12..=0.0, "Position: {position}")?;
-        let unk6 = reader.read_at::<f32be>(position)?;
-        test_any!(unk6, -1.0..=1.2)?;
+        let custom_anchor = reader.read_at::<(f32be, f32be)>(position)?;
+        test_any!(custom_anchor.0, -0.12..=0.0, "Position: {position}")?;
+        test_any!(custom_anchor.1, -1.0..=1.2)?;
         let material = reader.read_at_with::<GFXMaterialSerializable>(position, ctx)?;
         let sinus_amplitude = reader.read_at::<(f32be, f32be, f32be)>(position)?;
         let sinus_speed = reader.read_at::<f32be>(position)?;
@@ -432,7 +432,7 @@ impl<'de> BinaryDeserialize<'de> for MaterialGraphicComponent<'de> {
             disable_light: false,
             disable_shadow,
             atlas_index: 0,
-            custom_anchor: (0.0, 0.0),
+            custom_anchor,
             sinus_amplitude,
             sinus_speed,
             angle_x,
@@ -454,25 +454,22 @@ impl<'de> BinaryDeserialize<'de> for MapSceneConfig<'de> {
         position: &mut u64,
         _ctx: Self::Ctx,
     ) -> Result<Self::Output, ReadError> {
-        let unk1 = reader.read_at::<u32be>(position)?;
-        test_eq!(unk1, 0)?;
+        let name = reader.read_len_string_at::<u32be>(position)?;
         let pause_level = reader.read_at::<i32be>(position)?;
         test_eq!(pause_level, 6)?;
         let r#type = reader.read_at::<i32be>(position)?;
         test_eq!(r#type, 1)?;
         let musicscore = reader.read_at::<i32be>(position)?;
         test_eq!(musicscore, 2)?;
-        let unk5 = reader.read_at::<u32be>(position)?;
-        test_eq!(unk5, 0)?;
-        let unk6 = reader.read_at::<u32be>(position)?;
-        test_eq!(unk6, 0)?;
+        let sound_context = reader.read_len_string_at::<u32be>(position)?;
+        let hud = reader.read_at::<u32be>(position)?;
         let unk7 = reader.read_at::<u32be>(position)?;
         test_eq!(unk7, 0)?;
 
         Ok(Self {
-            name: HipStr::default(),
-            sound_context: HipStr::default(),
-            hud: 0,
+            name,
+            sound_context,
+            hud,
             phone_title_loc_id: None,
             phone_image: None,
             enums: vec![
@@ -611,13 +608,102 @@ impl<'de> BinaryDeserialize<'de> for WrappedJdSceneConfig<'de> {
                     wrapped: reader.read_at::<SongDatabaseSceneConfig>(position)?,
                 },
             )),
-            "JD_TransitionSceneConfig" => todo!("JD_TransitionSceneConfig"),
-            "JD_UIBannerSceneConfig" => todo!("JD_UIBannerSceneConfig"),
+            "JD_TransitionSceneConfig" => Ok(WrappedJdSceneConfig::Transition(
+                WrappedTransitionSceneConfig {
+                    wrapped: reader.read_at::<TransitionSceneConfig>(position)?,
+                },
+            )),
+            "JD_UIBannerSceneConfig" => {
+                Ok(WrappedJdSceneConfig::UIBanner(WrappedUIBannerSceneConfig {
+                    wrapped: reader.read_at::<UIBannerSceneConfig>(position)?,
+                }))
+            }
             _ => Err(ReadError::custom(format!("Unknown SceneConfig: {name}"))),
         }
     }
 }
 
+// The layouts of the transition and banner scene configs follow `JD_MapSceneConfig`:
+// the name and pause level of the base scene config, followed by the fields in the order
+// of the XML representation. The layout of param bindings is not known, so a scene config
+// with param bindings is rejected instead of guessing where the next field starts.
+
+impl<'de> BinaryDeserialize<'de> for TransitionSceneConfig<'de> {
+    type Ctx = ();
+    type Output = Self;
+
+    fn deserialize_at_with(
+        reader: &'de (impl ReadAtExt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<Self::Output, ReadError> {
+        let name = reader.read_len_string_at::<u32be>(position)?;
+        let pause_level = reader.read_at::<i32be>(position)?;
+        read_no_param_bindings(reader, position)?;
+
+        Ok(Self {
+            name,
+            param_bindings: Vec::new(),
+            enums: vec![Enum {
+                name: HipStr::borrowed("Pause_Level"),
+                selection: pause_level,
+            }],
+        })
+    }
+}
+
+impl<'de> BinaryDeserialize<'de> for UIBannerSceneConfig<'de> {
+    type Ctx = ();
+    type Output = Self;
+
+    fn deserialize_at_with(
+        reader: &'de (impl ReadAtExt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<Self::Output, ReadError> {
+        let name = reader.read_len_string_at::<u32be>(position)?;
+        let pause_level = reader.read_at::<i32be>(position)?;
+        let theme = reader.read_len_string_at::<u32be>(position)?;
+        let typed = reader.read_len_string_at::<u32be>(position)?;
+        let context = reader.read_len_string_at::<u32be>(position)?;
+        let enter_chain = reader.read_len_string_at::<u32be>(position)?;
+        let active_chain = reader.read_len_string_at::<u32be>(position)?;
+        let leave_chain = reader.read_len_string_at::<u32be>(position)?;
+        read_no_param_bindings(reader, position)?;
+
+        Ok(Self {
+            name,
+            theme,
+            typed,
+            context,
+            enter_chain: Some(enter_chain).filter(|chain| !chain.is_empty()),
+            active_chain: Some(active_chain).filter(|chain| !chain.is_empty()),
+            leave_chain: Some(leave_chain).filter(|chain| !chain.is_empty()),
+            param_bindings: Vec::new(),
+            enums: vec![Enum {
+                name: HipStr::borrowed("Pause_Level"),
+                selection: pause_level,
+            }],
+        })
+    }
+}
+
+/// Read the (empty) list of param bindings of a scene config
+///
+/// The binary layout of param bindings is unknown, so only empty lists are supported
+fn read_no_param_bindings(
+    reader: &(impl ReadAtExt + ?Sized),
+    position: &mut u64,
+) -> Result<(), ReadError> {
+    let len = reader.read_at::<u32be>(position)?;
+    if len != 0 {
+        return Err(ReadError::custom(format!(
+            "Param bindings are not supported in binary scenes, found {len}"
+        )));
+    }
+    Ok(())
+}
+
 impl<'de> BinaryDeserialize<'de> for GFXPrimitiveParam<'de> {
     type Ctx = UniqueGameId;
     type Output = Self;
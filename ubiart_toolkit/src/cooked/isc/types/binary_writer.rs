@@ -0,0 +1,721 @@
+//! Contains the writer implementation for binary scenes, the inverse of `binary.rs`
+
+use dotstar_toolkit_utils::bytes::{
+    primitives::{f32be, i32be, u32be},
+    write::{BinarySerialize, WriteAt, WriteError},
+};
+
+use super::Root;
+use crate::{
+    cooked::isc::{
+        Actor, CoverflowSkuSongs, CoverflowSong, Enum, GFXMaterialSerializable,
+        GFXMaterialTexturePathSet, GFXPrimitiveParam, MapSceneConfig, MaterialGraphicComponent,
+        PleoComponent, PleoTextureGraphicComponent, Scene, SceneConfigs, SongDatabaseSceneConfig,
+        SubSceneActor, TransitionSceneConfig, UIBannerSceneConfig, WrappedActors, WrappedComponent,
+        WrappedJdSceneConfig,
+    },
+    utils::{Game, InternedString, SplitPath, UniqueGameId},
+};
+
+impl BinarySerialize for Root<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        if ugi.game != Game::JustDance2015 {
+            return Err(WriteError::custom(format!(
+                "Binary scenes are not used by {ugi}"
+            )));
+        }
+        writer.write_at_with_ctx::<Scene>(position, input.scene, ugi)
+    }
+}
+
+impl BinarySerialize for Scene<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<u32be>(position, 1)?; // unk1
+        writer.write_at::<u32be>(position, input.engine_version)?;
+        writer.write_at::<u32be>(position, 0)?; // unk3
+        writer.write_at::<u32be>(position, 0)?; // unk4
+        writer.write_at::<u32be>(position, 0)?; // unk5
+        writer.write_len_type_at_with_ctx::<u32be, WrappedActors>(
+            position,
+            input.actors.into_iter(),
+            ugi,
+        )?;
+        writer.write_at::<u32be>(position, 0)?; // unk6
+        writer.write_at::<u32be>(position, 0)?; // unk7
+        writer.write_at_with_ctx::<SceneConfigs>(position, input.scene_configs.wrapped, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for WrappedActors<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        match input {
+            WrappedActors::Actor(actor) => {
+                writer.write_at::<InternedString>(position, "Actor")?;
+                writer.write_at_with_ctx::<Actor>(position, *actor.actor, ugi)
+            }
+            WrappedActors::SubSceneActor(actor) => {
+                writer.write_at::<InternedString>(position, "SubSceneActor")?;
+                writer.write_at_with_ctx::<SubSceneActor>(position, *actor.sub_scene_actor, ugi)
+            }
+        }
+    }
+}
+
+impl BinarySerialize for Actor<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<f32be>(position, input.relativez)?;
+        writer.write_at::<f32be>(position, input.scale.0)?;
+        writer.write_at::<f32be>(position, input.scale.1)?;
+        writer.write_at::<u32be>(position, u32::from(input.x_flipped))?;
+        writer.write_len_string_at::<u32be>(position, &input.userfriendly)?;
+        writer.write_at::<f32be>(position, input.pos2d.0)?;
+        writer.write_at::<f32be>(position, input.pos2d.1)?;
+        writer.write_at::<f32be>(position, input.angle)?;
+        writer.write_at::<u32be>(position, 0)?; // unk4
+        writer.write_at::<u32be>(position, 0)?; // unk5
+        writer.write_at::<u32be>(position, 0xFFFF_FFFF)?; // unk6
+        writer.write_at::<u32be>(position, 0)?; // unk7
+        write_split_path(writer, position, &input.lua)?;
+        writer.write_at::<u32be>(position, 0)?; // unk8, no ActorUnknown2 follows
+        writer.write_len_type_at_with_ctx::<u32be, WrappedComponent>(
+            position,
+            input.components.into_iter(),
+            ugi,
+        )?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for SubSceneActor<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        let view_type = enum_selection(&input.enums, "viewType", 3);
+        writer.write_at::<f32be>(position, input.relativez)?;
+        writer.write_at::<f32be>(position, input.scale.0)?;
+        writer.write_at::<f32be>(position, input.scale.1)?;
+        writer.write_at::<u32be>(position, u32::from(input.x_flipped))?;
+        writer.write_len_string_at::<u32be>(position, &input.userfriendly)?;
+        writer.write_at::<f32be>(position, input.pos2d.0)?;
+        writer.write_at::<f32be>(position, input.pos2d.1)?;
+        writer.write_at::<u32be>(position, 0)?; // unk3
+        writer.write_at::<u32be>(position, 0)?; // unk4
+        writer.write_at::<u32be>(position, 0)?; // unk5
+        writer.write_at::<u32be>(position, 0xFFFF_FFFF)?; // unk6
+        writer.write_at::<u32be>(position, 0)?; // unk7
+        write_split_path(writer, position, &input.lua)?;
+        writer.write_at::<u32be>(position, 0)?; // unk8
+        writer.write_len_type_at_with_ctx::<u32be, WrappedComponent>(
+            position,
+            input.components.into_iter(),
+            ugi,
+        )?;
+        write_split_path(writer, position, &input.relativepath)?;
+        writer.write_at::<u32be>(position, u32::from(input.embed_scene))?;
+        writer.write_at::<u32be>(position, u32::from(input.is_single_piece))?;
+        writer.write_at::<u32be>(position, u32::from(input.zforced))?;
+        writer.write_at::<u32be>(position, u32::from(input.direct_picking))?;
+        writer.write_at::<i32be>(position, view_type)?;
+        writer.write_at_with_ctx::<Scene>(position, input.wrapped_scene.wrapped, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for WrappedComponent<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        match input {
+            WrappedComponent::Autodance(_) => {
+                writer.write_at::<InternedString>(position, "JD_AutodanceComponent")
+            }
+            WrappedComponent::BlockFlowComponent(_) => {
+                writer.write_at::<InternedString>(position, "JD_BlockFlowComponent")
+            }
+            WrappedComponent::SongDatabase(_) => {
+                writer.write_at::<InternedString>(position, "JD_SongDatabaseComponent")
+            }
+            WrappedComponent::SongDesc(_) => {
+                writer.write_at::<InternedString>(position, "JD_SongDescComponent")
+            }
+            WrappedComponent::MasterTape(_) => {
+                writer.write_at::<InternedString>(position, "MasterTape")
+            }
+            WrappedComponent::MaterialGraphic(component) => {
+                writer.write_at::<InternedString>(position, "MaterialGraphicComponent")?;
+                writer.write_at_with_ctx::<MaterialGraphicComponent>(
+                    position,
+                    component.wrapped,
+                    ugi,
+                )
+            }
+            WrappedComponent::MusicTrack(_) => {
+                writer.write_at::<InternedString>(position, "MusicTrackComponent")
+            }
+            WrappedComponent::Pleo(component) => {
+                writer.write_at::<InternedString>(position, "PleoComponent")?;
+                writer.write_at_with_ctx::<PleoComponent>(position, component.wrapped, ugi)
+            }
+            WrappedComponent::PleoTextureGraphic(component) => {
+                writer.write_at::<InternedString>(position, "PleoTextureGraphicComponent")?;
+                writer.write_at_with_ctx::<PleoTextureGraphicComponent>(
+                    position,
+                    component.wrapped,
+                    ugi,
+                )
+            }
+            WrappedComponent::TapeCase(_) => {
+                writer.write_at::<InternedString>(position, "TapeCase_Component")
+            }
+            _ => Err(WriteError::custom(format!(
+                "Component is not supported in binary scenes: {input:?}"
+            ))),
+        }
+    }
+}
+
+impl BinarySerialize for PleoComponent<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        write_split_path(writer, position, &input.video)?;
+        if ugi.game > Game::JustDance2015 {
+            write_split_path(writer, position, &input.dash_mpd)?;
+        }
+        writer.write_len_string_at::<u32be>(position, &input.channel_id)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for MaterialGraphicComponent<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        let anchor = enum_selection(&input.enums, "anchor", 1);
+        let old_anchor = enum_selection(&input.enums, "oldAnchor", anchor);
+        writer.write_at_with_ctx::<GFXPrimitiveParam>(
+            position,
+            input.primitive_parameters.gfx_primitive_param,
+            ugi,
+        )?;
+        writer.write_at::<u32be>(position, input.color_computer_tag_id)?;
+        writer.write_at::<u32be>(position, u32::from(input.render_in_target))?;
+        writer.write_at::<u32be>(position, u32::from(input.disable_light))?;
+        writer.write_at::<u32be>(position, input.disable_shadow)?;
+        writer.write_at::<u32be>(position, input.atlas_index)?;
+        writer.write_at::<i32be>(position, anchor)?;
+        writer.write_at::<f32be>(position, input.custom_anchor.0)?;
+        writer.write_at::<f32be>(position, input.custom_anchor.1)?;
+        writer.write_at_with_ctx::<GFXMaterialSerializable>(
+            position,
+            input.material.gfx_material_serializable,
+            ugi,
+        )?;
+        writer.write_at::<f32be>(position, input.sinus_amplitude.0)?;
+        writer.write_at::<f32be>(position, input.sinus_amplitude.1)?;
+        writer.write_at::<f32be>(position, input.sinus_amplitude.2)?;
+        writer.write_at::<f32be>(position, input.sinus_speed)?;
+        writer.write_at::<f32be>(position, input.angle_y)?;
+        writer.write_at::<f32be>(position, input.angle_x)?;
+        writer.write_at::<i32be>(position, old_anchor)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for PleoTextureGraphicComponent<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        let core = MaterialGraphicComponent {
+            primitive_parameters: input.primitive_parameters,
+            color_computer_tag_id: input.color_computer_tag_id,
+            render_in_target: input.render_in_target,
+            disable_light: input.disable_light,
+            disable_shadow: input.disable_shadow,
+            atlas_index: input.atlas_index,
+            custom_anchor: input.custom_anchor,
+            sinus_amplitude: input.sinus_amplitude,
+            sinus_speed: input.sinus_speed,
+            angle_x: input.angle_x,
+            angle_y: input.angle_y,
+            enums: input.enums,
+            material: input.material,
+        };
+        writer.write_at_with_ctx::<MaterialGraphicComponent>(position, core, ugi)?;
+        writer.write_len_string_at::<u32be>(position, &input.channel_id)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for SceneConfigs<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_len_type_at_with_ctx::<u32be, WrappedJdSceneConfig>(
+            position,
+            input.jd_scene_config.into_iter(),
+            ugi,
+        )?;
+        writer.write_at::<u32be>(position, input.active_scene_config)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for WrappedJdSceneConfig<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        match input {
+            WrappedJdSceneConfig::Map(config) => {
+                writer.write_at::<InternedString>(position, "JD_MapSceneConfig")?;
+                writer.write_at_with_ctx::<MapSceneConfig>(position, config.wrapped, ugi)
+            }
+            WrappedJdSceneConfig::SongDatabase(config) => {
+                writer.write_at::<InternedString>(position, "JD_SongDatabaseSceneConfig")?;
+                writer.write_at::<SongDatabaseSceneConfig>(position, config.wrapped)
+            }
+            WrappedJdSceneConfig::Transition(config) => {
+                writer.write_at::<InternedString>(position, "JD_TransitionSceneConfig")?;
+                writer.write_at::<TransitionSceneConfig>(position, config.wrapped)
+            }
+            WrappedJdSceneConfig::UIBanner(config) => {
+                writer.write_at::<InternedString>(position, "JD_UIBannerSceneConfig")?;
+                writer.write_at::<UIBannerSceneConfig>(position, config.wrapped)
+            }
+        }
+    }
+}
+
+impl BinarySerialize for MapSceneConfig<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_len_string_at::<u32be>(position, &input.name)?;
+        writer.write_at::<i32be>(position, enum_selection(&input.enums, "Pause_Level", 6))?;
+        writer.write_at::<i32be>(position, enum_selection(&input.enums, "type", 1))?;
+        writer.write_at::<i32be>(position, enum_selection(&input.enums, "musicscore", 2))?;
+        writer.write_len_string_at::<u32be>(position, &input.sound_context)?;
+        writer.write_at::<u32be>(position, input.hud)?;
+        writer.write_at::<u32be>(position, 0)?; // unk7
+        Ok(())
+    }
+}
+
+impl BinarySerialize for TransitionSceneConfig<'_> {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_len_string_at::<u32be>(position, &input.name)?;
+        writer.write_at::<i32be>(position, enum_selection(&input.enums, "Pause_Level", 6))?;
+        write_no_param_bindings(writer, position, input.param_bindings.len())?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for UIBannerSceneConfig<'_> {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_len_string_at::<u32be>(position, &input.name)?;
+        writer.write_at::<i32be>(position, enum_selection(&input.enums, "Pause_Level", 6))?;
+        writer.write_len_string_at::<u32be>(position, &input.theme)?;
+        writer.write_len_string_at::<u32be>(position, &input.typed)?;
+        writer.write_len_string_at::<u32be>(position, &input.context)?;
+        for chain in [&input.enter_chain, &input.active_chain, &input.leave_chain] {
+            writer.write_len_string_at::<u32be>(position, chain.as_deref().unwrap_or_default())?;
+        }
+        write_no_param_bindings(writer, position, input.param_bindings.len())?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for SongDatabaseSceneConfig<'_> {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_len_string_at::<u32be>(position, &input.sku)?;
+        writer.write_len_string_at::<u32be>(position, &input.territory)?;
+        write_split_path(writer, position, &input.rating_ui)?;
+        writer.write_len_type_at::<u32be, CoverflowSong>(
+            position,
+            input
+                .coverflow_sku_songs
+                .into_iter()
+                .map(|CoverflowSkuSongs { coverflow_song }| coverflow_song),
+        )?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for CoverflowSong<'_> {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_len_string_at::<u32be>(position, &input.name)?;
+        write_split_path(writer, position, &input.cover_path)?;
+        writer.write_at::<u32be>(position, 0)?; // unk1
+        Ok(())
+    }
+}
+
+impl BinarySerialize for GFXPrimitiveParam<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        let (red, green, blue, alpha) = input.color_factor.color;
+        writer.write_at::<f32be>(position, red)?;
+        writer.write_at::<f32be>(position, green)?;
+        writer.write_at::<f32be>(position, blue)?;
+        writer.write_at::<f32be>(position, alpha)?;
+        writer.write_at::<i32be>(position, enum_selection(&input.enums, "gfxOccludeInfo", 0))?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for GFXMaterialSerializable<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<GFXMaterialTexturePathSet>(
+            position,
+            input.texture_set.gfx_material_texture_path_set,
+        )?;
+        writer.write_at::<u32be>(position, input.atl_channel)?;
+        write_split_path(writer, position, &input.shader_path)?;
+        writer.write_at::<f32be>(
+            position,
+            input
+                .material_params
+                .gfx_material_serializable_param
+                .reflector_factor,
+        )?;
+        writer.write_at::<u32be>(position, input.stencil_test.unwrap_or_default())?;
+        writer.write_at::<u32be>(position, input.alpha_test)?;
+        writer.write_at::<u32be>(position, input.alpha_ref)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for GFXMaterialTexturePathSet<'_> {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        for path in [
+            &input.diffuse,
+            &input.back_light,
+            &input.normal,
+            &input.separate_alpha,
+            &input.diffuse_2,
+            &input.back_light_2,
+            &input.anim_impostor,
+            &input.diffuse_3,
+            &input.diffuse_4,
+        ] {
+            write_split_path(writer, position, path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the selection of the enum called `name`, or `default` if there is no such enum
+fn enum_selection(enums: &[Enum<'_>], name: &str, default: i32) -> i32 {
+    enums
+        .iter()
+        .find(|e| e.name == name)
+        .map_or(default, |e| e.selection)
+}
+
+/// Write `path` as a `SplitPath`
+fn write_split_path(
+    writer: &mut (impl WriteAt + ?Sized),
+    position: &mut u64,
+    path: &str,
+) -> Result<(), WriteError> {
+    let split_path = SplitPath::try_from(path)
+        .map_err(|error| WriteError::custom(format!("Invalid path {path}: {error}")))?;
+    writer.write_at::<SplitPath>(position, split_path)
+}
+
+/// Write the (empty) list of param bindings of a scene config
+///
+/// The binary layout of param bindings is unknown, so only empty lists are supported
+fn write_no_param_bindings(
+    writer: &mut (impl WriteAt + ?Sized),
+    position: &mut u64,
+    len: usize,
+) -> Result<(), WriteError> {
+    if len != 0 {
+        return Err(WriteError::custom(format!(
+            "Param bindings are not supported in binary scenes, found {len}"
+        )));
+    }
+    writer.write_at::<u32be>(position, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::bytes::read::BinaryDeserialize;
+    use hipstr::HipStr;
+
+    use super::*;
+    use crate::cooked::isc::{
+        create_vec_binary, MasterTape, SongDesc, WrappedActor, WrappedMapSceneConfig,
+        WrappedSceneConfigs, WrappedTransitionSceneConfig, WrappedUIBannerSceneConfig,
+    };
+
+    fn scene_with_configs(jd_scene_config: Vec<WrappedJdSceneConfig<'static>>) -> Root<'static> {
+        let actor = Actor {
+            relativez: 0.5,
+            scale: (1.0, 2.0),
+            x_flipped: true,
+            userfriendly: HipStr::borrowed("coach"),
+            marker: None,
+            defaultenable: None,
+            is_enabled: None,
+            pos2d: (3.0, -4.0),
+            angle: 1.5,
+            instancedatafile: HipStr::new(),
+            lua: HipStr::borrowed("world/maps/test/test.tpl"),
+            components: vec![
+                WrappedComponent::SongDesc(SongDesc { wrapped: () }),
+                WrappedComponent::MasterTape(MasterTape { wrapped: () }),
+            ],
+            parent_bind: None,
+            markers: Vec::new(),
+        };
+        Root {
+            scene: Scene {
+                engine_version: 0x0002_6450,
+                actors: vec![WrappedActors::Actor(WrappedActor {
+                    actor: Box::new(actor),
+                })],
+                scene_configs: WrappedSceneConfigs {
+                    wrapped: SceneConfigs {
+                        active_scene_config: 0,
+                        jd_scene_config,
+                    },
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let root = scene_with_configs(vec![
+            WrappedJdSceneConfig::Map(WrappedMapSceneConfig {
+                wrapped: MapSceneConfig {
+                    name: HipStr::new(),
+                    sound_context: HipStr::new(),
+                    hud: 0,
+                    phone_title_loc_id: None,
+                    phone_image: None,
+                    enums: Vec::new(),
+                },
+            }),
+            WrappedJdSceneConfig::Transition(WrappedTransitionSceneConfig {
+                wrapped: TransitionSceneConfig {
+                    name: HipStr::borrowed("transition"),
+                    param_bindings: Vec::new(),
+                    enums: Vec::new(),
+                },
+            }),
+            WrappedJdSceneConfig::UIBanner(WrappedUIBannerSceneConfig {
+                wrapped: UIBannerSceneConfig {
+                    name: HipStr::borrowed("banner"),
+                    theme: HipStr::borrowed("theme"),
+                    typed: HipStr::borrowed("type"),
+                    context: HipStr::borrowed("context"),
+                    enter_chain: Some(HipStr::borrowed("enter")),
+                    active_chain: None,
+                    leave_chain: None,
+                    param_bindings: Vec::new(),
+                    enums: Vec::new(),
+                },
+            }),
+        ]);
+        let data = create_vec_binary(root, UniqueGameId::WIIU2015).unwrap();
+        let parsed = Root::deserialize_with(&data, UniqueGameId::WIIU2015).unwrap();
+        let actor = parsed.scene.actors[0].actor().unwrap();
+        assert_eq!(actor.angle, 1.5, "Angle should survive the roundtrip");
+        assert_eq!(actor.lua, "world/maps/test/test.tpl", "Lua path changed");
+        let configs = &parsed.scene.scene_configs.wrapped.jd_scene_config;
+        assert_eq!(configs.len(), 3, "Scene configs went missing");
+        let WrappedJdSceneConfig::UIBanner(banner) = &configs[2] else {
+            panic!("Expected a UIBanner scene config");
+        };
+        assert_eq!(
+            banner.wrapped.enter_chain.as_deref(),
+            Some("enter"),
+            "Wrong chain"
+        );
+        assert_eq!(
+            banner.wrapped.active_chain, None,
+            "Empty chain should be None"
+        );
+        let data_again = create_vec_binary(parsed, UniqueGameId::WIIU2015).unwrap();
+        assert_eq!(
+            data, data_again,
+            "Writing the parsed scene should be lossless"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_game() {
+        let root = scene_with_configs(Vec::new());
+        assert!(
+            create_vec_binary(root, UniqueGameId::NX2022).is_err(),
+            "JD2022 does not use binary scenes"
+        );
+    }
+
+    #[test]
+    fn test_param_bindings_rejected() {
+        let root = scene_with_configs(vec![WrappedJdSceneConfig::Transition(
+            WrappedTransitionSceneConfig {
+                wrapped: TransitionSceneConfig {
+                    name: HipStr::borrowed("transition"),
+                    param_bindings: Vec::new(),
+                    enums: Vec::new(),
+                },
+            },
+        )]);
+        let mut data = create_vec_binary(root, UniqueGameId::WIIU2015).unwrap();
+        assert!(
+            Root::deserialize_with(&data, UniqueGameId::WIIU2015).is_ok(),
+            "Empty param bindings should be supported"
+        );
+
+        // The param binding count follows the name and the pause level
+        let name = data.windows(10).position(|w| w == b"transition").unwrap();
+        let count = name + 10 + 4;
+        data[count..count + 4].copy_from_slice(&1u32.to_be_bytes());
+        assert!(
+            Root::deserialize_with(&data, UniqueGameId::WIIU2015).is_err(),
+            "Param bindings should be rejected instead of guessed"
+        );
+    }
+}
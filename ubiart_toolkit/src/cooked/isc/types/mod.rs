@@ -8,6 +8,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use ubiart_toolkit_shared_types::Color;
 
 mod binary;
+mod binary_writer;
 pub mod property_patcher;
 
 use property_patcher::WrappedPropertyPatcher;
@@ -1,9 +1,11 @@
 use std::io::Write;
 
+use dotstar_toolkit_utils::bytes::write::{WriteAt, WriteError};
 use serde::Serialize;
 use ubiart_toolkit_shared_types::errors::WriterError;
 
 use super::Root;
+use crate::utils::UniqueGameId;
 
 /// Write the `Root` to the writer
 pub fn create<W: Write>(mut src: W, root: &Root) -> Result<(), WriterError> {
@@ -40,3 +42,11 @@ pub fn create_vec_with_capacity_hint(root: &Root, capacity: usize) -> Result<Vec
     buf.shrink_to_fit();
     Ok(buf.into_bytes())
 }
+
+/// Create a `Vec` with the binary representation of `root`, as used by Just Dance 2015
+pub fn create_vec_binary(root: Root<'_>, ugi: UniqueGameId) -> Result<Vec<u8>, WriteError> {
+    let mut vec = Vec::with_capacity(0x400);
+    vec.write_at_with_ctx::<Root>(&mut 0, root, ugi)?;
+    vec.shrink_to_fit();
+    Ok(vec)
+}
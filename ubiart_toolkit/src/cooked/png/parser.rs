@@ -8,6 +8,7 @@ use dotstar_toolkit_utils::bytes::{
 use gtx::GtxDecoder;
 use image::DynamicImage;
 use test_eq::{test_any, test_eq};
+use tpl::TplDecoder;
 use xtx::XtxDecoder;
 
 use super::Png;
@@ -76,6 +77,13 @@ impl BinaryDeserialize<'_> for Png {
                     .map_err(|e| ReadError::custom(format!("{e:?}")))?
                     .into_rgba8()
             }
+            Platform::Wii => {
+                let decoder = TplDecoder::new(reader, position)
+                    .map_err(|e| ReadError::custom(format!("{e:?}")))?;
+                DynamicImage::from_decoder(decoder)
+                    .map_err(|e| ReadError::custom(format!("{e:?}")))?
+                    .into_rgba8()
+            }
             Platform::Win => {
                 let mut cursor_at = CursorAt::new(reader, *position);
                 let dds = image_dds::ddsfile::Dds::read(&mut cursor_at)
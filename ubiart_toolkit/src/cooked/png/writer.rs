@@ -2,46 +2,114 @@ use dotstar_toolkit_utils::bytes::{
     primitives::{u16be, u32be, u64be},
     write::{BinarySerialize, WriteAt, WriteError},
 };
+use gtx::GtxEncoder;
 use xtx::XtxEncoder;
 
 use super::Png;
+use crate::utils::{Platform, UniqueGameId};
 
 impl BinarySerialize for Png {
-    type Ctx = ();
+    type Ctx = UniqueGameId;
     type Input = Self;
 
     fn serialize_at_with_ctx(
         png: Self::Input,
         writer: &mut (impl WriteAt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        ugi: Self::Ctx,
     ) -> Result<(), WriteError> {
-        writer.write_at::<u64be>(position, 0x9_5445_5800)?;
-        writer.write_at::<u32be>(position, 0x2C)?;
-        writer.write_at::<u32be>(position, png.unk2)?;
-        writer.write_at::<u16be>(position, png.width)?;
-        writer.write_at::<u16be>(position, png.height)?;
-        writer.write_at::<u16be>(position, 0x1)?;
-        writer.write_at::<u16be>(position, png.unk5)?;
-        writer.write_at::<u32be>(position, png.unk2)?;
-        writer.write_at::<u32be>(position, 0x0)?;
-        writer.write_at::<u32be>(position, png.unk8)?;
-        writer.write_at::<u32be>(position, png.unk9)?;
-        writer.write_at::<u16be>(position, png.unk10)?;
-        writer.write_at::<u16be>(position, 0x0)?;
-
-        let encoder = XtxEncoder::new(writer, *position);
-        png.texture
-            .write_with_encoder(encoder)
-            .map_err(|e| WriteError::custom(format!("{e:?}")))?;
+        match ugi.platform {
+            Platform::Nx => {
+                write_header(writer, position, &png, png.unk2)?;
+                let encoder = XtxEncoder::new(writer, *position);
+                png.texture
+                    .write_with_encoder(encoder)
+                    .map_err(|e| WriteError::custom(format!("{e:?}")))?;
+            }
+            Platform::WiiU => {
+                // The size of the GTX payload needs to be known for the header
+                let mut gtx = Vec::new();
+                let encoder = GtxEncoder::new(&mut gtx, 0);
+                png.texture
+                    .write_with_encoder(encoder)
+                    .map_err(|e| WriteError::custom(format!("{e:?}")))?;
+                write_header(writer, position, &png, u32::try_from(gtx.len())?)?;
+                writer.write_slice_at(position, &gtx)?;
+            }
+            platform => {
+                return Err(WriteError::custom(format!(
+                    "Writing textures for {platform} is not supported!"
+                )))
+            }
+        }
         Ok(())
     }
 }
 
-/// Create the cooked PNG file in a newly allocated `Vec`
+/// Write the header of the cooked PNG, `texture_size` is the size of the texture after the header
+fn write_header(
+    writer: &mut (impl WriteAt + ?Sized),
+    position: &mut u64,
+    png: &Png,
+    texture_size: u32,
+) -> Result<(), WriteError> {
+    writer.write_at::<u64be>(position, 0x9_5445_5800)?;
+    writer.write_at::<u32be>(position, 0x2C)?;
+    writer.write_at::<u32be>(position, texture_size)?;
+    writer.write_at::<u16be>(position, png.width)?;
+    writer.write_at::<u16be>(position, png.height)?;
+    writer.write_at::<u16be>(position, 0x1)?;
+    writer.write_at::<u16be>(position, png.unk5)?;
+    writer.write_at::<u32be>(position, texture_size)?;
+    writer.write_at::<u32be>(position, 0x0)?;
+    writer.write_at::<u32be>(position, png.unk8)?;
+    writer.write_at::<u32be>(position, png.unk9)?;
+    writer.write_at::<u16be>(position, png.unk10)?;
+    writer.write_at::<u16be>(position, 0x0)?;
+    Ok(())
+}
+
+/// Create the cooked PNG file for Just Dance 2022 on the Switch in a newly allocated `Vec`
 pub fn create_vec(png: Png) -> Result<Vec<u8>, WriteError> {
+    create_vec_with_ugi(png, UniqueGameId::NX2022)
+}
+
+/// Create the cooked PNG file for `ugi` in a newly allocated `Vec`
+pub fn create_vec_with_ugi(png: Png, ugi: UniqueGameId) -> Result<Vec<u8>, WriteError> {
     let mut vec = Vec::new();
-    vec.write_at::<Png>(&mut 0, png)?;
+    vec.write_at_with_ctx::<Png>(&mut 0, png, ugi)?;
     vec.shrink_to_fit();
     Ok(vec)
 }
+
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::bytes::read::BinaryDeserializeExt as _;
+    use image::{Rgba, RgbaImage};
+
+    use super::create_vec_with_ugi;
+    use crate::{cooked::png::Png, utils::UniqueGameId};
+
+    #[test]
+    fn test_wiiu_texture_size() {
+        let png = Png {
+            width: 64,
+            height: 64,
+            texture: RgbaImage::from_pixel(64, 64, Rgba([0xFF, 0x00, 0xFF, 0xFF])),
+            ..Default::default()
+        };
+        let vec = create_vec_with_ugi(png, UniqueGameId::WIIU2017).unwrap();
+        let parsed = Png::deserialize_with(&vec, UniqueGameId::WIIU2017).unwrap();
+        assert_eq!(
+            usize::try_from(parsed.unk2).unwrap(),
+            vec.len() - 0x2C,
+            "unk2 should be the size of the GTX payload"
+        );
+        assert_eq!(parsed.width, 64, "Width changed");
+        assert_eq!(
+            parsed.texture.get_pixel(10, 10),
+            &Rgba([0xFF, 0x00, 0xFF, 0xFF]),
+            "Texture changed"
+        );
+    }
+}
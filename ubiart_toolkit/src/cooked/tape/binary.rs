@@ -23,7 +23,7 @@ use super::{
 };
 use crate::{
     shared_json_types::Empty,
-    utils::{Game, SplitPath, UniqueGameId},
+    utils::{Game, Platform, SplitPath, UniqueGameId},
 };
 
 impl<'a> BinaryDeserialize<'a> for Tape<'a> {
@@ -36,8 +36,12 @@ impl<'a> BinaryDeserialize<'a> for Tape<'a> {
         position: &mut u64,
         ugi: Self::Ctx,
     ) -> Result<Self::Output, ReadError> {
-        if ugi.game != Game::JustDance2015 {
-            return Err(ReadError::custom("Unsupported".into()));
+        if ugi.platform != Platform::Wii
+            && !(Game::JustDance2014..=Game::JustDance2016).contains(&ugi.game)
+        {
+            return Err(ReadError::custom(format!(
+                "Binary tapes are not used by {ugi}"
+            )));
         }
         let unk1 = reader.read_at::<u32be>(position)?;
         test_eq!(unk1, 1)?;
@@ -513,7 +517,8 @@ impl<'a> BinaryDeserialize<'a> for MaterialGraphicDiffuseAlphaClip<'a> {
         ctx: Self::Ctx,
     ) -> Result<Self::Output, ReadError> {
         let magic = reader.read_at::<u32be>(position)?;
-        test_eq!(magic, 0xE684_12CA)?;
+        // Also used for MaterialGraphicAlphaThresholdClip
+        test_any!(magic, [0xE684_12CA, 0xC236_72A1])?;
         let unk1 = reader.read_at::<u32be>(position)?;
         test_eq!(unk1, 0x34)?;
         let id = reader.read_at::<u32be>(position)?;
@@ -1094,7 +1099,7 @@ impl<'a> BinaryDeserialize<'a> for SpawnActorClip<'a> {
     fn deserialize_at_with(
         reader: &'a (impl ReadAtExt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        ctx: Self::Ctx,
     ) -> Result<Self::Output, ReadError> {
         let magic = reader.read_at::<u32be>(position)?;
         test_eq!(magic, 0xA247_B5D3)?;
@@ -1110,14 +1115,7 @@ impl<'a> BinaryDeserialize<'a> for SpawnActorClip<'a> {
         let spawn_x = reader.read_at::<f32be>(position)?;
         let spawn_y = reader.read_at::<f32be>(position)?;
         let spawn_z = reader.read_at::<f32be>(position)?;
-        let unk2 = reader.read_at::<u32be>(position)?;
-        test_eq!(unk2, 0x24)?;
-        let unk3 = reader.read_at::<u32be>(position)?;
-        test_eq!(unk3, 0)?;
-        let unk4 = reader.read_at::<u32be>(position)?;
-        test_eq!(unk4, 0)?;
-        let unk5 = reader.read_at::<u32be>(position)?;
-        test_eq!(unk5, 0)?;
+        let parent_actor = reader.read_at_with::<TargetActor>(position, ctx)?;
 
         Ok(Self {
             class: None,
@@ -1129,7 +1127,7 @@ impl<'a> BinaryDeserialize<'a> for SpawnActorClip<'a> {
             actor_path,
             actor_name,
             spawn_position: (spawn_x, spawn_y, spawn_z),
-            parent_actor: HipStr::new(),
+            parent_actor: parent_actor.path,
         })
     }
 }
@@ -1,5 +1,7 @@
 mod binary;
-//mod new_json;
+mod writer;
+
+pub use writer::*;
 
 use std::collections::HashMap;
 
@@ -11,14 +13,23 @@ use ubiart_toolkit_shared_types::{errors::ParserError, Color};
 
 use crate::{
     shared_json_types::Empty,
-    utils::{Game, UniqueGameId},
+    utils::{Game, Platform, UniqueGameId},
 };
 
+/// Parse a tape, which is either JSON or binary depending on the game
+///
+/// Games up to and including Just Dance 2016 and the Wii versions of every game use binary tapes,
+/// but mods for these games can also contain JSON tapes. Therefore the format is detected from the
+/// first byte.
 pub fn parse(data: &[u8], ugi: UniqueGameId, lax: bool) -> Result<Tape<'_>, ParserError> {
-    let tape = match ugi.game {
-        game if game >= Game::JustDance2016 => crate::utils::json::parse(data, lax)?,
-        Game::JustDance2015 => Tape::deserialize_with(data, ugi)?,
-        _ => todo!(),
+    let is_json = data
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|byte| *byte == b'{');
+    let tape = if is_json || (ugi.game >= Game::JustDance2017 && ugi.platform != Platform::Wii) {
+        crate::utils::json::parse(data, lax)?
+    } else {
+        Tape::deserialize_with(data, ugi)?
     };
     Ok(tape)
 }
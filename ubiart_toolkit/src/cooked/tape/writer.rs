@@ -0,0 +1,1449 @@
+//! Contains the writer implementation for binary tapes
+
+use dotstar_toolkit_utils::bytes::{
+    primitives::{f32be, i32be, u32be},
+    write::{BinarySerialize, WriteAt, WriteError},
+};
+use hipstr::HipStr;
+
+use super::{
+    AlphaClip, BezierCurveFloat, BezierCurveFloatValue, Clip, ColorClip, CommunityDancerClip,
+    FXClip, GameplayEventClip, GoldEffectClip, HideUserInterfaceClip, KaraokeClip, KeyFloat,
+    MaterialGraphicDiffuseAlphaClip, MaterialGraphicDiffuseColorClip,
+    MaterialGraphicEnableLayerClip, MaterialGraphicUVRotationClip, MaterialGraphicUVScaleClip,
+    MaterialGraphicUVScrollClip, MaterialGraphicUVTranslationClip, MotionClip,
+    MotionPlatformSpecific, PictogramClip, ProportionClip, RotationClip, SizeClip, SlotClip,
+    SoundSetClip, SpawnActorClip, StringOrId, Tape, TapeLauncherClip, TapeReferenceClip,
+    TargetActor, TextClip, TranslationClip, Unknown59FCC733Clip, Unknown5C944B01Clip,
+    UnknownCBB7C029Clip,
+};
+use crate::utils::{string_id, Game, Platform, SplitPath, UniqueGameId};
+
+/// Create a binary tape in a newly allocated `Vec`
+pub fn create_vec(tape: Tape<'_>, ugi: UniqueGameId) -> Result<Vec<u8>, WriteError> {
+    let mut vec = Vec::with_capacity(0x40 + tape.clips.len() * 0x80);
+    vec.write_at_with_ctx::<Tape>(&mut 0, tape, ugi)?;
+    vec.shrink_to_fit();
+    Ok(vec)
+}
+
+impl BinarySerialize for Tape<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        if ugi.platform != Platform::Wii
+            && !(Game::JustDance2014..=Game::JustDance2016).contains(&ugi.game)
+        {
+            return Err(WriteError::custom(format!(
+                "Binary tapes are not used by {ugi}"
+            )));
+        }
+        writer.write_at::<u32be>(position, 1)?; // unk1
+        writer.write_at::<u32be>(position, 0)?; // unk2
+        writer.write_at::<u32be>(position, 0x9E84_5460)?; // magic
+        writer.write_at::<u32be>(position, 0)?; // unk3
+        writer.write_len_type_at_with_ctx::<u32be, Clip>(position, input.clips.into_iter(), ugi)?;
+        writer.write_at::<u32be>(position, 0)?; // unk4
+        writer.write_at::<u32be>(position, 0)?; // unk5, no Unknown9BC67FC4 follows
+        writer.write_at::<u32be>(position, input.tape_clock)?;
+        writer.write_at::<u32be>(position, input.tape_bar_count)?;
+        writer.write_at::<u32be>(position, input.free_resources_after_play)?;
+        writer.write_len_string_at::<u32be>(position, &input.map_name)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for Clip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        match input {
+            Clip::Alpha(clip) => writer.write_at_with_ctx::<AlphaClip>(position, clip, ugi),
+            Clip::Color(clip) => writer.write_at_with_ctx::<ColorClip>(position, clip, ugi),
+            Clip::CommunityDancer(clip) => {
+                writer.write_at_with_ctx::<CommunityDancerClip>(position, clip, ugi)
+            }
+            Clip::FX(clip) => writer.write_at_with_ctx::<FXClip>(position, clip, ugi),
+            Clip::GameplayEvent(clip) => {
+                writer.write_at_with_ctx::<GameplayEventClip>(position, clip, ugi)
+            }
+            Clip::GoldEffect(clip) => {
+                writer.write_at_with_ctx::<GoldEffectClip>(position, clip, ugi)
+            }
+            Clip::HideUserInterface(clip) => {
+                writer.write_at_with_ctx::<HideUserInterfaceClip>(position, clip, ugi)
+            }
+            Clip::Karaoke(clip) => writer.write_at_with_ctx::<KaraokeClip>(position, clip, ugi),
+            Clip::MaterialGraphicAlphaThreshold(clip) => {
+                write_diffuse_alpha(writer, position, clip, 0xC236_72A1, ugi)
+            }
+            Clip::MaterialGraphicDiffuseAlpha(clip) => {
+                write_diffuse_alpha(writer, position, clip, 0xE684_12CA, ugi)
+            }
+            Clip::MaterialGraphicDiffuseColor(clip) => {
+                writer.write_at_with_ctx::<MaterialGraphicDiffuseColorClip>(position, clip, ugi)
+            }
+            Clip::MaterialGraphicEnableLayer(clip) => {
+                writer.write_at_with_ctx::<MaterialGraphicEnableLayerClip>(position, clip, ugi)
+            }
+            Clip::MaterialGraphicUVRotation(clip) => {
+                writer.write_at_with_ctx::<MaterialGraphicUVRotationClip>(position, clip, ugi)
+            }
+            Clip::MaterialGraphicUVScale(clip) => {
+                writer.write_at_with_ctx::<MaterialGraphicUVScaleClip>(position, clip, ugi)
+            }
+            Clip::MaterialGraphicUVScroll(clip) => {
+                writer.write_at_with_ctx::<MaterialGraphicUVScrollClip>(position, clip, ugi)
+            }
+            Clip::MaterialGraphicUVTranslation(clip) => {
+                writer.write_at_with_ctx::<MaterialGraphicUVTranslationClip>(position, clip, ugi)
+            }
+            Clip::Motion(clip) => writer.write_at_with_ctx::<MotionClip>(position, clip, ugi),
+            Clip::Pictogram(clip) => writer.write_at_with_ctx::<PictogramClip>(position, clip, ugi),
+            Clip::Proportion(clip) => {
+                writer.write_at_with_ctx::<ProportionClip>(position, clip, ugi)
+            }
+            Clip::Rotation(clip) => writer.write_at_with_ctx::<RotationClip>(position, clip, ugi),
+            Clip::Size(clip) => writer.write_at_with_ctx::<SizeClip>(position, clip, ugi),
+            Clip::Slot(clip) => writer.write_at_with_ctx::<SlotClip>(position, clip, ugi),
+            Clip::SpawnActor(clip) => {
+                writer.write_at_with_ctx::<SpawnActorClip>(position, clip, ugi)
+            }
+            Clip::SoundSet(clip) => writer.write_at_with_ctx::<SoundSetClip>(position, clip, ugi),
+            Clip::TapeLauncher(clip) => {
+                writer.write_at_with_ctx::<TapeLauncherClip>(position, clip, ugi)
+            }
+            Clip::TapeReference(clip) => {
+                writer.write_at_with_ctx::<TapeReferenceClip>(position, clip, ugi)
+            }
+            Clip::Text(clip) => writer.write_at_with_ctx::<TextClip>(position, clip, ugi),
+            Clip::Translation(clip) => {
+                writer.write_at_with_ctx::<TranslationClip>(position, clip, ugi)
+            }
+            Clip::Unknown59FCC733(clip) => {
+                writer.write_at_with_ctx::<Unknown59FCC733Clip>(position, clip, ugi)
+            }
+            Clip::UnknownCBB7C029(clip) => {
+                writer.write_at_with_ctx::<UnknownCBB7C029Clip>(position, clip, ugi)
+            }
+            Clip::Unknown5C944B01(clip) => {
+                writer.write_at_with_ctx::<Unknown5C944B01Clip>(position, clip, ugi)
+            }
+            Clip::ActorEnable(_) => Err(WriteError::custom(
+                "ActorEnable is not used in binary formats".into(),
+            )),
+            Clip::CameraFeed(_) => Err(WriteError::custom(
+                "CameraFeed is not used in binary formats".into(),
+            )),
+            Clip::MaterialGraphicUVAnimRotation(_) => Err(WriteError::custom(
+                "MaterialGraphicUVAnimRotation is not used in binary formats".into(),
+            )),
+            Clip::Proportion3D(_) => Err(WriteError::custom(
+                "Proportion3D is not used in binary formats".into(),
+            )),
+            Clip::Soundwich(_) => Err(WriteError::custom(
+                "SoundWich is not used in binary formats".into(),
+            )),
+            Clip::SoundwichWithId(_) => Err(WriteError::custom(
+                "SoundWichWithId is not used in binary formats".into(),
+            )),
+            Clip::TextAreaSize(_) => Err(WriteError::custom(
+                "TextAreaSize is not used in binary formats".into(),
+            )),
+            Clip::Vibration(_) => Err(WriteError::custom(
+                "Vibration is not used in binary formats".into(),
+            )),
+        }
+    }
+}
+
+/// The start of every binary clip
+struct ClipHeader {
+    /// Identifies the type of clip
+    magic: u32,
+    /// Unknown value, constant per type of clip
+    unk1: u32,
+    id: u32,
+    track_id: u32,
+    is_active: u8,
+    start_time: i32,
+    duration: i32,
+}
+
+impl ClipHeader {
+    const fn new(
+        magic: u32,
+        unk1: u32,
+        id: u32,
+        track_id: u32,
+        is_active: u8,
+        start_time: i32,
+        duration: i32,
+    ) -> Self {
+        Self {
+            magic,
+            unk1,
+            id,
+            track_id,
+            is_active,
+            start_time,
+            duration,
+        }
+    }
+}
+
+impl BinarySerialize for ClipHeader {
+    type Ctx = ();
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<u32be>(position, input.magic)?;
+        writer.write_at::<u32be>(position, input.unk1)?;
+        writer.write_at::<u32be>(position, input.id)?;
+        writer.write_at::<u32be>(position, input.track_id)?;
+        writer.write_at::<u32be>(position, u32::from(input.is_active))?;
+        writer.write_at::<i32be>(position, input.start_time)?;
+        writer.write_at::<i32be>(position, input.duration)?;
+        Ok(())
+    }
+}
+
+/// Write `path` as a [`SplitPath`]
+fn write_split_path(
+    writer: &mut (impl WriteAt + ?Sized),
+    position: &mut u64,
+    path: &str,
+) -> Result<(), WriteError> {
+    let split_path = SplitPath::try_from(path)
+        .map_err(|error| WriteError::custom(format!("Invalid path {path}: {error}")))?;
+    writer.write_at::<SplitPath>(position, split_path)
+}
+
+/// Write the length prefixed list of target actors
+fn write_target_actors(
+    writer: &mut (impl WriteAt + ?Sized),
+    position: &mut u64,
+    target_actors: Vec<TargetActor<'_>>,
+    ugi: UniqueGameId,
+) -> Result<(), WriteError> {
+    writer.write_len_type_at_with_ctx::<u32be, TargetActor>(
+        position,
+        target_actors.into_iter(),
+        ugi,
+    )
+}
+
+impl BinarySerialize for AlphaClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x8607_D582,
+                0x2C,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for ColorClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0xF61B_3A75,
+                0x34,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_red, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_green, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_blue, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for CommunityDancerClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x0F95_B841,
+                0x34,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        writer.write_len_string_at::<u32be>(position, &input.dancer_country_code)?;
+        writer.write_at::<u32be>(position, input.dancer_avatar_id)?;
+        writer.write_len_string_at::<u32be>(position, &input.dancer_name)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for FXClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x0F19_B038,
+                0x34,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        let fx_name = match input.fx_name {
+            StringOrId::String(string) => string_id(&string),
+            StringOrId::Id(id) => id,
+        };
+        writer.write_at::<u32be>(position, fx_name)?;
+        writer.write_at::<u32be>(position, input.kill_particles_on_end)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for GameplayEventClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0xCE73_233E,
+                0x38,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.event_type)?;
+        writer.write_len_string_at::<u32be>(position, &input.custom_param)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for GoldEffectClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0xFD69_B110,
+                0x1C,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        writer.write_at::<u32be>(position, u32::from(input.effect_type))?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for HideUserInterfaceClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x52E0_6A9A,
+                0x38,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.event_type)?;
+        writer.write_len_string_at::<u32be>(position, &input.custom_param)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for KaraokeClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x6855_2A41,
+                0x50,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        writer.write_at::<f32be>(position, input.pitch)?;
+        writer.write_len_string_at::<u32be>(position, &input.lyrics)?;
+        writer.write_at::<u32be>(position, u32::from(input.is_end_of_line))?;
+        writer.write_at::<u32be>(position, input.content_type)?;
+        writer.write_at::<u32be>(position, input.start_time_tolerance)?;
+        writer.write_at::<u32be>(position, input.end_time_tolerance)?;
+        writer.write_at::<f32be>(position, input.semitone_tolerance)?;
+        Ok(())
+    }
+}
+
+/// Write a `MaterialGraphicDiffuseAlphaClip` with `magic`
+///
+/// The same layout is used for `MaterialGraphicAlphaThresholdClip`, only the magic is different.
+fn write_diffuse_alpha(
+    writer: &mut (impl WriteAt + ?Sized),
+    position: &mut u64,
+    clip: MaterialGraphicDiffuseAlphaClip<'_>,
+    magic: u32,
+    ugi: UniqueGameId,
+) -> Result<(), WriteError> {
+    writer.write_at::<ClipHeader>(
+        position,
+        ClipHeader::new(
+            magic,
+            0x34,
+            clip.id,
+            clip.track_id,
+            clip.is_active,
+            clip.start_time,
+            clip.duration,
+        ),
+    )?;
+    write_target_actors(writer, position, clip.target_actors, ugi)?;
+    writer.write_at::<u32be>(position, clip.layer_idx)?;
+    writer.write_at::<u32be>(position, clip.uv_modifier_idx)?;
+    writer.write_at_with_ctx::<BezierCurveFloat>(position, clip.curve_a, ugi)?;
+    Ok(())
+}
+
+impl BinarySerialize for MaterialGraphicDiffuseColorClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0xC6FE_D58E,
+                0x3C,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.layer_idx)?;
+        writer.write_at::<u32be>(position, input.uv_modifier_idx)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_r, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_g, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_b, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for MaterialGraphicEnableLayerClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x4D30_9320,
+                0x34,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.layer_idx)?;
+        writer.write_at::<u32be>(position, input.uv_modifier_idx)?;
+        writer.write_at::<u32be>(position, u32::from(input.layer_enabled))?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for MaterialGraphicUVRotationClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0xDD4A_9D55,
+                0x3C,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.layer_idx)?;
+        writer.write_at::<u32be>(position, input.uv_modifier_idx)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_angle, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_pivot_x, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_pivot_y, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for MaterialGraphicUVScaleClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x511F_C7A5,
+                0x40,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.layer_idx)?;
+        writer.write_at::<u32be>(position, input.uv_modifier_idx)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_scale_u, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_scale_v, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_pivot_x, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_pivot_y, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for MaterialGraphicUVScrollClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x57E2_6726,
+                0x38,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.layer_idx)?;
+        writer.write_at::<u32be>(position, input.uv_modifier_idx)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_scroll_u, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_scroll_v, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for MaterialGraphicUVTranslationClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0xC411_5B2E,
+                0x38,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.layer_idx)?;
+        writer.write_at::<u32be>(position, input.uv_modifier_idx)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_u, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_v, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for MotionClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x9553_84A1,
+                0x6C,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_split_path(writer, position, &input.classifier_path)?;
+        writer.write_at::<u32be>(position, u32::from(input.gold_move))?;
+        writer.write_at::<u32be>(position, u32::from(input.coach_id))?;
+        writer.write_at::<u32be>(position, u32::from(input.move_type))?;
+        let (red, green, blue, alpha) = input.color.color;
+        writer.write_at::<f32be>(position, red)?;
+        writer.write_at::<f32be>(position, green)?;
+        writer.write_at::<f32be>(position, blue)?;
+        writer.write_at::<f32be>(position, alpha)?;
+
+        // Sort by platform so the output is deterministic
+        let mut motion_platform_specifics = input
+            .motion_platform_specifics
+            .into_iter()
+            .map(|(platform, mps)| Ok((platform_to_id(&platform)?, mps)))
+            .collect::<Result<Vec<_>, WriteError>>()?;
+        motion_platform_specifics.sort_unstable_by_key(|(platform, _)| *platform);
+        writer.write_at::<u32be>(position, u32::try_from(motion_platform_specifics.len())?)?;
+        for (platform, mps) in motion_platform_specifics {
+            writer.write_at::<u32be>(position, platform)?;
+            writer.write_at_with_ctx::<MotionPlatformSpecific>(position, mps, ugi)?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert the platform name of a [`MotionPlatformSpecific`] to the id used in binary tapes
+fn platform_to_id(platform: &HipStr<'_>) -> Result<u32, WriteError> {
+    match platform.as_str() {
+        "X360" => Ok(0x1),
+        "ORBIS" => Ok(0x3),
+        "DURANGO" => Ok(0xA),
+        _ => Err(WriteError::custom(format!("Unknown platform: {platform}"))),
+    }
+}
+
+impl BinarySerialize for MotionPlatformSpecific<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<u32be>(position, 0xC)?; // unk1
+        writer.write_at::<f32be>(position, input.score_scale)?;
+        writer.write_at::<f32be>(position, input.scoring_mode.unwrap_or_default())?;
+        writer.write_at::<f32be>(position, input.score_smoothing)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for PictogramClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x52EC_8962,
+                0x38,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_split_path(writer, position, &input.picto_path)?;
+        writer.write_at::<u32be>(position, input.coach_count)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for ProportionClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x5477_75BC,
+                0x30,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_x, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_y, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for RotationClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x7A9C_58B3,
+                0x34,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_x, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_y, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_z, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for SizeClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x52B8_9D18,
+                0x30,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_x, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_y, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for SlotClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x896A_96B0,
+                0x34,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        writer.write_at::<f32be>(position, input.bpm)?;
+        writer.write_len_string_at::<u32be>(position, &input.signature)?;
+        writer.write_len_string_at::<u32be>(position, &input.guid)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for SpawnActorClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0xA247_B5D3,
+                0x70,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_split_path(writer, position, &input.actor_path)?;
+        writer.write_len_string_at::<u32be>(position, &input.actor_name)?;
+        let (x, y, z) = input.spawn_position;
+        writer.write_at::<f32be>(position, x)?;
+        writer.write_at::<f32be>(position, y)?;
+        writer.write_at::<f32be>(position, z)?;
+        let parent_actor = TargetActor {
+            path: input.parent_actor,
+            scenes: Vec::new(),
+        };
+        writer.write_at_with_ctx::<TargetActor>(position, parent_actor, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for SoundSetClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        // unk1 varies for this clip, 0x40 is the most common value
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x2D8C_885B,
+                0x40,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_split_path(writer, position, &input.sound_set_path)?;
+        writer.write_at::<i32be>(position, input.sound_channel)?;
+        writer.write_at::<u32be>(position, input.stops_on_end)?;
+        writer.write_at::<u32be>(position, input.accounted_for_duration)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for TapeLauncherClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x115F_128D,
+                0x34,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.action)?;
+        writer.write_at::<f32be>(position, 0.0)?; // unk2
+        Ok(())
+    }
+}
+
+impl BinarySerialize for TapeReferenceClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        // unk1 varies for this clip, 0x3C is the most common value
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x0E1E_8158,
+                0x3C,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_split_path(writer, position, &input.path)?;
+        writer.write_at::<u32be>(position, input.loop_it)?;
+        writer.write_at::<u32be>(position, 0)?; // unk2
+        Ok(())
+    }
+}
+
+impl BinarySerialize for TextClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0xE5B3_34C8,
+                0x2C,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.localization_key)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for TranslationClip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x36A3_12DC,
+                0x34,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_x, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_y, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_z, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for Unknown59FCC733Clip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x59FC_C733,
+                0x38,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_one, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_two, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_three, ugi)?;
+        writer.write_at_with_ctx::<BezierCurveFloat>(position, input.curve_four, ugi)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for UnknownCBB7C029Clip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0xCBB7_C029,
+                0x3C,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        write_target_actors(writer, position, input.target_actors, ugi)?;
+        writer.write_at::<u32be>(position, input.unk2_stringid)?;
+        writer.write_at::<u32be>(position, input.unk3)?;
+        writer.write_at::<f32be>(position, input.unk4)?;
+        writer.write_at::<u32be>(position, input.unk5)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for Unknown5C944B01Clip<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<ClipHeader>(
+            position,
+            ClipHeader::new(
+                0x5C94_4B01,
+                0x24,
+                input.id,
+                input.track_id,
+                input.is_active,
+                input.start_time,
+                input.duration,
+            ),
+        )?;
+        writer.write_len_string_at::<u32be>(position, &input.string)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for BezierCurveFloat<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<u32be>(position, 0x4)?; // unk1
+        match input.value {
+            BezierCurveFloatValue::Empty(_) => {
+                writer.write_at::<u32be>(position, 0xFFFF_FFFF)?;
+            }
+            BezierCurveFloatValue::Constant(constant) => {
+                writer.write_at::<u32be>(position, 0xB791_4191)?;
+                writer.write_at::<u32be>(position, 0x8)?; // unk1
+                writer.write_at::<f32be>(position, constant.value)?;
+            }
+            BezierCurveFloatValue::Linear(linear) => {
+                writer.write_at::<u32be>(position, 0x4DE6_D871)?;
+                writer.write_at::<u32be>(position, 0x24)?; // unk1
+                writer.write_at::<f32be>(position, linear.value_left.0)?;
+                writer.write_at::<f32be>(position, linear.value_left.1)?;
+                writer.write_at::<f32be>(position, linear.normal_left_out.0)?;
+                writer.write_at::<f32be>(position, linear.normal_left_out.1)?;
+                writer.write_at::<f32be>(position, linear.value_right.0)?;
+                writer.write_at::<f32be>(position, linear.value_right.1)?;
+                writer.write_at::<f32be>(position, linear.normal_right_in.0)?;
+                writer.write_at::<f32be>(position, linear.normal_right_in.1)?;
+            }
+            BezierCurveFloatValue::Multi(multi) => {
+                writer.write_at::<u32be>(position, 0xE2BC_4FB2)?;
+                writer.write_at::<u32be>(position, 0x14)?; // unk1
+                writer.write_len_type_at_with_ctx::<u32be, KeyFloat>(
+                    position,
+                    multi.keys.into_iter(),
+                    ugi,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BinarySerialize for KeyFloat<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<u32be>(position, 0x18)?; // unk1
+        writer.write_at::<f32be>(position, input.value.0)?;
+        writer.write_at::<f32be>(position, input.value.1)?;
+        writer.write_at::<f32be>(position, input.normal_in.0)?;
+        writer.write_at::<f32be>(position, input.normal_in.1)?;
+        writer.write_at::<f32be>(position, input.normal_out.0)?;
+        writer.write_at::<f32be>(position, input.normal_out.1)?;
+        Ok(())
+    }
+}
+
+impl BinarySerialize for TargetActor<'_> {
+    type Ctx = UniqueGameId;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ugi: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        writer.write_at::<u32be>(position, 0x24)?; // unk1
+        writer.write_at::<u32be>(position, u32::try_from(input.scenes.len())?)?;
+        for scene in input.scenes {
+            writer.write_at::<u32be>(position, 0x10)?; // unk2
+            writer.write_len_string_at::<u32be>(position, &scene)?;
+            writer.write_at::<u32be>(position, 0)?; // unk3
+        }
+        writer.write_len_string_at::<u32be>(position, &input.path)?;
+        writer.write_at::<u32be>(position, 0)?; // unk4
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use dotstar_toolkit_utils::bytes::read::BinaryDeserialize as _;
+    use hipstr::HipStr;
+    use ubiart_toolkit_shared_types::Color;
+
+    use super::create_vec;
+    use crate::{
+        cooked::tape::{
+            AlphaClip, BezierCurveFloat, BezierCurveFloatMulti, BezierCurveFloatValue, Clip,
+            GoldEffectClip, KaraokeClip, KeyFloat, MotionClip, MotionPlatformSpecific,
+            PictogramClip, Tape, TargetActor,
+        },
+        utils::UniqueGameId,
+    };
+
+    fn tape() -> Tape<'static> {
+        let key = |value| KeyFloat {
+            class: None,
+            value: (value, value),
+            normal_in: (0.0, 0.5),
+            normal_out: (0.5, 0.0),
+        };
+        let mut motion_platform_specifics = HashMap::new();
+        for platform in ["DURANGO", "X360", "ORBIS"] {
+            motion_platform_specifics.insert(
+                HipStr::borrowed(platform),
+                MotionPlatformSpecific {
+                    class: None,
+                    score_scale: 1.0,
+                    score_smoothing: 0.0,
+                    scoring_mode: Some(2.0),
+                    low_threshold: None,
+                    high_threshold: None,
+                },
+            );
+        }
+        Tape {
+            class: None,
+            clips: vec![
+                Clip::GoldEffect(GoldEffectClip {
+                    class: None,
+                    id: 1,
+                    track_id: 2,
+                    is_active: 1,
+                    start_time: 24,
+                    duration: 12,
+                    effect_type: 1,
+                }),
+                Clip::Karaoke(KaraokeClip {
+                    class: None,
+                    id: 3,
+                    track_id: 4,
+                    is_active: 1,
+                    start_time: 36,
+                    duration: 6,
+                    pitch: 8.0,
+                    lyrics: HipStr::borrowed("Hello"),
+                    is_end_of_line: 1,
+                    content_type: 0,
+                    start_time_tolerance: 4,
+                    end_time_tolerance: 4,
+                    semitone_tolerance: 5.0,
+                }),
+                Clip::Pictogram(PictogramClip {
+                    class: None,
+                    id: 5,
+                    track_id: 6,
+                    is_active: 1,
+                    start_time: 48,
+                    duration: 24,
+                    picto_path: HipStr::borrowed("world/maps/test/timeline/pictos/test.png"),
+                    montage_path: None,
+                    atl_index: None,
+                    coach_count: u32::MAX,
+                }),
+                Clip::Motion(MotionClip {
+                    class: None,
+                    id: 7,
+                    track_id: 8,
+                    is_active: 1,
+                    start_time: 48,
+                    duration: 24,
+                    classifier_path: HipStr::borrowed(
+                        "world/maps/test/timeline/moves/wiiu/test.msm",
+                    ),
+                    gold_move: 0,
+                    coach_id: 0,
+                    move_type: 0,
+                    color: Color {
+                        color: (1.0, 0.5, 0.25, 0.0),
+                    },
+                    motion_platform_specifics,
+                }),
+                Clip::Alpha(AlphaClip {
+                    class: None,
+                    id: 9,
+                    track_id: 10,
+                    is_active: 1,
+                    start_time: 0,
+                    duration: 96,
+                    actor_indices: Vec::new(),
+                    target_actors: vec![TargetActor {
+                        path: HipStr::borrowed("test_main_scene|test_video"),
+                        scenes: vec![HipStr::borrowed("test_main_scene")],
+                    }],
+                    curve: BezierCurveFloat {
+                        class: None,
+                        value: BezierCurveFloatValue::Multi(BezierCurveFloatMulti {
+                            class: None,
+                            keys: vec![key(0.0), key(1.0)],
+                        }),
+                    },
+                }),
+            ],
+            tape_clock: 0,
+            tape_bar_count: 1,
+            free_resources_after_play: 0,
+            map_name: HipStr::borrowed("Test"),
+            soundwich_event: None,
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for ugi in [
+            UniqueGameId::WIIU2014,
+            UniqueGameId::WIIU2015,
+            UniqueGameId::WIIU2016,
+            UniqueGameId::WII2020,
+        ] {
+            let one = create_vec(tape(), ugi).unwrap();
+            let tape = Tape::deserialize_with(&one, ugi).unwrap();
+            assert_eq!(tape.clips.len(), 5, "Clips are missing");
+            let two = create_vec(tape, ugi).unwrap();
+            assert_eq!(one, two, "Roundtrip failed for {ugi}");
+        }
+    }
+
+    #[test]
+    fn test_parse_detects_binary() {
+        let data = create_vec(tape(), UniqueGameId::WIIU2016).unwrap();
+        let tape = crate::cooked::tape::parse(&data, UniqueGameId::WIIU2016, false).unwrap();
+        assert_eq!(tape.map_name, "Test", "Map name is wrong");
+    }
+
+    #[test]
+    fn test_unsupported_game() {
+        assert!(
+            create_vec(tape(), UniqueGameId::NX2017).is_err(),
+            "NX2017 does not use binary tapes"
+        );
+    }
+}
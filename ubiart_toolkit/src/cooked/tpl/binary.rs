@@ -8,7 +8,7 @@ use dotstar_toolkit_utils::bytes::{
     read::{BinaryDeserialize, ReadAtExt, ReadError},
 };
 use hipstr::HipStr;
-use test_eq::{test_any, test_eq, test_or};
+use test_eq::{test_any, test_eq, test_or, TestFailure};
 use ubiart_toolkit_shared_types::{Color, LocaleId};
 
 use crate::{
@@ -26,9 +26,36 @@ use crate::{
         AutoDanceFxDesc, AutodancePropData, AutodanceVideoStructure, GFXVector4, PlaybackEvent,
         PropEvent, PropPlayerConfig,
     },
-    utils::{path::ExpectedPadding, InternedString, SplitPath, UniqueGameId},
+    utils::{path::ExpectedPadding, Game, InternedString, Platform, SplitPath, UniqueGameId},
 };
 
+/// Check the Just Dance version in a binary template
+///
+/// Just Dance 2015 always stores 2015. The Wii versions store the version of the game the file
+/// was made for, which is at most the version of the game itself.
+fn test_jd_version(jd_version: u32, ugi: UniqueGameId) -> Result<(), TestFailure> {
+    if ugi.platform == Platform::Wii {
+        test_any!(jd_version, 2014..=wii_jd_version(ugi.game))
+    } else {
+        test_eq!(jd_version, 2015)
+    }
+}
+
+/// The newest Just Dance version that can be in the files of the Wii version of `game`
+///
+/// Just Dance 2020 is the last game released for the Wii.
+const fn wii_jd_version(game: Game) -> u32 {
+    match game {
+        Game::JustDance2014 => 2014,
+        Game::JustDance2015 => 2015,
+        Game::JustDance2016 => 2016,
+        Game::JustDance2017 => 2017,
+        Game::JustDance2018 => 2018,
+        Game::JustDance2019 => 2019,
+        _ => 2020,
+    }
+}
+
 impl<'de> BinaryDeserialize<'de> for Actor<'de> {
     type Ctx = UniqueGameId;
     type Output = Self;
@@ -96,7 +123,7 @@ impl<'de> BinaryDeserialize<'de> for Template<'de> {
                 reader.read_at::<AutodanceComponent>(position)?,
             )),
             "JD_AvatarDescTemplate" => Ok(Template::AvatarDescription(AvatarDescription::V16(
-                reader.read_at::<AvatarDescription16>(position)?,
+                reader.read_at_with::<AvatarDescription16>(position, ctx)?,
             ))),
             "JD_BlockFlowTemplate" => Ok(Template::BlockFlowTemplate(
                 reader.read_at::<BlockFlowTemplate>(position)?,
@@ -123,7 +150,9 @@ impl<'de> BinaryDeserialize<'de> for Template<'de> {
             "SoundComponent_Template" => Ok(Template::SoundComponent(
                 reader.read_at::<SoundComponent>(position)?,
             )),
-            _ => todo!("{class}"),
+            _ => Err(ReadError::custom(format!(
+                "Unknown template class: {class}"
+            ))),
         }
     }
 }
@@ -681,18 +710,18 @@ impl BinaryDeserialize<'_> for UnknownC {
 }
 
 impl<'de> BinaryDeserialize<'de> for AvatarDescription16<'de> {
-    type Ctx = ();
+    type Ctx = UniqueGameId;
     type Output = Self;
 
     fn deserialize_at_with(
         reader: &'de (impl ReadAtExt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        ugi: Self::Ctx,
     ) -> Result<Self::Output, ReadError> {
         let unk1 = reader.read_at::<u32be>(position)?;
         test_eq!(unk1, 0x54)?;
         let jd_version = reader.read_at::<u32be>(position)?;
-        test_eq!(jd_version, 2015)?;
+        test_jd_version(jd_version, ugi)?;
         let unk2 = reader.read_at::<u32be>(position)?;
         test_eq!(unk2, 0)?;
         let actor_path = HipStr::from(reader.read_at::<SplitPath>(position)?.to_string());
@@ -1500,16 +1529,19 @@ impl<'de> BinaryDeserialize<'de> for SongDescription<'de> {
     fn deserialize_at_with(
         reader: &'de (impl ReadAtExt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        ugi: Self::Ctx,
     ) -> Result<Self::Output, ReadError> {
         let unk1 = reader.read_at::<u32be>(position)?;
         test_eq!(unk1, 0xF4)?;
         let map_name = reader.read_len_string_at::<u32be>(position)?;
         let jd_version = reader.read_at::<u32be>(position)?;
-        test_eq!(jd_version, 2015)?;
+        test_jd_version(jd_version, ugi)?;
         // maybe original_jd_version?
         let original_jd_version = reader.read_at::<u32be>(position)?;
-        test_any!(original_jd_version, [0x5, 2015, 0xFFFF_FFFF])?;
+        test_or!(
+            test_any!(original_jd_version, [0x5, 0xFFFF_FFFF]),
+            test_jd_version(original_jd_version, ugi)
+        )?;
         let related_albums_len = reader.read_at::<u32be>(position)?;
         test_any!(related_albums_len, 0x0..=0x1)?;
         let mut related_albums = Vec::with_capacity(usize::try_from(related_albums_len)?);
@@ -1977,3 +2009,33 @@ impl<'de> BinaryDeserialize<'de> for CoreGraphicComponent<'de> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::test_jd_version;
+    use crate::utils::UniqueGameId;
+
+    #[test]
+    fn test_jd_versions() {
+        assert!(
+            test_jd_version(2015, UniqueGameId::WIIU2015).is_ok(),
+            "Just Dance 2015 stores 2015"
+        );
+        assert!(
+            test_jd_version(2016, UniqueGameId::WIIU2015).is_err(),
+            "Just Dance 2015 only stores 2015"
+        );
+        assert!(
+            test_jd_version(2020, UniqueGameId::WII2020).is_ok(),
+            "The Wii version of Just Dance 2020 stores its own version"
+        );
+        assert!(
+            test_jd_version(2017, UniqueGameId::WII2020).is_ok(),
+            "The Wii versions store the version a file was made for"
+        );
+        assert!(
+            test_jd_version(2021, UniqueGameId::WII2020).is_err(),
+            "There are no Wii versions after Just Dance 2020"
+        );
+    }
+}
@@ -11,23 +11,27 @@ use ubiart_toolkit_shared_types::errors::ParserError;
 
 use crate::{
     cooked::tpl::types::Actor,
-    utils::{Game, UniqueGameId},
+    utils::{Game, Platform, UniqueGameId},
 };
 
 /// Parse a .tpl.ckd file
+///
+/// The Wii versions use the binary format for every game.
 pub fn parse(data: &[u8], ugi: UniqueGameId, lax: bool) -> Result<Actor<'_>, ParserError> {
-    match ugi.game {
-        game if game >= Game::JustDance2016 => {
-            let actor = crate::utils::json::parse(data, lax)?;
-            Ok(actor)
-        }
-        Game::JustDance2015 => {
+    match (ugi.game, ugi.platform) {
+        (_, Platform::Wii) | (Game::JustDance2015, _) => {
             let mut position = 0;
             let actor = Actor::deserialize_at_with(data, &mut position, ugi)?;
             #[cfg(test)]
             assert_eq!(position, data.len() as u64);
             Ok(actor)
         }
-        _ => todo!("{ugi}"),
+        (game, _) if game >= Game::JustDance2016 => {
+            let actor = crate::utils::json::parse(data, lax)?;
+            Ok(actor)
+        }
+        _ => Err(ParserError::custom(format!(
+            "Parsing templates is not supported for {ugi}"
+        ))),
     }
 }
@@ -472,6 +472,17 @@ impl<'a> Template<'a> {
         }
     }
 
+    /// Convert this template to a [`BlockFlowTemplate`].
+    pub fn into_block_flow_template(self) -> Result<BlockFlowTemplate<'a>, ParserError> {
+        if let Template::BlockFlowTemplate(template) = self {
+            Ok(template)
+        } else {
+            Err(ParserError::custom(format!(
+                "BlockFlowTemplate not found in template: {self:?}"
+            )))
+        }
+    }
+
     /// Convert this template to a [`AvatarDescription`].
     pub fn into_avatar_description(self) -> Result<AvatarDescription<'a>, ParserError> {
         if let Template::AvatarDescription(template) = self {
@@ -0,0 +1,309 @@
+//! Encoder for Nintendo DSP-ADPCM
+//!
+//! Every frame is 8 bytes: a header byte with the predictor index in the high nibble and the scale
+//! in the low nibble, followed by 14 signed 4-bit samples. A sample is decoded as
+//! `((nibble << scale) << 11 + coef1 * hist1 + coef2 * hist2 + 1024) >> 11`, where the eight
+//! coefficient pairs are shared by the whole channel.
+//!
+//! The coefficient pairs are found by clustering the optimal second order predictor of every
+//! frame, the frames are then encoded with the pair and scale that give the smallest error.
+
+/// Amount of samples in a frame
+pub const SAMPLES_PER_FRAME: usize = 14;
+/// Size of a frame in bytes
+pub const BYTES_PER_FRAME: usize = 8;
+/// Amount of coefficient pairs in a channel
+const PREDICTORS: usize = 8;
+/// Highest scale that can be stored in a frame header
+const MAX_SCALE: u32 = 12;
+/// Amount of refinement passes when clustering the predictors
+const CLUSTER_PASSES: usize = 16;
+
+/// A DSP-ADPCM encoded channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoded {
+    /// The eight coefficient pairs in 4.11 fixed point
+    pub coefficients: [i16; 0x10],
+    /// The encoded frames, the last frame is padded with silence
+    pub data: Vec<u8>,
+}
+
+impl Encoded {
+    /// The header byte of the first frame
+    #[must_use]
+    pub fn initial_predictor_scale(&self) -> u16 {
+        self.data.first().copied().map_or(0, u16::from)
+    }
+}
+
+/// The amount of nibbles for `sample_count` samples, including the frame headers
+#[must_use]
+pub const fn nibble_count(sample_count: usize) -> usize {
+    let remainder = sample_count % SAMPLES_PER_FRAME;
+    let full_frames = sample_count / SAMPLES_PER_FRAME * BYTES_PER_FRAME * 2;
+    if remainder == 0 {
+        full_frames
+    } else {
+        full_frames + remainder + 2
+    }
+}
+
+/// Encode the samples of one channel
+#[must_use]
+pub fn encode(samples: &[i16]) -> Encoded {
+    let predictors = find_predictors(samples);
+    let mut coefficients = [0; 0x10];
+    for (pair, predictor) in coefficients.chunks_exact_mut(2).zip(predictors) {
+        pair.copy_from_slice(&predictor);
+    }
+
+    let mut data = Vec::with_capacity(samples.len().div_ceil(SAMPLES_PER_FRAME) * BYTES_PER_FRAME);
+    let mut history = (0, 0);
+    for frame in samples.chunks(SAMPLES_PER_FRAME) {
+        let mut padded = [0; SAMPLES_PER_FRAME];
+        padded[..frame.len()].copy_from_slice(frame);
+
+        let mut best: Option<Candidate> = None;
+        for (index, predictor) in (0u8..).zip(predictors) {
+            let estimate = estimate_scale(&padded, predictor, history);
+            for scale in estimate..=(estimate + 1).min(MAX_SCALE) {
+                let candidate = encode_frame(&padded, index, predictor, scale, history);
+                if best
+                    .as_ref()
+                    .is_none_or(|best| candidate.error < best.error)
+                {
+                    best = Some(candidate);
+                }
+            }
+        }
+        let best = best.unwrap_or_else(|| unreachable!("There are always eight predictors"));
+        data.extend_from_slice(&best.frame);
+        history = best.history;
+    }
+
+    Encoded { coefficients, data }
+}
+
+/// An encoded frame and the state of the decoder after decoding it
+struct Candidate {
+    /// The encoded frame
+    frame: [u8; BYTES_PER_FRAME],
+    /// Sum of the squared differences between the input and the decoded samples
+    error: i64,
+    /// The last two decoded samples
+    history: (i32, i32),
+}
+
+/// The prediction for the next sample in 4.11 fixed point, rounded to an integer
+fn predict(predictor: [i16; 2], history: (i32, i32)) -> i32 {
+    let (coef1, coef2) = (i32::from(predictor[0]), i32::from(predictor[1]));
+    (coef1 * history.0 + coef2 * history.1 + 1024) >> 11
+}
+
+/// The smallest scale that fits the residuals of the frame when predicted from the input
+fn estimate_scale(frame: &[i16], predictor: [i16; 2], history: (i32, i32)) -> u32 {
+    let mut history = history;
+    let mut max = 0;
+    for sample in frame {
+        let sample = i32::from(*sample);
+        max = max.max((sample - predict(predictor, history)).abs());
+        history = (sample, history.0);
+    }
+    let mut scale = 0;
+    while scale < MAX_SCALE && max > 7 << scale {
+        scale += 1;
+    }
+    scale
+}
+
+/// Encode a frame with a fixed predictor and scale
+fn encode_frame(
+    frame: &[i16],
+    index: u8,
+    predictor: [i16; 2],
+    scale: u32,
+    history: (i32, i32),
+) -> Candidate {
+    let mut encoded = [0; BYTES_PER_FRAME];
+    encoded[0] = (index << 4) | u8::try_from(scale).unwrap_or_else(|_| unreachable!());
+    let mut history = history;
+    let mut error = 0;
+    let half_step = (1 << scale) >> 1;
+    for (i, sample) in frame.iter().enumerate() {
+        let sample = i32::from(*sample);
+        let prediction = predict(predictor, history);
+        let residual = sample - prediction;
+        let nibble = if residual >= 0 {
+            (residual + half_step) >> scale
+        } else {
+            -((half_step - residual) >> scale)
+        }
+        .clamp(-8, 7);
+        let decoded =
+            (prediction + (nibble << scale)).clamp(i32::from(i16::MIN), i32::from(i16::MAX));
+        error += i64::from(sample - decoded).pow(2);
+        history = (decoded, history.0);
+
+        let nibble = u8::try_from(nibble & 0xF).unwrap_or_else(|_| unreachable!());
+        encoded[1 + i / 2] |= if i % 2 == 0 { nibble << 4 } else { nibble };
+    }
+
+    Candidate {
+        frame: encoded,
+        error,
+        history,
+    }
+}
+
+/// Find eight coefficient pairs that fit the samples
+///
+/// Calculates the least squares predictor of every frame and clusters them with k-means.
+fn find_predictors(samples: &[i16]) -> [[i16; 2]; PREDICTORS] {
+    let mut optimal = Vec::with_capacity(samples.len().div_ceil(SAMPLES_PER_FRAME));
+    let mut history = (0.0, 0.0);
+    for frame in samples.chunks(SAMPLES_PER_FRAME) {
+        if let Some(predictor) = least_squares(frame, history) {
+            optimal.push(predictor);
+        }
+        for sample in frame {
+            history = (f64::from(*sample), history.0);
+        }
+    }
+    optimal.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+
+    // Start with a spread over the predictors, a zero predictor is always useful for silence
+    let mut centroids = [[0.0; 2]; PREDICTORS];
+    if !optimal.is_empty() {
+        for (i, centroid) in centroids.iter_mut().enumerate().skip(1) {
+            *centroid = optimal[(i * 2 - 1) * optimal.len() / (PREDICTORS * 2)];
+        }
+    }
+
+    let mut assigned = vec![0; optimal.len()];
+    for _ in 0..CLUSTER_PASSES {
+        for (predictor, assigned) in optimal.iter().zip(assigned.iter_mut()) {
+            *assigned = nearest(&centroids, *predictor);
+        }
+        for (i, centroid) in centroids.iter_mut().enumerate().skip(1) {
+            let mut sum = [0.0; 2];
+            let mut count = 0.0;
+            for (predictor, _) in optimal.iter().zip(&assigned).filter(|(_, a)| **a == i) {
+                sum[0] += predictor[0];
+                sum[1] += predictor[1];
+                count += 1.0;
+            }
+            // Keep the old centroid if no predictors were assigned to it
+            if count > 0.0 {
+                *centroid = [sum[0] / count, sum[1] / count];
+            }
+        }
+    }
+
+    centroids.map(|centroid| centroid.map(to_fixed))
+}
+
+/// Calculate the predictor that minimizes the squared error for the frame
+///
+/// Returns `None` if the frame is silent.
+fn least_squares(frame: &[i16], history: (f64, f64)) -> Option<[f64; 2]> {
+    let (mut r11, mut r12, mut r22, mut r1, mut r2) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    let mut history = history;
+    for sample in frame {
+        let sample = f64::from(*sample);
+        r11 += history.0 * history.0;
+        r12 += history.0 * history.1;
+        r22 += history.1 * history.1;
+        r1 += sample * history.0;
+        r2 += sample * history.1;
+        history = (sample, history.0);
+    }
+    if r11 <= 0.0 {
+        return None;
+    }
+
+    let determinant = r11 * r22 - r12 * r12;
+    let (coef1, coef2) = if determinant.abs() > f64::EPSILON * r11 * r22 {
+        (
+            (r1 * r22 - r2 * r12) / determinant,
+            (r2 * r11 - r1 * r12) / determinant,
+        )
+    } else {
+        // The history is a scaled copy of itself, a first order predictor is as good
+        (r1 / r11, 0.0)
+    };
+    // Keep the filter stable
+    let coef2 = coef2.clamp(-1.0, 1.0);
+    let coef1 = coef1.clamp(-2.0, 2.0);
+    Some([coef1, coef2])
+}
+
+/// The index of the centroid closest to `predictor`
+fn nearest(centroids: &[[f64; 2]; PREDICTORS], predictor: [f64; 2]) -> usize {
+    let distance = |c: &[f64; 2]| (c[0] - predictor[0]).powi(2) + (c[1] - predictor[1]).powi(2);
+    centroids
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+        .map_or(0, |(i, _)| i)
+}
+
+/// Convert a coefficient to 4.11 fixed point
+#[allow(
+    clippy::as_conversions,
+    clippy::cast_possible_truncation,
+    reason = "The value is clamped to the range of an i16"
+)]
+fn to_fixed(coefficient: f64) -> i16 {
+    (coefficient * 2048.0)
+        .round()
+        .clamp(f64::from(i16::MIN), f64::from(i16::MAX)) as i16
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nibble_count() {
+        assert_eq!(nibble_count(0), 0, "No samples is no nibbles");
+        assert_eq!(nibble_count(14), 16, "A full frame has a header");
+        assert_eq!(nibble_count(15), 19, "A partial frame also has a header");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let samples: Vec<i16> = (0..1000u16)
+            .map(|i| {
+                let t = f64::from(i) / 48.0;
+                let value = (t * 0.7).sin().mul_add(8000.0, (t * 2.3).sin() * 3000.0);
+                to_fixed(value / 2048.0)
+            })
+            .collect();
+        let encoded = encode(&samples);
+        assert_eq!(
+            encoded.data.len(),
+            72 * BYTES_PER_FRAME,
+            "Wrong amount of frames"
+        );
+
+        let state = gc_adpcm::Dsp {
+            hist1: 0,
+            hist2: 0,
+            coefficients: encoded.coefficients,
+        };
+        let decoded = gc_adpcm::Decoder::mono(&encoded.data, state, 72)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let max_error = samples
+            .iter()
+            .zip(&decoded)
+            .map(|(a, b)| (i32::from(*a) - i32::from(*b)).abs())
+            .max()
+            .unwrap();
+        assert!(
+            max_error < 64,
+            "Decoded audio is too different: {max_error}"
+        );
+    }
+}
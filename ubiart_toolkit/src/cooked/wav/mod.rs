@@ -1,3 +1,4 @@
+mod adpcm;
 mod parser;
 mod types;
 mod writer;
@@ -1,7 +1,7 @@
 use std::collections::{hash_map::Entry, HashMap};
 
 use dotstar_toolkit_utils::bytes::{
-    endian::{Endian, BE, LE},
+    endian::Endian,
     primitives::u32be,
     read::{BinaryDeserialize, ReadAtExt, ReadError},
 };
@@ -45,10 +45,7 @@ impl<'de> BinaryDeserialize<'de> for Wav<'de> {
         let platform = reader.read_at::<WavPlatform>(position)?;
         let codec = reader.read_at::<Codec>(position)?;
 
-        let endian = match platform {
-            WavPlatform::Wii | WavPlatform::WiiU | WavPlatform::PS3 | WavPlatform::X360 => BE,
-            _ => LE,
-        };
+        let endian = platform.endianness();
 
         let header_size = reader.read_at_with::<u32>(position, endian)?;
         let data_start_offset = reader.read_at_with::<u32>(position, endian)?;
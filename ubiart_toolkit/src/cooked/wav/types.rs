@@ -1,6 +1,7 @@
 use std::{borrow::Cow, collections::HashMap};
 
 use dotstar_toolkit_utils::bytes::{
+    endian::{Endian, BE, LE},
     primitives::u32be,
     read::{BinaryDeserialize, ReadAtExt, ReadError},
 };
@@ -35,6 +36,17 @@ pub enum WavPlatform {
     X360 = u32::from_be_bytes(*b"X360"),
 }
 
+impl WavPlatform {
+    /// The endianness of the header and chunks for this platform
+    #[must_use]
+    pub const fn endianness(self) -> Endian {
+        match self {
+            Self::Wii | Self::WiiU | Self::PS3 | Self::X360 => BE,
+            _ => LE,
+        }
+    }
+}
+
 impl TryFrom<u32> for WavPlatform {
     type Error = ReadError;
 
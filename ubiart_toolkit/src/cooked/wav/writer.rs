@@ -1,10 +1,11 @@
 use dotstar_toolkit_utils::bytes::{
-    primitives::{i16le, u16le, u32be, u32le},
+    endian::Endian,
+    primitives::u32be,
     write::{BinarySerialize, WriteAt, WriteError},
 };
-use test_eq::test_eq;
+use test_eq::{test_any, test_eq};
 
-use super::{AdIn, Chunk, Codec, Data, Fmt, Wav, WavPlatform};
+use super::{adpcm, AdIn, Chunk, Codec, Data, Fmt, Wav, WavPlatform};
 use crate::cooked::wav::Dsp;
 
 pub struct Writer;
@@ -23,6 +24,7 @@ impl Writer {
         Self::create(
             writer,
             position,
+            WavPlatform::Switch,
             Codec::PCM,
             &[Chunk::Fmt(fmt), Chunk::Data(Data { data: data.into() })],
             main_song,
@@ -40,6 +42,7 @@ impl Writer {
         Self::create(
             writer,
             position,
+            WavPlatform::Switch,
             Codec::Nx,
             &[
                 Chunk::Fmt(fmt),
@@ -52,13 +55,95 @@ impl Writer {
         )
     }
 
+    /// Create a Wii audio file with the DSP-ADPCM encoded `samples`
+    ///
+    /// The samples are interleaved, every channel is encoded with its own coefficients.
+    pub fn create_dsp(
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        fmt: Fmt,
+        samples: &[i16],
+        main_song: bool,
+    ) -> Result<(), WriteError> {
+        let channel_count = usize::from(fmt.channel_count);
+        test_any!(channel_count, 1..=2, "Only mono and stereo are supported")?;
+        test_eq!(
+            samples.len() % channel_count,
+            0,
+            "Incomplete sample for a channel"
+        )?;
+        let sample_count = samples.len() / channel_count;
+        let sample_rate = fmt.sample_rate;
+
+        let mut dsp_chunks = Vec::with_capacity(channel_count);
+        let mut data_chunks = Vec::with_capacity(channel_count);
+        for channel in 0..channel_count {
+            let channel_samples: Vec<_> = samples
+                .iter()
+                .skip(channel)
+                .step_by(channel_count)
+                .copied()
+                .collect();
+            let encoded = adpcm::encode(&channel_samples);
+            let nibble_count = u32::try_from(adpcm::nibble_count(sample_count))?;
+            let dsp = Dsp {
+                coefficients: encoded.coefficients,
+                sample_count: u32::try_from(sample_count)?,
+                nibble_count,
+                sample_rate,
+                loop_flag: false,
+                // The first two nibbles are the header of the first frame
+                loop_start_offset: 2,
+                loop_end_offset: nibble_count.saturating_sub(1),
+                current_address: 2,
+                gain: 0,
+                initial_predictor_scale: encoded.initial_predictor_scale(),
+                initial_sample_history_1: 0,
+                initial_sample_history_2: 0,
+                loop_context_predictor_scale: 0,
+                loop_context_sample_history_1: 0,
+                loop_context_sample_history_2: 0,
+            };
+            let data = Data {
+                data: encoded.data.into(),
+            };
+            if channel == 0 {
+                dsp_chunks.push(Chunk::DspL(dsp));
+                data_chunks.push(Chunk::DatL(data));
+            } else {
+                dsp_chunks.push(Chunk::DspR(dsp));
+                data_chunks.push(Chunk::DatR(data));
+            }
+        }
+
+        let mut chunks = vec![Chunk::Fmt(fmt)];
+        chunks.append(&mut dsp_chunks);
+        chunks.append(&mut data_chunks);
+        Self::create(
+            writer,
+            position,
+            WavPlatform::Wii,
+            Codec::Adpc,
+            &chunks,
+            main_song,
+        )
+    }
+
     fn create(
         writer: &mut (impl WriteAt + ?Sized),
         position: &mut u64,
+        platform: WavPlatform,
         codec: Codec,
         chunks: &[Chunk],
         main_song: bool,
     ) -> Result<(), WriteError> {
+        let endian = platform.endianness();
+        // The Wii files use an older version
+        let version = if platform == WavPlatform::Wii {
+            0x9
+        } else {
+            0xB
+        };
         let original_position = *position;
         let chunk_len = u32::try_from(chunks.len())?;
         let header_size = 32u32; // header size without the chunks
@@ -66,15 +151,16 @@ impl Writer {
 
         // write the header
         writer.write_at::<u32be>(position, Wav::MAGIC)?;
-        writer.write_at::<u32le>(position, 0xB)?;
-        writer.write_at::<WavPlatform>(position, WavPlatform::Switch)?;
+        writer.write_at_with_ctx::<u32>(position, version, endian)?;
+        writer.write_at::<WavPlatform>(position, platform)?;
         writer.write_at::<Codec>(position, codec)?;
         let total_header_size_pos = *position;
-        writer.write_at::<u32le>(position, 0)?; // header size
+        writer.write_at_with_ctx::<u32>(position, 0, endian)?; // header size
         let data_start_pos = *position;
-        writer.write_at::<u32le>(position, 0)?; // data start offset
-        writer.write_at::<u32le>(position, chunk_len)?; // number of chunks
-        writer.write_at::<u32le>(position, if main_song { 3 } else { 0 })?; // unk2
+        writer.write_at_with_ctx::<u32>(position, 0, endian)?; // data start offset
+        writer.write_at_with_ctx::<u32>(position, chunk_len, endian)?; // number of chunks
+        let unk2 = if main_song { 3 } else { 0 };
+        writer.write_at_with_ctx::<u32>(position, unk2, endian)?;
 
         let mut chunk_data_start =
             original_position + u64::from(header_size) + u64::from(chunk_header_size);
@@ -83,10 +169,10 @@ impl Writer {
             match chunk {
                 Chunk::Fmt(fmt) => {
                     writer.write_at::<u32be>(position, Fmt::MAGIC)?;
-                    writer.write_at::<u32le>(position, relative_chunk_data_start)?;
-                    writer.write_at::<u32le>(position, Fmt::NORMAL_SIZE)?;
+                    writer.write_at_with_ctx::<u32>(position, relative_chunk_data_start, endian)?;
+                    writer.write_at_with_ctx::<u32>(position, Fmt::NORMAL_SIZE, endian)?;
                     let mut chunk_data_start_copy = chunk_data_start;
-                    writer.write_at::<&Fmt>(&mut chunk_data_start_copy, fmt)?;
+                    writer.write_at_with_ctx::<&Fmt>(&mut chunk_data_start_copy, fmt, endian)?;
                     chunk_data_start += u64::from(Fmt::NORMAL_SIZE);
                     test_eq!(
                         chunk_data_start,
@@ -97,10 +183,10 @@ impl Writer {
                 }
                 Chunk::AdIn(adin) => {
                     writer.write_at::<u32be>(position, AdIn::MAGIC)?;
-                    writer.write_at::<u32le>(position, relative_chunk_data_start)?;
-                    writer.write_at::<u32le>(position, AdIn::SIZE)?;
+                    writer.write_at_with_ctx::<u32>(position, relative_chunk_data_start, endian)?;
+                    writer.write_at_with_ctx::<u32>(position, AdIn::SIZE, endian)?;
                     let mut chunk_data_start_copy = chunk_data_start;
-                    writer.write_at::<&AdIn>(&mut chunk_data_start_copy, adin)?;
+                    writer.write_at_with_ctx::<&AdIn>(&mut chunk_data_start_copy, adin, endian)?;
                     chunk_data_start += u64::from(AdIn::SIZE);
                     test_eq!(
                         chunk_data_start,
@@ -113,10 +199,10 @@ impl Writer {
                 Chunk::Strg(_) => unimplemented!(),
                 Chunk::DspL(dsp) | Chunk::DspR(dsp) => {
                     writer.write_at::<u32be>(position, chunk.magic())?;
-                    writer.write_at::<u32le>(position, relative_chunk_data_start)?;
-                    writer.write_at::<u32le>(position, Dsp::SIZE)?;
+                    writer.write_at_with_ctx::<u32>(position, relative_chunk_data_start, endian)?;
+                    writer.write_at_with_ctx::<u32>(position, Dsp::SIZE, endian)?;
                     let mut chunk_data_start_copy = chunk_data_start;
-                    writer.write_at::<&Dsp>(&mut chunk_data_start_copy, dsp)?;
+                    writer.write_at_with_ctx::<&Dsp>(&mut chunk_data_start_copy, dsp, endian)?;
                     chunk_data_start += u64::from(Dsp::SIZE);
                     test_eq!(
                         chunk_data_start,
@@ -136,8 +222,9 @@ impl Writer {
             match chunk {
                 Chunk::Data(data) | Chunk::DatS(data) | Chunk::DatL(data) | Chunk::DatR(data) => {
                     writer.write_at::<u32be>(position, chunk.magic())?;
-                    writer.write_at::<u32le>(position, relative_data_offset)?;
-                    writer.write_at::<u32le>(position, u32::try_from(data.data.len())?)?;
+                    writer.write_at_with_ctx::<u32>(position, relative_data_offset, endian)?;
+                    let size = u32::try_from(data.data.len())?;
+                    writer.write_at_with_ctx::<u32>(position, size, endian)?;
                     writer.write_slice_at(&mut data_offset, &data.data)?;
                     relative_data_offset += u32::try_from(data.data.len())?;
                 }
@@ -146,9 +233,10 @@ impl Writer {
         }
 
         *position = total_header_size_pos;
-        writer.write_at::<u32le>(position, relative_chunk_data_start)?; // header size
+        let total_header_size = relative_chunk_data_start;
+        writer.write_at_with_ctx::<u32>(position, total_header_size, endian)?;
         *position = data_start_pos;
-        writer.write_at::<u32le>(position, data_start)?; // data start offset
+        writer.write_at_with_ctx::<u32>(position, data_start, endian)?; // data start offset
 
         Ok(())
     }
@@ -183,79 +271,79 @@ impl BinarySerialize for Codec {
 }
 
 impl BinarySerialize for Fmt<'_> {
-    type Ctx = ();
+    type Ctx = Endian;
     type Input = Self;
 
     fn serialize_at_with_ctx(
         input: Self::Input,
         writer: &mut (impl WriteAt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        endian: Self::Ctx,
     ) -> Result<(), WriteError> {
-        writer.write_at::<&Fmt>(position, &input)
+        writer.write_at_with_ctx::<&Fmt>(position, &input, endian)
     }
 }
 
 impl BinarySerialize for &Fmt<'_> {
-    type Ctx = ();
+    type Ctx = Endian;
     type Input = Self;
 
     fn serialize_at_with_ctx(
         fmt: Self::Input,
         writer: &mut (impl WriteAt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        endian: Self::Ctx,
     ) -> Result<(), WriteError> {
-        writer.write_at::<u16le>(position, fmt.unk1)?;
-        writer.write_at::<u16le>(position, fmt.channel_count)?;
-        writer.write_at::<u32le>(position, fmt.sample_rate)?;
-        writer.write_at::<u32le>(position, fmt.total_samples_hz)?;
-        writer.write_at::<u16le>(position, fmt.block_align)?;
-        writer.write_at::<u16le>(position, fmt.bits_per_sample)?;
+        writer.write_at_with_ctx::<u16>(position, fmt.unk1, endian)?;
+        writer.write_at_with_ctx::<u16>(position, fmt.channel_count, endian)?;
+        writer.write_at_with_ctx::<u32>(position, fmt.sample_rate, endian)?;
+        writer.write_at_with_ctx::<u32>(position, fmt.total_samples_hz, endian)?;
+        writer.write_at_with_ctx::<u16>(position, fmt.block_align, endian)?;
+        writer.write_at_with_ctx::<u16>(position, fmt.bits_per_sample, endian)?;
         Ok(())
     }
 }
 
 impl BinarySerialize for Dsp {
-    type Ctx = ();
+    type Ctx = Endian;
     type Input = Self;
 
     fn serialize_at_with_ctx(
         input: Self::Input,
         writer: &mut (impl WriteAt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        endian: Self::Ctx,
     ) -> Result<(), WriteError> {
-        writer.write_at::<&Self>(position, &input)
+        writer.write_at_with_ctx::<&Self>(position, &input, endian)
     }
 }
 
 impl BinarySerialize for &Dsp {
-    type Ctx = ();
+    type Ctx = Endian;
     type Input = Self;
 
     fn serialize_at_with_ctx(
         input: Self::Input,
         writer: &mut (impl WriteAt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        endian: Self::Ctx,
     ) -> Result<(), WriteError> {
-        writer.write_at::<u32le>(position, input.sample_count)?;
-        writer.write_at::<u32le>(position, input.nibble_count)?;
-        writer.write_at::<u32le>(position, input.sample_rate)?;
-        writer.write_at::<u16le>(position, u16::from(input.loop_flag))?;
-        writer.write_at::<u16le>(position, 0)?; // format
-        writer.write_at::<u32le>(position, input.loop_start_offset)?;
-        writer.write_at::<u32le>(position, input.loop_end_offset)?;
-        writer.write_at::<u32le>(position, input.current_address)?;
-        writer.write_at::<[i16le; 0x10]>(position, input.coefficients)?;
-        writer.write_at::<u16le>(position, input.gain)?;
-        writer.write_at::<u16le>(position, input.initial_predictor_scale)?;
-        writer.write_at::<i16le>(position, input.initial_sample_history_1)?;
-        writer.write_at::<i16le>(position, input.initial_sample_history_2)?;
-        writer.write_at::<u16le>(position, input.loop_context_predictor_scale)?;
-        writer.write_at::<i16le>(position, input.loop_context_sample_history_1)?;
-        writer.write_at::<i16le>(position, input.loop_context_sample_history_2)?;
+        writer.write_at_with_ctx::<u32>(position, input.sample_count, endian)?;
+        writer.write_at_with_ctx::<u32>(position, input.nibble_count, endian)?;
+        writer.write_at_with_ctx::<u32>(position, input.sample_rate, endian)?;
+        writer.write_at_with_ctx::<u16>(position, u16::from(input.loop_flag), endian)?;
+        writer.write_at_with_ctx::<u16>(position, 0, endian)?; // format
+        writer.write_at_with_ctx::<u32>(position, input.loop_start_offset, endian)?;
+        writer.write_at_with_ctx::<u32>(position, input.loop_end_offset, endian)?;
+        writer.write_at_with_ctx::<u32>(position, input.current_address, endian)?;
+        writer.write_at_with_ctx::<[i16; 0x10]>(position, input.coefficients, endian)?;
+        writer.write_at_with_ctx::<u16>(position, input.gain, endian)?;
+        writer.write_at_with_ctx::<u16>(position, input.initial_predictor_scale, endian)?;
+        writer.write_at_with_ctx::<i16>(position, input.initial_sample_history_1, endian)?;
+        writer.write_at_with_ctx::<i16>(position, input.initial_sample_history_2, endian)?;
+        writer.write_at_with_ctx::<u16>(position, input.loop_context_predictor_scale, endian)?;
+        writer.write_at_with_ctx::<i16>(position, input.loop_context_sample_history_1, endian)?;
+        writer.write_at_with_ctx::<i16>(position, input.loop_context_sample_history_2, endian)?;
         writer.write_slice_at(position, &[0; 22])?;
 
         Ok(())
@@ -263,29 +351,29 @@ impl BinarySerialize for &Dsp {
 }
 
 impl BinarySerialize for AdIn {
-    type Ctx = ();
+    type Ctx = Endian;
     type Input = Self;
 
     fn serialize_at_with_ctx(
         input: Self::Input,
         writer: &mut (impl WriteAt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        endian: Self::Ctx,
     ) -> Result<(), WriteError> {
-        writer.write_at::<&Self>(position, &input)
+        writer.write_at_with_ctx::<&Self>(position, &input, endian)
     }
 }
 
 impl BinarySerialize for &AdIn {
-    type Ctx = ();
+    type Ctx = Endian;
     type Input = Self;
 
     fn serialize_at_with_ctx(
         input: Self::Input,
         writer: &mut (impl WriteAt + ?Sized),
         position: &mut u64,
-        _ctx: Self::Ctx,
+        endian: Self::Ctx,
     ) -> Result<(), WriteError> {
-        writer.write_at::<u32le>(position, input.num_of_samples)
+        writer.write_at_with_ctx::<u32>(position, input.num_of_samples, endian)
     }
 }
@@ -1,6 +1,9 @@
 use std::{
+    collections::HashMap,
     fs::File,
+    hash::{DefaultHasher, Hasher},
     io::{BufWriter, Write},
+    num::NonZeroUsize,
     path::Path,
 };
 
@@ -8,9 +11,8 @@ use dotstar_toolkit_utils::{
     bytes::{
         primitives::{u32be, u64be},
         write::{WriteAt, WriteError},
-        CursorAt,
     },
-    vfs::{VirtualFileSystem, VirtualPath},
+    vfs::{VirtualFile, VirtualFileSystem, VirtualPath},
 };
 use flate2::{write::ZlibEncoder, Compression};
 use test_eq::test_eq;
@@ -25,6 +27,8 @@ pub struct Options {
     pub game_platform: UniqueGameId,
     pub unk4: u32,
     pub engine_version: u32,
+    /// The amount of threads to use for compressing files
+    pub threads: NonZeroUsize,
 }
 
 impl Default for Options {
@@ -34,6 +38,7 @@ impl Default for Options {
             game_platform: UniqueGameId::try_from(0x1DDB_2268).unwrap_or_else(|_| unreachable!()),
             unk4: 0x0009_37D0,
             engine_version: 0x0004_FD39,
+            threads: NonZeroUsize::MIN,
         }
     }
 }
@@ -75,16 +80,159 @@ impl From<ZopfliOptions> for zopfli::Options {
     }
 }
 
+/// Statistics about a written bundle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// The amount of files in the bundle
+    pub files: usize,
+    /// The amount of files that share their data with an identical file
+    pub deduplicated_files: usize,
+    /// The total size of the files before compression and deduplication
+    pub uncompressed_size: u64,
+    /// The size of the data stored in the bundle
+    pub stored_size: u64,
+    /// The amount of bytes that deduplication saved
+    pub saved_size: u64,
+}
+
+/// Metadata of a file whose content has been written
 #[derive(Clone, Copy, Debug)]
-struct ReducedMetadata<'a> {
+struct ReducedMetadata {
     pub size: u64,
     pub compressed: u64,
     pub offset: u64,
-    pub timestamp: u64,
-    pub path: &'a VirtualPath,
 }
 
-const STATIC_HEADER_SIZE: usize = 0x30;
+const STATIC_HEADER_SIZE: u64 = 0x30;
+
+/// This is presumably a timestamp, but the values don't add up. So we use a static value.
+const TIMESTAMP: u64 = 132_761_939_258_059_932;
+
+/// Check if there are 4 null bytes between the header and the content of the files
+///
+/// This is the case for JD2020-JD2022 on NX.
+fn has_separator(game_platform: UniqueGameId) -> bool {
+    game_platform.platform == Platform::Nx
+        && (game_platform.game == Game::JustDance2020
+            || game_platform.game == Game::JustDance2021
+            || game_platform.game == Game::JustDance2022)
+}
+
+/// Create a fingerprint of the content of a file for deduplication
+///
+/// The fingerprint combines the size with two independent hashes, so that files with different
+/// content almost never have the same fingerprint. Files with the same fingerprint are still
+/// compared byte for byte before they are deduplicated.
+fn fingerprint(content: &[u8]) -> Result<(u64, u64, u64), WriteError> {
+    let mut first = DefaultHasher::new();
+    first.write(content);
+    let mut second = DefaultHasher::new();
+    second.write_u8(0xFF);
+    second.write(content);
+    Ok((
+        u64::try_from(content.len())?,
+        first.finish(),
+        second.finish(),
+    ))
+}
+
+/// Compress `content` if it is useful for the file at `path`
+///
+/// Returns `None` if the file should be stored uncompressed.
+fn compress(
+    path: &VirtualPath,
+    content: &[u8],
+    compression: CompressionEffort,
+) -> Result<Option<Vec<u8>>, WriteError> {
+    // File content can be stored compressed.
+    // Skip compression for small files, and already compressed files.
+    if content.len() < 2048
+        || path.extension() == Some("jpg")
+        || path.extension() == Some("webm")
+        || path.extension() == Some("ogg")
+        || path.extension() == Some("png")
+    {
+        return Ok(None);
+    }
+
+    // Compress if enabled
+    match compression {
+        // Caller has disabled compression, so write uncompressed content
+        CompressionEffort::None => Ok(None),
+        CompressionEffort::Best => {
+            // Compress with flate2
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(content)?;
+            Ok(Some(encoder.finish()?))
+        }
+        #[cfg(feature = "zopfli")]
+        CompressionEffort::Zopfli(provided_options) => {
+            let mut compressed = Vec::new();
+            // Zopfli encoder consumes the writer
+            let mut encoder = zopfli::DeflateEncoder::new(
+                provided_options.into(),
+                zopfli::BlockType::default(),
+                &mut compressed,
+            );
+            encoder.write_all(content)?;
+            encoder.finish()?;
+            Ok(Some(compressed))
+        }
+    }
+}
+
+/// A file that has been opened, fingerprinted and compressed
+struct PreparedFile<'f> {
+    /// The content of the file
+    content: VirtualFile<'f>,
+    /// The fingerprint of the content
+    fingerprint: (u64, u64, u64),
+    /// The compressed content, `None` if the file should be stored uncompressed
+    compressed: Option<Vec<u8>>,
+}
+
+/// Open, fingerprint and compress `paths` divided over `threads` threads
+///
+/// The prepared files are returned in the same order as `paths`.
+fn prepare_files<'f>(
+    vfs: &'f impl VirtualFileSystem,
+    paths: &[&VirtualPath],
+    compression: CompressionEffort,
+    threads: NonZeroUsize,
+) -> Result<Vec<PreparedFile<'f>>, WriteError> {
+    let chunk_size = paths.len().div_ceil(threads.get()).max(1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| -> Result<PreparedFile<'f>, WriteError> {
+                            let content = vfs.open(path)?;
+                            let fingerprint = fingerprint(&content)?;
+                            let compressed = compress(path, &content, compression)?;
+                            Ok(PreparedFile {
+                                content,
+                                fingerprint,
+                                compressed,
+                            })
+                        })
+                        .collect::<Result<Vec<_>, WriteError>>()
+                })
+            })
+            .collect();
+
+        let mut prepared = Vec::with_capacity(paths.len());
+        for handle in handles {
+            let chunk = handle
+                .join()
+                .map_err(|_| WriteError::custom("Compression thread panicked!".to_string()))??;
+            prepared.extend(chunk);
+        }
+        Ok(prepared)
+    })
+}
 
 /// Create a secure_fat.gf file at the path
 pub fn create(
@@ -92,170 +240,491 @@ pub fn create(
     options: Options,
     vfs: &impl VirtualFileSystem,
     files: &[&VirtualPath],
-) -> Result<(), WriteError> {
+) -> Result<Statistics, WriteError> {
     let file = File::create(path)?;
     let mut writer = BufWriter::new(file);
     write(&mut writer, &mut 0, options, vfs, files)
 }
 
-/// Create an .ipk file with the specified files.
-// TODO: Add deduplication
+/// Create an .ipk file with the specified files at `position`.
+///
+/// The files are opened a few at a time, so the bundle never needs to fit in memory.
+/// Files with identical content are only stored once, with all their metadata pointing to the same data.
+///
+/// If `options.threads` is more than one, the files are compressed in parallel. They are still
+/// written in the order of `files`, so the result does not depend on the amount of threads.
 #[instrument(skip(writer, vfs, files))]
 pub fn write(
-    mut writer: &mut (impl WriteAt + ?Sized),
+    writer: &mut (impl WriteAt + ?Sized),
     position: &mut u64,
     options: Options,
     vfs: &impl VirtualFileSystem,
     files: &[&VirtualPath],
-) -> Result<(), WriteError> {
-    // TODO: Make this code position independent
-    assert_eq!(
-        *position, 0,
-        "TODO: This code is not yet position independent!"
-    );
-    // let static_header_size = *position + u64::try_from(STATIC_HEADER_SIZE)?;
-    // Calculate the size of the header, starting with the static size
-    let mut base_offset = STATIC_HEADER_SIZE;
-
-    // On NX, JD2020-JD2022 have 4 null bytes between the header and the content of the files
-    if options.game_platform.platform == Platform::Nx
-        && (options.game_platform.game == Game::JustDance2020
-            || options.game_platform.game == Game::JustDance2021
-            || options.game_platform.game == Game::JustDance2022)
-    {
-        base_offset += 0x4;
+) -> Result<Statistics, WriteError> {
+    let mut ipk_writer = Writer::new(writer, *position, options, vfs, files)?;
+    if options.threads == NonZeroUsize::MIN {
+        for path in files {
+            ipk_writer.add_file(path)?;
+        }
+    } else {
+        // Only prepare a limited amount of files at once, to limit memory usage
+        let chunk_size = options.threads.get().saturating_mul(4);
+        for chunk in files.chunks(chunk_size) {
+            let prepared = prepare_files(vfs, chunk, options.compression, options.threads)?;
+            for (path, file) in chunk.iter().zip(prepared) {
+                let duplicate = ipk_writer.duplicate_of(file.fingerprint, &file.content)?;
+                ipk_writer.store(
+                    path,
+                    &file.content,
+                    file.fingerprint,
+                    duplicate,
+                    file.compressed.as_deref(),
+                )?;
+            }
+        }
+    }
+    *position = ipk_writer.position();
+    ipk_writer.finish()
+}
+
+/// A streaming writer for .ipk files
+///
+/// The header contains the metadata of every file, so its size depends on the paths of the files.
+/// Therefore all paths need to be known when creating the writer. The files can then be added in
+/// any order, their content is read from the filesystem and written directly to the underlying
+/// writer. The header is written last by [`Writer::finish`].
+pub struct Writer<'a, 'w, W: WriteAt + ?Sized, V: VirtualFileSystem> {
+    /// The underlying writer
+    writer: &'w mut W,
+    /// The filesystem containing the files
+    vfs: &'a V,
+    /// The options for the .ipk
+    options: Options,
+    /// The position of the start of the .ipk
+    start: u64,
+    /// The position of the start of the file content, the offsets in the metadata are relative to this
+    base_offset: u64,
+    /// The position where the content of the next file will be written
+    position: u64,
+    /// The paths of the files in the order they are listed in the header
+    paths: &'a [&'a VirtualPath],
+    /// Maps the paths to their index in `paths`
+    indices: HashMap<&'a VirtualPath, usize>,
+    /// The metadata for every file in `paths`, `None` if the file has not been added yet
+    metadata: Vec<Option<ReducedMetadata>>,
+    /// Maps the fingerprint of the file content to the index of the first file with that content
+    fingerprints: HashMap<(u64, u64, u64), usize>,
+    /// Statistics about the files written so far
+    statistics: Statistics,
+}
+
+impl<'a, 'w, W: WriteAt + ?Sized, V: VirtualFileSystem> Writer<'a, 'w, W, V> {
+    /// Create a new writer for an .ipk at `position` that will contain `paths`
+    ///
+    /// # Errors
+    /// Will error if a path is listed more than once or if the header would be too big
+    pub fn new(
+        writer: &'w mut W,
+        position: u64,
+        options: Options,
+        vfs: &'a V,
+        paths: &'a [&'a VirtualPath],
+    ) -> Result<Self, WriteError> {
+        // Calculate the size of the header, starting with the static size
+        let mut header_size = STATIC_HEADER_SIZE;
+        if has_separator(options.game_platform) {
+            header_size += 0x4;
+        }
+
+        // Add the static metadata size for every file plus the length of the path
+        let mut indices = HashMap::with_capacity(paths.len());
+        for (index, path) in paths.iter().enumerate() {
+            header_size += 0x2C + u64::try_from(path.as_str().len())?; // metadata size + path length
+            if indices.insert(*path, index).is_some() {
+                return Err(WriteError::custom(format!(
+                    "{path} is listed more than once!"
+                )));
+            }
+        }
+        // The header size is stored as a u32
+        u32::try_from(header_size)?;
+
+        let base_offset = position.checked_add(header_size).ok_or_else(|| {
+            WriteError::custom(format!(
+                "Cannot add {position} and {header_size}, it would overflow!"
+            ))
+        })?;
+
+        Ok(Self {
+            writer,
+            vfs,
+            options,
+            start: position,
+            base_offset,
+            position: base_offset,
+            paths,
+            indices,
+            metadata: vec![None; paths.len()],
+            fingerprints: HashMap::with_capacity(paths.len()),
+            statistics: Statistics {
+                files: paths.len(),
+                ..Default::default()
+            },
+        })
     }
 
-    // Add the static metadata size for every file plus the length of the path
-    for path in files {
-        base_offset += 0x2C + path.as_str().len(); // metadata size + path length
+    /// The position after the content of the last added file
+    ///
+    /// After all files are added this is the end of the .ipk.
+    #[must_use]
+    pub const fn position(&self) -> u64 {
+        self.position
     }
 
-    // Start writing the header
-    writer.write_at::<u32be>(position, MAGIC)?;
-    writer.write_at::<u32be>(position, 0x5)?; // version
-    writer.write_at::<Platform>(position, options.game_platform.platform)?;
-    writer.write_at::<u32be>(position, u32::try_from(base_offset)?)?;
-    writer.write_at::<u32be>(position, u32::try_from(files.len())?)?;
-    writer.write_at::<u32be>(position, 0x0)?; // unk1
-    writer.write_at::<u32be>(position, 0x0)?; // unk2
-    writer.write_at::<u32be>(position, 0x0)?; // unk3
-    writer.write_at::<u32be>(position, options.unk4)?;
-    writer.write_at::<UniqueGameId>(position, options.game_platform)?;
-    writer.write_at::<u32be>(position, options.engine_version)?;
-    writer.write_at::<u32be>(position, u32::try_from(files.len())?)?;
-
-    // Skip the file metadata for now, as it depends on compression results
-    let base_offset = u64::try_from(base_offset)?;
-    *position = base_offset;
-
-    // For keeping track of the relevant metadata that needs to be written to the header
-    let mut reduced_metadata = Vec::with_capacity(files.len());
-
-    // Write the content of all files, while filling `reduced_metadata`
-    for path in files {
+    /// Read the file at `path` from the filesystem and write its content
+    ///
+    /// # Errors
+    /// Will error if `path` was not listed when creating the writer, if it was already added,
+    /// or if reading or writing fails
+    pub fn add_file(&mut self, path: &VirtualPath) -> Result<(), WriteError> {
+        let content = self.vfs.open(path)?;
+        let fingerprint = fingerprint(&content)?;
+        let duplicate = self.duplicate_of(fingerprint, &content)?;
+        // Don't bother compressing content that will be deduplicated
+        let compressed = if duplicate.is_some() {
+            None
+        } else {
+            compress(path, &content, self.options.compression)?
+        };
+        self.store(
+            path,
+            &content,
+            fingerprint,
+            duplicate,
+            compressed.as_deref(),
+        )
+    }
+
+    /// Find the metadata of an earlier file with exactly the same content
+    ///
+    /// The fingerprint is only used to find a candidate, which is then compared byte for byte.
+    fn duplicate_of(
+        &self,
+        fingerprint: (u64, u64, u64),
+        content: &[u8],
+    ) -> Result<Option<ReducedMetadata>, WriteError> {
+        let Some(index) = self.fingerprints.get(&fingerprint).copied() else {
+            return Ok(None);
+        };
+        let original = self.vfs.open(self.paths[index])?;
+        if *original == *content {
+            Ok(self.metadata[index])
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Store the file at `path`, writing `compressed` instead of `content` if it is not `None`
+    ///
+    /// If `duplicate` is not `None`, the file reuses the data of that earlier file.
+    fn store(
+        &mut self,
+        path: &VirtualPath,
+        content: &[u8],
+        fingerprint: (u64, u64, u64),
+        duplicate: Option<ReducedMetadata>,
+        compressed: Option<&[u8]>,
+    ) -> Result<(), WriteError> {
+        let index = *self.indices.get(path).ok_or_else(|| {
+            WriteError::custom(format!("{path} was not listed when creating the writer!"))
+        })?;
+        if self.metadata[index].is_some() {
+            return Err(WriteError::custom(format!("{path} was already added!")));
+        }
+
+        let size = u64::try_from(content.len())?;
+        self.statistics.uncompressed_size += size;
+
+        // Reuse the data of an earlier file if the content is identical
+        if let Some(original) = duplicate {
+            self.statistics.deduplicated_files += 1;
+            self.statistics.saved_size += if original.compressed == 0 {
+                original.size
+            } else {
+                original.compressed
+            };
+            self.metadata[index] = Some(original);
+            return Ok(());
+        }
+
         // The offset from the start of the file
         // NB: the metadata stores the offset relevant to the end of the header
-        let raw_offset = *position;
-        // This is presumably a timestamp, but the values don't add up. So we use a static value.
-        let timestamp = 132_761_939_258_059_932;
-
-        // Open the file and get the size
-        let file = vfs.open(path.as_ref())?;
-        let size = u64::try_from(file.len())?;
-
-        // File content can be stored compressed.
-        // Skip compression for small files, and already compressed files.
-        let compressed = if size < 2048
-            || path.extension() == Some("jpg")
-            || path.extension() == Some("webm")
-            || path.extension() == Some("ogg")
-            || path.extension() == Some("png")
-        {
-            writer.write_slice_at(position, &file)?;
+        let raw_offset = self.position;
+
+        let compressed = if let Some(compressed) = compressed {
+            self.writer.write_slice_at(&mut self.position, compressed)?;
+            // Return compressed size
+            self.position - raw_offset
+        } else {
+            self.writer.write_slice_at(&mut self.position, content)?;
             // No compression thus compressed size is 0
             0
-        } else {
-            // Compress if enabled
-            match options.compression {
-                CompressionEffort::None => {
-                    // Caller has disabled compression, so write uncompressed content
-                    writer.write_slice_at(position, &file)?;
-                    // No compression thus compressed size is 0
-                    0
-                }
-                CompressionEffort::Best => {
-                    // Compress with flate2
-                    let cursor = CursorAt::new(writer, *position);
-                    let mut encoder = ZlibEncoder::new(cursor, Compression::best());
-                    encoder.write_all(&file)?;
-                    (writer, *position) = encoder.finish()?.into_inner();
-                    // Return compressed size
-                    *position - raw_offset
-                }
-                #[cfg(feature = "zopfli")]
-                CompressionEffort::Zopfli(provided_options) => {
-                    let mut cursor = CursorAt::new(writer, *position);
-                    // Zopfli encoder consumes the writer
-                    let mut encoder = zopfli::DeflateEncoder::new(
-                        provided_options.into(),
-                        zopfli::BlockType::default(),
-                        &mut cursor,
-                    );
-                    encoder.write_all(&file)?;
-                    // Writer is returned at finish
-                    encoder.finish()?;
-                    *position = cursor.into_inner().1;
-                    // Return compressed size
-                    *position - raw_offset
-                }
-            }
         };
 
         // Save the reduced metadata
         let metadata = ReducedMetadata {
             size,
             compressed,
-            offset: raw_offset - base_offset,
-            timestamp,
-            path,
+            offset: raw_offset - self.base_offset,
         };
-        reduced_metadata.push(metadata);
+        // Keep the first file, a later file with the same fingerprint has different content
+        self.fingerprints.entry(fingerprint).or_insert(index);
+        self.metadata[index] = Some(metadata);
+
+        Ok(())
     }
 
-    // Go back to the start of the metadata portion of the header
-    *position = u64::try_from(STATIC_HEADER_SIZE).unwrap_or_else(|_| unreachable!());
-
-    // Write all the metadata
-    for metadata in &reduced_metadata {
-        // Convert the path into a `SplitPath`
-        let path =
-            SplitPath::try_from(metadata.path).map_err(|e| WriteError::custom(format!("{e:?}")))?;
-        // Write the file metadata
-        writer.write_at::<u32be>(position, 0x1)?; // unk1
-        writer.write_at::<u32be>(position, u32::try_from(metadata.size)?)?;
-        writer.write_at::<u32be>(position, u32::try_from(metadata.compressed)?)?;
-        writer.write_at::<u64be>(position, metadata.timestamp)?;
-        writer.write_at::<u64be>(position, metadata.offset)?;
-        writer.write_at::<SplitPath>(position, path)?;
-        // The SplitPath padding byte is reused as a cooked indicator
-        *position -= 4;
-        if metadata.path.starts_with("cache/itf_cooked") {
-            writer.write_at::<u32be>(position, 0x2)?;
-        } else {
-            writer.write_at::<u32be>(position, 0)?;
+    /// Write the header, finishing the .ipk
+    ///
+    /// # Errors
+    /// Will error if not all files have been added or if writing fails
+    pub fn finish(self) -> Result<Statistics, WriteError> {
+        let Self {
+            writer,
+            options,
+            start,
+            base_offset,
+            position: end,
+            paths,
+            metadata,
+            mut statistics,
+            ..
+        } = self;
+
+        // Start writing the header
+        let mut position = start;
+        let header_size = u32::try_from(base_offset - start)?;
+        let n_files = u32::try_from(paths.len())?;
+        writer.write_at::<u32be>(&mut position, MAGIC)?;
+        writer.write_at::<u32be>(&mut position, 0x5)?; // version
+        writer.write_at::<Platform>(&mut position, options.game_platform.platform)?;
+        writer.write_at::<u32be>(&mut position, header_size)?;
+        writer.write_at::<u32be>(&mut position, n_files)?;
+        writer.write_at::<u32be>(&mut position, 0x0)?; // unk1
+        writer.write_at::<u32be>(&mut position, 0x0)?; // unk2
+        writer.write_at::<u32be>(&mut position, 0x0)?; // unk3
+        writer.write_at::<u32be>(&mut position, options.unk4)?;
+        writer.write_at::<UniqueGameId>(&mut position, options.game_platform)?;
+        writer.write_at::<u32be>(&mut position, options.engine_version)?;
+        writer.write_at::<u32be>(&mut position, n_files)?;
+
+        // Write all the metadata
+        for (path, metadata) in paths.iter().zip(metadata) {
+            let Some(metadata) = metadata else {
+                return Err(WriteError::custom(format!("{path} was never added!")));
+            };
+            // Convert the path into a `SplitPath`
+            let split_path =
+                SplitPath::try_from(*path).map_err(|e| WriteError::custom(format!("{e:?}")))?;
+            // Write the file metadata
+            writer.write_at::<u32be>(&mut position, 0x1)?; // unk1
+            writer.write_at::<u32be>(&mut position, u32::try_from(metadata.size)?)?;
+            writer.write_at::<u32be>(&mut position, u32::try_from(metadata.compressed)?)?;
+            writer.write_at::<u64be>(&mut position, TIMESTAMP)?;
+            writer.write_at::<u64be>(&mut position, metadata.offset)?;
+            writer.write_at::<SplitPath>(&mut position, split_path)?;
+            // The SplitPath padding byte is reused as a cooked indicator
+            position -= 4;
+            if path.starts_with("cache/itf_cooked") {
+                writer.write_at::<u32be>(&mut position, 0x2)?;
+            } else {
+                writer.write_at::<u32be>(&mut position, 0)?;
+            }
+        }
+
+        if has_separator(options.game_platform) {
+            writer.write_at::<u32be>(&mut position, 0x0)?; // unknown seperator between metadata and data
+        }
+
+        test_eq!(position, base_offset)?;
+
+        statistics.stored_size = end - base_offset;
+        Ok(statistics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::{
+        bytes::read::BinaryDeserialize,
+        vfs::{vecfs::VecFs, VirtualPathBuf},
+    };
+
+    use super::*;
+    use crate::ipk::{Bundle, Data};
+
+    #[test]
+    fn test_deduplication() {
+        let shared = vec![0xAB; 4096];
+        let mut vfs = VecFs::new();
+        vfs.add_file(
+            VirtualPathBuf::from("world/maps/a/picto.png"),
+            shared.clone(),
+        )
+        .unwrap();
+        vfs.add_file(
+            VirtualPathBuf::from("world/maps/b/picto.png"),
+            shared.clone(),
+        )
+        .unwrap();
+        vfs.add_file(
+            VirtualPathBuf::from("world/maps/c/picto.png"),
+            vec![0xCD; 4096],
+        )
+        .unwrap();
+        let files: Vec<_> = vfs.walk_filesystem("".as_ref()).unwrap().collect();
+
+        let mut data = Vec::new();
+        let statistics = write(&mut data, &mut 0, Options::default(), &vfs, &files).unwrap();
+        assert_eq!(statistics.files, 3, "Wrong amount of files");
+        assert_eq!(
+            statistics.deduplicated_files, 1,
+            "One file should be deduplicated"
+        );
+        assert_eq!(
+            statistics.saved_size, 4096,
+            "One file of data should be saved"
+        );
+        assert_eq!(
+            statistics.stored_size,
+            2 * 4096,
+            "Only two files should be stored"
+        );
+
+        let bundle = Bundle::deserialize_with(&data, false).unwrap();
+        assert_eq!(bundle.files.len(), 3, "All files should still be listed");
+        for file in bundle.files.values() {
+            let Data::Uncompressed(content) = &file.data else {
+                panic!("PNG files are never compressed");
+            };
+            let expected = vfs.open(VirtualPathBuf::from(file.path.to_string()).as_ref());
+            assert_eq!(
+                content.data.as_ref(),
+                &*expected.unwrap(),
+                "Content of {} changed",
+                file.path
+            );
         }
     }
 
-    if options.game_platform.platform == Platform::Nx
-        && (options.game_platform.game == Game::JustDance2020
-            || options.game_platform.game == Game::JustDance2021
-            || options.game_platform.game == Game::JustDance2022)
-    {
-        writer.write_at::<u32be>(position, 0x0)?; // unknown seperator between metadata and data
+    #[test]
+    fn test_position_independent() {
+        let mut vfs = VecFs::new();
+        vfs.add_file(VirtualPathBuf::from("world/maps/a/a.ckd"), vec![0x1; 4096])
+            .unwrap();
+        vfs.add_file(VirtualPathBuf::from("world/maps/a/b.ckd"), vec![0x2; 16])
+            .unwrap();
+        let files: Vec<_> = vfs.walk_filesystem("".as_ref()).unwrap().collect();
+
+        // Start after some unrelated data and add the files in reverse order
+        let mut data = vec![0xFF; 16];
+        let mut writer = Writer::new(&mut data, 16, Options::default(), &vfs, &files).unwrap();
+        for path in files.iter().rev() {
+            writer.add_file(path).unwrap();
+        }
+        let end = writer.position();
+        writer.finish().unwrap();
+        assert_eq!(
+            end,
+            u64::try_from(data.len()).unwrap(),
+            "Position should be at the end of the .ipk"
+        );
+        assert_eq!(&data[..16], &[0xFF; 16], "Data before the .ipk was changed");
+
+        let bundle = Bundle::deserialize_with(&data[16..], false).unwrap();
+        assert_eq!(bundle.files.len(), 2, "All files should be listed");
+        for file in bundle.files.values() {
+            let Data::Uncompressed(content) = &file.data else {
+                panic!("Compression is disabled");
+            };
+            let expected = vfs.open(VirtualPathBuf::from(file.path.to_string()).as_ref());
+            assert_eq!(
+                content.data.as_ref(),
+                &*expected.unwrap(),
+                "Content of {} changed",
+                file.path
+            );
+        }
     }
 
-    test_eq!(*position, base_offset)?;
+    #[test]
+    fn test_parallel_deterministic() {
+        let mut vfs = VecFs::new();
+        for i in 0..10u8 {
+            let content: Vec<u8> = (0..8192u16).map(|n| n.to_le_bytes()[0] ^ i).collect();
+            vfs.add_file(VirtualPathBuf::from(format!("world/maps/{i}.ckd")), content)
+                .unwrap();
+        }
+        let files: Vec<_> = vfs.walk_filesystem("".as_ref()).unwrap().collect();
+
+        let mut options = Options {
+            compression: CompressionEffort::Best,
+            ..Default::default()
+        };
+        let mut single = Vec::new();
+        write(&mut single, &mut 0, options, &vfs, &files).unwrap();
+        options.threads = NonZeroUsize::new(3).unwrap();
+        let mut parallel = Vec::new();
+        write(&mut parallel, &mut 0, options, &vfs, &files).unwrap();
+        assert_eq!(
+            single, parallel,
+            "Output should not depend on the amount of threads"
+        );
+    }
 
-    Ok(())
+    #[test]
+    fn test_fingerprint_collision() {
+        let mut vfs = VecFs::new();
+        vfs.add_file(VirtualPathBuf::from("world/maps/a.png"), vec![0x1; 64])
+            .unwrap();
+        vfs.add_file(VirtualPathBuf::from("world/maps/b.png"), vec![0x2; 64])
+            .unwrap();
+        let files: Vec<_> = vfs.walk_filesystem("".as_ref()).unwrap().collect();
+
+        let mut data = Vec::new();
+        let mut writer = Writer::new(&mut data, 0, Options::default(), &vfs, &files).unwrap();
+        let first = vfs.open(files[0]).unwrap();
+        let fingerprint = fingerprint(&first).unwrap();
+        writer.add_file(files[0]).unwrap();
+
+        // Pretend the second file has the same fingerprint as the first file
+        let second = vfs.open(files[1]).unwrap();
+        let duplicate = writer.duplicate_of(fingerprint, &second).unwrap();
+        assert!(
+            duplicate.is_none(),
+            "Different content should never be deduplicated"
+        );
+        writer
+            .store(files[1], &second, fingerprint, duplicate, None)
+            .unwrap();
+        let statistics = writer.finish().unwrap();
+        assert_eq!(
+            statistics.deduplicated_files, 0,
+            "Nothing should be deduplicated"
+        );
+
+        let bundle = Bundle::deserialize_with(&data, false).unwrap();
+        for file in bundle.files.values() {
+            let Data::Uncompressed(content) = &file.data else {
+                panic!("PNG files are never compressed");
+            };
+            let expected = vfs.open(VirtualPathBuf::from(file.path.to_string()).as_ref());
+            assert_eq!(
+                content.data.as_ref(),
+                &*expected.unwrap(),
+                "Content of {} changed",
+                file.path
+            );
+        }
+    }
 }
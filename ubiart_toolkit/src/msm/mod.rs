@@ -1,4 +1,6 @@
 mod parser;
 mod types;
+mod writer;
 
 pub use types::*;
+pub use writer::*;
@@ -175,6 +175,21 @@ impl<'de> BinaryDeserialize<'de> for MovementSpaceMove<'de> {
             data.push((x, y));
         }
 
+        // The second movement pattern starts with its own unk14 and unk15
+        let (unk16, unk17, data2) = if unk13 == 2 {
+            let unk16 = reader.read_at_with::<f32>(position, endianness)?;
+            let unk17 = reader.read_at_with::<f32>(position, endianness)?;
+            let mut data2 = Vec::with_capacity(usize::try_from(unk11)?);
+            for _ in 0..unk11 {
+                let x = reader.read_at_with::<f32>(position, endianness)?;
+                let y = reader.read_at_with::<f32>(position, endianness)?;
+                data2.push((x, y));
+            }
+            (Some(unk16), Some(unk17), data2)
+        } else {
+            (None, None, Vec::new())
+        };
+
         Ok(MovementSpaceMove {
             name,
             map,
@@ -191,6 +206,9 @@ impl<'de> BinaryDeserialize<'de> for MovementSpaceMove<'de> {
             unk13,
             unk14,
             unk15,
+            unk16,
+            unk17,
+            data2,
         })
     }
 }
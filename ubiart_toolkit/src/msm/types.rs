@@ -1,5 +1,6 @@
 //! Contains the types that describe the usefull information in this filetype
 
+use dotstar_toolkit_utils::bytes::endian::Endian;
 use hipstr::HipStr;
 use serde::{Deserialize, Serialize};
 
@@ -26,4 +27,25 @@ pub struct MovementSpaceMove<'a> {
     pub unk13: u32,
     pub unk14: f32,
     pub unk15: f32,
+    /// Only when `unk13` is 2
+    pub unk16: Option<f32>,
+    /// Only when `unk13` is 2
+    pub unk17: Option<f32>,
+    /// The second movement pattern, only when `unk13` is 2
+    #[serde(default)]
+    pub data2: Vec<(f32, f32)>,
+}
+
+impl MovementSpaceMove<'_> {
+    /// The endianness of the file this move was parsed from
+    ///
+    /// Only the big endian format has `unk10`
+    #[must_use]
+    pub const fn endianness(&self) -> Endian {
+        if self.unk10.is_some() {
+            Endian::Big
+        } else {
+            Endian::Little
+        }
+    }
 }
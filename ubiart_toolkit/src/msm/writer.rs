@@ -0,0 +1,209 @@
+//! Contains the writer implementation
+
+use dotstar_toolkit_utils::bytes::{
+    endian::Endian,
+    write::{BinarySerialize, WriteAt, WriteError},
+};
+use test_eq::{test_any, test_eq};
+
+use super::MovementSpaceMove;
+
+/// Size of the fixed string fields in the header
+const STRING_FIELD_SIZE: usize = 64;
+
+/// Create a .msm file in a newly allocated `Vec`
+///
+/// The file will have the same endianness as the file it was parsed from
+pub fn create_vec(msm: MovementSpaceMove<'_>) -> Result<Vec<u8>, WriteError> {
+    let endianness = msm.endianness();
+    create_vec_with_endianness(msm, endianness)
+}
+
+/// Create a .msm file with `endianness` in a newly allocated `Vec`
+pub fn create_vec_with_endianness(
+    msm: MovementSpaceMove<'_>,
+    endianness: Endian,
+) -> Result<Vec<u8>, WriteError> {
+    let capacity = 0x100 + (msm.data.len() + msm.data2.len()) * 8;
+    let mut vec = Vec::with_capacity(capacity);
+    vec.write_at_with_ctx::<MovementSpaceMove>(&mut 0, msm, endianness)?;
+    vec.shrink_to_fit();
+    Ok(vec)
+}
+
+impl BinarySerialize for MovementSpaceMove<'_> {
+    type Ctx = Endian;
+    type Input = Self;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        endianness: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        test_any!(input.version, [0x5, 0x6, 0x7])?;
+        let unk11 = u32::try_from(input.data.len())?;
+        test_eq!(unk11, input.unk11)?;
+        test_any!(input.unk13, [0, 2])?;
+        if input.unk13 == 2 {
+            test_eq!(input.data2.len(), input.data.len())?;
+        }
+
+        // The magic is 1 in the endianness of the file
+        writer.write_at_with_ctx::<u32>(position, 0x1, endianness)?;
+        writer.write_at_with_ctx::<u32>(position, input.version, endianness)?;
+
+        write_string_field(writer, position, &input.name)?;
+        write_string_field(writer, position, &input.map)?;
+        write_string_field(writer, position, &input.device)?;
+
+        writer.write_at_with_ctx::<f32>(position, input.unk3, endianness)?;
+        writer.write_at_with_ctx::<f32>(position, input.unk4, endianness)?;
+        writer.write_at_with_ctx::<f32>(position, input.unk5, endianness)?;
+
+        if input.version == 0x7 {
+            let unk6 = input
+                .unk6
+                .ok_or_else(|| WriteError::custom("unk6 is required for version 7".into()))?;
+            let unk7 = input
+                .unk7
+                .ok_or_else(|| WriteError::custom("unk7 is required for version 7".into()))?;
+            writer.write_at_with_ctx::<f32>(position, unk6, endianness)?;
+            writer.write_at_with_ctx::<f32>(position, unk7, endianness)?;
+        }
+
+        if endianness == Endian::Little {
+            writer.write_at_with_ctx::<u32>(position, 0, endianness)?; // unk7_5
+        }
+
+        writer.write_at_with_ctx::<u32>(position, 0x211C_0000, endianness)?; // unk8
+        writer.write_at_with_ctx::<u32>(position, 0, endianness)?; // unk9
+
+        if endianness == Endian::Big {
+            // Converting from little endian, which doesn't have unk10
+            let unk10 = input.unk10.unwrap_or(0);
+            writer.write_at_with_ctx::<u32>(position, unk10, endianness)?;
+        }
+
+        writer.write_at_with_ctx::<u32>(position, unk11, endianness)?;
+        writer.write_at_with_ctx::<u32>(position, 2, endianness)?; // unk12
+        writer.write_at_with_ctx::<u32>(position, input.unk13, endianness)?;
+
+        writer.write_at_with_ctx::<f32>(position, input.unk14, endianness)?;
+        writer.write_at_with_ctx::<f32>(position, input.unk15, endianness)?;
+
+        for (x, y) in input.data {
+            writer.write_at_with_ctx::<f32>(position, x, endianness)?;
+            writer.write_at_with_ctx::<f32>(position, y, endianness)?;
+        }
+
+        if input.unk13 == 2 {
+            let unk16 = input
+                .unk16
+                .ok_or_else(|| WriteError::custom("unk16 is required if unk13 is 2".into()))?;
+            let unk17 = input
+                .unk17
+                .ok_or_else(|| WriteError::custom("unk17 is required if unk13 is 2".into()))?;
+            writer.write_at_with_ctx::<f32>(position, unk16, endianness)?;
+            writer.write_at_with_ctx::<f32>(position, unk17, endianness)?;
+            for (x, y) in input.data2 {
+                writer.write_at_with_ctx::<f32>(position, x, endianness)?;
+                writer.write_at_with_ctx::<f32>(position, y, endianness)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Write `string` as a null-padded field of [`STRING_FIELD_SIZE`] bytes
+fn write_string_field(
+    writer: &mut (impl WriteAt + ?Sized),
+    position: &mut u64,
+    string: &str,
+) -> Result<(), WriteError> {
+    let bytes = string.as_bytes();
+    // There always needs to be room for the null byte
+    if bytes.len() >= STRING_FIELD_SIZE {
+        return Err(WriteError::custom(format!(
+            "String is too long for a {STRING_FIELD_SIZE} byte field: {string}"
+        )));
+    }
+    let mut field = [0u8; STRING_FIELD_SIZE];
+    field[..bytes.len()].copy_from_slice(bytes);
+    writer.write_slice_at(position, &field)
+}
+
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::bytes::{endian::Endian, read::BinaryDeserializeExt as _};
+    use hipstr::HipStr;
+
+    use super::{create_vec, create_vec_with_endianness};
+    use crate::msm::MovementSpaceMove;
+
+    fn movement_space_move(version: u32, unk13: u32) -> MovementSpaceMove<'static> {
+        let data: Vec<_> = (0..10u8)
+            .map(|i| (f32::from(i) * 0.5, f32::from(i) * 0.25))
+            .collect();
+        let second = unk13 == 2;
+        MovementSpaceMove {
+            name: HipStr::borrowed("testmove"),
+            map: HipStr::borrowed("TestMap"),
+            device: HipStr::borrowed("Acc_Dev_Dir_NP"),
+            data2: if second { data.clone() } else { Vec::new() },
+            data,
+            version,
+            unk3: 1.5,
+            unk4: 1.0,
+            unk5: 2.0,
+            unk6: (version == 7).then_some(1.0),
+            unk7: (version == 7).then_some(0.5),
+            unk10: Some(1),
+            unk11: 10,
+            unk13,
+            unk14: 0.0,
+            unk15: 0.0,
+            unk16: second.then_some(1.0),
+            unk17: second.then_some(-1.0),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_big_endian() {
+        for version in [5, 6, 7] {
+            for unk13 in [0, 2] {
+                let one = create_vec(movement_space_move(version, unk13)).unwrap();
+                let msm = MovementSpaceMove::deserialize(&one).unwrap();
+                assert_eq!(msm.endianness(), Endian::Big, "Endianness changed");
+                let two = create_vec(msm).unwrap();
+                assert_eq!(one, two, "Roundtrip failed for version {version}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_little_endian() {
+        for version in [5, 6, 7] {
+            for unk13 in [0, 2] {
+                let one = create_vec_with_endianness(
+                    movement_space_move(version, unk13),
+                    Endian::Little,
+                )
+                .unwrap();
+                let msm = MovementSpaceMove::deserialize(&one).unwrap();
+                assert_eq!(msm.endianness(), Endian::Little, "Endianness changed");
+                assert_eq!(msm.unk10, None, "Little endian has no unk10");
+                let two = create_vec(msm).unwrap();
+                assert_eq!(one, two, "Roundtrip failed for version {version}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_string_too_long() {
+        let mut msm = movement_space_move(7, 0);
+        msm.name = HipStr::from("a".repeat(64));
+        assert!(create_vec(msm).is_err(), "Name does not fit in the field");
+    }
+}
@@ -183,6 +183,11 @@ pub fn bundle_name_to_filename(name: &str, ugi: UniqueGameId) -> String {
         Platform::WiiU => {
             format!("{name}_wiiu.ipk")
         }
-        _ => todo!("Unsupported platform: {}", ugi.platform),
+        Platform::Ps4 => {
+            format!("{name}_orbis.ipk")
+        }
+        Platform::X360 => {
+            format!("{name}_x360.ipk")
+        }
     }
 }
@@ -149,6 +149,25 @@ impl BinaryDeserialize<'_> for InternedString {
     }
 }
 
+impl BinarySerialize for InternedString {
+    type Ctx = ();
+    type Input = &'static str;
+
+    fn serialize_at_with_ctx(
+        input: Self::Input,
+        writer: &mut (impl WriteAt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<(), WriteError> {
+        let id = if input.is_empty() {
+            0xFFFF_FFFF
+        } else {
+            string_id(input)
+        };
+        writer.write_at::<u32be>(position, id)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UniqueGameId {
     pub game: Game,
@@ -157,6 +176,11 @@ pub struct UniqueGameId {
 }
 
 impl UniqueGameId {
+    pub const WIIU2014: Self = Self {
+        game: Game::JustDance2014,
+        platform: Platform::WiiU,
+        id: 0x1C24_B91A,
+    };
     pub const WIIU2015: Self = Self {
         game: Game::JustDance2015,
         platform: Platform::WiiU,
@@ -202,6 +226,11 @@ impl UniqueGameId {
         platform: Platform::Nx,
         id: 0x217A_94CE,
     };
+    pub const WII2020: Self = Self {
+        game: Game::JustDance2020,
+        platform: Platform::Wii,
+        id: 0x4C8E_C5C5,
+    };
     pub const NX_CHINA: Self = Self {
         game: Game::JustDanceChina,
         platform: Platform::Nx,
@@ -245,11 +274,7 @@ impl TryFrom<u32> for UniqueGameId {
     type Error = ParserError;
     fn try_from(value: u32) -> Result<Self, Self::Error> {
         match value {
-            0x1C24_B91A => Ok(Self {
-                game: Game::JustDance2014,
-                platform: Platform::WiiU,
-                id: value,
-            }),
+            0x1C24_B91A => Ok(Self::WIIU2014),
             0xC563_9F58 => Ok(Self {
                 game: Game::JustDance2015,
                 platform: Platform::WiiU,
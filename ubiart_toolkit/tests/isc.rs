@@ -4,6 +4,13 @@ use std::path::Path;
 
 use ubiart_toolkit::{cooked::isc, utils::UniqueGameId};
 
+fn isc_roundtrip_wiiu2015(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    let parsed = isc::parse(&data, UniqueGameId::WIIU2015)?;
+    let written = isc::create_vec_binary(parsed, UniqueGameId::WIIU2015)?;
+    assert!(data == written, "Written file does not match the original!");
+    Ok(())
+}
+
 fn isc_parse_wiiu2015(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
     isc::parse(&data, UniqueGameId::WIIU2015)?;
     Ok(())
@@ -92,5 +99,8 @@ datatest_stable::harness!(
     r".*/isc.ckd/.*",
     isc_parse_nx2022,
     "files/nx2022",
+    r".*/isc.ckd/.*",
+    isc_roundtrip_wiiu2015,
+    "files/wiiu2015",
     r".*/isc.ckd/.*"
 );
@@ -3,7 +3,14 @@
 use std::path::Path;
 
 use dotstar_toolkit_utils::bytes::read::BinaryDeserializeExt as _;
-use ubiart_toolkit::msm::MovementSpaceMove;
+use ubiart_toolkit::msm::{self, MovementSpaceMove};
+
+fn msm_roundtrip(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    let parsed = MovementSpaceMove::deserialize(&data)?;
+    let written = msm::create_vec(parsed)?;
+    assert!(data == written, "Written file does not match the original!");
+    Ok(())
+}
 
 fn msm_parse_wiiu2015(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
     MovementSpaceMove::deserialize(&data)?;
@@ -93,5 +100,38 @@ datatest_stable::harness!(
     r".*/msm/.*",
     msm_parse_nx2022,
     "files/nx2022",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/wiiu2015",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/wiiu2016",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/nx2017",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/win2017",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/wiiu2017",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/nx2018",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/nx2019",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/nx2020",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/nxChina",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/nx2021",
+    r".*/msm/.*",
+    msm_roundtrip,
+    "files/nx2022",
     r".*/msm/.*"
 );
@@ -44,6 +44,11 @@ fn png_parse_nx2020(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()>
     Ok(())
 }
 
+fn png_parse_wii2020(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    Png::deserialize_with(&data, UniqueGameId::WII2020)?;
+    Ok(())
+}
+
 fn png_parse_nx2020_china(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
     Png::deserialize_with(&data, UniqueGameId::NX_CHINA)?;
     Ok(())
@@ -84,6 +89,9 @@ datatest_stable::harness!(
     png_parse_nx2020,
     "files/nx2020",
     r".*/png.ckd/.*",
+    png_parse_wii2020,
+    "files/wii2020",
+    r".*/png.ckd/.*",
     png_parse_nx2020_china,
     "files/nxChina",
     r".*/png.ckd/.*",
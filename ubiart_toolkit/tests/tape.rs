@@ -4,6 +4,11 @@ use std::path::Path;
 
 use ubiart_toolkit::{cooked::tape, utils::UniqueGameId};
 
+fn tape_parse_wiiu2014(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    tape::parse(&data, UniqueGameId::WIIU2014, false)?;
+    Ok(())
+}
+
 fn tape_parse_wiiu2015(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
     tape::parse(&data, UniqueGameId::WIIU2015, false)?;
     Ok(())
@@ -60,6 +65,9 @@ fn tape_parse_nx2022(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()>
 }
 
 datatest_stable::harness!(
+    tape_parse_wiiu2014,
+    "files/wiiu2014",
+    r".*/tape.ckd/.*",
     tape_parse_wiiu2015,
     "files/wiiu2015",
     r".*/tape.ckd/.*",
@@ -45,6 +45,11 @@ fn tga_parse_nx2020(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()>
     Ok(())
 }
 
+fn tga_parse_wii2020(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
+    Png::deserialize_with(&data, UniqueGameId::WII2020)?;
+    Ok(())
+}
+
 fn tga_parse_nx2020_china(_path: &Path, data: Vec<u8>) -> datatest_stable::Result<()> {
     Png::deserialize_with(&data, UniqueGameId::NX_CHINA)?;
     Ok(())
@@ -85,6 +90,9 @@ datatest_stable::harness!(
     tga_parse_nx2020,
     "files/nx2020",
     r".*/tga.ckd/.*",
+    tga_parse_wii2020,
+    "files/wii2020",
+    r".*/tga.ckd/.*",
     tga_parse_nx2020_china,
     "files/nxChina",
     r".*/tga.ckd/.*",
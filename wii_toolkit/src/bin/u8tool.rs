@@ -1,46 +1,135 @@
 #![allow(clippy::missing_panics_doc, reason = "Tool not a library")]
 
-use std::{fs::File, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ffi::OsStr,
+    fs::{create_dir_all, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
+use anyhow::{anyhow, Error};
 use clap::Parser;
-use dotstar_toolkit_utils::bytes::read::BinaryDeserializeExt as _;
-use wii_toolkit::u8a::types::U8Archive;
+use dotstar_toolkit_utils::{
+    bytes::{read::BinaryDeserializeExt as _, write::BinarySerializeExt as _},
+    vfs::{native::NativeFs, VirtualFileSystem, VirtualPath},
+};
+use hipstr::HipStr;
+use wii_toolkit::{
+    disc::vfs::DiscFilesystem,
+    parse_key,
+    u8a::types::{FileTree, U8Archive},
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// The file to parse
+    /// The file to parse, or the directory to pack into a U8 archive
     source: PathBuf,
-    /// The directory to extract all files too
+    /// The directory to extract all files too, or the file to write the U8 archive to
+    ///
+    /// If not specified, the archive is only printed
     destination: Option<PathBuf>,
+    /// Read the source from this Wii disc image (.iso or .wbfs)
+    #[arg(long, requires = "key")]
+    disc: Option<PathBuf>,
+    /// File with the Wii common key, needed to decrypt the disc image
+    ///
+    /// The file contains the 16 byte key or the key encoded as 32 hex characters.
+    #[arg(short, long)]
+    key: Option<PathBuf>,
 }
 
-fn main() {
+fn main() -> Result<(), Error> {
     let cli = Cli::parse();
 
-    let source = cli.source;
-    // let destination = cli.destination.unwrap_or_else(|| {
-    //     source
-    //         .parent()
-    //         .expect("No parent directory for source!")
-    //         .to_path_buf()
-    // });
+    if cli.disc.is_none() && cli.source.is_dir() {
+        let destination = cli
+            .destination
+            .ok_or_else(|| anyhow!("A destination is required when packing a directory"))?;
+        let file_tree = read_dir_rec(&cli.source)?;
+        let mut file = File::create(destination)?;
+        U8Archive::serialize(U8Archive { file_tree }, &mut file)?;
+        return Ok(());
+    }
 
-    let file = File::open(source).unwrap();
+    let data = match (&cli.disc, &cli.key) {
+        (Some(disc), Some(key)) => read_from_disc(disc, key, &cli.source)?,
+        _ => std::fs::read(&cli.source)?,
+    };
+    let u8a = U8Archive::deserialize(data.as_slice())?;
 
-    let u8a = U8Archive::deserialize(&file).unwrap();
+    if let Some(destination) = cli.destination {
+        extract_rec(&u8a.file_tree, &destination)?;
+    } else {
+        println!("{u8a:#?}");
+    }
 
-    println!("{u8a:#?}");
+    Ok(())
+}
+
+/// Read the file at `path` in the data partition of `disc`
+fn read_from_disc(disc: &Path, key: &Path, path: &Path) -> Result<Vec<u8>, Error> {
+    let key = parse_key(&std::fs::read(key)?)?;
+    let file_name = disc
+        .file_name()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| anyhow!("Invalid disc path {disc:?}!"))?;
+    let native_vfs = NativeFs::new(
+        disc.parent()
+            .ok_or_else(|| anyhow!("No parent directory for {file_name}!"))?,
+    )?;
+    let disc_vfs = DiscFilesystem::new(native_vfs.open(VirtualPath::new(file_name))?, &key)?;
+    let path = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid path {path:?}!"))?;
+    Ok(disc_vfs.open(VirtualPath::new(path))?.to_vec())
+}
 
-    // create_dir_all(&destination).expect("Could not create directory!");
+/// Write all files in `tree` to `destination`
+fn extract_rec(tree: &FileTree, destination: &Path) -> Result<(), Error> {
+    create_dir_all(destination)?;
+    if let Some(name) = tree
+        .files
+        .keys()
+        .chain(tree.directories.keys())
+        .find(|name| name.contains(['/', '\\']) || name.as_str() == "..")
+    {
+        return Err(anyhow!(
+            "Refusing to extract {name:?}, it's not a valid filename"
+        ));
+    }
+    for (name, data) in &tree.files {
+        let mut file = File::create(destination.join(name.as_str()))?;
+        file.write_all(data)?;
+    }
+    for (name, directory) in &tree.directories {
+        extract_rec(directory, &destination.join(name.as_str()))?;
+    }
+    Ok(())
+}
 
-    // for packedfile in u8a.files {
-    //     let mut path = destination.clone();
-    //     path.extend(&packedfile.path);
-    //     create_dir_all(&path).unwrap();
-    //     path.push(packedfile.name);
-    //     let mut file = File::create(path.join(packedfile.name)).expect("Could not create file!");
-    //     file.write_all(packedfile.data)
-    //         .expect("Could not write to file!");
-    // }
+/// Read all files in `source` into a tree
+fn read_dir_rec(source: &Path) -> Result<FileTree<'static>, Error> {
+    let mut tree = FileTree {
+        directories: HashMap::new(),
+        files: HashMap::new(),
+    };
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let name = entry
+            .file_name()
+            .into_string()
+            .map_err(|name| anyhow!("Filename is not valid UTF-8: {name:?}"))?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            tree.directories
+                .insert(HipStr::from(name), read_dir_rec(&path)?);
+        } else {
+            tree.files
+                .insert(HipStr::from(name), Cow::Owned(std::fs::read(&path)?));
+        }
+    }
+    Ok(tree)
 }
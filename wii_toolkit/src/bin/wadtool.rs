@@ -1,10 +1,15 @@
 #![allow(clippy::missing_panics_doc, reason = "Tool not a library")]
 
-use std::{fs::File, path::PathBuf};
+use std::{
+    fs::{create_dir_all, File},
+    io::Write,
+    path::PathBuf,
+};
 
+use anyhow::{anyhow, Error};
 use clap::Parser;
 use dotstar_toolkit_utils::bytes::read::BinaryDeserializeExt as _;
-use wii_toolkit::wad::types::WadArchive;
+use wii_toolkit::{parse_key, parse_root_key, wad::types::WadArchive};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -12,50 +17,79 @@ struct Cli {
     /// The file to parse
     source: PathBuf,
     /// The directory to extract all files too
+    ///
+    /// If not specified, the contents are only decrypted and checked
     destination: Option<PathBuf>,
+    /// File with the key to decrypt the contents with
+    ///
+    /// For installable WADs this is the Wii common key, for backup WADs this is the PRNG key of
+    /// the console that made the backup. The file contains the 16 byte key or the key encoded as 32
+    /// hex characters. If not specified, the archive is only printed.
+    #[arg(short, long)]
+    key: Option<PathBuf>,
+    /// File with the Wii root key, needed to verify the signatures before decrypting installable
+    /// WADs
+    ///
+    /// The file contains the 512 byte modulus of the key or the modulus encoded as 1024 hex
+    /// characters.
+    #[arg(short, long)]
+    root_key: Option<PathBuf>,
+    /// Don't verify the signatures of the ticket and title metadata when decrypting
+    #[arg(long)]
+    no_verify: bool,
 }
 
-fn main() {
+fn main() -> Result<(), Error> {
     let cli = Cli::parse();
 
-    let source = cli.source;
-    // let destination = cli.destination.unwrap_or_else(|| {
-    //     source
-    //         .parent()
-    //         .expect("No parent directory for source!")
-    //         .to_path_buf()
-    // });
-
-    let file = File::open(source).unwrap();
-    let wad_archive = WadArchive::deserialize(&file).unwrap();
-
-    println!("{wad_archive:#?}");
-
-    // create_dir_all(&destination).expect("Could not create directory!");
-
-    // match wad_archive.archive() {
-    //     WadArchive::Installable(installable) => {
-    //         for content in &installable.content {
-    //             let cid = content.metadata.content_id;
-    //             let index = content.metadata.index;
-    //             let cmd_type = u16::from(content.metadata.content_type);
-    //             let filename = format!("{cid:08x}.{index:04x}.{cmd_type:04x}.app");
-    //             let filepath = destination.join(&filename);
-    //             let mut file = File::create(filepath).expect("Could not create file!");
-    //             let buffer = content.decrypt().expect("Could not decrypt file!");
-
-    //             let mut hasher = Sha1::new();
-    //             hasher.update(&buffer);
-    //             let result = hasher.finalize();
-
-    //             assert!(
-    //                 content.metadata.sha1_hash == result.as_slice(),
-    //                 "SHA-1 hashes don't match!"
-    //             );
-
-    //             file.write_all(&buffer).expect("Could not write to file!");
-    //         }
-    //     }
-    //     WadArchive::Backup(_) => todo!(),
-    // };
+    let file = File::open(&cli.source)?;
+    let wad_archive = WadArchive::deserialize(&file)?;
+
+    let Some(key) = cli.key else {
+        println!("{wad_archive:#?}");
+        return Ok(());
+    };
+
+    // Only verify the signatures if the contents are decrypted, printing does not need the root key
+    match &wad_archive {
+        WadArchive::Installable(installable) if !cli.no_verify => {
+            let root_key = cli.root_key.as_ref().ok_or_else(|| {
+                anyhow!(
+                    "Verifying the signatures needs the root key, see --root-key or --no-verify"
+                )
+            })?;
+            installable.verify_signatures(&parse_root_key(&std::fs::read(root_key)?)?)?;
+            println!("Signatures are valid");
+        }
+        WadArchive::Installable(_) => {}
+        WadArchive::Backup(_) => {
+            println!("Backup WADs have no certificate chain, skipping signature verification");
+        }
+    }
+
+    let key = parse_key(&std::fs::read(key)?)?;
+    let key = match &wad_archive {
+        WadArchive::Installable(installable) => installable.title_key(&key),
+        WadArchive::Backup(_) => key,
+    };
+
+    if let Some(destination) = &cli.destination {
+        create_dir_all(destination)?;
+    }
+
+    for content in wad_archive.content() {
+        let cid = content.metadata.content_id;
+        let index = content.metadata.index;
+        let cmd_type = u16::from(content.metadata.content_type);
+        let filename = format!("{cid:08x}.{index:04x}.{cmd_type:04x}.app");
+        // also verifies the SHA-1 hash
+        let buffer = content.decrypt(&key)?;
+        if let Some(destination) = &cli.destination {
+            let mut file = File::create(destination.join(&filename))?;
+            file.write_all(&buffer)?;
+        }
+        println!("{filename}: OK");
+    }
+
+    Ok(())
 }
@@ -0,0 +1,162 @@
+//! Contains the reader for raw and WBFS disc images
+
+use std::io::{Error, ErrorKind};
+
+use dotstar_toolkit_utils::{
+    bytes::{
+        primitives::{u16be, u32be},
+        read::{ReadAtExt, ReadError},
+    },
+    vfs::VirtualFile,
+};
+use test_eq::test_eq;
+
+use super::{invalid_data, overflow};
+
+/// Magic of a Wii disc, found at 0x18 in the disc header
+const DISC_MAGIC: u32 = 0x5D1C_9EA3;
+/// Magic of a WBFS image
+const WBFS_MAGIC: [u8; 4] = *b"WBFS";
+/// Size of a Wii sector as a shift
+const WII_SECTOR_SHIFT: u32 = 15;
+/// The amount of Wii sectors on a dual layer disc (2 * 143432)
+const WII_SECTORS_PER_DISC: u64 = 286_864;
+/// Size of the copy of the disc header before the sector table
+const WBFS_DISC_HEADER_SIZE: u64 = 0x100;
+
+/// A raw or WBFS disc image
+pub struct DiscImage<'f> {
+    /// The file that contains the image
+    file: VirtualFile<'f>,
+    /// The sector table if the image is a WBFS image
+    wbfs: Option<Wbfs>,
+}
+
+/// The sector table of a disc in a WBFS image
+struct Wbfs {
+    /// Size of a WBFS sector as a shift
+    sector_shift: u32,
+    /// The WBFS sector for every disc sector, zero if the sector is not stored
+    sectors: Vec<u16>,
+}
+
+impl<'f> DiscImage<'f> {
+    /// Open the disc image in `file`
+    ///
+    /// # Errors
+    /// Will return an error if the file is not a Wii disc or WBFS image
+    pub fn new(file: VirtualFile<'f>) -> Result<Self, Error> {
+        let wbfs = if file.starts_with(&WBFS_MAGIC) {
+            Some(parse_wbfs(&file).map_err(invalid_data)?)
+        } else {
+            None
+        };
+        let image = Self { file, wbfs };
+        let header = image.read(0x18, 4)?;
+        let magic = header
+            .as_slice()
+            .read_at::<u32be>(&mut 0)
+            .map_err(invalid_data)?;
+        test_eq!(magic, DISC_MAGIC).map_err(invalid_data)?;
+        Ok(image)
+    }
+
+    /// The six character game id of the disc
+    ///
+    /// # Errors
+    /// Will return an error if the game id cannot be read or is not valid UTF-8
+    pub fn game_id(&self) -> Result<String, Error> {
+        let game_id = self.read(0, 6)?;
+        String::from_utf8(game_id).map_err(invalid_data)
+    }
+
+    /// Read `size` bytes at `offset` on the disc
+    ///
+    /// # Errors
+    /// Will return an error if the range is not in the image
+    pub fn read(&self, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+        let Some(wbfs) = &self.wbfs else {
+            return Ok(self.slice(offset, size)?.to_vec());
+        };
+
+        let sector_size = 1u64.checked_shl(wbfs.sector_shift).ok_or_else(overflow)?;
+        let mut data = Vec::with_capacity(usize::try_from(size).map_err(invalid_data)?);
+        let mut offset = offset;
+        let mut remaining = size;
+        while remaining > 0 {
+            let sector = offset.checked_shr(wbfs.sector_shift).ok_or_else(overflow)?;
+            let in_sector = offset & (sector_size.checked_sub(1).ok_or_else(overflow)?);
+            let len = remaining.min(sector_size.checked_sub(in_sector).ok_or_else(overflow)?);
+            let wbfs_sector = usize::try_from(sector)
+                .ok()
+                .and_then(|sector| wbfs.sectors.get(sector))
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::UnexpectedEof,
+                        format!("Offset {offset:#x} is outside the disc!"),
+                    )
+                })?;
+            if *wbfs_sector == 0 {
+                // Unused sectors are not stored
+                data.resize(
+                    data.len()
+                        .checked_add(usize::try_from(len).map_err(invalid_data)?)
+                        .ok_or_else(overflow)?,
+                    0,
+                );
+            } else {
+                let physical = u64::from(*wbfs_sector)
+                    .checked_shl(wbfs.sector_shift)
+                    .and_then(|o| o.checked_add(in_sector))
+                    .ok_or_else(overflow)?;
+                data.extend_from_slice(self.slice(physical, len)?);
+            }
+            offset = offset.checked_add(len).ok_or_else(overflow)?;
+            remaining = remaining.checked_sub(len).ok_or_else(overflow)?;
+        }
+        Ok(data)
+    }
+
+    /// Get `size` bytes at `offset` in the file
+    fn slice(&self, offset: u64, size: u64) -> Result<&[u8], Error> {
+        let start = usize::try_from(offset).map_err(invalid_data)?;
+        let end = start
+            .checked_add(usize::try_from(size).map_err(invalid_data)?)
+            .ok_or_else(overflow)?;
+        self.file.get(start..end).ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("Range {start:#x}..{end:#x} is outside the image!"),
+            )
+        })
+    }
+}
+
+/// Parse the header of a WBFS image and the sector table of the first disc
+fn parse_wbfs(reader: &[u8]) -> Result<Wbfs, ReadError> {
+    let mut position = 0x4;
+    let _hd_sectors = reader.read_at::<u32be>(&mut position)?;
+    let hd_sector_shift = u32::from(reader.read_at::<u8>(&mut position)?);
+    let sector_shift = u32::from(reader.read_at::<u8>(&mut position)?);
+    // The disc table starts at 0xC, every byte is a used flag
+    let first_disc_used = reader.read_at::<u8>(&mut 0xC)?;
+    test_eq!(first_disc_used, 1u8)?;
+
+    let mut position = 1u64
+        .checked_shl(hd_sector_shift)
+        .and_then(|p| p.checked_add(WBFS_DISC_HEADER_SIZE))
+        .ok_or_else(ReadError::int_under_overflow)?;
+    let sector_count = sector_shift
+        .checked_sub(WII_SECTOR_SHIFT)
+        .and_then(|shift| WII_SECTORS_PER_DISC.checked_shr(shift))
+        .ok_or_else(ReadError::int_under_overflow)?;
+    let mut sectors = Vec::with_capacity(usize::try_from(sector_count)?);
+    for _ in 0..sector_count {
+        sectors.push(reader.read_at::<u16be>(&mut position)?);
+    }
+
+    Ok(Wbfs {
+        sector_shift,
+        sectors,
+    })
+}
@@ -0,0 +1,30 @@
+//! # Wii discs
+//! A Wii disc contains a partition table with one or more partitions. Every partition is encrypted
+//! with its own title key, which is encrypted with the common key in the partition ticket. The
+//! partition data is divided in clusters of 0x8000 bytes, where the first 0x400 bytes are hashes.
+//! The files are described by the FST in the data partition.
+//!
+//! Both raw disc images (.iso) and WBFS images (.wbfs) are supported. Only the first disc in a WBFS
+//! image is used.
+//!
+//! This parser implementation is based on the excellent documentation on [WiiBrew](https://wiibrew.org/wiki/Wii_disc)
+//! and the WBFS source code.
+
+pub mod image;
+pub mod partition;
+pub mod vfs;
+
+/// Convert a parsing error into an I/O error, so it can be returned by the filesystem
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
+
+/// Error for when a calculation overflows
+fn overflow() -> std::io::Error {
+    invalid_data("Overflow occurred!")
+}
+
+/// Offsets and sizes on Wii discs are stored divided by four
+fn shifted(value: u32) -> Result<u64, std::io::Error> {
+    u64::from(value).checked_mul(4).ok_or_else(overflow)
+}
@@ -0,0 +1,271 @@
+//! Contains the partition table and the decryption of partitions
+
+use std::io::{Error, ErrorKind};
+
+use cipher::{block_padding::NoPadding, generic_array::GenericArray, BlockDecryptMut, KeyIvInit};
+use dotstar_toolkit_utils::bytes::{primitives::u32be, read::ReadAtExt};
+
+use super::{image::DiscImage, invalid_data, overflow, shifted};
+use crate::wad::types::{Aes128CbcDec, TicketMetadata};
+
+/// Offset of the partition info table
+const PARTITION_INFO_OFFSET: u64 = 0x4_0000;
+/// The amount of partition tables
+const PARTITION_TABLES: u64 = 4;
+/// Size of an encrypted cluster
+const CLUSTER_SIZE: u64 = 0x8000;
+/// Size of the hashes at the start of every cluster
+const CLUSTER_HASH_SIZE: u64 = 0x400;
+/// Size of the data in every cluster
+const CLUSTER_DATA_SIZE: u64 = 0x7C00;
+/// Offset of the IV in an encrypted cluster
+const CLUSTER_IV_OFFSET: usize = 0x3D0;
+/// Offset of the partition header, right after the ticket
+const PARTITION_HEADER_OFFSET: u64 = 0x2A4;
+
+/// The type of a partition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionType {
+    /// The game data
+    Data,
+    /// System updates
+    Update,
+    /// The channel installer
+    Channel,
+    /// Other partitions, the value is the raw type
+    Other(u32),
+}
+
+impl From<u32> for PartitionType {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::Data,
+            1 => Self::Update,
+            2 => Self::Channel,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+/// An entry in the partition table
+#[derive(Debug, Clone, Copy)]
+pub struct PartitionEntry {
+    /// Offset of the partition on the disc
+    pub offset: u64,
+    /// The type of the partition
+    pub partition_type: PartitionType,
+}
+
+/// Read all partition tables of the disc
+///
+/// # Errors
+/// Will return an error if the partition tables cannot be read
+pub fn partitions(image: &DiscImage) -> Result<Vec<PartitionEntry>, Error> {
+    let info_size = PARTITION_TABLES.checked_mul(8).ok_or_else(overflow)?;
+    let info = image.read(PARTITION_INFO_OFFSET, info_size)?;
+    let mut position = 0;
+    let mut partitions = Vec::new();
+    for _ in 0..PARTITION_TABLES {
+        let count = info.read_at::<u32be>(&mut position).map_err(invalid_data)?;
+        let offset = shifted(info.read_at::<u32be>(&mut position).map_err(invalid_data)?)?;
+        let table = image.read(
+            offset,
+            u64::from(count).checked_mul(8).ok_or_else(overflow)?,
+        )?;
+        let mut table_position = 0;
+        for _ in 0..count {
+            let offset = shifted(
+                table
+                    .read_at::<u32be>(&mut table_position)
+                    .map_err(invalid_data)?,
+            )?;
+            let partition_type = table
+                .read_at::<u32be>(&mut table_position)
+                .map_err(invalid_data)?;
+            partitions.push(PartitionEntry {
+                offset,
+                partition_type: PartitionType::from(partition_type),
+            });
+        }
+    }
+    Ok(partitions)
+}
+
+/// A decrypted view of a partition
+pub struct Partition {
+    /// Offset of the encrypted data on the disc
+    data_offset: u64,
+    /// Size of the decrypted data
+    data_size: u64,
+    /// The decrypted title key
+    title_key: [u8; 0x10],
+}
+
+impl Partition {
+    /// Open the partition at `offset` on the disc and decrypt its title key with `common_key`
+    ///
+    /// `common_key` is the Wii common key, partitions that use the Korean or vWii common key are
+    /// not supported.
+    ///
+    /// # Errors
+    /// Will return an error if the ticket or the partition header cannot be read, or if the ticket
+    /// uses another common key
+    pub fn new(image: &DiscImage, offset: u64, common_key: &[u8; 0x10]) -> Result<Self, Error> {
+        let header_size = PARTITION_HEADER_OFFSET
+            .checked_add(0x1C)
+            .ok_or_else(overflow)?;
+        let header = image.read(offset, header_size)?;
+        let ticket = header
+            .read_at::<TicketMetadata>(&mut 0)
+            .map_err(invalid_data)?;
+        match ticket.common_key_index {
+            0 => {}
+            1 => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Partition is encrypted with the Korean common key, which is not supported!",
+                ))
+            }
+            index => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("Partition is encrypted with unsupported common key {index}!"),
+                ))
+            }
+        }
+        let title_key = ticket.title_key(common_key);
+
+        // Skip the TMD, certificate chain, and H3 table offsets and sizes
+        let mut position = PARTITION_HEADER_OFFSET
+            .checked_add(0x14)
+            .ok_or_else(overflow)?;
+        let data_offset = shifted(
+            header
+                .read_at::<u32be>(&mut position)
+                .map_err(invalid_data)?,
+        )?;
+        let data_clusters = shifted(
+            header
+                .read_at::<u32be>(&mut position)
+                .map_err(invalid_data)?,
+        )?
+        .checked_div(CLUSTER_SIZE)
+        .ok_or_else(overflow)?;
+
+        Ok(Self {
+            data_offset: offset.checked_add(data_offset).ok_or_else(overflow)?,
+            data_size: data_clusters
+                .checked_mul(CLUSTER_DATA_SIZE)
+                .ok_or_else(overflow)?,
+            title_key,
+        })
+    }
+
+    /// Read and decrypt `size` bytes at `offset` in the partition data
+    ///
+    /// # Errors
+    /// Will return an error if the range is not in the partition
+    pub fn read(&self, image: &DiscImage, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+        let end = offset.checked_add(size).ok_or_else(overflow)?;
+        if end > self.data_size {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("Range {offset:#x}..{end:#x} is outside the partition!"),
+            ));
+        }
+
+        let mut data = Vec::with_capacity(usize::try_from(size).map_err(invalid_data)?);
+        let mut offset = offset;
+        while offset < end {
+            let cluster = offset.checked_div(CLUSTER_DATA_SIZE).ok_or_else(overflow)?;
+            let in_cluster = offset.checked_rem(CLUSTER_DATA_SIZE).ok_or_else(overflow)?;
+            let len = end
+                .checked_sub(offset)
+                .and_then(|len| Some(len.min(CLUSTER_DATA_SIZE.checked_sub(in_cluster)?)))
+                .ok_or_else(overflow)?;
+
+            let decrypted = self.decrypt_cluster(image, cluster)?;
+            let start = usize::try_from(in_cluster).map_err(invalid_data)?;
+            let stop = start
+                .checked_add(usize::try_from(len).map_err(invalid_data)?)
+                .ok_or_else(overflow)?;
+            data.extend_from_slice(decrypted.get(start..stop).ok_or_else(overflow)?);
+
+            offset = offset.checked_add(len).ok_or_else(overflow)?;
+        }
+        Ok(data)
+    }
+
+    /// Read and decrypt the data of the cluster at `index`
+    fn decrypt_cluster(&self, image: &DiscImage, index: u64) -> Result<Vec<u8>, Error> {
+        let cluster_offset = index
+            .checked_mul(CLUSTER_SIZE)
+            .and_then(|o| o.checked_add(self.data_offset))
+            .ok_or_else(overflow)?;
+        let mut cluster = image.read(cluster_offset, CLUSTER_SIZE)?;
+        let iv: [u8; 0x10] = cluster
+            .get(CLUSTER_IV_OFFSET..)
+            .and_then(|iv| iv.get(..0x10))
+            .and_then(|iv| iv.try_into().ok())
+            .ok_or_else(overflow)?;
+        let hash_size = usize::try_from(CLUSTER_HASH_SIZE).map_err(invalid_data)?;
+        let data = cluster.get_mut(hash_size..).ok_or_else(overflow)?;
+        Aes128CbcDec::new(&GenericArray::from(self.title_key), &GenericArray::from(iv))
+            .decrypt_padded_mut::<NoPadding>(data)
+            .map_err(|_| invalid_data("Cluster is not a multiple of the block size"))?;
+        cluster.drain(..hash_size);
+        Ok(cluster)
+    }
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[allow(
+    clippy::arithmetic_side_effects,
+    reason = "The offsets in the tests are small constants"
+)]
+#[cfg(test)]
+mod tests {
+    use std::{io::ErrorKind, sync::Arc};
+
+    use dotstar_toolkit_utils::vfs::VirtualFile;
+
+    use super::Partition;
+    use crate::disc::image::DiscImage;
+
+    /// Offset of the partition in the test image
+    const OFFSET: u64 = 0x100;
+
+    /// A disc image with an empty partition whose ticket uses `common_key_index`
+    fn image(common_key_index: u8) -> DiscImage<'static> {
+        let offset = usize::try_from(OFFSET).unwrap();
+        let mut image = vec![0; offset + 0x2C0];
+        image[0x18..0x1C].copy_from_slice(&0x5D1C_9EA3u32.to_be_bytes());
+        let ticket = offset + 0x1BC;
+        // The constant after the title id
+        image[ticket + 0x28..ticket + 0x2A].fill(0xFF);
+        image[ticket + 0x35] = common_key_index;
+        DiscImage::new(VirtualFile::Vec(Arc::new(image))).unwrap()
+    }
+
+    #[test]
+    fn test_common_key() {
+        let partition = Partition::new(&image(0), OFFSET, &[0; 0x10]).unwrap();
+        assert_eq!(partition.data_size, 0, "Partition is empty");
+        assert_eq!(
+            partition.data_offset, OFFSET,
+            "Data starts at the partition"
+        );
+    }
+
+    #[test]
+    fn test_korean_common_key() {
+        let Err(error) = Partition::new(&image(1), OFFSET, &[0; 0x10]) else {
+            panic!("Korean common key is not supported");
+        };
+        assert_eq!(error.kind(), ErrorKind::Unsupported, "Error kind is wrong");
+        assert!(
+            error.to_string().contains("Korean"),
+            "Error should mention the Korean common key"
+        );
+    }
+}
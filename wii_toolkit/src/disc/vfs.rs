@@ -0,0 +1,238 @@
+//! # Disc Filesystem
+//! The files in the data partition of a Wii disc as a virtual filesystem.
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io::{Error, ErrorKind},
+    sync::{Arc, Mutex, Weak},
+};
+
+use dotstar_toolkit_utils::{
+    bytes::{
+        primitives::{u24be, u32be},
+        read::{ReadAtExt, ReadError},
+    },
+    vfs::{VirtualFile, VirtualFileSystem, VirtualMetadata, VirtualPath, VirtualPathBuf, WalkFs},
+};
+
+use super::{
+    image::DiscImage,
+    invalid_data,
+    partition::{partitions, Partition, PartitionType},
+    shifted,
+};
+
+/// Offset of the FST offset and size in the partition header
+const FST_POINTER_OFFSET: u64 = 0x424;
+/// Size of an entry in the FST
+const FST_ENTRY_SIZE: u64 = 0xC;
+
+/// The data partition of a Wii disc as a virtual filesystem
+pub struct DiscFilesystem<'f> {
+    /// The disc image
+    image: DiscImage<'f>,
+    /// The data partition
+    partition: Partition,
+    /// The offset and size of every file, the offset is from the start of the partition data
+    files: HashMap<VirtualPathBuf, (u64, u64)>,
+    /// All paths in the FST
+    list: Vec<VirtualPathBuf>,
+    /// Recently opened files, so they're not decrypted twice
+    cache: Mutex<HashMap<VirtualPathBuf, Weak<Vec<u8>>>>,
+}
+
+impl<'f> DiscFilesystem<'f> {
+    /// Open the data partition of the ISO or WBFS image in `file`
+    ///
+    /// The title key of the partition is decrypted with `common_key`.
+    ///
+    /// # Errors
+    /// Will return an error if the file is not a Wii disc, there is no data partition, or the FST
+    /// cannot be parsed
+    pub fn new(file: VirtualFile<'f>, common_key: &[u8; 0x10]) -> Result<Self, Error> {
+        let image = DiscImage::new(file)?;
+        let entry = partitions(&image)?
+            .into_iter()
+            .find(|p| p.partition_type == PartitionType::Data)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "Disc has no data partition!"))?;
+        let partition = Partition::new(&image, entry.offset, common_key)?;
+
+        let pointers = partition.read(&image, FST_POINTER_OFFSET, 8)?;
+        let mut position = 0;
+        let fst_offset = shifted(
+            pointers
+                .read_at::<u32be>(&mut position)
+                .map_err(invalid_data)?,
+        )?;
+        let fst_size = shifted(
+            pointers
+                .read_at::<u32be>(&mut position)
+                .map_err(invalid_data)?,
+        )?;
+        let fst = partition.read(&image, fst_offset, fst_size)?;
+        let files = parse_fst(&fst).map_err(invalid_data)?;
+        let list = files.keys().cloned().collect();
+
+        Ok(Self {
+            image,
+            partition,
+            files,
+            list,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The six character game id of the disc
+    ///
+    /// # Errors
+    /// Will return an error if the game id cannot be read or is not valid UTF-8
+    pub fn game_id(&self) -> Result<String, Error> {
+        self.image.game_id()
+    }
+
+    /// The offset and size of the file at `path`
+    fn location(&self, path: &VirtualPath) -> Result<(VirtualPathBuf, u64, u64), Error> {
+        let path = path.clean_relative();
+        let (offset, size) = self.files.get(&path).copied().ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Could not find {path:?} on the disc!"),
+            )
+        })?;
+        Ok((path, offset, size))
+    }
+}
+
+impl VirtualFileSystem for DiscFilesystem<'_> {
+    fn open(&self, path: &VirtualPath) -> std::io::Result<VirtualFile> {
+        let (path, offset, size) = self.location(path)?;
+        let mut cache = self
+            .cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let entry = cache.entry(path);
+        if let Entry::Occupied(entry) = &entry {
+            if let Some(arc) = entry.get().upgrade() {
+                return Ok(VirtualFile::Vec(arc));
+            }
+        }
+        let arc = Arc::new(self.partition.read(&self.image, offset, size)?);
+        match entry {
+            Entry::Occupied(mut entry) => {
+                entry.insert(Arc::downgrade(&arc));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Arc::downgrade(&arc));
+            }
+        }
+        Ok(VirtualFile::Vec(arc))
+    }
+
+    fn metadata(&self, path: &VirtualPath) -> std::io::Result<VirtualMetadata> {
+        let (_, _, size) = self.location(path)?;
+        Ok(VirtualMetadata {
+            file_size: size,
+            created: Err(ErrorKind::Unsupported),
+        })
+    }
+
+    fn walk_filesystem<'rf>(&'rf self, path: &VirtualPath) -> std::io::Result<WalkFs<'rf>> {
+        Ok(WalkFs::filtered(&self.list, path))
+    }
+
+    fn exists(&self, path: &VirtualPath) -> bool {
+        self.files.contains_key(&path.clean_relative())
+    }
+}
+
+/// Parse the FST and return the offset and size of every file
+///
+/// Every directory entry contains the index of the first entry after the directory, the entries
+/// in between are its children.
+fn parse_fst(fst: &[u8]) -> Result<HashMap<VirtualPathBuf, (u64, u64)>, ReadError> {
+    let mut position = 0;
+    let (_, _, _, total_entries) = read_entry(fst, &mut position)?;
+    let string_table_offset = u64::from(total_entries)
+        .checked_mul(FST_ENTRY_SIZE)
+        .ok_or_else(ReadError::int_under_overflow)?;
+
+    let mut files = HashMap::new();
+    // The current directory path and the index of the first entry after it
+    let mut directories: Vec<(String, u32)> = vec![(String::new(), total_entries)];
+    for index in 1..total_entries {
+        while directories.len() > 1 && directories.last().is_some_and(|(_, end)| *end <= index) {
+            directories.pop();
+        }
+        let (is_directory, name_offset, offset, size) = read_entry(fst, &mut position)?;
+        let mut name_position = string_table_offset
+            .checked_add(u64::from(name_offset))
+            .ok_or_else(ReadError::int_under_overflow)?;
+        let name = fst.read_null_terminated_string_at(&mut name_position)?;
+        let parent = &directories.last().unwrap_or_else(|| unreachable!()).0;
+        let path = format!("{parent}{name}");
+        if is_directory {
+            directories.push((format!("{path}/"), size));
+        } else {
+            let offset = u64::from(offset)
+                .checked_mul(4)
+                .ok_or_else(ReadError::int_under_overflow)?;
+            files.insert(VirtualPathBuf::from(path), (offset, u64::from(size)));
+        }
+    }
+    Ok(files)
+}
+
+/// Read an entry of the FST
+///
+/// Returns if it's a directory, the offset of the name, the offset or parent, and the size or end.
+fn read_entry(fst: &[u8], position: &mut u64) -> Result<(bool, u32, u32, u32), ReadError> {
+    let entry_type = fst.read_at::<u8>(position)?;
+    let name_offset = fst.read_at::<u24be>(position)?;
+    let offset = fst.read_at::<u32be>(position)?;
+    let size = fst.read_at::<u32be>(position)?;
+    match entry_type {
+        0 => Ok((false, name_offset, offset, size)),
+        1 => Ok((true, name_offset, offset, size)),
+        _ => Err(ReadError::custom(format!(
+            "Unknown FST entry type: {entry_type:x}"
+        ))),
+    }
+}
+
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::vfs::VirtualPathBuf;
+
+    use super::parse_fst;
+
+    #[test]
+    fn test_parse_fst() {
+        #[rustfmt::skip]
+        let fst = [
+            // root, 5 entries
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05,
+            // directory "a", ends after entry 3
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04,
+            // directory "a/b", empty
+            0x01, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03,
+            // file "a/c" at 0x40 with size 0x10
+            0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x10,
+            // file "d" at 0x80 with size 0x20
+            0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x20,
+            // string table
+            b'a', 0x00, b'b', 0x00, b'c', 0x00, b'd', 0x00,
+        ];
+        let files = parse_fst(&fst).unwrap();
+        assert_eq!(files.len(), 2, "Should only contain the files");
+        assert_eq!(
+            files.get(&VirtualPathBuf::from("a/c")),
+            Some(&(0x40, 0x10)),
+            "File in directory is wrong"
+        );
+        assert_eq!(
+            files.get(&VirtualPathBuf::from("d")),
+            Some(&(0x80, 0x20)),
+            "File after directory is wrong"
+        );
+    }
+}
@@ -10,15 +10,66 @@
 //! Currently supported are:
 //! | File format | Extension | Supported                        |
 //! | U8          | .app      | yes                              |
-//! | WAD         | .wad      | Partially, only Installable WADs |
+//! | WAD         | .wad      | yes, Installable and Backup WADs |
+//! | Disc image  | .iso      | yes, only the data partition     |
+//! | WBFS        | .wbfs     | yes, only the first disc         |
 //!
 //! ## Features
 //! This crate has no features that can be enabled
 //!
 
+pub mod disc; // wii .iso and .wbfs files
 pub mod u8a; // wii .app files
 pub mod wad; // wii .wad files
 
+use anyhow::{anyhow, Error};
+use wad::types::PublicKey;
+
+/// Parse a 16 byte key, either raw or encoded as 32 hex characters
+///
+/// # Errors
+/// Will return an error if `data` is not a raw or hex encoded key
+pub fn parse_key(data: &[u8]) -> Result<[u8; 0x10], Error> {
+    parse_bytes(data)
+}
+
+/// Parse the Wii root key, the 512 byte modulus either raw or encoded as 1024 hex characters
+///
+/// The root key is an RSA-4096 key with public exponent 0x10001. It signs the CA certificate
+/// in the certificate chain of every WAD and disc.
+///
+/// # Errors
+/// Will return an error if `data` is not a raw or hex encoded modulus
+pub fn parse_root_key(data: &[u8]) -> Result<PublicKey, Error> {
+    let modulus = parse_bytes::<0x200>(data)?;
+    Ok(PublicKey::Rsa {
+        modulus: modulus.to_vec(),
+        exponent: 0x10001,
+    })
+}
+
+/// Parse `N` bytes, either raw or encoded as `2 * N` hex characters
+///
+/// # Errors
+/// Will return an error if `data` is not `N` raw or hex encoded bytes
+fn parse_bytes<const N: usize>(data: &[u8]) -> Result<[u8; N], Error> {
+    if let Ok(key) = <[u8; N]>::try_from(data) {
+        return Ok(key);
+    }
+    let hex = std::str::from_utf8(data)?.trim();
+    if Some(hex.len()) != N.checked_mul(2) {
+        return Err(anyhow!(
+            "Key should be {N} bytes or {} hex characters",
+            N.saturating_mul(2)
+        ));
+    }
+    let mut key = [0; N];
+    for (byte, pair) in key.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair)?, 16)?;
+    }
+    Ok(key)
+}
+
 /// Round address to the next boundary
 ///
 /// # Panics
@@ -2,3 +2,4 @@
 //! This parser implementation is based on the excellent documentation on [WiiBrew](https://wiibrew.org/wiki/U8_archive).
 
 pub mod types;
+pub mod vfs;
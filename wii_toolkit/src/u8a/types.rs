@@ -1,9 +1,6 @@
 //! Contains the types that describe the usefull information in this filetype
 
-use std::{
-    borrow::Cow,
-    collections::{hash_map::Entry, HashMap},
-};
+use std::{borrow::Cow, collections::HashMap};
 
 use dotstar_toolkit_utils::bytes::{
     primitives::{u24be, u32be},
@@ -12,6 +9,7 @@ use dotstar_toolkit_utils::bytes::{
 };
 use hipstr::HipStr;
 use test_eq::test_eq;
+use yoke::Yokeable;
 
 use crate::round_to_boundary;
 
@@ -63,6 +61,7 @@ impl<'de> BinaryDeserialize<'de> for UnparsedNode {
                 })),
                 Self::MAGIC_DIRECTORY => Ok(Self::Directory(NewUnparsedDirectory {
                     name_offset,
+                    parent_node_index: data_offset,
                     last_included_node_index: size,
                 })),
                 _ => Err(ReadError::custom("Node magic is unknown!".into())),
@@ -80,7 +79,9 @@ impl<'de> BinaryDeserialize<'de> for UnparsedNode {
 pub struct NewUnparsedDirectory {
     /// Offset to the name from the start of the string table
     pub name_offset: u32,
-    /// The index of the last node included in this directory
+    /// The index of the parent directory
+    pub parent_node_index: u32,
+    /// The index of the first node after this directory
     pub last_included_node_index: u32,
 }
 
@@ -96,7 +97,7 @@ impl BinarySerialize for NewUnparsedDirectory {
     ) -> Result<(), WriteError> {
         writer.write_at::<u8>(position, UnparsedNode::MAGIC_DIRECTORY)?;
         writer.write_at::<u24be>(position, input.name_offset)?;
-        writer.write_at::<u32be>(position, 0u32)?;
+        writer.write_at::<u32be>(position, input.parent_node_index)?;
         writer.write_at::<u32be>(position, input.last_included_node_index)?;
         Ok(())
     }
@@ -131,7 +132,7 @@ impl BinarySerialize for NewUnparsedFile {
 }
 
 /// The contents of a U8 archive
-#[derive(Debug)]
+#[derive(Debug, Yokeable)]
 pub struct U8Archive<'a> {
     /// The complete file tree of the archive
     pub file_tree: FileTree<'a>,
@@ -144,6 +145,8 @@ impl U8Archive<'_> {
     const ROOTNODE_OFFSET: u32 = 0x20;
     /// Padding between the header and root node
     const PADDING: [u8; 16] = [0; 16];
+    /// Alignment of the data of every file
+    const DATA_ALIGNMENT: u32 = 0x20;
 }
 
 impl<'de> BinaryDeserialize<'de> for U8Archive<'de> {
@@ -194,9 +197,10 @@ impl<'de> BinaryDeserialize<'de> for U8Archive<'de> {
             };
 
             let mut trees = vec![(HipStr::borrowed(""), file_tree)];
-            let mut indexes = vec![total_nodes];
+            let mut ends = vec![total_nodes];
 
-            for index in 2..=total_nodes {
+            for index in 1..total_nodes {
+                close_directories(&mut trees, &mut ends, index);
                 let node = reader.read_at::<UnparsedNode>(position)?;
                 match node {
                     UnparsedNode::Directory(node) => {
@@ -209,7 +213,7 @@ impl<'de> BinaryDeserialize<'de> for U8Archive<'de> {
                             files: HashMap::new(),
                         };
                         trees.push((name, tree));
-                        indexes.push(node.last_included_node_index);
+                        ends.push(node.last_included_node_index);
                     }
                     UnparsedNode::File(node) => {
                         let mut data_offset = u64::from(node.data_offset)
@@ -228,24 +232,10 @@ impl<'de> BinaryDeserialize<'de> for U8Archive<'de> {
                             .1
                             .files
                             .insert(name, data);
-                        while indexes.last() != indexes.first()
-                            && index == *indexes.last().unwrap_or_else(|| unreachable!())
-                        {
-                            let tree = trees.pop().unwrap_or_else(|| unreachable!());
-                            trees
-                                .last_mut()
-                                .unwrap_or_else(|| unreachable!())
-                                .1
-                                .directories
-                                .insert(tree.0, tree.1);
-                            assert!(
-                                indexes.pop().is_some(),
-                                "There should be something left to pop!"
-                            );
-                        }
                     }
                 }
             }
+            close_directories(&mut trees, &mut ends, total_nodes);
 
             U8Archive {
                 file_tree: trees.pop().unwrap_or_else(|| unreachable!()).1,
@@ -258,6 +248,25 @@ impl<'de> BinaryDeserialize<'de> for U8Archive<'de> {
     }
 }
 
+/// Add all directories that end at or before `index` to their parent directory
+fn close_directories<'a>(
+    trees: &mut Vec<(HipStr<'a>, FileTree<'a>)>,
+    ends: &mut Vec<u32>,
+    index: u32,
+) {
+    // The root directory is never closed
+    while trees.len() > 1 && ends.last().is_some_and(|end| *end <= index) {
+        let (name, tree) = trees.pop().unwrap_or_else(|| unreachable!());
+        ends.pop();
+        trees
+            .last_mut()
+            .unwrap_or_else(|| unreachable!())
+            .1
+            .directories
+            .insert(name, tree);
+    }
+}
+
 impl BinarySerialize for U8Archive<'_> {
     type Ctx = ();
     type Input = Self;
@@ -268,17 +277,28 @@ impl BinarySerialize for U8Archive<'_> {
         position: &mut u64,
         _ctx: Self::Ctx,
     ) -> Result<(), WriteError> {
-        let count = input.file_tree.count()?;
-        let (string_table_size, string_table) = input.file_tree.string_table()?;
+        let begin_position = *position;
+
+        let mut nodes = Vec::new();
+        flatten_filetree_rec(&input.file_tree, "", 0, &mut nodes)?;
+
+        // The names are stored in the same order as the nodes, starting with the empty root name
+        let mut string_table = Vec::new();
+        let mut name_offsets = Vec::with_capacity(nodes.len());
+        for node in &nodes {
+            name_offsets.push(u32::try_from(string_table.len())?);
+            string_table.extend_from_slice(node.name().as_bytes());
+            string_table.push(0);
+        }
 
         // Write the magic value
         writer.write_at::<u32be>(position, Self::MAGIC)?;
         // Write the rootnode offset
         writer.write_at::<u32be>(position, Self::ROOTNODE_OFFSET)?;
         // Calculate and write the header size and data offset
-        let header_size = count
+        let header_size = u32::try_from(nodes.len())?
             .checked_mul(12)
-            .and_then(|c| c.checked_add(string_table_size))
+            .and_then(|c| c.checked_add(u32::try_from(string_table.len()).ok()?))
             .ok_or_else(WriteError::int_under_overflow)?;
         let mut data_offset = round_to_boundary(
             Self::ROOTNODE_OFFSET
@@ -290,77 +310,106 @@ impl BinarySerialize for U8Archive<'_> {
         // Write the padding
         writer.write_at::<[u8; 16]>(position, Self::PADDING)?;
 
-        let mut current_idx = 1;
-        write_filetree_rec(
-            writer,
-            position,
-            &mut data_offset,
-            &input.file_tree,
-            &string_table,
-            &mut current_idx,
-            "",
-        )?;
+        // Write the nodes and the data of the files
+        for (node, name_offset) in nodes.into_iter().zip(name_offsets) {
+            match node {
+                FlatNode::Directory { parent, end, .. } => {
+                    let node = NewUnparsedDirectory {
+                        name_offset,
+                        parent_node_index: parent,
+                        last_included_node_index: end,
+                    };
+                    writer.write_at::<NewUnparsedDirectory>(position, node)?;
+                }
+                FlatNode::File { data, .. } => {
+                    data_offset = data_offset
+                        .checked_next_multiple_of(Self::DATA_ALIGNMENT)
+                        .ok_or_else(WriteError::int_under_overflow)?;
+                    let size = u32::try_from(data.len())?;
+                    let node = NewUnparsedFile {
+                        name_offset,
+                        data_offset,
+                        size,
+                    };
+                    writer.write_at::<NewUnparsedFile>(position, node)?;
+                    let mut data_position = begin_position
+                        .checked_add(u64::from(data_offset))
+                        .ok_or_else(WriteError::int_under_overflow)?;
+                    writer.write_slice_at(&mut data_position, data)?;
+                    data_offset = data_offset
+                        .checked_add(size)
+                        .ok_or_else(WriteError::int_under_overflow)?;
+                }
+            }
+        }
+        writer.write_slice_at(position, &string_table)?;
+
+        *position = begin_position
+            .checked_add(u64::from(data_offset))
+            .ok_or_else(WriteError::int_under_overflow)?;
 
         Ok(())
     }
 }
 
-/// Write the tree metadata recursively
-fn write_filetree_rec(
-    writer: &mut (impl WriteAt + ?Sized),
-    position: &mut u64,
-    data_offset: &mut u32,
-    file_tree: &FileTree,
-    string_table: &HashMap<HipStr<'_>, u32>,
-    current_idx: &mut u32, // start with one
-    name: &str,
+/// A node in the order it will be written
+enum FlatNode<'b> {
+    /// A directory
+    Directory {
+        /// The name of the directory
+        name: &'b str,
+        /// The index of the parent directory
+        parent: u32,
+        /// The index of the first node after this directory
+        end: u32,
+    },
+    /// A file
+    File {
+        /// The name of the file
+        name: &'b str,
+        /// The contents of the file
+        data: &'b [u8],
+    },
+}
+
+impl FlatNode<'_> {
+    /// The name of the node
+    const fn name(&self) -> &str {
+        match self {
+            FlatNode::Directory { name, .. } | FlatNode::File { name, .. } => name,
+        }
+    }
+}
+
+/// Flatten the tree recursively into the order of the node table
+///
+/// Every directory is followed by its files and then its subdirectories, both sorted by name.
+fn flatten_filetree_rec<'b>(
+    file_tree: &'b FileTree<'_>,
+    name: &'b str,
+    parent: u32,
+    nodes: &mut Vec<FlatNode<'b>>,
 ) -> Result<(), WriteError> {
-    // Create and write this directory node
-    let count = file_tree.count()?;
-    let node = NewUnparsedDirectory {
-        name_offset: *string_table.get(name).unwrap_or_else(|| unreachable!()),
-        last_included_node_index: current_idx
-            .checked_add(count)
-            .ok_or_else(WriteError::int_under_overflow)?,
-    };
-    writer.write_at::<NewUnparsedDirectory>(position, node)?;
-    *current_idx = current_idx
+    let index = u32::try_from(nodes.len())?;
+    let end = index
         .checked_add(1)
+        .and_then(|i| i.checked_add(file_tree.count().ok()?))
         .ok_or_else(WriteError::int_under_overflow)?;
-    // Write all files directly in this directory
-    for (filename, data) in &file_tree.files {
-        let size = u32::try_from(data.len())?;
-        let node = NewUnparsedFile {
-            name_offset: *string_table
-                .get(filename.as_str())
-                .unwrap_or_else(|| unreachable!()),
-            data_offset: *data_offset,
-            size,
-        };
-        // Write the data
-        let mut data_offset_u64 = u64::from(*data_offset);
-        writer.write_slice_at(&mut data_offset_u64, data.as_ref())?;
-        *data_offset = data_offset
-            .checked_add(size)
-            .ok_or_else(WriteError::int_under_overflow)?;
-        // Write the file node
-        writer.write_at::<NewUnparsedFile>(position, node)?;
-        *current_idx = current_idx
-            .checked_add(1)
-            .ok_or_else(WriteError::int_under_overflow)?;
+    nodes.push(FlatNode::Directory { name, parent, end });
+
+    let mut files: Vec<_> = file_tree.files.iter().collect();
+    files.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    for (name, data) in files {
+        nodes.push(FlatNode::File {
+            name: name.as_str(),
+            data: data.as_ref(),
+        });
     }
 
-    // Write all subdirectories and files
-    for (name, tree) in &file_tree.directories {
-        write_filetree_rec(
-            writer,
-            position,
-            data_offset,
-            tree,
-            string_table,
-            current_idx,
-            name.as_ref(),
-        )?;
+    let mut directories: Vec<_> = file_tree.directories.iter().collect();
+    directories.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    for (name, tree) in directories {
+        flatten_filetree_rec(tree, name.as_str(), index, nodes)?;
     }
 
     Ok(())
@@ -393,42 +442,59 @@ impl FileTree<'_> {
     }
 }
 
-impl<'a> FileTree<'a> {
-    /// Creates a string table from this tree
-    fn string_table(&self) -> Result<(u32, HashMap<HipStr<'a>, u32>), WriteError> {
-        let mut string_map = HashMap::new();
-        let mut offset = 1;
-        self.string_table_rec(&mut string_map, &mut offset)?;
-        Ok((offset, string_map))
-    }
+#[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, collections::HashMap};
 
-    /// Recursive part of `string_table`
-    fn string_table_rec(
-        &self,
-        string_map: &mut HashMap<HipStr<'a>, u32>,
-        offset: &mut u32,
-    ) -> Result<(), WriteError> {
-        for file in &self.files {
-            if let Entry::Vacant(entry) = string_map.entry(file.0.clone()) {
-                let length = entry.key().len();
-                entry.insert(*offset);
-                *offset = offset
-                    .checked_add(length.try_into()?)
-                    .and_then(|i| i.checked_add(1))
-                    .ok_or_else(WriteError::int_under_overflow)?;
-            }
-        }
-        for directory in &self.directories {
-            if let Entry::Vacant(entry) = string_map.entry(directory.0.clone()) {
-                let length = entry.key().len();
-                entry.insert(*offset);
-                *offset = offset
-                    .checked_add(length.try_into()?)
-                    .and_then(|i| i.checked_add(1))
-                    .ok_or_else(WriteError::int_under_overflow)?;
-            }
-            directory.1.string_table_rec(string_map, offset)?;
-        }
-        Ok(())
+    use dotstar_toolkit_utils::bytes::{
+        read::BinaryDeserializeExt as _, write::BinarySerializeExt as _,
+    };
+    use hipstr::HipStr;
+
+    use super::{FileTree, U8Archive};
+
+    #[test]
+    fn test_roundtrip() {
+        let empty = FileTree {
+            directories: HashMap::new(),
+            files: HashMap::new(),
+        };
+        let nested = FileTree {
+            directories: HashMap::from([(HipStr::borrowed("empty"), empty)]),
+            files: HashMap::from([(HipStr::borrowed("b.bin"), Cow::Borrowed(&[1, 2, 3][..]))]),
+        };
+        let root = FileTree {
+            directories: HashMap::from([(HipStr::borrowed("nested"), nested)]),
+            files: HashMap::from([
+                (HipStr::borrowed("a.bin"), Cow::Borrowed(&[4][..])),
+                (HipStr::borrowed("c.bin"), Cow::Borrowed(&[5, 6][..])),
+            ]),
+        };
+
+        let mut buffer = Vec::new();
+        U8Archive::serialize(U8Archive { file_tree: root }, &mut buffer).unwrap();
+        let archive = U8Archive::deserialize(buffer.as_slice()).unwrap();
+
+        let root = archive.file_tree;
+        assert_eq!(root.files.len(), 2, "Root should have two files");
+        assert_eq!(root.files["a.bin"].as_ref(), &[4], "Data of a.bin is wrong");
+        assert_eq!(
+            root.files["c.bin"].as_ref(),
+            &[5, 6],
+            "Data of c.bin is wrong"
+        );
+        let nested = &root.directories["nested"];
+        assert_eq!(
+            nested.files["b.bin"].as_ref(),
+            &[1, 2, 3],
+            "Data of b.bin is wrong"
+        );
+        let empty = &nested.directories["empty"];
+        assert!(empty.files.is_empty(), "Empty directory should be empty");
+        assert!(
+            empty.directories.is_empty(),
+            "Empty directory should be empty"
+        );
     }
 }
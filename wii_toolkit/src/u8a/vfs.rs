@@ -0,0 +1,98 @@
+//! # U8 Filesystem
+//! A U8 archive as a virtual filesystem, so it can be layered with other filesystems.
+use std::io::{Error, ErrorKind};
+
+use dotstar_toolkit_utils::{
+    bytes::read::BinaryDeserializeExt as _,
+    vfs::{VirtualFile, VirtualFileSystem, VirtualMetadata, VirtualPath, VirtualPathBuf, WalkFs},
+};
+use yoke::Yoke;
+
+use super::types::{FileTree, U8Archive};
+
+/// A U8 archive as a virtual filesystem
+pub struct U8Filesystem<'fs> {
+    /// The parsed archive
+    archive: Yoke<U8Archive<'static>, VirtualFile<'fs>>,
+    /// All paths in the archive
+    list: Vec<VirtualPathBuf>,
+}
+
+impl<'fs> U8Filesystem<'fs> {
+    /// Create a new virtual filesystem from the U8 archive at `path`
+    ///
+    /// # Errors
+    /// Will return an error if the file cannot be opened or is not a U8 archive
+    pub fn new(fs: &'fs dyn VirtualFileSystem, path: &VirtualPath) -> Result<Self, Error> {
+        let file = fs.open(path)?;
+        let archive = Yoke::try_attach_to_cart(file, |data: &[u8]| U8Archive::deserialize(data))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let mut list = Vec::new();
+        list_files_rec(root(&archive.get().file_tree), "", &mut list);
+        Ok(Self { archive, list })
+    }
+
+    /// Get the file at `path`
+    fn get(&self, path: &VirtualPath) -> Result<&[u8], Error> {
+        let path = path.clean_relative();
+        let mut tree = root(&self.archive.get().file_tree);
+        let mut components = path.as_str().split('/').peekable();
+        while let Some(component) = components.next() {
+            if components.peek().is_none() {
+                if let Some(data) = tree.files.get(component) {
+                    return Ok(data.as_ref());
+                }
+            } else if let Some(directory) = tree.directories.get(component) {
+                tree = directory;
+                continue;
+            }
+            break;
+        }
+        Err(Error::new(
+            ErrorKind::NotFound,
+            format!("Could not find {path:?} in U8 archive!"),
+        ))
+    }
+}
+
+impl VirtualFileSystem for U8Filesystem<'_> {
+    fn open(&self, path: &VirtualPath) -> std::io::Result<VirtualFile> {
+        self.get(path).map(VirtualFile::Slice)
+    }
+
+    fn metadata(&self, path: &VirtualPath) -> std::io::Result<VirtualMetadata> {
+        let data = self.get(path)?;
+        Ok(VirtualMetadata {
+            file_size: u64::try_from(data.len()).map_err(Error::other)?,
+            created: Err(ErrorKind::Unsupported),
+        })
+    }
+
+    fn walk_filesystem<'rf>(&'rf self, path: &VirtualPath) -> std::io::Result<WalkFs<'rf>> {
+        Ok(WalkFs::filtered(&self.list, path))
+    }
+
+    fn exists(&self, path: &VirtualPath) -> bool {
+        self.get(path).is_ok()
+    }
+}
+
+/// The directory that contains the files
+///
+/// Most archives have a single `.` directory in the root, which is skipped.
+fn root<'a, 'b>(tree: &'a FileTree<'b>) -> &'a FileTree<'b> {
+    match tree.directories.get(".") {
+        Some(directory) if tree.files.is_empty() && tree.directories.len() == 1 => directory,
+        _ => tree,
+    }
+}
+
+/// Add the paths of all files in `tree` to `list`
+fn list_files_rec(tree: &FileTree, prefix: &str, list: &mut Vec<VirtualPathBuf>) {
+    for name in tree.files.keys() {
+        list.push(VirtualPathBuf::from(format!("{prefix}{name}")));
+    }
+    for (name, directory) in &tree.directories {
+        list_files_rec(directory, &format!("{prefix}{name}/"), list);
+    }
+}
@@ -1,26 +1,16 @@
 //! Contains the parser implementation
 
-use cipher::{generic_array::GenericArray, BlockDecryptMut, KeyIvInit};
 use dotstar_toolkit_utils::bytes::{
     primitives::{u16be, u32be, u64be},
     read::{BinaryDeserialize, ReadAtExt, ReadError},
 };
-use test_eq::test_eq;
+use test_eq::{test_eq, test_le};
 
 use super::types::{
-    AccessRights, Aes128CbcDec, Content, ContentMetadata, ContentType, InstallableArchive, Region,
-    TicketMetadata, TitleMetadata, TitleType, WadArchive, WadType,
+    AccessRights, BackupArchive, Certificate, Content, ContentMetadata, ContentType,
+    InstallableArchive, PublicKey, Region, SignatureType, Signed, TicketMetadata, TitleMetadata,
+    TitleType, WadArchive, WadType,
 };
-use crate::round_to_boundary;
-
-/// Decrypt the data inplace
-fn aes_128_cbc_decrypt_inplace(data: &mut [u8], iv: &[u8], key: &[u8]) {
-    let mut decrypter = Aes128CbcDec::new(key.into(), iv.into());
-    for chunk in data.chunks_exact_mut(16) {
-        let block = GenericArray::from_mut_slice(chunk);
-        decrypter.decrypt_block_mut(block);
-    }
-}
 
 impl<'de> BinaryDeserialize<'de> for WadArchive<'de> {
     type Ctx = ();
@@ -35,7 +25,7 @@ impl<'de> BinaryDeserialize<'de> for WadArchive<'de> {
         let wad_type = reader.read_at::<WadType>(position)?;
         match (size, wad_type) {
             (0x20, WadType::Bootable | WadType::Installable) => Ok(WadArchive::Installable(reader.read_at_with::<InstallableArchive>(position, wad_type)?)),
-            (0x70, WadType::Backup) => Err(ReadError::custom("Backup WAD parsing not yet implemented!".to_string())),
+            (0x70, WadType::Backup) => Ok(WadArchive::Backup(reader.read_at_with::<BackupArchive>(position, wad_type)?)),
             _ => Err(ReadError::custom(format!("Unknown WAD type found or file is not a WAD file! Metadata size: {size}, WAD type: {wad_type:?}"))),
         }
     }
@@ -62,44 +52,202 @@ impl<'de> BinaryDeserialize<'de> for InstallableArchive<'de> {
         // verify known constant values`
         test_eq!(version, 0x0u16)?;
         test_eq!(unk1, 0x0u32)?;
+        *position = round_to_boundary_u64(*position);
 
-        // skip cert chain
-        // TODO: implement verification of cert chain
-        *position = position
-            .checked_add(u64::from(round_to_boundary(cert_chain_size)))
+        // parse cert chain
+        let cert_chain_end = position
+            .checked_add(u64::from(cert_chain_size))
             .ok_or_else(ReadError::int_under_overflow)?;
+        let mut certificates = Vec::new();
+        while *position < cert_chain_end {
+            certificates.push(reader.read_at::<Certificate>(position)?);
+        }
+        test_eq!(*position, cert_chain_end)?;
+        *position = round_to_boundary_u64(*position);
 
         // parse ticket data
         let ticket_start = *position;
+        let ticket_signature =
+            reader.read_at_with::<Signed>(&mut ticket_start.clone(), ticket_size)?;
         let ticket_metadata = reader.read_at::<TicketMetadata>(position)?;
+        test_eq!(position.checked_sub(ticket_start), Some(ticket_size))?;
         *position = round_to_boundary_u64(*position);
-        let ticket_end = *position;
-        test_eq!(ticket_end.checked_sub(ticket_start), Some(ticket_size))?;
 
         // parse title_metadata
         let tmd_start = *position;
+        let title_metadata_signature =
+            reader.read_at_with::<Signed>(&mut tmd_start.clone(), tmd_size)?;
         let title_metadata = reader.read_at::<TitleMetadata>(position)?;
+        test_eq!(position.checked_sub(tmd_start), Some(tmd_size))?;
         *position = round_to_boundary_u64(*position);
-        let tmd_end = *position;
-        test_eq!(tmd_end.checked_sub(tmd_start), Some(tmd_size))?;
 
-        let content = parse_content(reader, position, &ticket_metadata, &title_metadata)?;
+        let content = parse_content(reader, position, &title_metadata, |_| true)?;
 
         // TODO: Parse footer?
 
         Ok(InstallableArchive {
             wad_type,
+            certificates,
+            ticket_signature,
             ticket_metadata,
+            title_metadata_signature,
             title_metadata,
             content,
         })
     }
 }
 
-/// Wii Common Key, used to decrypt the title key
-const COMMON_KEY: [u8; 0x10] = [
-    0xEB, 0xE4, 0x2A, 0x22, 0x5E, 0x85, 0x93, 0xE4, 0x48, 0xD9, 0xC5, 0x45, 0x73, 0x81, 0xAA, 0xF7,
-];
+impl<'de> BinaryDeserialize<'de> for BackupArchive<'de> {
+    type Ctx = WadType;
+    type Output = Self;
+
+    fn deserialize_at_with(
+        reader: &'de (impl ReadAtExt + ?Sized),
+        position: &mut u64,
+        wad_type: Self::Ctx,
+    ) -> Result<Self::Output, ReadError> {
+        // parse header
+        let _version = reader.read_at::<u16be>(position)?;
+        let console_id = reader.read_at::<u32be>(position)?;
+        let save_file_count = reader.read_at::<u32be>(position)?;
+        let _save_file_size = reader.read_at::<u32be>(position)?;
+        let tmd_size = u64::from(reader.read_at::<u32be>(position)?);
+        let _content_size = reader.read_at::<u32be>(position)?;
+        let _backup_area_size = reader.read_at::<u32be>(position)?;
+        let included_contents = reader.read_at::<[u8; 0x40]>(position)?;
+        let title_id = reader.read_at::<u64be>(position)?;
+        let _mac_address = reader.read_at::<[u8; 0x6]>(position)?;
+        let padding = reader.read_at::<u16be>(position)?;
+        test_eq!(padding, 0x0u16)?;
+        // TODO: Support save files
+        test_eq!(save_file_count, 0x0u32)?;
+        *position = round_to_boundary_u64(*position);
+
+        // parse title_metadata
+        let tmd_start = *position;
+        let title_metadata_signature =
+            reader.read_at_with::<Signed>(&mut tmd_start.clone(), tmd_size)?;
+        let title_metadata = reader.read_at::<TitleMetadata>(position)?;
+        test_eq!(position.checked_sub(tmd_start), Some(tmd_size))?;
+        test_eq!(title_metadata.title_id, title_id)?;
+        *position = round_to_boundary_u64(*position);
+
+        let mut archive = BackupArchive {
+            wad_type,
+            console_id,
+            included_contents,
+            title_id,
+            title_metadata_signature,
+            title_metadata,
+            content: Vec::new(),
+        };
+        archive.content = parse_content(reader, position, &archive.title_metadata, |index| {
+            archive.is_included(index)
+        })?;
+
+        Ok(archive)
+    }
+}
+
+impl<'de> BinaryDeserialize<'de> for Signed<'de> {
+    /// Size of the signed structure, including the signature
+    type Ctx = u64;
+    type Output = Self;
+
+    /// Read the signature at the start of a signed structure
+    ///
+    /// `position` is left at the issuer, which is where the signed data starts.
+    fn deserialize_at_with(
+        reader: &'de (impl ReadAtExt + ?Sized),
+        position: &mut u64,
+        size: Self::Ctx,
+    ) -> Result<Self::Output, ReadError> {
+        let start = *position;
+        let signature_type = reader.read_at::<SignatureType>(position)?;
+        let signature = reader.read_slice_at(position, signature_type.signature_size())?;
+        *position = position
+            .checked_add(signature_type.padding_size())
+            .ok_or_else(ReadError::int_under_overflow)?;
+
+        let data_start = *position;
+        let data_size = start
+            .checked_add(size)
+            .and_then(|end| end.checked_sub(data_start))
+            .ok_or_else(ReadError::int_under_overflow)?;
+        let issuer = read_name(reader, &mut data_start.clone())?;
+        let data = reader.read_slice_at(&mut data_start.clone(), usize::try_from(data_size)?)?;
+
+        Ok(Self {
+            signature_type,
+            signature,
+            issuer,
+            data,
+        })
+    }
+}
+
+impl<'de> BinaryDeserialize<'de> for Certificate<'de> {
+    type Ctx = ();
+    type Output = Self;
+
+    fn deserialize_at_with(
+        reader: &'de (impl ReadAtExt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<Self::Output, ReadError> {
+        // The size of the certificate depends on the type of the signature and the public key
+        let start = *position;
+        let signature_type = reader.read_at::<SignatureType>(&mut start.clone())?;
+        let signature_size = u64::try_from(signature_type.signature_size())?;
+        let mut key_type_position = start
+            .checked_add(0x4)
+            .and_then(|p| p.checked_add(signature_size))
+            .and_then(|p| p.checked_add(signature_type.padding_size()))
+            .and_then(|p| p.checked_add(0x40))
+            .ok_or_else(ReadError::int_under_overflow)?;
+        let key_type = reader.read_at::<u32be>(&mut key_type_position)?;
+        let key_size = match key_type {
+            0 => 0x238,
+            1 => 0x138,
+            2 => 0x78,
+            _ => {
+                return Err(ReadError::custom(format!(
+                    "Unknown value for key type: {key_type:x}"
+                )))
+            }
+        };
+        let size = key_type_position
+            .checked_add(0x44)
+            .and_then(|p| p.checked_add(key_size))
+            .and_then(|end| end.checked_sub(start))
+            .ok_or_else(ReadError::int_under_overflow)?;
+
+        let signed = reader.read_at_with::<Signed>(position, size)?;
+        // skip issuer and key type
+        *position = key_type_position;
+        let name = read_name(reader, position)?;
+        let key_id = reader.read_at::<u32be>(position)?;
+        let public_key = match key_type {
+            0 | 1 => {
+                let modulus_size = if key_type == 0 { 0x200 } else { 0x100 };
+                let modulus = reader.read_slice_at(position, modulus_size)?.into_owned();
+                let exponent = reader.read_at::<u32be>(position)?;
+                PublicKey::Rsa { modulus, exponent }
+            }
+            _ => PublicKey::Ecc(reader.read_slice_at(position, 0x3C)?.into_owned()),
+        };
+        *position = start
+            .checked_add(size)
+            .ok_or_else(ReadError::int_under_overflow)?;
+
+        Ok(Self {
+            signed,
+            name,
+            key_id,
+            public_key,
+        })
+    }
+}
 
 impl BinaryDeserialize<'_> for TicketMetadata {
     type Ctx = ();
@@ -111,7 +259,7 @@ impl BinaryDeserialize<'_> for TicketMetadata {
         _ctx: Self::Ctx,
     ) -> Result<Self::Output, ReadError> {
         // skip signature, padding, issuer and ECDH data
-        // TODO: Verify signature
+        // the signature is parsed separately as [`Signed`]
         *position = position
             .checked_add(0x1BC)
             .ok_or_else(ReadError::int_under_overflow)?;
@@ -119,7 +267,7 @@ impl BinaryDeserialize<'_> for TicketMetadata {
         let format_version = reader.read_at::<u8>(position)?;
         let unk1 = reader.read_at::<u16be>(position)?;
         test_eq!(unk1, 0x0)?;
-        let mut title_key = reader.read_at::<[u8; 0x10]>(position)?;
+        let encrypted_title_key = reader.read_at::<[u8; 0x10]>(position)?;
         let unk2 = reader.read_at::<u8>(position)?; // TODO: Figure out this value
         test_eq!(unk2, 0x0)?;
         let ticket_id = reader.read_at::<u64be>(position)?;
@@ -128,8 +276,8 @@ impl BinaryDeserialize<'_> for TicketMetadata {
         let unk3 = reader.read_at::<u16be>(position)?;
         test_eq!(unk3, 0xFFFF)?;
         let title_version = reader.read_at::<u16be>(position)?;
-        let permitted_titles_mask = reader.read_at::<u64be>(position)?;
-        let permit_mask = reader.read_at::<u64be>(position)?;
+        let permitted_titles_mask = reader.read_at::<u32be>(position)?;
+        let permit_mask = reader.read_at::<u32be>(position)?;
         let tea = reader.read_at::<u8>(position)?;
         let title_export_allowed = if tea == 1 {
             true
@@ -141,7 +289,7 @@ impl BinaryDeserialize<'_> for TicketMetadata {
             )));
         };
         let common_key_index = reader.read_at::<u8>(position)?;
-        test_eq!(common_key_index, 0x0)?;
+        test_le!(common_key_index, 0x2)?;
         // Skip remainder of the header
         // TODO: Parse this?
         *position = position
@@ -154,13 +302,8 @@ impl BinaryDeserialize<'_> for TicketMetadata {
             ));
         }
 
-        // decrypt title key
-        let mut iv = title_id.to_be_bytes().to_vec();
-        iv.resize(0x10, 0);
-        aes_128_cbc_decrypt_inplace(&mut title_key, &iv, &COMMON_KEY);
-
         Ok(Self {
-            title_key,
+            encrypted_title_key,
             ticket_id,
             console_id,
             title_id,
@@ -168,6 +311,7 @@ impl BinaryDeserialize<'_> for TicketMetadata {
             permitted_titles_mask,
             permit_mask,
             title_export_allowed,
+            common_key_index,
         })
     }
 }
@@ -184,7 +328,7 @@ impl BinaryDeserialize<'_> for TitleMetadata {
         let signature_type = reader.read_at::<u32be>(position)?;
         test_eq!(signature_type, 0x10001u32)?;
         // skip signature, padding and issuer
-        // TODO: Verify signature
+        // the signature is parsed separately as [`Signed`]
         *position = position
             .checked_add(0x17C)
             .ok_or_else(ReadError::int_under_overflow)?;
@@ -261,25 +405,29 @@ impl BinaryDeserialize<'_> for TitleMetadata {
 
 /// Parse the content part of the WAD archive
 ///
+/// Only the contents for which `is_included` returns true are in the archive.
+///
 /// # Errors
 /// Will error if the various contents are too large
 fn parse_content<'de>(
     reader: &'de (impl ReadAtExt + ?Sized),
     position: &mut u64,
-    ticket: &TicketMetadata,
     title: &TitleMetadata,
+    is_included: impl Fn(u16) -> bool,
 ) -> Result<Vec<Content<'de>>, ReadError> {
     let mut contents = Vec::with_capacity(title.contents.len());
-    let key = ticket.title_key;
 
-    for metadata in &title.contents {
-        let size = usize::try_from(metadata.size)?;
+    for metadata in title.contents.iter().filter(|m| is_included(m.index)) {
+        // The encrypted data is padded to the AES block size
+        let size = metadata
+            .size
+            .checked_next_multiple_of(0x10)
+            .ok_or_else(ReadError::int_under_overflow)?;
         let mut iv = [0; 0x10];
         iv[..2].copy_from_slice(&metadata.index.to_be_bytes());
-        let data = reader.read_slice_at(position, size)?;
+        let data = reader.read_slice_at(position, usize::try_from(size)?)?;
         let new_content = Content {
             data,
-            key,
             iv,
             metadata: *metadata,
         };
@@ -290,6 +438,13 @@ fn parse_content<'de>(
     Ok(contents)
 }
 
+/// Read a name of 0x40 bytes, padded with null bytes
+fn read_name(reader: &(impl ReadAtExt + ?Sized), position: &mut u64) -> Result<String, ReadError> {
+    let name = reader.read_at::<[u8; 0x40]>(position)?;
+    let name = name.split(|b| *b == 0).next().unwrap_or_default();
+    Ok(std::str::from_utf8(name)?.to_owned())
+}
+
 /// Round address to the next boundary
 ///
 /// # Panics
@@ -301,9 +456,203 @@ fn round_to_boundary_u64(n: u64) -> u64 {
 }
 
 #[allow(clippy::missing_panics_doc, reason = "They're tests")]
+#[allow(
+    clippy::arithmetic_side_effects,
+    reason = "The sizes in the tests are small constants"
+)]
 #[cfg(test)]
 mod tests {
+    use aes::Aes128;
+    use cipher::{
+        block_padding::NoPadding, generic_array::GenericArray, BlockEncryptMut, KeyIvInit,
+    };
+    use dotstar_toolkit_utils::bytes::read::BinaryDeserializeExt as _;
+    use rsa::{traits::PublicKeyParts, BigUint, Pkcs1v15Sign, RsaPrivateKey};
+    use sha1::{Digest, Sha1};
+
     use super::round_to_boundary_u64;
+    use crate::wad::types::{
+        Certificate, InstallableArchive, PublicKey, SignatureType, TicketMetadata, TitleMetadata,
+        WadArchive,
+    };
+
+    /// Encryptor for the title key and the contents
+    type Aes128CbcEnc = cbc::Encryptor<Aes128>;
+
+    /// First prime of the key that signs everything in the tests
+    const P: &str = concat!(
+        "ea782248407d8594167724c69d166734c2879d064c7cc1c7428895556498fa03",
+        "be7bdcd9cee76fd63aadb118cba1fba20770c1e1c347ac8692e95b8d85d16cd2",
+        "842bee88bbdc921df47ccb445392b7ad8a2d8b00919d14857940ce287163d05d",
+        "234238e5dd23e9da380a9c24e5f9bc14fc2e871bf258d73c10591a8b4dab15c7",
+    );
+    /// Second prime of the key that signs everything in the tests
+    const Q: &str = concat!(
+        "b70f3fb5630b7b04484ae11b53d00e72d2b6f92348590851f3930857a0220108",
+        "fd22d9e28e6bedc1f4a10b88aa367cde72a501f76784374869f428f5c50c8a2c",
+        "514e6d49b2e89170a183deb1ed6e5ac61c7b6e4d12ad35a9fe41f2c56f5a4cb6",
+        "727ddc98ea6a22902e4cc55fe480cca0fb37a2d9b5dc4001b167c1be566c719b",
+    );
+    /// Common key used in the tests
+    const COMMON_KEY: [u8; 0x10] = [0x11; 0x10];
+    /// Title id used in the tests
+    const TITLE_ID: u64 = 0x0001_0001_5344_3545;
+    /// The decrypted title key used in the tests
+    const TITLE_KEY: [u8; 0x10] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E,
+        0x0F,
+    ];
+    /// [`TITLE_KEY`] encrypted with [`COMMON_KEY`]
+    const ENCRYPTED_TITLE_KEY: [u8; 0x10] = [
+        0xE2, 0x6A, 0xBA, 0x2A, 0xB5, 0xFC, 0x54, 0x61, 0x9D, 0xCE, 0x2A, 0x34, 0x85, 0x35, 0xE9,
+        0xA3,
+    ];
+
+    /// The RSA-2048 key that signs everything in the tests, it's also used as the root key
+    fn test_key() -> RsaPrivateKey {
+        let p = BigUint::parse_bytes(P.as_bytes(), 16).unwrap();
+        let q = BigUint::parse_bytes(Q.as_bytes(), 16).unwrap();
+        RsaPrivateKey::from_p_q(p, q, BigUint::from(0x10001u32)).unwrap()
+    }
+
+    /// The public part of `key`
+    fn public_key(key: &RsaPrivateKey) -> PublicKey {
+        PublicKey::Rsa {
+            modulus: key.n().to_bytes_be(),
+            exponent: 0x10001,
+        }
+    }
+
+    /// Pad `name` with null bytes to 0x40 bytes
+    fn name(name: &str) -> Vec<u8> {
+        let mut name = name.as_bytes().to_vec();
+        name.resize(0x40, 0);
+        name
+    }
+
+    /// Prepend an RSA-2048 signature of `data` made with `key`
+    ///
+    /// `data` starts with the issuer.
+    fn sign(key: &RsaPrivateKey, data: Vec<u8>) -> Vec<u8> {
+        let signature = key
+            .sign(Pkcs1v15Sign::new::<Sha1>(), &Sha1::digest(&data))
+            .unwrap();
+        let mut signed = 0x10001u32.to_be_bytes().to_vec();
+        signed.extend(signature);
+        signed.resize(signed.len() + 0x3C, 0);
+        signed.extend(data);
+        signed
+    }
+
+    /// An RSA-2048 certificate for `key` named `certificate`, issued by `issuer`
+    fn certificate(key: &RsaPrivateKey, issuer: &str, certificate: &str) -> Vec<u8> {
+        let mut data = name(issuer);
+        data.extend(1u32.to_be_bytes());
+        data.extend(name(certificate));
+        data.extend(0x1234_5678u32.to_be_bytes());
+        data.extend(key.n().to_bytes_be());
+        data.extend(0x10001u32.to_be_bytes());
+        data.resize(data.len() + 0x34, 0);
+        sign(key, data)
+    }
+
+    /// A ticket for [`TITLE_ID`] with [`ENCRYPTED_TITLE_KEY`], issued by `issuer`
+    fn ticket(key: &RsaPrivateKey, issuer: &str) -> Vec<u8> {
+        let mut data = name(issuer);
+        // ECDH data
+        data.resize(0x7C, 0);
+        // format version and unknown
+        data.extend([0; 3]);
+        data.extend(ENCRYPTED_TITLE_KEY);
+        data.push(0);
+        data.extend(0x0005_0000_1234_5678u64.to_be_bytes());
+        data.extend(0xAABB_CCDDu32.to_be_bytes());
+        data.extend(TITLE_ID.to_be_bytes());
+        data.extend([0xFF, 0xFF]);
+        data.extend(0x0102u16.to_be_bytes());
+        data.extend(0x1u32.to_be_bytes());
+        data.extend(0x2u32.to_be_bytes());
+        // title export allowed and common key index
+        data.extend([1, 0]);
+        data.resize(0x164, 0);
+        sign(key, data)
+    }
+
+    /// Title metadata for [`TITLE_ID`] with one content, issued by `issuer`
+    fn title_metadata(key: &RsaPrivateKey, issuer: &str, content: &[u8]) -> Vec<u8> {
+        let mut data = name(issuer);
+        // version, CA CRL version, signer CRL version and vWii
+        data.extend([0, 1, 2, 0]);
+        data.extend(0x0000_0001_0000_0038u64.to_be_bytes());
+        data.extend(TITLE_ID.to_be_bytes());
+        data.extend(0x19u32.to_be_bytes());
+        data.extend(0x3031u16.to_be_bytes());
+        data.extend([0; 2]);
+        // Europe
+        data.extend(0x2u16.to_be_bytes());
+        // ratings, reserved, IPC mask and reserved
+        data.resize(data.len() + 0x10 + 0xC + 0xC + 0x12, 0);
+        // access rights
+        data.extend([0; 4]);
+        data.extend(0x0102u16.to_be_bytes());
+        // number of contents, boot index and minor version
+        data.extend(1u16.to_be_bytes());
+        data.extend([0; 4]);
+        // the content
+        data.extend(0x0000_0042u32.to_be_bytes());
+        data.extend(0u16.to_be_bytes());
+        data.extend(0x0001u16.to_be_bytes());
+        data.extend(u64::try_from(content.len()).unwrap().to_be_bytes());
+        data.extend(Sha1::digest(content).as_slice());
+        sign(key, data)
+    }
+
+    /// Pad `data` with null bytes to the next multiple of `n`
+    fn pad(data: &mut Vec<u8>, n: usize) {
+        data.resize(data.len().next_multiple_of(n), 0);
+    }
+
+    /// An installable WAD with `certificates` and one content, signed by `key`
+    fn installable(key: &RsaPrivateKey, certificates: &[Vec<u8>], content: &[u8]) -> Vec<u8> {
+        let certificates = certificates.concat();
+        let ticket = ticket(key, "Root-CA00000001-XS00000003");
+        let title_metadata = title_metadata(key, "Root-CA00000001-CP00000004", content);
+        let mut encrypted = content.to_vec();
+        pad(&mut encrypted, 0x10);
+        let mut iv = [0; 0x10];
+        iv[..2].copy_from_slice(&0u16.to_be_bytes());
+        let encrypted = Aes128CbcEnc::new(&GenericArray::from(TITLE_KEY), &GenericArray::from(iv))
+            .encrypt_padded_vec_mut::<NoPadding>(&encrypted);
+
+        let mut wad = 0x20u32.to_be_bytes().to_vec();
+        wad.extend(0x4973u16.to_be_bytes());
+        wad.extend(0u16.to_be_bytes());
+        for size in [
+            certificates.len(),
+            0,
+            ticket.len(),
+            title_metadata.len(),
+            encrypted.len(),
+            0,
+        ] {
+            wad.extend(u32::try_from(size).unwrap().to_be_bytes());
+        }
+        for part in [certificates, ticket, title_metadata, encrypted] {
+            pad(&mut wad, 0x40);
+            wad.extend(part);
+        }
+        pad(&mut wad, 0x40);
+        wad
+    }
+
+    /// The certificates of a complete chain
+    fn chain(key: &RsaPrivateKey) -> Vec<Vec<u8>> {
+        vec![
+            certificate(key, "Root", "CA00000001"),
+            certificate(key, "Root-CA00000001", "XS00000003"),
+            certificate(key, "Root-CA00000001", "CP00000004"),
+        ]
+    }
 
     #[test]
     fn test_rounding() {
@@ -315,4 +664,191 @@ mod tests {
         assert_eq!(round_to_boundary_u64(0x2A4), 0x2C0);
         assert_eq!(round_to_boundary_u64(576), 576);
     }
+
+    #[test]
+    fn test_certificate() {
+        let key = test_key();
+        let data = certificate(&key, "Root-CA00000001", "XS00000003");
+        assert_eq!(data.len(), 0x300, "RSA-2048 certificates are 0x300 bytes");
+        let certificate = Certificate::deserialize(data.as_slice()).unwrap();
+        assert_eq!(certificate.name, "XS00000003", "Name is wrong");
+        assert_eq!(certificate.key_id, 0x1234_5678, "Key id is wrong");
+        assert_eq!(
+            certificate.full_name(),
+            "Root-CA00000001-XS00000003",
+            "Full name is wrong"
+        );
+        assert_eq!(
+            certificate.signed.signature_type,
+            SignatureType::Rsa2048,
+            "Signature type is wrong"
+        );
+        assert_eq!(
+            certificate.signed.issuer, "Root-CA00000001",
+            "Issuer is wrong"
+        );
+        assert_eq!(
+            certificate.signed.data.as_ref(),
+            &data[0x140..],
+            "Signed data should start at the issuer"
+        );
+        let PublicKey::Rsa { modulus, exponent } = &certificate.public_key else {
+            panic!("Public key should be RSA");
+        };
+        assert_eq!(modulus, &key.n().to_bytes_be(), "Modulus is wrong");
+        assert_eq!(*exponent, 0x10001, "Exponent is wrong");
+        certificate
+            .verify(&certificate.signed)
+            .expect("Certificate is signed by its own key");
+    }
+
+    #[test]
+    fn test_ticket() {
+        let data = ticket(&test_key(), "Root-CA00000001-XS00000003");
+        assert_eq!(data.len(), 0x2A4, "Tickets are 0x2A4 bytes");
+        let ticket = TicketMetadata::deserialize(data.as_slice()).unwrap();
+        assert_eq!(ticket.title_id, TITLE_ID, "Title id is wrong");
+        assert_eq!(
+            ticket.ticket_id, 0x0005_0000_1234_5678,
+            "Ticket id is wrong"
+        );
+        assert_eq!(ticket.console_id, 0xAABB_CCDD, "Console id is wrong");
+        assert_eq!(ticket.title_version, 0x0102, "Title version is wrong");
+        assert_eq!(
+            ticket.permitted_titles_mask, 0x1,
+            "Permitted titles mask is wrong"
+        );
+        assert_eq!(ticket.permit_mask, 0x2, "Permit mask is wrong");
+        assert!(
+            ticket.title_export_allowed,
+            "Title export should be allowed"
+        );
+        assert_eq!(
+            ticket.title_key(&COMMON_KEY),
+            TITLE_KEY,
+            "Title key should be decrypted with the title id as IV"
+        );
+    }
+
+    #[test]
+    fn test_title_metadata() {
+        let data = title_metadata(&test_key(), "Root-CA00000001-CP00000004", b"content");
+        assert_eq!(
+            data.len(),
+            0x1E4 + 0x24,
+            "Title metadata is 0x1E4 bytes plus the contents"
+        );
+        let title_metadata = TitleMetadata::deserialize(data.as_slice()).unwrap();
+        assert_eq!(title_metadata.title_id, TITLE_ID, "Title id is wrong");
+        assert_eq!(title_metadata.ca_crl_version, 1, "CA CRL version is wrong");
+        assert_eq!(
+            title_metadata.signer_crl_version, 2,
+            "Signer CRL version is wrong"
+        );
+        assert!(!title_metadata.is_vwii, "Title is not a vWii title");
+        assert_eq!(
+            title_metadata.system_version, 0x0000_0001_0000_0038,
+            "System version is wrong"
+        );
+        assert_eq!(title_metadata.group_id, 0x3031, "Group id is wrong");
+        assert_eq!(
+            title_metadata.title_version, 0x0102,
+            "Title version is wrong"
+        );
+        assert_eq!(title_metadata.boot_index, 0, "Boot index is wrong");
+        assert_eq!(title_metadata.contents.len(), 1, "There is one content");
+        let content = &title_metadata.contents[0];
+        assert_eq!(content.content_id, 0x42, "Content id is wrong");
+        assert_eq!(content.index, 0, "Content index is wrong");
+        assert_eq!(content.size, 7, "Content size is wrong");
+        assert_eq!(
+            content.sha1_hash.as_slice(),
+            Sha1::digest(b"content").as_slice(),
+            "Content hash is wrong"
+        );
+    }
+
+    #[test]
+    fn test_installable() {
+        let key = test_key();
+        let data = installable(&key, &chain(&key), b"Just Dance");
+        let wad = parse_installable(&data);
+        assert_eq!(wad.certificates.len(), 3, "There are three certificates");
+        wad.verify_signatures(&public_key(&key))
+            .expect("Signatures should be valid");
+        let title_key = wad.title_key(&COMMON_KEY);
+        assert_eq!(title_key, TITLE_KEY, "Title key is wrong");
+        assert_eq!(wad.content.len(), 1, "There is one content");
+        assert_eq!(
+            wad.content[0].decrypt(&title_key).unwrap(),
+            b"Just Dance",
+            "Content should be decrypted and unpadded"
+        );
+    }
+
+    /// Parse `data` as an installable WAD
+    fn parse_installable(data: &[u8]) -> InstallableArchive<'_> {
+        match WadArchive::deserialize(data).unwrap() {
+            WadArchive::Installable(wad) => wad,
+            WadArchive::Backup(_) => panic!("WAD should be installable"),
+        }
+    }
+
+    #[test]
+    fn test_verify_wrong_root_key() {
+        let key = test_key();
+        let data = installable(&key, &chain(&key), b"Just Dance");
+        let root_key = PublicKey::Rsa {
+            modulus: key.n().to_bytes_be(),
+            exponent: 3,
+        };
+        assert!(
+            parse_installable(&data)
+                .verify_signatures(&root_key)
+                .is_err(),
+            "CA certificate should not verify with a different root key"
+        );
+    }
+
+    #[test]
+    fn test_verify_root_issued_not_ca() {
+        let key = test_key();
+        let mut certificates = chain(&key);
+        certificates.push(certificate(&key, "Root", "XS00000003"));
+        let data = installable(&key, &certificates, b"Just Dance");
+        assert!(
+            parse_installable(&data)
+                .verify_signatures(&public_key(&key))
+                .is_err(),
+            "Root should only issue CA certificates"
+        );
+    }
+
+    #[test]
+    fn test_verify_missing_ca() {
+        let key = test_key();
+        let certificates = chain(&key).split_off(1);
+        let data = installable(&key, &certificates, b"Just Dance");
+        assert!(
+            parse_installable(&data)
+                .verify_signatures(&public_key(&key))
+                .is_err(),
+            "Chain without the CA certificate is not anchored at the root key"
+        );
+    }
+
+    #[test]
+    fn test_verify_tampered_ticket() {
+        let key = test_key();
+        let mut data = installable(&key, &chain(&key), b"Just Dance");
+        // The ticket starts after the header and the certificates, change the console id
+        let console_id = 0x40 + 3 * 0x300 + 0x1D8;
+        data[console_id] ^= 0xFF;
+        assert!(
+            parse_installable(&data)
+                .verify_signatures(&public_key(&key))
+                .is_err(),
+            "Ticket signature should not match"
+        );
+    }
 }
@@ -3,12 +3,14 @@
 use std::borrow::Cow;
 
 use aes::Aes128;
-use anyhow::{anyhow, Error};
+use anyhow::{anyhow, bail, Error};
 use cipher::{block_padding::NoPadding, generic_array::GenericArray, BlockDecryptMut, KeyIvInit};
 use dotstar_toolkit_utils::bytes::{
     primitives::{u16be, u32be},
     read::{BinaryDeserialize, ReadAtExt, ReadError},
 };
+use rsa::{BigUint, Pkcs1v15Sign, RsaPublicKey};
+use sha1::{Digest, Sha1};
 
 /// Describes which variant of WAD this is
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -221,7 +223,7 @@ impl BinaryDeserialize<'_> for AccessRights {
         position: &mut u64,
         _ctx: Self::Ctx,
     ) -> Result<Self::Output, ReadError> {
-        let value = reader.read_at::<u16be>(position)?;
+        let value = reader.read_at::<u32be>(position)?;
         match value {
             0x0 => Ok(Self::None),
             _ => Err(ReadError::custom(format!(
@@ -260,10 +262,8 @@ pub struct ContentMetadata {
 /// Content with it's data, crypto, and metadata
 #[derive(Debug)]
 pub struct Content<'a> {
-    /// The encrypted data
+    /// The encrypted data, padded to the AES block size
     pub data: Cow<'a, [u8]>,
-    /// Decryption key
-    pub key: [u8; 0x10],
     /// Decryption IV
     pub iv: [u8; 0x10],
     /// The header
@@ -274,17 +274,171 @@ pub struct Content<'a> {
 pub type Aes128CbcDec = cbc::Decryptor<Aes128>;
 
 impl Content<'_> {
-    /// Decrypt the content into a newly allocated `Vec`
+    /// Decrypt the content into a newly allocated `Vec` and verify its SHA-1 hash
+    ///
+    /// For installable WADs `key` is the title key, for backup WADs it's the PRNG key of the
+    /// console.
+    ///
+    /// # Errors
+    /// Returns an error if the content is malformed or the hash does not match
+    pub fn decrypt(&self, key: &[u8; 0x10]) -> Result<Vec<u8>, Error> {
+        let decryptor = Aes128CbcDec::new(&GenericArray::from(*key), &GenericArray::from(self.iv));
+        let mut data = decryptor.decrypt_padded_vec_mut::<NoPadding>(self.data.as_ref())?;
+        data.truncate(usize::try_from(self.metadata.size)?);
+        if Sha1::digest(&data).as_slice() != self.metadata.sha1_hash {
+            bail!(
+                "SHA-1 hash of content {:08x} does not match, is the key correct?",
+                self.metadata.content_id
+            );
+        }
+        Ok(data)
+    }
+}
+
+/// The algorithm used for a signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SignatureType {
+    /// RSA-4096 with SHA-1
+    Rsa4096 = 0x10000,
+    /// RSA-2048 with SHA-1
+    Rsa2048 = 0x10001,
+    /// ECC with SHA-1
+    Ecc = 0x10002,
+}
+
+impl SignatureType {
+    /// Size of the signature
+    #[must_use]
+    pub const fn signature_size(self) -> usize {
+        match self {
+            Self::Rsa4096 => 0x200,
+            Self::Rsa2048 => 0x100,
+            Self::Ecc => 0x3C,
+        }
+    }
+
+    /// Size of the padding after the signature
+    #[must_use]
+    pub const fn padding_size(self) -> u64 {
+        match self {
+            Self::Rsa4096 | Self::Rsa2048 => 0x3C,
+            Self::Ecc => 0x40,
+        }
+    }
+}
+
+impl BinaryDeserialize<'_> for SignatureType {
+    type Ctx = ();
+    type Output = Self;
+
+    fn deserialize_at_with(
+        reader: &(impl ReadAtExt + ?Sized),
+        position: &mut u64,
+        _ctx: Self::Ctx,
+    ) -> Result<Self::Output, ReadError> {
+        let value = reader.read_at::<u32be>(position)?;
+        match value {
+            0x10000 => Ok(Self::Rsa4096),
+            0x10001 => Ok(Self::Rsa2048),
+            0x10002 => Ok(Self::Ecc),
+            _ => Err(ReadError::custom(format!(
+                "Unknown value for signature type: {value:x}"
+            ))),
+        }
+    }
+}
+
+/// A signature and the data it signs
+#[derive(Debug)]
+pub struct Signed<'a> {
+    /// See [`SignatureType`]
+    pub signature_type: SignatureType,
+    /// The signature
+    pub signature: Cow<'a, [u8]>,
+    /// Full name of the certificate that made the signature (like `Root-CA00000001-XS00000003`)
+    pub issuer: String,
+    /// The signed data, starts with the issuer
+    pub data: Cow<'a, [u8]>,
+}
+
+/// A public key
+#[derive(Debug)]
+pub enum PublicKey {
+    /// RSA-4096 or RSA-2048
+    Rsa {
+        /// The modulus (big-endian)
+        modulus: Vec<u8>,
+        /// The public exponent
+        exponent: u32,
+    },
+    /// ECC
+    Ecc(Vec<u8>),
+}
+
+impl PublicKey {
+    /// Verify that `signed` was signed with this key
     ///
     /// # Errors
-    /// Returns an error if the content is malformed
-    pub fn decrypt(&self) -> Result<Vec<u8>, Error> {
-        let decryptor =
-            Aes128CbcDec::new(&GenericArray::from(self.key), &GenericArray::from(self.iv));
-        Ok(decryptor.decrypt_padded_vec_mut::<NoPadding>(self.data.as_ref())?)
+    /// Will return an error if the signature does not match or uses ECC
+    pub fn verify(&self, signed: &Signed<'_>) -> Result<(), Error> {
+        let Self::Rsa { modulus, exponent } = self else {
+            bail!("Verifying ECC signatures is not supported!");
+        };
+        if signed.signature_type == SignatureType::Ecc {
+            bail!("Verifying ECC signatures is not supported!");
+        }
+        let key = RsaPublicKey::new(BigUint::from_bytes_be(modulus), BigUint::from(*exponent))?;
+        key.verify(
+            Pkcs1v15Sign::new::<Sha1>(),
+            &Sha1::digest(&signed.data),
+            &signed.signature,
+        )
+        .map_err(|_| anyhow!("Signature by {} is invalid!", signed.issuer))
     }
 }
 
+/// A certificate from a certificate chain
+#[derive(Debug)]
+pub struct Certificate<'a> {
+    /// The signature of the certificate
+    pub signed: Signed<'a>,
+    /// Name of the certificate (like `XS00000003`)
+    pub name: String,
+    /// Unknown
+    pub key_id: u32,
+    /// The key used for verifying signatures made by this certificate
+    pub public_key: PublicKey,
+}
+
+impl Certificate<'_> {
+    /// Full name of the certificate, this is what signatures use as issuer
+    #[must_use]
+    pub fn full_name(&self) -> String {
+        format!("{}-{}", self.signed.issuer, self.name)
+    }
+
+    /// Verify that `signed` was signed by this certificate
+    ///
+    /// # Errors
+    /// Will return an error if the signature does not match or uses ECC
+    pub fn verify(&self, signed: &Signed<'_>) -> Result<(), Error> {
+        self.public_key.verify(signed)
+    }
+}
+
+/// Verify `signed` with the certificate in `certificates` that issued it
+///
+/// # Errors
+/// Will return an error if the issuer is not in `certificates` or the signature does not match
+fn verify_with_chain(certificates: &[Certificate<'_>], signed: &Signed<'_>) -> Result<(), Error> {
+    certificates
+        .iter()
+        .find(|certificate| certificate.full_name() == signed.issuer)
+        .ok_or_else(|| anyhow!("Issuer {} is not in the certificate chain!", signed.issuer))?
+        .verify(signed)
+}
+
 /// Header of the WAD itself
 #[derive(Debug)]
 pub struct TitleMetadata {
@@ -321,8 +475,8 @@ pub struct TitleMetadata {
 /// Ticket metadata
 #[derive(Debug)]
 pub struct TicketMetadata {
-    /// Title key (already decrypted)
-    pub title_key: [u8; 0x10],
+    /// Title key, encrypted with the common key
+    pub encrypted_title_key: [u8; 0x10],
     /// Used as IV for title key decryption of console specific titles
     pub ticket_id: u64,
     /// Unknown
@@ -332,11 +486,27 @@ pub struct TicketMetadata {
     /// Unknown
     pub title_version: u16,
     /// Unknown
-    pub permitted_titles_mask: u64,
+    pub permitted_titles_mask: u32,
     /// Unknown
-    pub permit_mask: u64,
+    pub permit_mask: u32,
     /// Title Export allowed using PRNG key
     pub title_export_allowed: bool,
+    /// The common key the title key is encrypted with (0 is the Wii, 1 the Korean, 2 the vWii key)
+    pub common_key_index: u8,
+}
+
+impl TicketMetadata {
+    /// Decrypt the title key with the common key
+    #[must_use]
+    pub fn title_key(&self, common_key: &[u8; 0x10]) -> [u8; 0x10] {
+        let mut iv = [0; 0x10];
+        iv[..8].copy_from_slice(&self.title_id.to_be_bytes());
+        let mut title_key = self.encrypted_title_key;
+        Aes128CbcDec::new(&GenericArray::from(*common_key), &GenericArray::from(iv))
+            .decrypt_padded_mut::<NoPadding>(&mut title_key)
+            .unwrap_or_else(|_| unreachable!("The title key is exactly one block"));
+        title_key
+    }
 }
 
 /// Represents an installable/bootable WAD
@@ -344,33 +514,87 @@ pub struct TicketMetadata {
 pub struct InstallableArchive<'a> {
     /// See [`WadType`]
     pub wad_type: WadType,
+    /// The certificates used for signing the ticket and title metadata
+    pub certificates: Vec<Certificate<'a>>,
+    /// Signature of the ticket
+    pub ticket_signature: Signed<'a>,
     /// See [`TicketMetadata`]
     pub ticket_metadata: TicketMetadata,
+    /// Signature of the title metadata
+    pub title_metadata_signature: Signed<'a>,
     /// See [`TitleMetadata`]
     pub title_metadata: TitleMetadata,
     /// All content in this WAD
     pub content: Vec<Content<'a>>,
 }
 
+impl InstallableArchive<'_> {
+    /// Verify the certificate chain and the signatures of the ticket and title metadata
+    ///
+    /// The root key is not in the WAD, so the CA certificate is verified with `root_key` (see
+    /// [`crate::parse_root_key`]). As the full name of a certificate starts with the name of its
+    /// issuer, every chain ends at the CA certificate.
+    ///
+    /// # Errors
+    /// Will return an error if any of the signatures does not match or if `Root` issued anything
+    /// but a CA certificate
+    pub fn verify_signatures(&self, root_key: &PublicKey) -> Result<(), Error> {
+        for certificate in &self.certificates {
+            if certificate.signed.issuer == "Root" {
+                if !certificate.name.starts_with("CA") {
+                    bail!("Root issued {}, which is not a CA!", certificate.name);
+                }
+                root_key.verify(&certificate.signed)?;
+            } else {
+                verify_with_chain(&self.certificates, &certificate.signed)?;
+            }
+        }
+        verify_with_chain(&self.certificates, &self.ticket_signature)?;
+        verify_with_chain(&self.certificates, &self.title_metadata_signature)
+    }
+
+    /// Decrypt the title key with the common key
+    #[must_use]
+    pub fn title_key(&self, common_key: &[u8; 0x10]) -> [u8; 0x10] {
+        self.ticket_metadata.title_key(common_key)
+    }
+}
+
 /// Represents a backup WAD
+///
+/// The content is encrypted with the PRNG key of the console that made the backup.
 #[derive(Debug)]
 pub struct BackupArchive<'a> {
     /// See [`WadType`]
     pub wad_type: WadType,
     /// Console id
     pub console_id: u32,
-    /// Unknown
-    pub included_contents: &'a [u8],
+    /// Bitfield of which contents of the title are in this WAD
+    pub included_contents: [u8; 0x40],
     /// Title id
     pub title_id: u64,
-    /// See [`TicketMetadata`]
-    pub ticket_metadata: TicketMetadata,
+    /// Signature of the title metadata, the certificate chain is not in the WAD
+    pub title_metadata_signature: Signed<'a>,
     /// See [`TitleMetadata`]
     pub title_metadata: TitleMetadata,
     /// All content in this WAD
     pub content: Vec<Content<'a>>,
 }
 
+impl BackupArchive<'_> {
+    /// Is the content with `index` included in this WAD
+    #[must_use]
+    pub fn is_included(&self, index: u16) -> bool {
+        let byte = index
+            .checked_div(8)
+            .and_then(|byte| self.included_contents.get(usize::from(byte)));
+        let mask = index
+            .checked_rem(8)
+            .and_then(|bit| 1u8.checked_shl(u32::from(bit)));
+        byte.zip(mask).is_some_and(|(byte, mask)| byte & mask != 0)
+    }
+}
+
 /// The decoded WAD archive
 #[derive(Debug)]
 pub enum WadArchive<'a> {
@@ -380,6 +604,26 @@ pub enum WadArchive<'a> {
     Backup(BackupArchive<'a>),
 }
 
+impl<'a> WadArchive<'a> {
+    /// See [`TitleMetadata`]
+    #[must_use]
+    pub const fn title_metadata(&self) -> &TitleMetadata {
+        match self {
+            Self::Installable(archive) => &archive.title_metadata,
+            Self::Backup(archive) => &archive.title_metadata,
+        }
+    }
+
+    /// All content in this WAD
+    #[must_use]
+    pub fn content(&self) -> &[Content<'a>] {
+        match self {
+            Self::Installable(archive) => &archive.content,
+            Self::Backup(archive) => &archive.content,
+        }
+    }
+}
+
 /// MAGIC for bootable WAD
 pub const MAGIC_IB: [u8; 6] = [0x0, 0x0, 0x0, 0x20, 0x49, 0x62];
 /// MAGIC for installable WAD
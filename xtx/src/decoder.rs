@@ -2,9 +2,12 @@
 
 use std::{collections::VecDeque, num::TryFromIntError};
 
-use dotstar_toolkit_utils::bytes::{
-    primitives::{u32le, u64le},
-    read::{BinaryDeserialize, ReadAtExt, ReadError},
+use dotstar_toolkit_utils::{
+    bytes::{
+        primitives::{u32le, u64le},
+        read::{BinaryDeserialize, ReadAtExt, ReadError},
+    },
+    texture,
 };
 use image::{
     error::{DecodingError, ImageFormatHint},
@@ -17,9 +20,12 @@ use tegra_swizzle::{
 use test_eq::{test_eq, test_le, TestFailure};
 use thiserror::Error;
 
-use crate::types::{
-    Block, BlockData, Data, Format, TextureHeader, XtxRaw, DATA_BLK_TYPE, FIVE_EXPECTED_DATA,
-    TEX_HEAD_BLK_TYPE, UNKNOWN_BLK_TYPE_FIVE,
+use crate::{
+    formats,
+    types::{
+        Block, BlockData, Data, Format, TextureHeader, XtxRaw, DATA_BLK_TYPE, FIVE_EXPECTED_DATA,
+        TEX_HEAD_BLK_TYPE, UNKNOWN_BLK_TYPE_FIVE,
+    },
 };
 
 /// Errors returned when the decoder fails
@@ -43,6 +49,9 @@ pub enum DecoderError {
     /// Invalid block height
     #[error("Invalid block height: 2^{0}")]
     InvalidBlockHeight(u32),
+    /// The requested mipmap does not exist
+    #[error("Mipmap {0} does not exist, the texture has {1} mipmaps")]
+    MipmapOutOfRange(u32, u32),
     /// Swizzling went wrong
     #[error("Texture deswizzling failed")]
     SwizzleError(#[from] SwizzleError),
@@ -70,10 +79,14 @@ impl From<DecoderError> for ImageError {
 ///
 /// The decoder is lazy and will only read the metadata in the file. The
 /// image data is read on demand after calling [`read_image`].
+///
+/// By default the first mipmap is decoded, use [`XtxDecoder::set_mipmap`]
+/// to decode a different one.
 pub struct XtxDecoder<R: ReadAtExt> {
     reader: R,
     header: TextureHeader,
     data: Data,
+    mipmap: u32,
     pub minor_version: u32,
 }
 
@@ -123,14 +136,55 @@ impl<R: ReadAtExt> XtxDecoder<R> {
             reader,
             header,
             data,
+            mipmap: 0,
             minor_version,
         })
     }
+
+    /// The amount of mipmaps in the texture, including the base level
+    #[must_use]
+    pub const fn mipmaps(&self) -> u32 {
+        self.header.mipmaps
+    }
+
+    /// Select the mipmap that will be decoded
+    ///
+    /// # Errors
+    /// Will error if the texture does not have this mipmap
+    pub fn set_mipmap(&mut self, mipmap: u32) -> Result<(), DecoderError> {
+        if mipmap >= self.header.mipmaps {
+            return Err(DecoderError::MipmapOutOfRange(mipmap, self.header.mipmaps));
+        }
+        self.mipmap = mipmap;
+        Ok(())
+    }
+
+    /// The dimensions of `mipmap` in pixels
+    const fn mipmap_dimensions(&self, mipmap: u32) -> (u32, u32) {
+        let width = self.header.width >> mipmap;
+        let height = self.header.height >> mipmap;
+        (
+            if width == 0 { 1 } else { width },
+            if height == 0 { 1 } else { height },
+        )
+    }
+
+    /// The size of `mipmap` in bytes after deswizzling
+    fn mipmap_size(&self, mipmap: u32) -> Result<usize, DecoderError> {
+        let (width, height) = self.mipmap_dimensions(mipmap);
+        let bpp = u64::from(self.header.format.bytes_per_pixel());
+        let size = if self.header.format.is_bcn() {
+            u64::from(width.div_ceil(4)) * u64::from(height.div_ceil(4)) * bpp
+        } else {
+            u64::from(width) * u64::from(height) * bpp
+        };
+        Ok(usize::try_from(size)?)
+    }
 }
 
 impl<R: ReadAtExt> ImageDecoder for XtxDecoder<R> {
     fn dimensions(&self) -> (u32, u32) {
-        (self.header.width, self.header.height)
+        self.mipmap_dimensions(self.mipmap)
     }
 
     fn color_type(&self) -> ColorType {
@@ -181,23 +235,71 @@ impl<R: ReadAtExt> ImageDecoder for XtxDecoder<R> {
         .map_err(DecoderError::from)?;
         drop(data); // drop original data early
 
-        let width = usize::try_from(self.header.width).map_err(DecoderError::from)?;
-        let height = usize::try_from(self.header.height).map_err(DecoderError::from)?;
+        // The deswizzled mipmaps are stored one after the other
+        let mut offset = 0;
+        for mipmap in 0..self.mipmap {
+            offset += self.mipmap_size(mipmap)?;
+        }
+        test_le!(offset, deswizzled.len()).map_err(DecoderError::from)?;
+        let deswizzled = &deswizzled[offset..];
+
+        let (width, height) = self.dimensions();
+        let width = usize::try_from(width).map_err(DecoderError::from)?;
+        let height = usize::try_from(height).map_err(DecoderError::from)?;
         match self.header.format {
             Format::BC1 => {
-                texpresso::Format::Bc1.decompress(&deswizzled, width, height, buf);
+                texpresso::Format::Bc1.decompress(deswizzled, width, height, buf);
             }
             Format::BC2 => {
-                texpresso::Format::Bc2.decompress(&deswizzled, width, height, buf);
+                texpresso::Format::Bc2.decompress(deswizzled, width, height, buf);
             }
             Format::BC3 => {
-                texpresso::Format::Bc3.decompress(&deswizzled, width, height, buf);
+                texpresso::Format::Bc3.decompress(deswizzled, width, height, buf);
+            }
+            Format::BC4U => {
+                texture::decode_blocks(deswizzled, width, height, buf, (4, 4), 8, |block| {
+                    texture::bc4_block(block, false)
+                })
+                .map_err(DecoderError::from)?;
+            }
+            Format::BC4S => {
+                texture::decode_blocks(deswizzled, width, height, buf, (4, 4), 8, |block| {
+                    texture::bc4_block(block, true)
+                })
+                .map_err(DecoderError::from)?;
+            }
+            Format::BC5U => {
+                texture::decode_blocks(deswizzled, width, height, buf, (4, 4), 16, |block| {
+                    texture::bc5_block(block, false)
+                })
+                .map_err(DecoderError::from)?;
+            }
+            Format::BC5S => {
+                texture::decode_blocks(deswizzled, width, height, buf, (4, 4), 16, |block| {
+                    texture::bc5_block(block, true)
+                })
+                .map_err(DecoderError::from)?;
+            }
+            // sRGB is only a hint for the GPU, the data is the same
+            Format::NvnFormatRGBA8 | Format::NvnFormatRGBA8SRGB => {
+                test_le!(expected_size, deswizzled.len()).map_err(DecoderError::from)?;
+                buf.copy_from_slice(&deswizzled[..expected_size]);
+            }
+            Format::NvnFormatRGB10A2 => {
+                texture::decode_pixels(deswizzled, buf, formats::rgb10a2)
+                    .map_err(DecoderError::from)?;
+            }
+            Format::NvnFormatRGB565 => texture::decode_pixels(deswizzled, buf, formats::rgb565)
+                .map_err(DecoderError::from)?,
+            Format::NvnFormatRGB5A1 => texture::decode_pixels(deswizzled, buf, formats::rgb5a1)
+                .map_err(DecoderError::from)?,
+            Format::NvnFormatRGBA4 => texture::decode_pixels(deswizzled, buf, formats::rgba4)
+                .map_err(DecoderError::from)?,
+            Format::NvnFormatR8 => {
+                texture::decode_pixels(deswizzled, buf, texture::r8).map_err(DecoderError::from)?
             }
-            Format::NvnFormatRGBA8 => buf.copy_from_slice(&deswizzled[..expected_size]),
-            _ => unimplemented!(
-                "Decoding of {:?} is not yet implemented",
-                self.header.format
-            ),
+            Format::NvnFormatRG8 => texture::decode_pixels(deswizzled, buf, texture::r8g8)
+                .map_err(DecoderError::from)?,
         }
 
         Ok(())
@@ -6,14 +6,13 @@ use dotstar_toolkit_utils::bytes::{
 };
 use image::{
     error::{EncodingError, ImageFormatHint},
-    ExtendedColorType, ImageEncoder, ImageError, ImageResult,
+    imageops::{self, FilterType},
+    ExtendedColorType, ImageBuffer, ImageEncoder, ImageError, ImageResult, Rgba,
 };
 use tegra_swizzle::{
-    block_height_mip0,
-    surface::{swizzle_surface, BlockDim},
-    BlockHeight, SwizzleError,
+    block_height_mip0, mip_block_height, swizzle::swizzle_block_linear, BlockHeight, SwizzleError,
 };
-use test_eq::TestFailure;
+use test_eq::{test_eq, TestFailure};
 use thiserror::Error;
 
 use crate::types::{
@@ -26,6 +25,7 @@ pub struct XtxEncoder<W: WriteAt> {
     position: u64,
     format: Option<Format>,
     mipmaps: u8,
+    mipmap_filter: FilterType,
     params: texpresso::Params,
     minor_version: u32,
 }
@@ -38,6 +38,7 @@ impl<W: WriteAt> XtxEncoder<W> {
             position,
             format: None,
             mipmaps: 0,
+            mipmap_filter: FilterType::Triangle,
             params: texpresso::Params {
                 algorithm: texpresso::Algorithm::IterativeClusterFit,
                 ..Default::default()
@@ -56,7 +57,8 @@ impl<W: WriteAt> XtxEncoder<W> {
 
     /// Set the amount of mip maps
     ///
-    /// Maximum amount possible is 16.
+    /// Maximum amount possible is 16. If the image is too small for the amount
+    /// of mip maps, the chain will stop at the 1x1 level.
     ///
     /// # Panics
     /// Will panic if mipmaps is larger than 16.
@@ -65,6 +67,13 @@ impl<W: WriteAt> XtxEncoder<W> {
         self.mipmaps = mipmaps;
     }
 
+    /// Set the filter used for generating the mip maps
+    ///
+    /// Defaults to [`FilterType::Triangle`].
+    pub fn set_mipmap_filter(&mut self, filter: FilterType) {
+        self.mipmap_filter = filter;
+    }
+
     /// Set the encoder parameters
     ///
     /// The default is `texpresso::Params::default()` except for the
@@ -87,6 +96,9 @@ pub enum EncoderError {
     /// The input color type was wrong
     #[error("The input color format was not RGBA8")]
     InvalidInputColor,
+    /// The texture format is not supported by the encoder
+    #[error("Encoding {0:?} is not supported")]
+    UnsupportedFormat(Format),
     /// Swizzling went wrong
     #[error("Texture deswizzling failed")]
     SwizzleError(#[from] SwizzleError),
@@ -118,9 +130,12 @@ impl<W: WriteAt> ImageEncoder for XtxEncoder<W> {
         if color_type != ExtendedColorType::Rgba8 {
             return Err(EncoderError::InvalidInputColor.into());
         }
-        if self.mipmaps != 0 {
-            unimplemented!("Encoding mipmaps is not yet implemented!");
-        }
+        let expected_size = u64::from(width) * u64::from(height) * 4;
+        test_eq!(
+            u64::try_from(buf.len()).map_err(EncoderError::from)?,
+            expected_size
+        )
+        .map_err(EncoderError::from)?;
 
         let format = match self.format {
             Some(format) => format,
@@ -133,23 +148,18 @@ impl<W: WriteAt> ImageEncoder for XtxEncoder<W> {
             }
         };
 
-        let width_usize = usize::try_from(width).map_err(EncoderError::from)?;
-        let height_usize = usize::try_from(height).map_err(EncoderError::from)?;
-
-        let buffer_size = match format {
-            Format::NvnFormatRGBA8 | Format::NvnFormatRGBA8SRGB => buf.len(),
-            Format::BC1 => texpresso::Format::Bc1.compressed_size(width_usize, height_usize),
-            Format::BC2 => texpresso::Format::Bc2.compressed_size(width_usize, height_usize),
-            Format::BC3 => texpresso::Format::Bc3.compressed_size(width_usize, height_usize),
-            _ => unimplemented!("Encoding {format:?} is not yet implemented!"),
-        };
+        // The chain stops at 1x1
+        let max_mipmaps = u32::BITS - width.max(height).leading_zeros();
+        let mipmaps = (u32::from(self.mipmaps) + 1).min(max_mipmaps.max(1));
 
-        let (block_dim, block_height) = if format.is_bcn() {
-            (BlockDim::block_4x4(), block_height_mip0(height.div_ceil(4)))
-        } else {
-            (BlockDim::uncompressed(), block_height_mip0(height))
+        let to_blocks = |pixels: u32| {
+            if format.is_bcn() {
+                pixels.div_ceil(4)
+            } else {
+                pixels
+            }
         };
-
+        let block_height = block_height_mip0(to_blocks(height));
         let block_height_log2: u8 = match block_height {
             BlockHeight::One => 0,
             BlockHeight::Two => 1,
@@ -159,72 +169,54 @@ impl<W: WriteAt> ImageEncoder for XtxEncoder<W> {
             BlockHeight::ThirtyTwo => 5,
         };
 
+        let base = ImageBuffer::<Rgba<u8>, &[u8]>::from_raw(width, height, buf)
+            .unwrap_or_else(|| unreachable!("Size is checked above"));
+
+        let mut swizzled = Vec::new();
+        let mut mipmap_offsets = [0; 17];
+        for (mipmap, offset) in (0..mipmaps).zip(mipmap_offsets.iter_mut()) {
+            let mip_width = (width >> mipmap).max(1);
+            let mip_height = (height >> mipmap).max(1);
+            // Every level is resized from the base image to prevent accumulating errors
+            let pixels = if mipmap == 0 {
+                Cow::Borrowed(buf)
+            } else {
+                Cow::Owned(
+                    imageops::resize(&base, mip_width, mip_height, self.mipmap_filter).into_raw(),
+                )
+            };
+            let data = compress(format, &pixels, mip_width, mip_height, self.params)?;
+
+            let mip_height_in_blocks = to_blocks(mip_height);
+            *offset = u32::try_from(swizzled.len()).map_err(EncoderError::from)?;
+            swizzled.extend(
+                swizzle_block_linear(
+                    to_blocks(mip_width),
+                    mip_height_in_blocks,
+                    1,
+                    &data,
+                    mip_block_height(mip_height_in_blocks, block_height),
+                    format.bytes_per_pixel(),
+                )
+                .map_err(EncoderError::from)?,
+            );
+        }
+
+        let image_size = swizzled.len();
         let header = TextureHeader {
-            image_size: u64::try_from(buffer_size).map_err(EncoderError::from)?,
+            image_size: u64::try_from(image_size).map_err(EncoderError::from)?,
             alignment: 0x200,
             width,
             height,
             depth: 1,
             target: 1,
             format,
-            mipmaps: u32::from(self.mipmaps + 1),
-            slice_size: u32::try_from(buffer_size).map_err(EncoderError::from)?,
-            mipmap_offsets: [0; 17],
+            mipmaps,
+            slice_size: u32::try_from(image_size).map_err(EncoderError::from)?,
+            mipmap_offsets,
             block_height_log2,
         };
 
-        let data = match format {
-            Format::NvnFormatRGBA8 | Format::NvnFormatRGBA8SRGB => Cow::Borrowed(buf),
-            Format::BC1 => {
-                let mut output = vec![0; buffer_size];
-                texpresso::Format::Bc1.compress(
-                    buf,
-                    width_usize,
-                    height_usize,
-                    self.params,
-                    &mut output,
-                );
-                Cow::Owned(output)
-            }
-            Format::BC2 => {
-                let mut output = vec![0; buffer_size];
-                texpresso::Format::Bc2.compress(
-                    buf,
-                    width_usize,
-                    height_usize,
-                    self.params,
-                    &mut output,
-                );
-                Cow::Owned(output)
-            }
-            Format::BC3 => {
-                let mut output = vec![0; buffer_size];
-                texpresso::Format::Bc3.compress(
-                    buf,
-                    width_usize,
-                    height_usize,
-                    self.params,
-                    &mut output,
-                );
-                Cow::Owned(output)
-            }
-            _ => unreachable!(),
-        };
-
-        let swizzled = swizzle_surface(
-            width,
-            height,
-            1,
-            &data,
-            block_dim,
-            Some(block_height),
-            format.bytes_per_pixel(),
-            header.mipmaps,
-            1,
-        )
-        .map_err(EncoderError::from)?;
-        drop(data); // Don't keep the original image in ram
-
         let mut blocks = VecDeque::with_capacity(2);
         blocks.push_back(Block {
             id: 0,
@@ -252,6 +244,28 @@ impl<W: WriteAt> ImageEncoder for XtxEncoder<W> {
     }
 }
 
+/// Compress `buf` to `format`
+fn compress(
+    format: Format,
+    buf: &[u8],
+    width: u32,
+    height: u32,
+    params: texpresso::Params,
+) -> Result<Cow<'_, [u8]>, EncoderError> {
+    let width = usize::try_from(width)?;
+    let height = usize::try_from(height)?;
+    let texpresso_format = match format {
+        Format::NvnFormatRGBA8 | Format::NvnFormatRGBA8SRGB => return Ok(Cow::Borrowed(buf)),
+        Format::BC1 => texpresso::Format::Bc1,
+        Format::BC2 => texpresso::Format::Bc2,
+        Format::BC3 => texpresso::Format::Bc3,
+        _ => return Err(EncoderError::UnsupportedFormat(format)),
+    };
+    let mut output = vec![0; texpresso_format.compressed_size(width, height)];
+    texpresso_format.compress(buf, width, height, params, &mut output);
+    Ok(Cow::Owned(output))
+}
+
 impl BinarySerialize for XtxRaw<'_> {
     type Ctx = ();
     type Input = Self;
@@ -388,3 +402,94 @@ impl BinarySerialize for Format {
         writer.write_at::<u32le>(position, u32::from(format))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::bytes::read::ReadAtExt as _;
+    use image::{imageops::FilterType, ImageDecoder, ImageEncoder};
+
+    use super::XtxEncoder;
+    use crate::{
+        types::{BlockData, Format, XtxRaw},
+        XtxDecoder,
+    };
+
+    fn encode(buf: &[u8], width: u32, height: u32, format: Format, mipmaps: u8) -> Vec<u8> {
+        let mut xtx = Vec::new();
+        let mut encoder = XtxEncoder::new(&mut xtx, 0);
+        encoder.set_format(format);
+        encoder.set_mipmaps(mipmaps);
+        encoder.set_mipmap_filter(FilterType::Nearest);
+        encoder
+            .write_image(buf, width, height, image::ExtendedColorType::Rgba8)
+            .unwrap();
+        xtx
+    }
+
+    fn decode(xtx: &[u8], mipmap: u32) -> ((u32, u32), Vec<u8>) {
+        let mut decoder = XtxDecoder::new(xtx, &mut 0).unwrap();
+        decoder.set_mipmap(mipmap).unwrap();
+        let dimensions = decoder.dimensions();
+        let mut buf = vec![0; usize::try_from(decoder.total_bytes()).unwrap()];
+        decoder.read_image(&mut buf).unwrap();
+        (dimensions, buf)
+    }
+
+    #[test]
+    fn test_mipmaps_rgba8() {
+        // Solid colour so every level has the same pixels
+        let buf: Vec<u8> = [0x12, 0x34, 0x56, 0x78].repeat(64 * 32);
+        let xtx = encode(&buf, 64, 32, Format::NvnFormatRGBA8, 3);
+
+        let decoder = XtxDecoder::new(xtx.as_slice(), &mut 0).unwrap();
+        assert_eq!(decoder.mipmaps(), 4, "Mipmap count is wrong");
+        let raw = xtx.read_at::<XtxRaw>(&mut 0).unwrap();
+        let Some(BlockData::TextureHeader(header)) = raw.blocks.into_iter().next().map(|b| b.data)
+        else {
+            panic!("First block should be the texture header");
+        };
+        let offsets = header.mipmap_offsets;
+        assert_eq!(offsets[0], 0, "First mipmap should be at the start");
+        assert!(
+            offsets[1] > offsets[0] && offsets[2] > offsets[1] && offsets[3] > offsets[2],
+            "Mipmap offsets should be increasing: {offsets:?}"
+        );
+        assert!(
+            offsets[4..].iter().all(|o| *o == 0),
+            "Unused offsets should be zero"
+        );
+
+        let (dimensions, level0) = decode(&xtx, 0);
+        assert_eq!(dimensions, (64, 32), "Wrong dimensions for level 0");
+        assert_eq!(level0, buf, "Level 0 does not match the input");
+        for (mipmap, expected) in [(1, (32, 16)), (2, (16, 8)), (3, (8, 4))] {
+            let (dimensions, level) = decode(&xtx, mipmap);
+            assert_eq!(dimensions, expected, "Wrong dimensions for level {mipmap}");
+            assert!(
+                level.chunks_exact(4).all(|p| p == [0x12, 0x34, 0x56, 0x78]),
+                "Level {mipmap} does not match the input"
+            );
+        }
+    }
+
+    #[test]
+    fn test_mipmaps_clamped() {
+        let buf = [0xFF; 4 * 4 * 4];
+        let xtx = encode(&buf, 4, 4, Format::BC1, 16);
+        let mut decoder = XtxDecoder::new(xtx.as_slice(), &mut 0).unwrap();
+        assert_eq!(decoder.mipmaps(), 3, "Chain should stop at 1x1");
+        assert!(decoder.set_mipmap(3).is_err(), "Level 3 does not exist");
+        let (dimensions, level) = decode(&xtx, 2);
+        assert_eq!(dimensions, (1, 1), "Last level should be 1x1");
+        assert_eq!(level, [0xFF; 4], "White should stay white");
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        let mut xtx = Vec::new();
+        let mut encoder = XtxEncoder::new(&mut xtx, 0);
+        encoder.set_format(Format::BC5U);
+        let result = encoder.write_image(&[0xFF; 4 * 4 * 4], 4, 4, image::ExtendedColorType::Rgba8);
+        assert!(result.is_err(), "Encoding BC5 is not supported");
+    }
+}
@@ -0,0 +1,79 @@
+//! Conversion of the packed formats to RGBA8, the other formats are handled by `texpresso` and
+//! [`dotstar_toolkit_utils::texture`]
+//!
+//! The packed 16-bit NVN formats have the first component in the most significant bits,
+//! while `RGB10A2` has the first component in the least significant bits.
+
+use dotstar_toolkit_utils::texture::expand;
+
+/// Decode a `RGB565` pixel
+pub fn rgb565(pixel: [u8; 2]) -> [u8; 4] {
+    let value = u32::from(u16::from_le_bytes(pixel));
+    [
+        expand(value >> 11, 5),
+        expand(value >> 5, 6),
+        expand(value, 5),
+        u8::MAX,
+    ]
+}
+
+/// Decode a `RGB5A1` pixel
+pub fn rgb5a1(pixel: [u8; 2]) -> [u8; 4] {
+    let value = u32::from(u16::from_le_bytes(pixel));
+    [
+        expand(value >> 11, 5),
+        expand(value >> 6, 5),
+        expand(value >> 1, 5),
+        expand(value, 1),
+    ]
+}
+
+/// Decode a `RGBA4` pixel
+pub fn rgba4(pixel: [u8; 2]) -> [u8; 4] {
+    let value = u32::from(u16::from_le_bytes(pixel));
+    [
+        expand(value >> 12, 4),
+        expand(value >> 8, 4),
+        expand(value >> 4, 4),
+        expand(value, 4),
+    ]
+}
+
+/// Decode a `RGB10A2` pixel
+pub fn rgb10a2(pixel: [u8; 4]) -> [u8; 4] {
+    let value = u32::from_le_bytes(pixel);
+    [
+        expand(value, 10),
+        expand(value >> 10, 10),
+        expand(value >> 20, 10),
+        expand(value >> 30, 2),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use dotstar_toolkit_utils::texture::decode_pixels;
+
+    use super::*;
+
+    #[test]
+    fn test_packed_pixels() {
+        assert_eq!(rgb565([0xFF, 0xFF]), [255, 255, 255, 255], "White");
+        assert_eq!(rgb565([0x00, 0xF8]), [255, 0, 0, 255], "Red");
+        assert_eq!(rgb5a1([0x01, 0x00]), [0, 0, 0, 255], "Black with alpha");
+        assert_eq!(rgba4([0x0F, 0xF0]), [255, 0, 0, 255], "Red with alpha");
+        assert_eq!(
+            rgb10a2([0xFF, 0x03, 0, 0xC0]),
+            [255, 0, 0, 255],
+            "Red with alpha"
+        );
+    }
+
+    #[test]
+    fn test_decode_pixels() {
+        let data = [0x00, 0xF8, 0xFF, 0xFF];
+        let mut buf = vec![0; 8];
+        decode_pixels(&data, &mut buf, rgb565).unwrap();
+        assert_eq!(buf, [255, 0, 0, 255, 255, 255, 255, 255], "Two pixels");
+    }
+}
@@ -1,5 +1,6 @@
 mod decoder;
 mod encoder;
+mod formats;
 mod types;
 
 pub use decoder::XtxDecoder;